@@ -0,0 +1,116 @@
+// HumanAddr is deprecated from cosmwasm-std 0.14 on, the messages keep it so they stay
+// wire compatible with the contract
+#![allow(deprecated)]
+
+use cosmwasm_std::{HumanAddr, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// The holder facing part of the token's interface, for contracts and services that only
+// move tokens around. The contract re-exports the shared types so both stay in step.
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Expiration {
+    AtHeight(u64),
+    // Time in seconds
+    AtTime(u64),
+}
+
+impl Expiration {
+    pub fn is_expired(&self, env: &cosmwasm_std::Env) -> bool {
+        match self {
+            Expiration::AtHeight(height) => env.block.height >= *height,
+            Expiration::AtTime(time) => env.block.time.seconds() >= *time,
+        }
+    }
+}
+
+// Subset of the token's execute messages, serialized the same way as the full enum.
+// Memos are left out when empty so `Transfer` also works against plain cw20 tokens.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    Transfer {
+        recipient: HumanAddr,
+        amount: Uint128,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+    },
+    TransferFrom {
+        owner: HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+    },
+    Approve { spender: HumanAddr, amount: Uint128 },
+    DecreaseAllowance { spender: HumanAddr, amount: Uint128 },
+    Burn { amount: Uint128 },
+    IncreaseAllowanceAndCall { spender: HumanAddr, amount: Uint128, expires: Option<Expiration>, msg: cosmwasm_std::Binary },
+    Send {
+        contract: HumanAddr,
+        amount: Uint128,
+        msg: cosmwasm_std::Binary,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+    },
+    SendChecked {
+        contract: HumanAddr,
+        amount: Uint128,
+        msg: cosmwasm_std::Binary,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        memo: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Balance { address: HumanAddr },
+    TokenInfo {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BalanceResponse {
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenInfoResponse {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub total_supply: Uint128,
+    pub cap: Option<Uint128>,
+}
+
+// Hook message sent by a cw20 contract when tokens are Sent to this contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw20ReceiveMsg {
+    pub sender: HumanAddr,
+    pub amount: Uint128,
+    pub msg: cosmwasm_std::Binary,
+}
+
+// Execute message of a contract implementing the cw20 receiver interface
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiverExecuteMsg {
+    Receive(Cw20ReceiveMsg),
+}
+
+// Notification sent to the spender of `IncreaseAllowanceAndCall`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw20ApprovalMsg {
+    pub owner: HumanAddr,
+    pub amount: Uint128,
+    pub expires: Option<Expiration>,
+    pub msg: cosmwasm_std::Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalReceiverExecuteMsg {
+    ReceiveApproval(Cw20ApprovalMsg),
+}
@@ -0,0 +1,1466 @@
+use cosmwasm_std::{Decimal, HumanAddr, Uint128};
+use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BLACKLISTER_KEY, BLACKLIST_PREFIX, DEFAULT_LIMIT, Duration, Expiration, MAX_LIMIT,
+    NATIVE_MIGRATION_KEY, NativeMigration, assert_owner, assert_unlocked, balance_of,
+    buyback_pending, calc_range_start, escrowed_total, is_paused, locked_balance, move_balance,
+    next_id, set_paused, settle_transfer, token_info,
+};
+
+// Checks every transfer restriction that applies to moving `amount` from `from` to `to`
+pub fn assert_transfer_allowed(
+    deps: cosmwasm_std::Deps,
+    env: &cosmwasm_std::Env,
+    from: &HumanAddr,
+    to: &HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<()> {
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    // The contract only accepts its own token while a buyback swap is paying out
+    if to.as_str() == env.contract.address.as_str() && !buyback_pending(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Cannot transfer to the token contract"));
+    }
+    if let Some(migration) = ReadonlySingleton::<NativeMigration>::new(deps.storage, NATIVE_MIGRATION_KEY).may_load()? {
+        if migration.deadline.is_expired(env) {
+            return Err(cosmwasm_std::StdError::generic_err("cw20 transfers ended with the native migration, use ConvertToNative"));
+        }
+    }
+    assert_not_blacklisted(deps.storage, from)?;
+    assert_not_blacklisted(deps.storage, to)?;
+    if is_frozen(deps.storage, from)? {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Account {} is frozen", from)));
+    }
+    if whitelist_enabled(deps.storage)? && !(is_whitelisted(deps.storage, from)? && is_whitelisted(deps.storage, to)?) {
+        return Err(cosmwasm_std::StdError::generic_err("Transfers are restricted to whitelisted addresses"));
+    }
+    if let Some(contract) = ReadonlySingleton::<HumanAddr>::new(deps.storage, RESTRICTION_CONTRACT_KEY).may_load()? {
+        let query = RestrictionQueryMsg::DetectTransferRestriction { from: from.clone(), to: to.clone(), amount };
+        let res: RestrictionResponse = deps.querier.query_wasm_smart(contract, &query)?;
+        if res.code != 0 {
+            let message = res.message.unwrap_or_else(|| "Transfer rejected by restriction contract".to_string());
+            return Err(cosmwasm_std::StdError::generic_err(format!("Restriction code {}: {}", res.code, message)));
+        }
+    }
+    if let Some(kyc) = ReadonlySingleton::<KycConfig>::new(deps.storage, KYC_CONFIG_KEY).may_load()? {
+        if kyc.policy == KycPolicy::SenderAndRecipient {
+            assert_kyc_verified(deps, &kyc.registry, from)?;
+        }
+        assert_kyc_verified(deps, &kyc.registry, to)?;
+    }
+    if let Some(sanctions) = sanctions_config(deps.storage)? {
+        if sanctions.enabled {
+            assert_not_sanctioned(deps, &sanctions.oracle, from)?;
+            assert_not_sanctioned(deps, &sanctions.oracle, to)?;
+        }
+    }
+    assert_max_wallet(deps.storage, to, amount)?;
+    assert_max_transaction(deps.storage, from, amount)?;
+    assert_min_transfer(deps.storage, amount)?;
+    if let Some(daily) = ReadonlySingleton::<DailyLimit>::new(deps.storage, DAILY_LIMIT_KEY).may_load()? {
+        let outflow = current_outflow(deps.storage, &daily, env, from)?;
+        if outflow.amount.checked_add(amount)? > daily.limit {
+            return Err(cosmwasm_std::StdError::generic_err("Daily transfer limit exceeded"));
+        }
+    }
+    assert_usd_limits(deps, env, from, amount)?;
+    assert_cooldown_elapsed(deps.storage, env, from)?;
+    if let Some(launch) = ReadonlySingleton::<LaunchConfig>::new(deps.storage, LAUNCH_KEY).may_load()? {
+        if !launch.is_live(env) && !is_limit_exempt(deps.storage, &LimitKind::Launch, from)? {
+            return Err(cosmwasm_std::StdError::generic_err("Trading is not enabled yet"));
+        }
+    }
+    if let Some(until) = ReadonlyBucket::<Expiration>::new(deps.storage, SELF_LOCK_PREFIX).may_load(from.as_bytes())? {
+        if !until.is_expired(env) {
+            return Err(cosmwasm_std::StdError::generic_err(format!("Account {} is locked by its owner", from)));
+        }
+    }
+    assert_unlocked(deps.storage, env, from, amount)?;
+    Ok(())
+}
+
+// Updates the per-account and global trackers used by the transfer restrictions,
+// returning any events the handler should attach to its response
+pub fn record_transfer(
+    storage: &mut dyn cosmwasm_std::Storage,
+    env: &cosmwasm_std::Env,
+    from: &HumanAddr,
+    _to: &HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<Vec<cosmwasm_std::Event>> {
+    let mut events = vec![];
+    if let Some(daily) = ReadonlySingleton::<DailyLimit>::new(storage, DAILY_LIMIT_KEY).may_load()? {
+        let mut outflow = current_outflow(storage, &daily, env, from)?;
+        outflow.amount = outflow.amount.checked_add(amount)?;
+        Bucket::new(storage, OUTFLOW_PREFIX).save(from.as_bytes(), &outflow)?;
+    }
+    if let Some(breaker) = ReadonlySingleton::<CircuitBreaker>::new(storage, CIRCUIT_BREAKER_KEY).may_load()? {
+        if let Some(event) = record_window_volume(storage, &breaker, env, amount)? {
+            events.push(event);
+        }
+    }
+    if ReadonlySingleton::<Duration>::new(storage, COOLDOWN_KEY).may_load()?.is_some() {
+        let last = LastTransfer { height: env.block.height, time: env.block.time.seconds() };
+        Bucket::new(storage, LAST_TRANSFER_PREFIX).save(from.as_bytes(), &last)?;
+    }
+    let mut switches = Bucket::<DeadManSwitch>::new(storage, DEAD_MAN_SWITCH_PREFIX);
+    if let Some(mut switch) = switches.may_load(from.as_bytes())? {
+        switch.last_active_height = env.block.height;
+        switch.last_active_time = env.block.time.seconds();
+        switches.save(from.as_bytes(), &switch)?;
+    }
+    Ok(events)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IsBlacklistedResponse {
+    pub blacklisted: bool,
+}
+
+pub fn is_blacklisted(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<bool> {
+    let blacklisted = ReadonlyBucket::<bool>::new(storage, BLACKLIST_PREFIX).may_load(address.as_bytes())?;
+    Ok(blacklisted.unwrap_or(false))
+}
+
+pub fn assert_not_blacklisted(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<()> {
+    if is_blacklisted(storage, address)? {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Address {} is blacklisted", address)));
+    }
+    Ok(())
+}
+
+fn assert_blacklister(storage: &dyn cosmwasm_std::Storage, sender: &HumanAddr) -> cosmwasm_std::StdResult<()> {
+    let blacklister = ReadonlySingleton::<HumanAddr>::new(storage, BLACKLISTER_KEY).may_load()?;
+    if blacklister.as_ref() != Some(sender) {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    Ok(())
+}
+
+pub fn set_blacklister(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    blacklister: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    Singleton::new(deps.storage, BLACKLISTER_KEY).save(&blacklister)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_blacklister").add_attribute("blacklister", blacklister))
+}
+
+pub fn blacklist(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    address: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_blacklister(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    Bucket::new(deps.storage, BLACKLIST_PREFIX).save(address.as_bytes(), &true)?;
+
+    let event = cosmwasm_std::Event::new("blacklist").add_attribute("address", address.clone()).add_attribute("blacklister", info.sender);
+    Ok(cosmwasm_std::Response::new().add_event(event).add_attribute("action", "blacklist").add_attribute("address", address))
+}
+
+pub fn unblacklist(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    address: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_blacklister(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    Bucket::<bool>::new(deps.storage, BLACKLIST_PREFIX).remove(address.as_bytes());
+
+    let event = cosmwasm_std::Event::new("unblacklist").add_attribute("address", address.clone()).add_attribute("blacklister", info.sender);
+    Ok(cosmwasm_std::Response::new().add_event(event).add_attribute("action", "unblacklist").add_attribute("address", address))
+}
+
+pub fn query_is_blacklisted(deps: cosmwasm_std::Deps, address: HumanAddr) -> cosmwasm_std::StdResult<IsBlacklistedResponse> {
+    Ok(IsBlacklistedResponse { blacklisted: is_blacklisted(deps.storage, &address)? })
+}
+
+pub const WHITELIST_ENABLED_KEY: &[u8] = b"whitelist_enabled";
+pub const WHITELIST_PREFIX: &[u8] = b"whitelist";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IsWhitelistedResponse {
+    pub whitelisted: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WhitelistResponse {
+    pub enabled: bool,
+    pub addresses: Vec<HumanAddr>,
+}
+
+pub fn whitelist_enabled(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<bool> {
+    Ok(ReadonlySingleton::<bool>::new(storage, WHITELIST_ENABLED_KEY).may_load()?.unwrap_or(false))
+}
+
+pub fn is_whitelisted(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<bool> {
+    let whitelisted = ReadonlyBucket::<bool>::new(storage, WHITELIST_PREFIX).may_load(address.as_bytes())?;
+    Ok(whitelisted.unwrap_or(false))
+}
+
+pub fn set_whitelist_mode(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    enabled: bool,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    Singleton::new(deps.storage, WHITELIST_ENABLED_KEY).save(&enabled)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_whitelist_mode").add_attribute("enabled", enabled.to_string()))
+}
+
+pub fn add_to_whitelist(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    address: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    Bucket::new(deps.storage, WHITELIST_PREFIX).save(address.as_bytes(), &true)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "add_to_whitelist").add_attribute("address", address))
+}
+
+pub fn remove_from_whitelist(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    address: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    Bucket::<bool>::new(deps.storage, WHITELIST_PREFIX).remove(address.as_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "remove_from_whitelist").add_attribute("address", address))
+}
+
+pub fn query_is_whitelisted(deps: cosmwasm_std::Deps, address: HumanAddr) -> cosmwasm_std::StdResult<IsWhitelistedResponse> {
+    Ok(IsWhitelistedResponse { whitelisted: is_whitelisted(deps.storage, &address)? })
+}
+
+pub fn query_whitelist(
+    deps: cosmwasm_std::Deps,
+    start_after: Option<HumanAddr>,
+    limit: Option<u32>,
+) -> cosmwasm_std::StdResult<WhitelistResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = calc_range_start(start_after);
+
+    let addresses = ReadonlyBucket::<bool>::new(deps.storage, WHITELIST_PREFIX)
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, _) = item?;
+            Ok(HumanAddr::from(String::from_utf8(key)?))
+        })
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    Ok(WhitelistResponse { enabled: whitelist_enabled(deps.storage)?, addresses })
+}
+
+pub const FROZEN_PREFIX: &[u8] = b"frozen";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IsFrozenResponse {
+    pub frozen: bool,
+}
+
+pub fn is_frozen(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<bool> {
+    let frozen = ReadonlyBucket::<bool>::new(storage, FROZEN_PREFIX).may_load(address.as_bytes())?;
+    Ok(frozen.unwrap_or(false))
+}
+
+pub fn freeze_account(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    address: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    Bucket::new(deps.storage, FROZEN_PREFIX).save(address.as_bytes(), &true)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "freeze_account").add_attribute("address", address))
+}
+
+pub fn unfreeze_account(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    address: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    Bucket::<bool>::new(deps.storage, FROZEN_PREFIX).remove(address.as_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "unfreeze_account").add_attribute("address", address))
+}
+
+pub fn query_is_frozen(deps: cosmwasm_std::Deps, address: HumanAddr) -> cosmwasm_std::StdResult<IsFrozenResponse> {
+    Ok(IsFrozenResponse { frozen: is_frozen(deps.storage, &address)? })
+}
+
+pub const COMPLIANCE_KEY: &[u8] = b"compliance_officer";
+
+pub fn assert_compliance_officer(storage: &dyn cosmwasm_std::Storage, sender: &HumanAddr) -> cosmwasm_std::StdResult<()> {
+    let officer = ReadonlySingleton::<HumanAddr>::new(storage, COMPLIANCE_KEY).may_load()?;
+    if officer.as_ref() != Some(sender) {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    Ok(())
+}
+
+pub fn set_compliance_officer(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    officer: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    Singleton::new(deps.storage, COMPLIANCE_KEY).save(&officer)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_compliance_officer").add_attribute("officer", officer))
+}
+
+pub fn force_transfer(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    from: HumanAddr,
+    to: HumanAddr,
+    amount: Uint128,
+    reason: String,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_compliance_officer(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    if reason.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err("A reason is required for forced transfers"));
+    }
+    // What the contract holds is escrowed for others or collected as fees
+    if from.as_str() == env.contract.address.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Tokens held by the token contract can't be force transferred"));
+    }
+
+    move_balance(deps.storage, &from, &to, amount)?;
+
+    let event = cosmwasm_std::Event::new("force_transfer")
+        .add_attribute("officer", info.sender)
+        .add_attribute("from", from.clone())
+        .add_attribute("to", to.clone())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("reason", reason)
+        .add_attribute("height", env.block.height.to_string());
+    Ok(cosmwasm_std::Response::new().add_event(event).add_attribute("action", "force_transfer").add_attribute("from", from).add_attribute("to", to).add_attribute("amount", amount.to_string()))
+}
+
+pub const RESTRICTION_CONTRACT_KEY: &[u8] = b"restriction_contract";
+
+// Query sent to the registered restriction contract, a code of 0 means the transfer is allowed
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RestrictionQueryMsg {
+    DetectTransferRestriction { from: HumanAddr, to: HumanAddr, amount: Uint128 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RestrictionResponse {
+    pub code: u8,
+    pub message: Option<String>,
+}
+
+pub fn set_restriction_contract(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    contract: Option<HumanAddr>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<HumanAddr>::new(deps.storage, RESTRICTION_CONTRACT_KEY);
+    let attr = match contract {
+        Some(contract) => {
+            singleton.save(&contract)?;
+            contract.to_string()
+        }
+        None => {
+            singleton.remove();
+            "none".to_string()
+        }
+    };
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_restriction_contract").add_attribute("contract", attr))
+}
+
+pub const KYC_CONFIG_KEY: &[u8] = b"kyc_config";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KycPolicy {
+    RecipientOnly,
+    SenderAndRecipient,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KycConfig {
+    pub registry: HumanAddr,
+    pub policy: KycPolicy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KycQueryMsg {
+    IsVerified { address: HumanAddr },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct KycVerifiedResponse {
+    pub verified: bool,
+}
+
+pub fn assert_kyc_verified(deps: cosmwasm_std::Deps, registry: &HumanAddr, address: &HumanAddr) -> cosmwasm_std::StdResult<()> {
+    let res: KycVerifiedResponse = deps.querier.query_wasm_smart(registry.as_str(), &KycQueryMsg::IsVerified { address: address.clone() })?;
+    if !res.verified {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Address {} is not KYC verified", address)));
+    }
+    Ok(())
+}
+
+pub fn set_kyc_registry(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    config: Option<KycConfig>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<KycConfig>::new(deps.storage, KYC_CONFIG_KEY);
+    let attr = match config {
+        Some(config) => {
+            singleton.save(&config)?;
+            config.registry.to_string()
+        }
+        None => {
+            singleton.remove();
+            "none".to_string()
+        }
+    };
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_kyc_registry").add_attribute("registry", attr))
+}
+
+pub const SANCTIONS_CONFIG_KEY: &[u8] = b"sanctions_config";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SanctionsConfig {
+    pub oracle: HumanAddr,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SanctionsQueryMsg {
+    IsSanctioned { address: HumanAddr },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SanctionedResponse {
+    pub sanctioned: bool,
+}
+
+pub fn sanctions_config(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Option<SanctionsConfig>> {
+    ReadonlySingleton::new(storage, SANCTIONS_CONFIG_KEY).may_load()
+}
+
+pub fn assert_not_sanctioned(deps: cosmwasm_std::Deps, oracle: &HumanAddr, address: &HumanAddr) -> cosmwasm_std::StdResult<()> {
+    let res: SanctionedResponse = deps.querier.query_wasm_smart(oracle.as_str(), &SanctionsQueryMsg::IsSanctioned { address: address.clone() })?;
+    if res.sanctioned {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Address {} is on the sanctions list", address)));
+    }
+    Ok(())
+}
+
+pub fn update_sanctions_config(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    oracle: Option<HumanAddr>,
+    enabled: Option<bool>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let current = sanctions_config(deps.storage)?;
+    let oracle = match (oracle, current.as_ref()) {
+        (Some(oracle), _) => oracle,
+        (None, Some(config)) => config.oracle.clone(),
+        (None, None) => return Err(cosmwasm_std::StdError::generic_err("No sanctions oracle configured")),
+    };
+    let enabled = enabled.unwrap_or_else(|| current.map(|c| c.enabled).unwrap_or(true));
+
+    let config = SanctionsConfig { oracle, enabled };
+    Singleton::new(deps.storage, SANCTIONS_CONFIG_KEY).save(&config)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "update_sanctions_config").add_attribute("oracle", config.oracle).add_attribute("enabled", config.enabled.to_string()))
+}
+
+pub const MAX_WALLET_KEY: &[u8] = b"max_wallet";
+pub const MAX_WALLET_EXEMPT_PREFIX: &[u8] = b"max_wallet_exempt";
+pub const MAX_TX_KEY: &[u8] = b"max_tx";
+pub const MAX_TX_EXEMPT_PREFIX: &[u8] = b"max_tx_exempt";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LimitKind {
+    MaxWallet,
+    MaxTransaction,
+    Cooldown,
+    Launch,
+}
+
+impl LimitKind {
+    pub fn exempt_prefix(&self) -> &'static [u8] {
+        match self {
+            LimitKind::MaxWallet => MAX_WALLET_EXEMPT_PREFIX,
+            LimitKind::MaxTransaction => MAX_TX_EXEMPT_PREFIX,
+            LimitKind::Cooldown => COOLDOWN_EXEMPT_PREFIX,
+            LimitKind::Launch => LAUNCH_EXEMPT_PREFIX,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LimitsResponse {
+    pub max_wallet_balance: Option<Uint128>,
+    pub max_transaction_amount: Option<Uint128>,
+    pub daily_limit: Option<DailyLimit>,
+    pub transfer_cooldown: Option<Duration>,
+    pub large_transfer: Option<LargeTransferConfig>,
+}
+
+pub fn is_limit_exempt(storage: &dyn cosmwasm_std::Storage, limit: &LimitKind, address: &HumanAddr) -> cosmwasm_std::StdResult<bool> {
+    let exempt = ReadonlyBucket::<bool>::new(storage, limit.exempt_prefix()).may_load(address.as_bytes())?;
+    Ok(exempt.unwrap_or(false))
+}
+
+pub fn assert_max_wallet(storage: &dyn cosmwasm_std::Storage, to: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    let max = match ReadonlySingleton::<Uint128>::new(storage, MAX_WALLET_KEY).may_load()? {
+        Some(max) => max,
+        None => return Ok(()),
+    };
+    if is_limit_exempt(storage, &LimitKind::MaxWallet, to)? {
+        return Ok(());
+    }
+    if balance_of(storage, to)?.checked_add(amount)? > max {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Balance of {} would exceed the maximum wallet balance of {}", to, max)));
+    }
+    Ok(())
+}
+
+pub fn set_max_wallet_balance(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    max: Option<Uint128>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<Uint128>::new(deps.storage, MAX_WALLET_KEY);
+    match max {
+        Some(max) => singleton.save(&max)?,
+        None => singleton.remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_max_wallet_balance").add_attribute("max", max.map(|m| m.to_string()).unwrap_or_else(|| "none".to_string())))
+}
+
+pub fn set_limit_exemption(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    limit: LimitKind,
+    address: HumanAddr,
+    exempt: bool,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut bucket = Bucket::<bool>::new(deps.storage, limit.exempt_prefix());
+    if exempt {
+        bucket.save(address.as_bytes(), &true)?;
+    } else {
+        bucket.remove(address.as_bytes());
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_limit_exemption").add_attribute("address", address).add_attribute("exempt", exempt.to_string()))
+}
+
+pub fn query_limits(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<LimitsResponse> {
+    Ok(LimitsResponse {
+        max_wallet_balance: ReadonlySingleton::new(deps.storage, MAX_WALLET_KEY).may_load()?,
+        max_transaction_amount: ReadonlySingleton::new(deps.storage, MAX_TX_KEY).may_load()?,
+        daily_limit: ReadonlySingleton::new(deps.storage, DAILY_LIMIT_KEY).may_load()?,
+        transfer_cooldown: ReadonlySingleton::new(deps.storage, COOLDOWN_KEY).may_load()?,
+        large_transfer: ReadonlySingleton::new(deps.storage, LARGE_TRANSFER_KEY).may_load()?,
+    })
+}
+
+pub fn assert_max_transaction(storage: &dyn cosmwasm_std::Storage, from: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    let max = match ReadonlySingleton::<Uint128>::new(storage, MAX_TX_KEY).may_load()? {
+        Some(max) => max,
+        None => return Ok(()),
+    };
+    if amount > max && !is_limit_exempt(storage, &LimitKind::MaxTransaction, from)? {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Transfer amount exceeds the maximum of {}", max)));
+    }
+    Ok(())
+}
+
+pub fn set_max_transaction_amount(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    max: Option<Uint128>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<Uint128>::new(deps.storage, MAX_TX_KEY);
+    match max {
+        Some(max) => singleton.save(&max)?,
+        None => singleton.remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_max_transaction_amount").add_attribute("max", max.map(|m| m.to_string()).unwrap_or_else(|| "none".to_string())))
+}
+
+pub const DAILY_LIMIT_KEY: &[u8] = b"daily_limit";
+pub const OUTFLOW_PREFIX: &[u8] = b"outflow";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DailyLimit {
+    pub limit: Uint128,
+    // Length of an epoch in seconds, 86400 for a daily limit
+    pub epoch_seconds: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Outflow {
+    pub epoch: u64,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RemainingQuotaResponse {
+    pub remaining: Option<Uint128>,
+    pub resets_at: Option<u64>,
+}
+
+// Returns the outflow recorded in the current epoch, starting from zero when a new epoch began
+pub fn current_outflow(
+    storage: &dyn cosmwasm_std::Storage,
+    daily: &DailyLimit,
+    env: &cosmwasm_std::Env,
+    address: &HumanAddr,
+) -> cosmwasm_std::StdResult<Outflow> {
+    let epoch = env.block.time.seconds() / daily.epoch_seconds;
+    let outflow = ReadonlyBucket::<Outflow>::new(storage, OUTFLOW_PREFIX).may_load(address.as_bytes())?;
+    match outflow {
+        Some(outflow) if outflow.epoch == epoch => Ok(outflow),
+        _ => Ok(Outflow { epoch, amount: Uint128::zero() }),
+    }
+}
+
+pub fn set_daily_limit(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    limit: Option<DailyLimit>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    // The daily USD limit counts outflow in this limit's epochs, every transfer would fail without it
+    if limit.is_none() && ReadonlySingleton::<UsdLimits>::new(deps.storage, USD_LIMITS_KEY).may_load()?.is_some_and(|l| l.daily_limit_usd.is_some()) {
+        return Err(cosmwasm_std::StdError::generic_err("Remove the daily USD limit before the daily limit"));
+    }
+
+    let mut singleton = Singleton::<DailyLimit>::new(deps.storage, DAILY_LIMIT_KEY);
+    match &limit {
+        Some(limit) => {
+            if limit.epoch_seconds == 0 {
+                return Err(cosmwasm_std::StdError::generic_err("Epoch length must be greater than zero"));
+            }
+            singleton.save(limit)?
+        }
+        None => singleton.remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_daily_limit").add_attribute("limit", limit.map(|l| l.limit.to_string()).unwrap_or_else(|| "none".to_string())))
+}
+
+pub fn query_remaining_quota(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, address: HumanAddr) -> cosmwasm_std::StdResult<RemainingQuotaResponse> {
+    let daily = match ReadonlySingleton::<DailyLimit>::new(deps.storage, DAILY_LIMIT_KEY).may_load()? {
+        Some(daily) => daily,
+        None => return Ok(RemainingQuotaResponse { remaining: None, resets_at: None }),
+    };
+    let outflow = current_outflow(deps.storage, &daily, &env, &address)?;
+    Ok(RemainingQuotaResponse {
+        remaining: Some(daily.limit.saturating_sub(outflow.amount)),
+        resets_at: Some((outflow.epoch + 1) * daily.epoch_seconds),
+    })
+}
+
+pub const CIRCUIT_BREAKER_KEY: &[u8] = b"circuit_breaker";
+pub const WINDOW_VOLUME_KEY: &[u8] = b"window_volume";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CircuitBreaker {
+    pub window_blocks: u64,
+    // Maximum volume per window, in basis points of the total supply
+    pub max_supply_bps: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WindowVolume {
+    pub window: u64,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CircuitBreakerResponse {
+    pub breaker: Option<CircuitBreaker>,
+    pub current_volume: Uint128,
+}
+
+fn current_window_volume(storage: &dyn cosmwasm_std::Storage, breaker: &CircuitBreaker, env: &cosmwasm_std::Env) -> cosmwasm_std::StdResult<WindowVolume> {
+    let window = env.block.height / breaker.window_blocks;
+    match ReadonlySingleton::<WindowVolume>::new(storage, WINDOW_VOLUME_KEY).may_load()? {
+        Some(volume) if volume.window == window => Ok(volume),
+        _ => Ok(WindowVolume { window, amount: Uint128::zero() }),
+    }
+}
+
+// Adds `amount` to the current window and pauses the contract once the threshold is crossed.
+// The transfer that trips the breaker still completes, an error here would revert the pause.
+fn record_window_volume(
+    storage: &mut dyn cosmwasm_std::Storage,
+    breaker: &CircuitBreaker,
+    env: &cosmwasm_std::Env,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<Option<cosmwasm_std::Event>> {
+    let mut volume = current_window_volume(storage, breaker, env)?;
+    volume.amount = volume.amount.checked_add(amount)?;
+    Singleton::new(storage, WINDOW_VOLUME_KEY).save(&volume)?;
+
+    let total_supply = token_info(storage)?.total_supply;
+    let threshold = total_supply.multiply_ratio(breaker.max_supply_bps, 10_000u64);
+    if volume.amount <= threshold {
+        return Ok(None);
+    }
+
+    set_paused(storage, true)?;
+
+    let event = cosmwasm_std::Event::new("circuit_breaker")
+        .add_attribute("window", volume.window.to_string())
+        .add_attribute("volume", volume.amount.to_string())
+        .add_attribute("threshold", threshold.to_string());
+    Ok(Some(event))
+}
+
+pub fn set_circuit_breaker(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    breaker: Option<CircuitBreaker>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<CircuitBreaker>::new(deps.storage, CIRCUIT_BREAKER_KEY);
+    match &breaker {
+        Some(breaker) => {
+            if breaker.window_blocks == 0 || breaker.max_supply_bps > 10_000 {
+                return Err(cosmwasm_std::StdError::generic_err("Invalid circuit breaker configuration"));
+            }
+            singleton.save(breaker)?
+        }
+        None => singleton.remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_circuit_breaker").add_attribute("enabled", breaker.is_some().to_string()))
+}
+
+pub fn query_circuit_breaker(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env) -> cosmwasm_std::StdResult<CircuitBreakerResponse> {
+    let breaker = ReadonlySingleton::<CircuitBreaker>::new(deps.storage, CIRCUIT_BREAKER_KEY).may_load()?;
+    let current_volume = match &breaker {
+        Some(breaker) => current_window_volume(deps.storage, breaker, &env)?.amount,
+        None => Uint128::zero(),
+    };
+    Ok(CircuitBreakerResponse { breaker, current_volume })
+}
+
+pub const COOLDOWN_KEY: &[u8] = b"cooldown";
+pub const COOLDOWN_EXEMPT_PREFIX: &[u8] = b"cooldown_exempt";
+pub const LAST_TRANSFER_PREFIX: &[u8] = b"last_transfer";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LastTransfer {
+    pub height: u64,
+    pub time: u64,
+}
+
+pub fn assert_cooldown_elapsed(storage: &dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, from: &HumanAddr) -> cosmwasm_std::StdResult<()> {
+    let cooldown = match ReadonlySingleton::<Duration>::new(storage, COOLDOWN_KEY).may_load()? {
+        Some(cooldown) => cooldown,
+        None => return Ok(()),
+    };
+    if is_limit_exempt(storage, &LimitKind::Cooldown, from)? {
+        return Ok(());
+    }
+    if let Some(last) = ReadonlyBucket::<LastTransfer>::new(storage, LAST_TRANSFER_PREFIX).may_load(from.as_bytes())? {
+        if !cooldown.has_elapsed(env, last.height, last.time) {
+            return Err(cosmwasm_std::StdError::generic_err("Transfer cooldown has not elapsed"));
+        }
+    }
+    Ok(())
+}
+
+pub fn set_transfer_cooldown(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    cooldown: Option<Duration>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<Duration>::new(deps.storage, COOLDOWN_KEY);
+    match &cooldown {
+        Some(cooldown) => singleton.save(cooldown)?,
+        None => singleton.remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_transfer_cooldown").add_attribute("enabled", cooldown.is_some().to_string()))
+}
+
+pub const LAUNCH_KEY: &[u8] = b"launch";
+pub const LAUNCH_EXEMPT_PREFIX: &[u8] = b"launch_exempt";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LaunchConfig {
+    pub trading_enabled: bool,
+    pub launch_height: Option<u64>,
+}
+
+impl LaunchConfig {
+    pub fn is_live(&self, env: &cosmwasm_std::Env) -> bool {
+        self.trading_enabled && self.launch_height.map(|h| env.block.height >= h).unwrap_or(true)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LaunchResponse {
+    pub config: Option<LaunchConfig>,
+    pub live: bool,
+}
+
+pub fn set_launch_config(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    launch_height: Option<u64>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<LaunchConfig>::new(deps.storage, LAUNCH_KEY);
+    let mut config = singleton.may_load()?.unwrap_or(LaunchConfig { trading_enabled: false, launch_height: None });
+    if config.trading_enabled {
+        return Err(cosmwasm_std::StdError::generic_err("Trading is already enabled"));
+    }
+    config.launch_height = launch_height;
+    singleton.save(&config)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_launch_config").add_attribute("launch_height", launch_height.map(|h| h.to_string()).unwrap_or_else(|| "none".to_string())))
+}
+
+pub fn enable_trading(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<LaunchConfig>::new(deps.storage, LAUNCH_KEY);
+    let mut config = singleton.may_load()?.ok_or_else(|| cosmwasm_std::StdError::generic_err("Launch mode is not configured"))?;
+    config.trading_enabled = true;
+    singleton.save(&config)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "enable_trading").add_attribute("height", env.block.height.to_string()))
+}
+
+pub fn query_launch(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env) -> cosmwasm_std::StdResult<LaunchResponse> {
+    let config = ReadonlySingleton::<LaunchConfig>::new(deps.storage, LAUNCH_KEY).may_load()?;
+    let live = config.as_ref().map(|c| c.is_live(&env)).unwrap_or(true);
+    Ok(LaunchResponse { config, live })
+}
+
+pub fn recover_stuck_tokens(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipient: HumanAddr,
+    amount: Option<Uint128>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let contract = HumanAddr::from(env.contract.address.as_str());
+    let recoverable = balance_of(deps.storage, &contract)?.checked_sub(escrowed_total(deps.storage)?)?;
+    let amount = amount.unwrap_or(recoverable);
+    if amount > recoverable {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Only {} tokens are recoverable", recoverable)));
+    }
+
+    move_balance(deps.storage, &contract, &recipient, amount)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "recover_stuck_tokens").add_attribute("to", recipient).add_attribute("amount", amount.to_string()))
+}
+
+pub const CLAWBACK_CONFIG_KEY: &[u8] = b"clawback_config";
+pub const CLAWBACK_COUNT_KEY: &[u8] = b"clawback_count";
+pub const CLAWBACKS_PREFIX: &[u8] = b"clawbacks";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClawbackConfig {
+    // Mandatory delay between proposing and executing a clawback
+    pub delay: Duration,
+    pub vetoer: HumanAddr,
+    // Receives clawed back funds, fixed per clawback when it is proposed
+    pub recipient: HumanAddr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClawbackStatus {
+    Pending,
+    Executed,
+    Vetoed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingClawback {
+    pub id: u64,
+    pub from: HumanAddr,
+    pub recipient: HumanAddr,
+    pub amount: Uint128,
+    pub reason: String,
+    pub proposer: HumanAddr,
+    pub proposed_height: u64,
+    pub proposed_time: u64,
+    pub delay: Duration,
+    pub status: ClawbackStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClawbacksResponse {
+    pub clawbacks: Vec<PendingClawback>,
+}
+
+pub fn set_clawback_config(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    config: ClawbackConfig,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    deps.api.addr_validate(config.recipient.as_str())?;
+
+    Singleton::new(deps.storage, CLAWBACK_CONFIG_KEY).save(&config)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_clawback_config").add_attribute("vetoer", config.vetoer).add_attribute("recipient", config.recipient))
+}
+
+pub fn propose_clawback(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    from: HumanAddr,
+    amount: Uint128,
+    reason: String,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_compliance_officer(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let config = ReadonlySingleton::<ClawbackConfig>::new(deps.storage, CLAWBACK_CONFIG_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Clawbacks are not configured"))?;
+    if reason.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err("A reason is required for clawbacks"));
+    }
+    if from.as_str() == env.contract.address.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Tokens held by the token contract can't be clawed back"));
+    }
+
+    let id = next_id(deps.storage, CLAWBACK_COUNT_KEY)?;
+    let clawback = PendingClawback {
+        id,
+        from: from.clone(),
+        recipient: config.recipient,
+        amount,
+        reason: reason.clone(),
+        proposer: HumanAddr::from(&info.sender),
+        proposed_height: env.block.height,
+        proposed_time: env.block.time.seconds(),
+        delay: config.delay,
+        status: ClawbackStatus::Pending,
+    };
+    Bucket::new(deps.storage, CLAWBACKS_PREFIX).save(&id.to_be_bytes(), &clawback)?;
+
+    let event = cosmwasm_std::Event::new("clawback_proposed")
+        .add_attribute("id", id.to_string())
+        .add_attribute("target", from)
+        .add_attribute("recipient", clawback.recipient)
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("reason", reason);
+    Ok(cosmwasm_std::Response::new().add_event(event).add_attribute("action", "clawback").add_attribute("id", id.to_string()))
+}
+
+pub fn execute_clawback(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_compliance_officer(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut clawback = Bucket::<PendingClawback>::new(deps.storage, CLAWBACKS_PREFIX).load(&id.to_be_bytes())?;
+    if clawback.status != ClawbackStatus::Pending {
+        return Err(cosmwasm_std::StdError::generic_err("Clawback is not pending"));
+    }
+    if !clawback.delay.has_elapsed(&env, clawback.proposed_height, clawback.proposed_time) {
+        return Err(cosmwasm_std::StdError::generic_err("Clawback delay has not elapsed"));
+    }
+
+    move_balance(deps.storage, &clawback.from, &clawback.recipient, clawback.amount)?;
+
+    clawback.status = ClawbackStatus::Executed;
+    Bucket::new(deps.storage, CLAWBACKS_PREFIX).save(&id.to_be_bytes(), &clawback)?;
+
+    let event = cosmwasm_std::Event::new("clawback_executed")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", clawback.from)
+        .add_attribute("to", clawback.recipient)
+        .add_attribute("amount", clawback.amount.to_string());
+    Ok(cosmwasm_std::Response::new().add_event(event).add_attribute("action", "execute_clawback").add_attribute("id", id.to_string()))
+}
+
+pub fn veto_clawback(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let config = ReadonlySingleton::<ClawbackConfig>::new(deps.storage, CLAWBACK_CONFIG_KEY).load()?;
+    if info.sender.as_str() != config.vetoer.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+
+    let mut clawback = Bucket::<PendingClawback>::new(deps.storage, CLAWBACKS_PREFIX).load(&id.to_be_bytes())?;
+    if clawback.status != ClawbackStatus::Pending {
+        return Err(cosmwasm_std::StdError::generic_err("Clawback is not pending"));
+    }
+    clawback.status = ClawbackStatus::Vetoed;
+    Bucket::new(deps.storage, CLAWBACKS_PREFIX).save(&id.to_be_bytes(), &clawback)?;
+
+    let event = cosmwasm_std::Event::new("clawback_vetoed").add_attribute("id", id.to_string()).add_attribute("vetoer", info.sender);
+    Ok(cosmwasm_std::Response::new().add_event(event).add_attribute("action", "veto_clawback").add_attribute("id", id.to_string()))
+}
+
+pub fn query_clawbacks(deps: cosmwasm_std::Deps, start_after: Option<u64>, limit: Option<u32>) -> cosmwasm_std::StdResult<ClawbacksResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| (id + 1).to_be_bytes().to_vec());
+
+    let clawbacks = ReadonlyBucket::<PendingClawback>::new(deps.storage, CLAWBACKS_PREFIX)
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, clawback)| clawback))
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    Ok(ClawbacksResponse { clawbacks })
+}
+
+pub const LARGE_TRANSFER_KEY: &[u8] = b"large_transfer";
+pub const ANNOUNCEMENT_COUNT_KEY: &[u8] = b"announcement_count";
+pub const ANNOUNCEMENTS_PREFIX: &[u8] = b"announcements";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LargeTransferConfig {
+    // Transfers above this amount must be announced first
+    pub threshold: Uint128,
+    pub delay: Duration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AnnouncedTransfer {
+    pub id: u64,
+    pub from: HumanAddr,
+    pub to: HumanAddr,
+    pub amount: Uint128,
+    pub announced_height: u64,
+    pub announced_time: u64,
+    // Copied from the config, so changing or removing it later doesn't shorten the wait
+    pub delay: Duration,
+}
+
+pub fn assert_not_large_transfer(storage: &dyn cosmwasm_std::Storage, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    if let Some(config) = ReadonlySingleton::<LargeTransferConfig>::new(storage, LARGE_TRANSFER_KEY).may_load()? {
+        if amount > config.threshold {
+            return Err(cosmwasm_std::StdError::generic_err("Transfers above the threshold must be announced first"));
+        }
+    }
+    Ok(())
+}
+
+pub fn set_large_transfer_config(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    config: Option<LargeTransferConfig>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<LargeTransferConfig>::new(deps.storage, LARGE_TRANSFER_KEY);
+    match &config {
+        Some(config) => singleton.save(config)?,
+        None => singleton.remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_large_transfer_config").add_attribute("enabled", config.is_some().to_string()))
+}
+
+pub fn announce_transfer(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipient: HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let config = ReadonlySingleton::<LargeTransferConfig>::new(deps.storage, LARGE_TRANSFER_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Large transfer announcements are not enabled"))?;
+
+    let id = next_id(deps.storage, ANNOUNCEMENT_COUNT_KEY)?;
+    let announcement = AnnouncedTransfer {
+        id,
+        from: HumanAddr::from(&info.sender),
+        to: recipient.clone(),
+        amount,
+        announced_height: env.block.height,
+        announced_time: env.block.time.seconds(),
+        delay: config.delay,
+    };
+    Bucket::new(deps.storage, ANNOUNCEMENTS_PREFIX).save(&id.to_be_bytes(), &announcement)?;
+
+    let event = cosmwasm_std::Event::new("transfer_announced")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", info.sender)
+        .add_attribute("to", recipient)
+        .add_attribute("amount", amount.to_string());
+    Ok(cosmwasm_std::Response::new().add_event(event).add_attribute("action", "announce_transfer").add_attribute("id", id.to_string()))
+}
+
+pub fn execute_announced_transfer(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let announcement = Bucket::<AnnouncedTransfer>::new(deps.storage, ANNOUNCEMENTS_PREFIX).load(&id.to_be_bytes())?;
+    if info.sender.as_str() != announcement.from.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+
+    if !announcement.delay.has_elapsed(&env, announcement.announced_height, announcement.announced_time) {
+        return Err(cosmwasm_std::StdError::generic_err("Announcement delay has not elapsed"));
+    }
+
+    assert_transfer_allowed(deps.as_ref(), &env, &announcement.from, &announcement.to, announcement.amount)?;
+    let events = record_transfer(deps.storage, &env, &announcement.from, &announcement.to, announcement.amount)?;
+    let outcome = settle_transfer(deps.storage, &env, &announcement.from, &announcement.to, announcement.amount)?;
+    Bucket::<AnnouncedTransfer>::new(deps.storage, ANNOUNCEMENTS_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_events(events).add_events(outcome.events).add_attribute("action", "transfer").add_attribute("from", announcement.from).add_attribute("to", announcement.to).add_attribute("amount", outcome.net.to_string()).add_attribute("fee", outcome.fee.to_string()).add_attribute("announcement", id.to_string()))
+}
+
+pub fn cancel_announced_transfer(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut bucket = Bucket::<AnnouncedTransfer>::new(deps.storage, ANNOUNCEMENTS_PREFIX);
+    let announcement = bucket.load(&id.to_be_bytes())?;
+    if info.sender.as_str() != announcement.from.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    bucket.remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "cancel_announced_transfer").add_attribute("id", id.to_string()))
+}
+
+pub const SELF_LOCK_PREFIX: &[u8] = b"self_lock";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AccountLockResponse {
+    pub until: Option<Expiration>,
+    pub locked: bool,
+}
+
+pub fn lock_my_account(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    until: Expiration,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if until.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Lock expiration must be in the future"));
+    }
+
+    let mut locks = Bucket::<Expiration>::new(deps.storage, SELF_LOCK_PREFIX);
+
+    // An active lock can only be extended, never shortened
+    if let Some(current) = locks.may_load(info.sender.as_bytes())? {
+        if !current.is_expired(&env) {
+            let extends = match (current, until) {
+                (Expiration::AtHeight(a), Expiration::AtHeight(b)) => b >= a,
+                (Expiration::AtTime(a), Expiration::AtTime(b)) => b >= a,
+                _ => false,
+            };
+            if !extends {
+                return Err(cosmwasm_std::StdError::generic_err("An active lock can only be extended"));
+            }
+        }
+    }
+    locks.save(info.sender.as_bytes(), &until)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "lock_my_account").add_attribute("address", info.sender))
+}
+
+pub fn query_account_lock(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, address: HumanAddr) -> cosmwasm_std::StdResult<AccountLockResponse> {
+    let until = ReadonlyBucket::<Expiration>::new(deps.storage, SELF_LOCK_PREFIX).may_load(address.as_bytes())?;
+    let locked = until.map(|u| !u.is_expired(&env)).unwrap_or(false);
+    Ok(AccountLockResponse { until, locked })
+}
+
+pub const DEAD_MAN_SWITCH_PREFIX: &[u8] = b"dead_man_switch";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DeadManSwitch {
+    pub beneficiary: HumanAddr,
+    pub inactivity_period: Duration,
+    pub last_active_height: u64,
+    pub last_active_time: u64,
+}
+
+pub fn set_beneficiary(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    beneficiary: HumanAddr,
+    inactivity_period: Duration,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if beneficiary.as_str() == info.sender.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Cannot designate yourself as beneficiary"));
+    }
+
+    let switch = DeadManSwitch {
+        beneficiary: beneficiary.clone(),
+        inactivity_period,
+        last_active_height: env.block.height,
+        last_active_time: env.block.time.seconds(),
+    };
+    Bucket::new(deps.storage, DEAD_MAN_SWITCH_PREFIX).save(info.sender.as_bytes(), &switch)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_beneficiary").add_attribute("account", info.sender).add_attribute("beneficiary", beneficiary))
+}
+
+pub fn remove_beneficiary(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    Bucket::<DeadManSwitch>::new(deps.storage, DEAD_MAN_SWITCH_PREFIX).remove(info.sender.as_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "remove_beneficiary").add_attribute("account", info.sender))
+}
+
+pub fn reset_inactivity_timer(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut switches = Bucket::<DeadManSwitch>::new(deps.storage, DEAD_MAN_SWITCH_PREFIX);
+    let mut switch = switches.load(info.sender.as_bytes())?;
+    switch.last_active_height = env.block.height;
+    switch.last_active_time = env.block.time.seconds();
+    switches.save(info.sender.as_bytes(), &switch)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "reset_inactivity_timer").add_attribute("account", info.sender))
+}
+
+pub fn claim_inheritance(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    from: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let switch = Bucket::<DeadManSwitch>::new(deps.storage, DEAD_MAN_SWITCH_PREFIX).load(from.as_bytes())?;
+    if info.sender.as_str() != switch.beneficiary.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    if !switch.inactivity_period.has_elapsed(&env, switch.last_active_height, switch.last_active_time) {
+        return Err(cosmwasm_std::StdError::generic_err("Account has not been inactive long enough"));
+    }
+
+    // Vesting and lockups stay with the account, the switch is kept until they have
+    // unlocked so the rest can be claimed then
+    let locked = locked_balance(deps.storage, &env, &from)?;
+    let amount = balance_of(deps.storage, &from)?.checked_sub(locked).unwrap_or_default();
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Nothing unlocked to claim"));
+    }
+    let heir = HumanAddr::from(&info.sender);
+    assert_transfer_allowed(deps.as_ref(), &env, &from, &heir, amount)?;
+    let events = record_transfer(deps.storage, &env, &from, &heir, amount)?;
+    move_balance(deps.storage, &from, &heir, amount)?;
+    if locked.is_zero() {
+        Bucket::<DeadManSwitch>::new(deps.storage, DEAD_MAN_SWITCH_PREFIX).remove(from.as_bytes());
+    } else {
+        // The claim itself doesn't count as activity of the account
+        Bucket::new(deps.storage, DEAD_MAN_SWITCH_PREFIX).save(from.as_bytes(), &switch)?;
+    }
+
+    Ok(cosmwasm_std::Response::new().add_events(events).add_attribute("action", "claim_inheritance").add_attribute("from", from).add_attribute("to", info.sender).add_attribute("amount", amount.to_string()))
+}
+
+pub const MIN_TRANSFER_KEY: &[u8] = b"min_transfer";
+
+// Rejects dust transfers below the configured threshold
+pub fn assert_min_transfer(storage: &dyn cosmwasm_std::Storage, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    if let Some(min) = ReadonlySingleton::<Uint128>::new(storage, MIN_TRANSFER_KEY).may_load()? {
+        if amount < min {
+            return Err(cosmwasm_std::StdError::generic_err(format!("Transfer amount is below the minimum of {}", min)));
+        }
+    }
+    Ok(())
+}
+
+pub fn set_min_transfer_amount(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    min: Option<Uint128>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<Uint128>::new(deps.storage, MIN_TRANSFER_KEY);
+    match min {
+        Some(min) => singleton.save(&min)?,
+        None => singleton.remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_min_transfer_amount").add_attribute("min", min.map(|m| m.to_string()).unwrap_or_else(|| "none".to_string())))
+}
+
+pub const USD_LIMITS_KEY: &[u8] = b"usd_limits";
+
+// Limits in USD, converted with the oracle price at the time of each transfer. Amounts
+// are in the oracle's USD units, usually micro USD.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UsdLimits {
+    pub oracle: HumanAddr,
+    pub max_transaction_usd: Option<Uint128>,
+    // Counted over the epochs of the daily limit, which has to be configured as well
+    pub daily_limit_usd: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceOracleQueryMsg {
+    Price { asset: HumanAddr },
+}
+
+// USD units per base unit of the asset
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PriceResponse {
+    pub price: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UsdLimitsResponse {
+    pub limits: Option<UsdLimits>,
+    pub price: Option<Decimal>,
+}
+
+pub fn query_usd_price(deps: cosmwasm_std::Deps, env: &cosmwasm_std::Env, oracle: &HumanAddr) -> cosmwasm_std::StdResult<Decimal> {
+    let query = PriceOracleQueryMsg::Price { asset: HumanAddr::from(env.contract.address.as_str()) };
+    let res: PriceResponse = deps.querier.query_wasm_smart(oracle.as_str(), &query)?;
+    Ok(res.price)
+}
+
+pub fn assert_usd_limits(deps: cosmwasm_std::Deps, env: &cosmwasm_std::Env, from: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    let limits = match ReadonlySingleton::<UsdLimits>::new(deps.storage, USD_LIMITS_KEY).may_load()? {
+        Some(limits) => limits,
+        None => return Ok(()),
+    };
+    let price = query_usd_price(deps, env, &limits.oracle)?;
+    if let Some(max) = limits.max_transaction_usd {
+        if amount * price > max && !is_limit_exempt(deps.storage, &LimitKind::MaxTransaction, from)? {
+            return Err(cosmwasm_std::StdError::generic_err(format!("Transfer value exceeds the maximum of {} USD", max)));
+        }
+    }
+    if let Some(max) = limits.daily_limit_usd {
+        let daily = ReadonlySingleton::<DailyLimit>::new(deps.storage, DAILY_LIMIT_KEY).load()?;
+        let outflow = current_outflow(deps.storage, &daily, env, from)?;
+        if outflow.amount.checked_add(amount)? * price > max {
+            return Err(cosmwasm_std::StdError::generic_err("Daily USD transfer limit exceeded"));
+        }
+    }
+    Ok(())
+}
+
+pub fn set_usd_limits(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    limits: Option<UsdLimits>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    match limits {
+        Some(limits) => {
+            if limits.daily_limit_usd.is_some() && ReadonlySingleton::<DailyLimit>::new(deps.storage, DAILY_LIMIT_KEY).may_load()?.is_none() {
+                return Err(cosmwasm_std::StdError::generic_err("A daily USD limit needs the daily limit to be configured"));
+            }
+            // Fails early on an oracle that doesn't answer for this token
+            query_usd_price(deps.as_ref(), &env, &limits.oracle)?;
+            Singleton::new(deps.storage, USD_LIMITS_KEY).save(&limits)?;
+        }
+        None => Singleton::<UsdLimits>::new(deps.storage, USD_LIMITS_KEY).remove(),
+    }
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_usd_limits"))
+}
+
+pub fn query_usd_limits(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env) -> cosmwasm_std::StdResult<UsdLimitsResponse> {
+    let limits = ReadonlySingleton::<UsdLimits>::new(deps.storage, USD_LIMITS_KEY).may_load()?;
+    let price = match &limits {
+        Some(limits) => Some(query_usd_price(deps, &env, &limits.oracle)?),
+        None => None,
+    };
+    Ok(UsdLimitsResponse { limits, price })
+}
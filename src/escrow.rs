@@ -0,0 +1,1291 @@
+use cosmwasm_std::{HumanAddr, Uint128};
+use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DEFAULT_LIMIT, Expiration, MAX_BATCH_SIZE, MAX_LIMIT, SELF_LOCK_PREFIX, SignatureDomain,
+    TransferOutcome, assert_not_blacklisted, assert_not_large_transfer, assert_not_sanctioned,
+    assert_pubkey_matches, assert_transfer_allowed, assert_unlocked, cw20_transfer_msg, is_frozen,
+    is_paused, move_balance, next_id, record_transfer, sanctions_config, settle_transfer,
+    transfer_hook_msgs, validate_memo, verify_signed,
+};
+
+pub const ESCROWED_TOTAL_KEY: &[u8] = b"escrowed_total";
+
+// Tokens held on the contract address on behalf of other modules, which must never be recovered
+pub fn escrowed_total(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Uint128> {
+    Ok(ReadonlySingleton::<Uint128>::new(storage, ESCROWED_TOTAL_KEY).may_load()?.unwrap_or_else(Uint128::zero))
+}
+
+pub fn add_escrowed(storage: &mut dyn cosmwasm_std::Storage, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    let total = escrowed_total(storage)?.checked_add(amount)?;
+    Singleton::new(storage, ESCROWED_TOTAL_KEY).save(&total)
+}
+
+pub fn sub_escrowed(storage: &mut dyn cosmwasm_std::Storage, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    let total = escrowed_total(storage)?.checked_sub(amount)?;
+    Singleton::new(storage, ESCROWED_TOTAL_KEY).save(&total)
+}
+
+// Sender-side checks for moving tokens into one of the contract's escrow modules
+pub fn assert_escrow_allowed(
+    deps: cosmwasm_std::Deps,
+    env: &cosmwasm_std::Env,
+    from: &HumanAddr,
+    to: &HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<()> {
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    assert_not_blacklisted(deps.storage, from)?;
+    assert_not_blacklisted(deps.storage, to)?;
+    if is_frozen(deps.storage, from)? {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Account {} is frozen", from)));
+    }
+    if let Some(until) = ReadonlyBucket::<Expiration>::new(deps.storage, SELF_LOCK_PREFIX).may_load(from.as_bytes())? {
+        if !until.is_expired(env) {
+            return Err(cosmwasm_std::StdError::generic_err(format!("Account {} is locked by its owner", from)));
+        }
+    }
+    assert_unlocked(deps.storage, env, from, amount)
+}
+
+// Moves tokens onto the contract address and marks them as escrowed
+pub fn escrow_in(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, from: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    move_balance(storage, from, &HumanAddr::from(env.contract.address.as_str()), amount)?;
+    add_escrowed(storage, amount)
+}
+
+pub fn escrow_out(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, to: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    sub_escrowed(storage, amount)?;
+    move_balance(storage, &HumanAddr::from(env.contract.address.as_str()), to, amount)
+}
+
+// Escrows a transfer to a known recipient. Restrictions, trackers and fees apply here
+// once, the escrowed amount is what is left after fees.
+pub fn escrow_transfer_in(
+    deps: &mut cosmwasm_std::DepsMut,
+    env: &cosmwasm_std::Env,
+    from: &HumanAddr,
+    to: &HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<TransferOutcome> {
+    assert_not_large_transfer(deps.storage, amount)?;
+    assert_transfer_allowed(deps.as_ref(), env, from, to, amount)?;
+    let mut events = record_transfer(deps.storage, env, from, to, amount)?;
+
+    let mut outcome = settle_transfer(deps.storage, env, from, &HumanAddr::from(env.contract.address.as_str()), amount)?;
+    add_escrowed(deps.storage, outcome.net)?;
+    events.append(&mut outcome.events);
+    outcome.events = events;
+    Ok(outcome)
+}
+
+// Checked again when tokens from `escrow_transfer_in` are released, the recipient may
+// have been listed in the meantime
+pub fn assert_release_allowed(deps: cosmwasm_std::Deps, to: &HumanAddr) -> cosmwasm_std::StdResult<()> {
+    assert_not_blacklisted(deps.storage, to)?;
+    if let Some(sanctions) = sanctions_config(deps.storage)? {
+        if sanctions.enabled {
+            assert_not_sanctioned(deps, &sanctions.oracle, to)?;
+        }
+    }
+    Ok(())
+}
+
+pub const STREAM_COUNT_KEY: &[u8] = b"stream_count";
+pub const STREAMS_PREFIX: &[u8] = b"streams";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Stream {
+    pub id: u64,
+    pub sender: HumanAddr,
+    pub recipient: HumanAddr,
+    pub amount: Uint128,
+    // Times in seconds
+    pub start: u64,
+    pub end: u64,
+    pub withdrawn: Uint128,
+}
+
+impl Stream {
+    pub fn streamed(&self, now: u64) -> Uint128 {
+        if now <= self.start {
+            Uint128::zero()
+        } else if now >= self.end {
+            self.amount
+        } else {
+            self.amount.multiply_ratio(now - self.start, self.end - self.start)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StreamResponse {
+    pub stream: Stream,
+    pub withdrawable: Uint128,
+}
+
+pub fn create_stream(
+    mut deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipient: HumanAddr,
+    amount: Uint128,
+    start: u64,
+    end: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if end <= start || amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Invalid stream"));
+    }
+    let outcome = escrow_transfer_in(&mut deps, &env, &HumanAddr::from(&info.sender), &recipient, amount)?;
+
+    let id = next_id(deps.storage, STREAM_COUNT_KEY)?;
+    let stream = Stream { id, sender: HumanAddr::from(&info.sender), recipient: recipient.clone(), amount: outcome.net, start, end, withdrawn: Uint128::zero() };
+    Bucket::new(deps.storage, STREAMS_PREFIX).save(&id.to_be_bytes(), &stream)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_events(outcome.events)
+        .add_attribute("action", "create_stream")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", info.sender)
+        .add_attribute("to", recipient)
+        .add_attribute("amount", outcome.net.to_string())
+        .add_attribute("fee", outcome.fee.to_string()))
+}
+
+pub fn withdraw_stream(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut stream = Bucket::<Stream>::new(deps.storage, STREAMS_PREFIX).load(&id.to_be_bytes())?;
+    if info.sender.as_str() != stream.recipient.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    assert_release_allowed(deps.as_ref(), &stream.recipient)?;
+
+    let amount = stream.streamed(env.block.time.seconds()).checked_sub(stream.withdrawn)?;
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Nothing to withdraw"));
+    }
+    escrow_out(deps.storage, &env, &stream.recipient, amount)?;
+
+    stream.withdrawn = stream.withdrawn.checked_add(amount)?;
+    if stream.withdrawn == stream.amount {
+        Bucket::<Stream>::new(deps.storage, STREAMS_PREFIX).remove(&id.to_be_bytes());
+    } else {
+        Bucket::new(deps.storage, STREAMS_PREFIX).save(&id.to_be_bytes(), &stream)?;
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "withdraw_stream").add_attribute("id", id.to_string()).add_attribute("amount", amount.to_string()))
+}
+
+pub fn cancel_stream(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let stream = Bucket::<Stream>::new(deps.storage, STREAMS_PREFIX).load(&id.to_be_bytes())?;
+    if info.sender.as_str() != stream.sender.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+
+    // The recipient keeps what has already streamed, the sender gets the rest back
+    let streamed = stream.streamed(env.block.time.seconds());
+    let owed = streamed.checked_sub(stream.withdrawn)?;
+    let refund = stream.amount.checked_sub(streamed)?;
+    if !owed.is_zero() {
+        assert_release_allowed(deps.as_ref(), &stream.recipient)?;
+        escrow_out(deps.storage, &env, &stream.recipient, owed)?;
+    }
+    if !refund.is_zero() {
+        escrow_out(deps.storage, &env, &stream.sender, refund)?;
+    }
+    Bucket::<Stream>::new(deps.storage, STREAMS_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "cancel_stream").add_attribute("id", id.to_string()).add_attribute("paid", owed.to_string()).add_attribute("refunded", refund.to_string()))
+}
+
+pub fn query_stream(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, id: u64) -> cosmwasm_std::StdResult<StreamResponse> {
+    let stream = ReadonlyBucket::<Stream>::new(deps.storage, STREAMS_PREFIX).load(&id.to_be_bytes())?;
+    let withdrawable = stream.streamed(env.block.time.seconds()).checked_sub(stream.withdrawn)?;
+    Ok(StreamResponse { stream, withdrawable })
+}
+
+pub const ESCROW_COUNT_KEY: &[u8] = b"escrow_count";
+pub const ESCROWS_PREFIX: &[u8] = b"escrows";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Escrow {
+    pub id: u64,
+    pub depositor: HumanAddr,
+    pub recipient: HumanAddr,
+    pub arbiter: HumanAddr,
+    pub amount: Uint128,
+    pub expires: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EscrowsResponse {
+    pub escrows: Vec<Escrow>,
+}
+
+pub fn create_escrow(
+    mut deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipient: HumanAddr,
+    arbiter: HumanAddr,
+    amount: Uint128,
+    expires: Expiration,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if expires.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Escrow expiration must be in the future"));
+    }
+    let outcome = escrow_transfer_in(&mut deps, &env, &HumanAddr::from(&info.sender), &recipient, amount)?;
+
+    let id = next_id(deps.storage, ESCROW_COUNT_KEY)?;
+    let escrow = Escrow { id, depositor: HumanAddr::from(&info.sender), recipient: recipient.clone(), arbiter, amount: outcome.net, expires };
+    Bucket::new(deps.storage, ESCROWS_PREFIX).save(&id.to_be_bytes(), &escrow)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_events(outcome.events)
+        .add_attribute("action", "create_escrow")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", info.sender)
+        .add_attribute("to", recipient)
+        .add_attribute("amount", outcome.net.to_string())
+        .add_attribute("fee", outcome.fee.to_string()))
+}
+
+pub fn approve_escrow(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let escrow = Bucket::<Escrow>::new(deps.storage, ESCROWS_PREFIX).load(&id.to_be_bytes())?;
+    if info.sender.as_str() != escrow.arbiter.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    if escrow.expires.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Escrow has expired"));
+    }
+    assert_release_allowed(deps.as_ref(), &escrow.recipient)?;
+
+    escrow_out(deps.storage, &env, &escrow.recipient, escrow.amount)?;
+    Bucket::<Escrow>::new(deps.storage, ESCROWS_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "approve_escrow").add_attribute("id", id.to_string()).add_attribute("to", escrow.recipient).add_attribute("amount", escrow.amount.to_string()))
+}
+
+// The arbiter can refund at any time, anyone else only once the escrow has expired
+pub fn refund_escrow(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let escrow = Bucket::<Escrow>::new(deps.storage, ESCROWS_PREFIX).load(&id.to_be_bytes())?;
+    if info.sender.as_str() != escrow.arbiter.as_str() && !escrow.expires.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Escrow has not expired"));
+    }
+
+    escrow_out(deps.storage, &env, &escrow.depositor, escrow.amount)?;
+    Bucket::<Escrow>::new(deps.storage, ESCROWS_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "refund_escrow").add_attribute("id", id.to_string()).add_attribute("to", escrow.depositor).add_attribute("amount", escrow.amount.to_string()))
+}
+
+pub fn query_escrows(deps: cosmwasm_std::Deps, start_after: Option<u64>, limit: Option<u32>) -> cosmwasm_std::StdResult<EscrowsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| (id + 1).to_be_bytes().to_vec());
+
+    let escrows = ReadonlyBucket::<Escrow>::new(deps.storage, ESCROWS_PREFIX)
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, escrow)| escrow))
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    Ok(EscrowsResponse { escrows })
+}
+
+pub const INVOICE_COUNT_KEY: &[u8] = b"invoice_count";
+pub const INVOICES_PREFIX: &[u8] = b"invoices";
+pub const MAX_MEMO_LENGTH: usize = 256;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Open,
+    Approved,
+    Paid,
+    Cancelled,
+    Expired,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Invoice {
+    pub id: u64,
+    pub payee: HumanAddr,
+    pub payer: HumanAddr,
+    pub amount: Uint128,
+    pub memo: Option<String>,
+    pub expires: Expiration,
+    pub approved: bool,
+    pub paid: bool,
+    pub cancelled: bool,
+}
+
+impl Invoice {
+    pub fn status(&self, env: &cosmwasm_std::Env) -> InvoiceStatus {
+        if self.paid {
+            InvoiceStatus::Paid
+        } else if self.cancelled {
+            InvoiceStatus::Cancelled
+        } else if self.expires.is_expired(env) {
+            InvoiceStatus::Expired
+        } else if self.approved {
+            InvoiceStatus::Approved
+        } else {
+            InvoiceStatus::Open
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvoiceResponse {
+    pub invoice: Invoice,
+    pub status: InvoiceStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InvoicesResponse {
+    pub invoices: Vec<InvoiceResponse>,
+}
+
+pub(crate) fn load_invoice(storage: &dyn cosmwasm_std::Storage, id: u64) -> cosmwasm_std::StdResult<Invoice> {
+    ReadonlyBucket::<Invoice>::new(storage, INVOICES_PREFIX).load(&id.to_be_bytes())
+}
+
+pub fn create_invoice(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    payer: HumanAddr,
+    amount: Uint128,
+    memo: Option<String>,
+    expires: Expiration,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+    if expires.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Invoice expiration must be in the future"));
+    }
+    validate_memo(&memo)?;
+
+    let id = next_id(deps.storage, INVOICE_COUNT_KEY)?;
+    let invoice = Invoice {
+        id,
+        payee: HumanAddr::from(info.sender.as_str()),
+        payer: payer.clone(),
+        amount,
+        memo,
+        expires,
+        approved: false,
+        paid: false,
+        cancelled: false,
+    };
+    Bucket::new(deps.storage, INVOICES_PREFIX).save(&id.to_be_bytes(), &invoice)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "create_invoice").add_attribute("id", id.to_string()).add_attribute("payee", info.sender).add_attribute("payer", payer).add_attribute("amount", amount.to_string()))
+}
+
+// Approving only authorizes the pull, the tokens stay with the payer until collected
+pub fn approve_invoice(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut invoice = load_invoice(deps.storage, id)?;
+    if info.sender.as_str() != invoice.payer.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    if invoice.status(&env) != InvoiceStatus::Open {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Invoice is {:?}", invoice.status(&env))));
+    }
+    invoice.approved = true;
+    Bucket::new(deps.storage, INVOICES_PREFIX).save(&id.to_be_bytes(), &invoice)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "approve_invoice").add_attribute("id", id.to_string()))
+}
+
+pub fn collect_invoice(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut invoice = load_invoice(deps.storage, id)?;
+    if info.sender.as_str() != invoice.payee.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    if invoice.status(&env) != InvoiceStatus::Approved {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Invoice is {:?}", invoice.status(&env))));
+    }
+    invoice.paid = true;
+    Bucket::new(deps.storage, INVOICES_PREFIX).save(&id.to_be_bytes(), &invoice)?;
+
+    let (payer, payee, amount) = (invoice.payer, invoice.payee, invoice.amount);
+    assert_not_large_transfer(deps.storage, amount)?;
+    assert_transfer_allowed(deps.as_ref(), &env, &payer, &payee, amount)?;
+    let events = record_transfer(deps.storage, &env, &payer, &payee, amount)?;
+    let outcome = settle_transfer(deps.storage, &env, &payer, &payee, amount)?;
+    let (before, after) = transfer_hook_msgs(deps.storage, &payer, &payee, outcome.net)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_submessages(before)
+        .add_submessages(after)
+        .add_events(events)
+        .add_events(outcome.events)
+        .add_attribute("action", "collect_invoice")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", payer)
+        .add_attribute("to", payee)
+        .add_attribute("amount", outcome.net.to_string())
+        .add_attribute("fee", outcome.fee.to_string()))
+}
+
+// Either party can cancel an invoice that hasn't been paid
+pub fn cancel_invoice(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut invoice = load_invoice(deps.storage, id)?;
+    if info.sender.as_str() != invoice.payee.as_str() && info.sender.as_str() != invoice.payer.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    if invoice.paid || invoice.cancelled {
+        return Err(cosmwasm_std::StdError::generic_err("Invoice is already settled"));
+    }
+    invoice.cancelled = true;
+    Bucket::new(deps.storage, INVOICES_PREFIX).save(&id.to_be_bytes(), &invoice)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "cancel_invoice").add_attribute("id", id.to_string()).add_attribute("by", info.sender))
+}
+
+pub fn query_invoice(deps: cosmwasm_std::Deps, env: &cosmwasm_std::Env, id: u64) -> cosmwasm_std::StdResult<InvoiceResponse> {
+    let invoice = load_invoice(deps.storage, id)?;
+    Ok(InvoiceResponse { status: invoice.status(env), invoice })
+}
+
+pub fn query_invoices(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, start_after: Option<u64>, limit: Option<u32>) -> cosmwasm_std::StdResult<InvoicesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| (id + 1).to_be_bytes().to_vec());
+
+    let invoices = ReadonlyBucket::<Invoice>::new(deps.storage, INVOICES_PREFIX)
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, invoice)| InvoiceResponse { status: invoice.status(&env), invoice }))
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    Ok(InvoicesResponse { invoices })
+}
+
+pub const RECURRING_ALLOWANCES_PREFIX: &[u8] = b"recurring_allowances";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RecurringAllowanceMsg {
+    // Maximum pulled per period
+    pub amount: Uint128,
+    pub period_seconds: u64,
+}
+
+// Periods are counted from `start`, `spent` belongs to period number `period`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RecurringAllowance {
+    pub amount: Uint128,
+    pub period_seconds: u64,
+    pub start: u64,
+    pub period: u64,
+    pub spent: Uint128,
+}
+
+impl RecurringAllowance {
+    fn current_period(&self, env: &cosmwasm_std::Env) -> u64 {
+        env.block.time.seconds().saturating_sub(self.start) / self.period_seconds
+    }
+
+    pub fn spent_now(&self, env: &cosmwasm_std::Env) -> Uint128 {
+        if self.current_period(env) == self.period {
+            self.spent
+        } else {
+            Uint128::zero()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RecurringAllowanceResponse {
+    pub allowance: Option<RecurringAllowance>,
+    pub available: Uint128,
+    pub resets_at: Option<u64>,
+}
+
+pub(crate) fn spend_recurring_allowance(
+    storage: &mut dyn cosmwasm_std::Storage,
+    env: &cosmwasm_std::Env,
+    owner: &HumanAddr,
+    spender: &HumanAddr,
+    mut recurring: RecurringAllowance,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<()> {
+    let spent = recurring.spent_now(env).checked_add(amount)?;
+    if spent > recurring.amount {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Recurring allowance exceeded, {} left this period", recurring.amount.saturating_sub(recurring.spent_now(env)))));
+    }
+    recurring.period = recurring.current_period(env);
+    recurring.spent = spent;
+    Bucket::multilevel(storage, &[RECURRING_ALLOWANCES_PREFIX, owner.as_bytes()]).save(spender.as_bytes(), &recurring)
+}
+
+// Replacing a recurring allowance starts a fresh first period
+pub fn set_recurring_allowance(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    spender: HumanAddr,
+    allowance: Option<RecurringAllowanceMsg>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut bucket = Bucket::<RecurringAllowance>::multilevel(deps.storage, &[RECURRING_ALLOWANCES_PREFIX, info.sender.as_bytes()]);
+    match allowance {
+        Some(msg) => {
+            if msg.period_seconds == 0 {
+                return Err(cosmwasm_std::StdError::generic_err("Period must be positive"));
+            }
+            let recurring = RecurringAllowance { amount: msg.amount, period_seconds: msg.period_seconds, start: env.block.time.seconds(), period: 0, spent: Uint128::zero() };
+            bucket.save(spender.as_bytes(), &recurring)?;
+        }
+        None => bucket.remove(spender.as_bytes()),
+    }
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_recurring_allowance").add_attribute("owner", info.sender).add_attribute("spender", spender))
+}
+
+pub fn query_recurring_allowance(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, owner: HumanAddr, spender: HumanAddr) -> cosmwasm_std::StdResult<RecurringAllowanceResponse> {
+    let allowance = ReadonlyBucket::<RecurringAllowance>::multilevel(deps.storage, &[RECURRING_ALLOWANCES_PREFIX, owner.as_bytes()]).may_load(spender.as_bytes())?;
+    Ok(match allowance {
+        Some(a) => RecurringAllowanceResponse {
+            available: a.amount.saturating_sub(a.spent_now(&env)),
+            resets_at: Some(a.start + (a.current_period(&env) + 1) * a.period_seconds),
+            allowance: Some(a),
+        },
+        None => RecurringAllowanceResponse { allowance: None, available: Uint128::zero(), resets_at: None },
+    })
+}
+
+pub const HTLC_COUNT_KEY: &[u8] = b"htlc_count";
+pub const HTLCS_PREFIX: &[u8] = b"htlcs";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Htlc {
+    pub id: u64,
+    pub sender: HumanAddr,
+    pub recipient: HumanAddr,
+    pub amount: Uint128,
+    // Hex encoded sha256 of the preimage
+    pub hash: String,
+    pub timeout: Expiration,
+}
+
+// Locks tokens for `recipient` until the preimage of `hash` is revealed or `timeout` passes
+pub fn lock_htlc(
+    mut deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipient: HumanAddr,
+    amount: Uint128,
+    hash: String,
+    timeout: Expiration,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if timeout.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Timeout must be in the future"));
+    }
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+    let mut hash_bytes = [0u8; 32];
+    hex::decode_to_slice(&hash, &mut hash_bytes).map_err(|_| cosmwasm_std::StdError::generic_err("Hash must be a hex encoded sha256"))?;
+    let outcome = escrow_transfer_in(&mut deps, &env, &HumanAddr::from(&info.sender), &recipient, amount)?;
+
+    let id = next_id(deps.storage, HTLC_COUNT_KEY)?;
+    let htlc = Htlc { id, sender: HumanAddr::from(&info.sender), recipient: recipient.clone(), amount: outcome.net, hash: hex::encode(hash_bytes), timeout };
+    Bucket::new(deps.storage, HTLCS_PREFIX).save(&id.to_be_bytes(), &htlc)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_events(outcome.events)
+        .add_attribute("action", "lock_htlc")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", info.sender)
+        .add_attribute("to", recipient)
+        .add_attribute("amount", outcome.net.to_string())
+        .add_attribute("fee", outcome.fee.to_string())
+        .add_attribute("hash", htlc.hash))
+}
+
+// Anyone can submit the preimage, the tokens always go to the recipient. The preimage
+// is emitted so the counterparty of a swap can use it on the other side.
+pub fn claim_htlc(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    _info: cosmwasm_std::MessageInfo,
+    id: u64,
+    preimage: cosmwasm_std::Binary,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    use sha2::Digest;
+    let htlc = ReadonlyBucket::<Htlc>::new(deps.storage, HTLCS_PREFIX).load(&id.to_be_bytes())?;
+    if htlc.timeout.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("HTLC has timed out"));
+    }
+    if hex::encode(sha2::Sha256::digest(preimage.as_slice())) != htlc.hash {
+        return Err(cosmwasm_std::StdError::generic_err("Invalid preimage"));
+    }
+    assert_release_allowed(deps.as_ref(), &htlc.recipient)?;
+
+    escrow_out(deps.storage, &env, &htlc.recipient, htlc.amount)?;
+    Bucket::<Htlc>::new(deps.storage, HTLCS_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "claim_htlc")
+        .add_attribute("id", id.to_string())
+        .add_attribute("to", htlc.recipient)
+        .add_attribute("amount", htlc.amount.to_string())
+        .add_attribute("preimage", hex::encode(preimage.as_slice())))
+}
+
+pub fn refund_htlc(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    _info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let htlc = ReadonlyBucket::<Htlc>::new(deps.storage, HTLCS_PREFIX).load(&id.to_be_bytes())?;
+    if !htlc.timeout.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("HTLC has not timed out"));
+    }
+
+    escrow_out(deps.storage, &env, &htlc.sender, htlc.amount)?;
+    Bucket::<Htlc>::new(deps.storage, HTLCS_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "refund_htlc").add_attribute("id", id.to_string()).add_attribute("to", htlc.sender).add_attribute("amount", htlc.amount.to_string()))
+}
+
+pub const TRADE_COUNT_KEY: &[u8] = b"trade_count";
+pub const TRADES_PREFIX: &[u8] = b"trades";
+
+// The maker's tokens are escrowed, the ask is paid in another cw20 by Sending it with `FillTrade`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Trade {
+    pub id: u64,
+    pub maker: HumanAddr,
+    pub amount: Uint128,
+    pub ask_token: HumanAddr,
+    pub ask_amount: Uint128,
+    pub expires: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TradesResponse {
+    pub trades: Vec<Trade>,
+}
+
+pub fn create_trade(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    amount: Uint128,
+    ask_token: HumanAddr,
+    ask_amount: Uint128,
+    expires: Expiration,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if amount.is_zero() || ask_amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Trade amounts must be greater than zero"));
+    }
+    if ask_token.as_str() == env.contract.address.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Ask token must be another cw20"));
+    }
+    if expires.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Trade expiration must be in the future"));
+    }
+    let contract = HumanAddr::from(env.contract.address.as_str());
+    assert_escrow_allowed(deps.as_ref(), &env, &HumanAddr::from(&info.sender), &contract, amount)?;
+    escrow_in(deps.storage, &env, &HumanAddr::from(&info.sender), amount)?;
+
+    let id = next_id(deps.storage, TRADE_COUNT_KEY)?;
+    let trade = Trade { id, maker: HumanAddr::from(&info.sender), amount, ask_token: ask_token.clone(), ask_amount, expires };
+    Bucket::new(deps.storage, TRADES_PREFIX).save(&id.to_be_bytes(), &trade)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "create_trade")
+        .add_attribute("id", id.to_string())
+        .add_attribute("maker", info.sender)
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("ask", format!("{}{}", ask_amount, ask_token)))
+}
+
+// Both legs settle in the same transaction, a wrong token or amount reverts the Send
+pub fn fill_trade(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    token: HumanAddr,
+    taker: HumanAddr,
+    amount: Uint128,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let trade = ReadonlyBucket::<Trade>::new(deps.storage, TRADES_PREFIX).load(&id.to_be_bytes())?;
+    if trade.expires.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Trade has expired"));
+    }
+    if token != trade.ask_token || amount != trade.ask_amount {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Trade asks exactly {}{}", trade.ask_amount, trade.ask_token)));
+    }
+    assert_not_blacklisted(deps.storage, &taker)?;
+
+    escrow_out(deps.storage, &env, &taker, trade.amount)?;
+    Bucket::<Trade>::new(deps.storage, TRADES_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new()
+        .add_message(cw20_transfer_msg(&trade.ask_token, &trade.maker, trade.ask_amount)?)
+        .add_attribute("action", "fill_trade")
+        .add_attribute("id", id.to_string())
+        .add_attribute("maker", trade.maker)
+        .add_attribute("taker", taker)
+        .add_attribute("amount", trade.amount.to_string())
+        .add_attribute("paid", format!("{}{}", trade.ask_amount, trade.ask_token)))
+}
+
+// The maker can cancel at any time, anyone else only once the trade has expired
+pub fn cancel_trade(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let trade = ReadonlyBucket::<Trade>::new(deps.storage, TRADES_PREFIX).load(&id.to_be_bytes())?;
+    if info.sender.as_str() != trade.maker.as_str() && !trade.expires.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Trade has not expired"));
+    }
+
+    escrow_out(deps.storage, &env, &trade.maker, trade.amount)?;
+    Bucket::<Trade>::new(deps.storage, TRADES_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "cancel_trade").add_attribute("id", id.to_string()).add_attribute("to", trade.maker).add_attribute("amount", trade.amount.to_string()))
+}
+
+pub fn query_trades(deps: cosmwasm_std::Deps, start_after: Option<u64>, limit: Option<u32>) -> cosmwasm_std::StdResult<TradesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| (id + 1).to_be_bytes().to_vec());
+
+    let trades = ReadonlyBucket::<Trade>::new(deps.storage, TRADES_PREFIX)
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, trade)| trade))
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    Ok(TradesResponse { trades })
+}
+
+pub const PAYMENT_CHANNEL_COUNT_KEY: &[u8] = b"payment_channel_count";
+pub const PAYMENT_CHANNELS_PREFIX: &[u8] = b"payment_channels";
+
+// One-way channel: the funder signs states paying the counterparty a growing total
+// off-chain, the latest state submitted on-chain decides the split on close
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PaymentChannel {
+    pub id: u64,
+    pub funder: HumanAddr,
+    pub funder_pubkey: cosmwasm_std::Binary,
+    pub counterparty: HumanAddr,
+    pub deposit: Uint128,
+    // Seconds the counterparty has to answer a close started by the funder
+    pub challenge_period: u64,
+    pub nonce: u64,
+    pub paid: Uint128,
+    pub closes_at: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PaymentChannelState {
+    pub domain: SignatureDomain,
+    pub channel_id: u64,
+    pub nonce: u64,
+    pub paid: Uint128,
+}
+
+pub(crate) fn load_payment_channel(storage: &dyn cosmwasm_std::Storage, id: u64) -> cosmwasm_std::StdResult<PaymentChannel> {
+    ReadonlyBucket::<PaymentChannel>::new(storage, PAYMENT_CHANNELS_PREFIX).load(&id.to_be_bytes())
+}
+
+pub fn open_payment_channel(
+    mut deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    counterparty: HumanAddr,
+    amount: Uint128,
+    challenge_period: u64,
+    pubkey: cosmwasm_std::Binary,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+    if challenge_period == 0 {
+        return Err(cosmwasm_std::StdError::generic_err("Challenge period must be positive"));
+    }
+    let funder = HumanAddr::from(info.sender.as_str());
+    if funder == counterparty {
+        return Err(cosmwasm_std::StdError::generic_err("Cannot open a channel with yourself"));
+    }
+    assert_pubkey_matches(deps.api, &funder, &pubkey)?;
+    // Checked as a transfer of the whole deposit, the payout only checks the counterparty again
+    let outcome = escrow_transfer_in(&mut deps, &env, &funder, &counterparty, amount)?;
+
+    let id = next_id(deps.storage, PAYMENT_CHANNEL_COUNT_KEY)?;
+    let channel = PaymentChannel {
+        id,
+        funder: funder.clone(),
+        funder_pubkey: pubkey,
+        counterparty: counterparty.clone(),
+        deposit: outcome.net,
+        challenge_period,
+        nonce: 0,
+        paid: Uint128::zero(),
+        closes_at: None,
+    };
+    Bucket::new(deps.storage, PAYMENT_CHANNELS_PREFIX).save(&id.to_be_bytes(), &channel)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_events(outcome.events)
+        .add_attribute("action", "open_payment_channel")
+        .add_attribute("id", id.to_string())
+        .add_attribute("funder", funder)
+        .add_attribute("counterparty", counterparty)
+        .add_attribute("amount", outcome.net.to_string())
+        .add_attribute("fee", outcome.fee.to_string()))
+}
+
+// Anyone can submit a newer state signed by the funder, also during the challenge window
+pub fn update_payment_channel(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    _info: cosmwasm_std::MessageInfo,
+    id: u64,
+    nonce: u64,
+    paid: Uint128,
+    signature: cosmwasm_std::Binary,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut channel = load_payment_channel(deps.storage, id)?;
+    if channel.closes_at.is_some_and(|t| env.block.time.seconds() >= t) {
+        return Err(cosmwasm_std::StdError::generic_err("Challenge period is over"));
+    }
+    if nonce <= channel.nonce {
+        return Err(cosmwasm_std::StdError::generic_err("State is not newer than the one on chain"));
+    }
+    if paid > channel.deposit {
+        return Err(cosmwasm_std::StdError::generic_err("State pays more than the deposit"));
+    }
+    let state = PaymentChannelState { domain: SignatureDomain::new(deps.storage, &env, "payment_channel")?, channel_id: id, nonce, paid };
+    verify_signed(deps.api, &channel.funder, &state, &channel.funder_pubkey, &signature)?;
+
+    channel.nonce = nonce;
+    channel.paid = paid;
+    Bucket::new(deps.storage, PAYMENT_CHANNELS_PREFIX).save(&id.to_be_bytes(), &channel)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "update_payment_channel").add_attribute("id", id.to_string()).add_attribute("nonce", nonce.to_string()).add_attribute("paid", paid.to_string()))
+}
+
+// The counterparty closes right away with the state on chain, the funder starts the
+// challenge window instead
+pub fn close_payment_channel(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut channel = load_payment_channel(deps.storage, id)?;
+    if info.sender.as_str() == channel.counterparty.as_str() {
+        return settle_payment_channel(deps, &env, channel);
+    }
+    if info.sender.as_str() != channel.funder.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    if channel.closes_at.is_some() {
+        return Err(cosmwasm_std::StdError::generic_err("Channel is already closing"));
+    }
+    let closes_at = env.block.time.seconds() + channel.challenge_period;
+    channel.closes_at = Some(closes_at);
+    Bucket::new(deps.storage, PAYMENT_CHANNELS_PREFIX).save(&id.to_be_bytes(), &channel)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "close_payment_channel").add_attribute("id", id.to_string()).add_attribute("closes_at", closes_at.to_string()))
+}
+
+pub fn finalize_payment_channel(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    _info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let channel = load_payment_channel(deps.storage, id)?;
+    match channel.closes_at {
+        Some(t) if env.block.time.seconds() >= t => settle_payment_channel(deps, &env, channel),
+        Some(_) => Err(cosmwasm_std::StdError::generic_err("Challenge period is not over")),
+        None => Err(cosmwasm_std::StdError::generic_err("Channel is not closing")),
+    }
+}
+
+pub(crate) fn settle_payment_channel(deps: cosmwasm_std::DepsMut, env: &cosmwasm_std::Env, channel: PaymentChannel) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let refund = channel.deposit.checked_sub(channel.paid)?;
+    if !channel.paid.is_zero() {
+        assert_release_allowed(deps.as_ref(), &channel.counterparty)?;
+        escrow_out(deps.storage, env, &channel.counterparty, channel.paid)?;
+    }
+    if !refund.is_zero() {
+        escrow_out(deps.storage, env, &channel.funder, refund)?;
+    }
+    Bucket::<PaymentChannel>::new(deps.storage, PAYMENT_CHANNELS_PREFIX).remove(&channel.id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "settle_payment_channel")
+        .add_attribute("id", channel.id.to_string())
+        .add_attribute("paid", channel.paid.to_string())
+        .add_attribute("refunded", refund.to_string()))
+}
+
+pub const PAYROLL_COUNT_KEY: &[u8] = b"payroll_count";
+pub const PAYROLLS_PREFIX: &[u8] = b"payrolls";
+pub const PAYOUT_COUNT_KEY: &[u8] = b"payout_count";
+pub const PAYOUTS_PREFIX: &[u8] = b"payouts";
+
+// Paid from the employer's balance each time the crank runs after `next_run`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Payroll {
+    pub id: u64,
+    pub employer: HumanAddr,
+    pub payouts: Vec<(HumanAddr, Uint128)>,
+    // Seconds between runs
+    pub interval: u64,
+    pub next_run: u64,
+    pub runs: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PayoutRecord {
+    pub id: u64,
+    pub payroll_id: u64,
+    pub amount: Uint128,
+    pub time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PayoutHistoryResponse {
+    pub payouts: Vec<PayoutRecord>,
+}
+
+pub fn register_payroll(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    payouts: Vec<(HumanAddr, Uint128)>,
+    interval: u64,
+    start: Option<u64>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if payouts.is_empty() || payouts.len() > MAX_BATCH_SIZE {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Payroll must contain between 1 and {} entries", MAX_BATCH_SIZE)));
+    }
+    if interval == 0 {
+        return Err(cosmwasm_std::StdError::generic_err("Interval must be positive"));
+    }
+    let id = next_id(deps.storage, PAYROLL_COUNT_KEY)?;
+    let payroll = Payroll {
+        id,
+        employer: HumanAddr::from(info.sender.as_str()),
+        payouts,
+        interval,
+        next_run: start.unwrap_or_else(|| env.block.time.seconds()),
+        runs: 0,
+    };
+    Bucket::new(deps.storage, PAYROLLS_PREFIX).save(&id.to_be_bytes(), &payroll)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "register_payroll").add_attribute("id", id.to_string()).add_attribute("employer", info.sender).add_attribute("next_run", payroll.next_run.to_string()))
+}
+
+pub fn cancel_payroll(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let payroll = ReadonlyBucket::<Payroll>::new(deps.storage, PAYROLLS_PREFIX).load(&id.to_be_bytes())?;
+    if info.sender.as_str() != payroll.employer.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    Bucket::<Payroll>::new(deps.storage, PAYROLLS_PREFIX).remove(&id.to_be_bytes());
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "cancel_payroll").add_attribute("id", id.to_string()))
+}
+
+// Permissionless, pays one period per call so missed periods are caught up one run at a time
+pub fn run_payroll(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    _info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut payroll = ReadonlyBucket::<Payroll>::new(deps.storage, PAYROLLS_PREFIX).load(&id.to_be_bytes())?;
+    let now = env.block.time.seconds();
+    if now < payroll.next_run {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Payroll is not due until {}", payroll.next_run)));
+    }
+
+    let employer = payroll.employer.clone();
+    let mut events = vec![];
+    let mut total = Uint128::zero();
+    for (recipient, amount) in &payroll.payouts {
+        assert_not_large_transfer(deps.storage, *amount)?;
+        assert_transfer_allowed(deps.as_ref(), &env, &employer, recipient, *amount)?;
+        events.extend(record_transfer(deps.storage, &env, &employer, recipient, *amount)?);
+        let outcome = settle_transfer(deps.storage, &env, &employer, recipient, *amount)?;
+        events.extend(outcome.events);
+        total = total.checked_add(*amount)?;
+
+        let payout_id = next_id(deps.storage, PAYOUT_COUNT_KEY)?;
+        let record = PayoutRecord { id: payout_id, payroll_id: id, amount: outcome.net, time: now };
+        Bucket::multilevel(deps.storage, &[PAYOUTS_PREFIX, recipient.as_bytes()]).save(&payout_id.to_be_bytes(), &record)?;
+    }
+
+    payroll.next_run += payroll.interval;
+    payroll.runs += 1;
+    Bucket::new(deps.storage, PAYROLLS_PREFIX).save(&id.to_be_bytes(), &payroll)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_events(events)
+        .add_attribute("action", "run_payroll")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", employer)
+        .add_attribute("recipients", payroll.payouts.len().to_string())
+        .add_attribute("amount", total.to_string())
+        .add_attribute("next_run", payroll.next_run.to_string()))
+}
+
+pub fn query_payout_history(deps: cosmwasm_std::Deps, recipient: HumanAddr, start_after: Option<u64>, limit: Option<u32>) -> cosmwasm_std::StdResult<PayoutHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| (id + 1).to_be_bytes().to_vec());
+
+    let payouts = ReadonlyBucket::<PayoutRecord>::multilevel(deps.storage, &[PAYOUTS_PREFIX, recipient.as_bytes()])
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, record)| record))
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    Ok(PayoutHistoryResponse { payouts })
+}
+
+pub const PENDING_TRANSFER_COUNT_KEY: &[u8] = b"pending_transfer_count";
+pub const PENDING_TRANSFERS_PREFIX: &[u8] = b"pending_transfers";
+
+// Escrowed until the recipient claims it, so a send to a mistyped address can be taken back
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingTransfer {
+    pub id: u64,
+    pub sender: HumanAddr,
+    pub recipient: HumanAddr,
+    pub amount: Uint128,
+    pub expires: Expiration,
+}
+
+pub fn transfer_pending(
+    mut deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipient: HumanAddr,
+    amount: Uint128,
+    expires: Expiration,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if expires.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Expiration must be in the future"));
+    }
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+    let outcome = escrow_transfer_in(&mut deps, &env, &HumanAddr::from(&info.sender), &recipient, amount)?;
+
+    let id = next_id(deps.storage, PENDING_TRANSFER_COUNT_KEY)?;
+    let pending = PendingTransfer { id, sender: HumanAddr::from(&info.sender), recipient: recipient.clone(), amount: outcome.net, expires };
+    Bucket::new(deps.storage, PENDING_TRANSFERS_PREFIX).save(&id.to_be_bytes(), &pending)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_events(outcome.events)
+        .add_attribute("action", "transfer_pending")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", info.sender)
+        .add_attribute("to", recipient)
+        .add_attribute("amount", outcome.net.to_string())
+        .add_attribute("fee", outcome.fee.to_string()))
+}
+
+// Only the recipient can claim, which proves the address is one somebody controls
+pub fn claim_transfer(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let pending = ReadonlyBucket::<PendingTransfer>::new(deps.storage, PENDING_TRANSFERS_PREFIX).load(&id.to_be_bytes())?;
+    if info.sender.as_str() != pending.recipient.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    if pending.expires.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Pending transfer has expired"));
+    }
+    assert_release_allowed(deps.as_ref(), &pending.recipient)?;
+
+    escrow_out(deps.storage, &env, &pending.recipient, pending.amount)?;
+    Bucket::<PendingTransfer>::new(deps.storage, PENDING_TRANSFERS_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "claim_transfer")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", pending.sender)
+        .add_attribute("to", pending.recipient)
+        .add_attribute("amount", pending.amount.to_string()))
+}
+
+// The sender can take the tokens back at any point until they are claimed
+pub fn cancel_transfer(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let pending = ReadonlyBucket::<PendingTransfer>::new(deps.storage, PENDING_TRANSFERS_PREFIX).load(&id.to_be_bytes())?;
+    if info.sender.as_str() != pending.sender.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+
+    escrow_out(deps.storage, &env, &pending.sender, pending.amount)?;
+    Bucket::<PendingTransfer>::new(deps.storage, PENDING_TRANSFERS_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "cancel_transfer").add_attribute("id", id.to_string()).add_attribute("to", pending.sender).add_attribute("amount", pending.amount.to_string()))
+}
+
+pub const SCHEDULED_TRANSFER_COUNT_KEY: &[u8] = b"scheduled_transfer_count";
+pub const SCHEDULED_TRANSFERS_PREFIX: &[u8] = b"scheduled_transfers";
+
+// Escrowed when scheduled, released to the recipient by whoever cranks it once `at` is reached
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScheduledTransfer {
+    pub id: u64,
+    pub sender: HumanAddr,
+    pub recipient: HumanAddr,
+    pub amount: Uint128,
+    pub at: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ScheduledTransfersResponse {
+    pub transfers: Vec<ScheduledTransfer>,
+}
+
+pub fn schedule_transfer(
+    mut deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipient: HumanAddr,
+    amount: Uint128,
+    at: Expiration,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if at.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Scheduled time must be in the future"));
+    }
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+    let outcome = escrow_transfer_in(&mut deps, &env, &HumanAddr::from(&info.sender), &recipient, amount)?;
+
+    let id = next_id(deps.storage, SCHEDULED_TRANSFER_COUNT_KEY)?;
+    let scheduled = ScheduledTransfer { id, sender: HumanAddr::from(&info.sender), recipient: recipient.clone(), amount: outcome.net, at };
+    Bucket::new(deps.storage, SCHEDULED_TRANSFERS_PREFIX).save(&id.to_be_bytes(), &scheduled)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_events(outcome.events)
+        .add_attribute("action", "schedule_transfer")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", info.sender)
+        .add_attribute("to", recipient)
+        .add_attribute("amount", outcome.net.to_string())
+        .add_attribute("fee", outcome.fee.to_string()))
+}
+
+// Anyone can crank a due transfer, the tokens always go to the recipient
+pub fn execute_scheduled_transfer(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    _info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let scheduled = ReadonlyBucket::<ScheduledTransfer>::new(deps.storage, SCHEDULED_TRANSFERS_PREFIX).load(&id.to_be_bytes())?;
+    if !scheduled.at.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Scheduled transfer is not due yet"));
+    }
+    assert_release_allowed(deps.as_ref(), &scheduled.recipient)?;
+
+    escrow_out(deps.storage, &env, &scheduled.recipient, scheduled.amount)?;
+    Bucket::<ScheduledTransfer>::new(deps.storage, SCHEDULED_TRANSFERS_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "execute_scheduled_transfer")
+        .add_attribute("id", id.to_string())
+        .add_attribute("from", scheduled.sender)
+        .add_attribute("to", scheduled.recipient)
+        .add_attribute("amount", scheduled.amount.to_string()))
+}
+
+// Once due the transfer belongs to the recipient, so the sender can only cancel before then
+pub fn cancel_scheduled_transfer(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let scheduled = ReadonlyBucket::<ScheduledTransfer>::new(deps.storage, SCHEDULED_TRANSFERS_PREFIX).load(&id.to_be_bytes())?;
+    if info.sender.as_str() != scheduled.sender.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    if scheduled.at.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Scheduled transfer is already due"));
+    }
+
+    escrow_out(deps.storage, &env, &scheduled.sender, scheduled.amount)?;
+    Bucket::<ScheduledTransfer>::new(deps.storage, SCHEDULED_TRANSFERS_PREFIX).remove(&id.to_be_bytes());
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "cancel_scheduled_transfer").add_attribute("id", id.to_string()).add_attribute("to", scheduled.sender).add_attribute("amount", scheduled.amount.to_string()))
+}
+
+pub fn query_scheduled_transfers(deps: cosmwasm_std::Deps, start_after: Option<u64>, limit: Option<u32>) -> cosmwasm_std::StdResult<ScheduledTransfersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| (id + 1).to_be_bytes().to_vec());
+
+    let transfers = ReadonlyBucket::<ScheduledTransfer>::new(deps.storage, SCHEDULED_TRANSFERS_PREFIX)
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, scheduled)| scheduled))
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    Ok(ScheduledTransfersResponse { transfers })
+}
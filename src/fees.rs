@@ -0,0 +1,395 @@
+use cosmwasm_std::{HumanAddr, Uint128};
+use cosmwasm_storage::{ReadonlySingleton, Singleton};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    REFLECTION_KEY, adjust_total_shares, assert_owner, balance_of, burn_balance, credit_shares,
+    debit_shares, enable_share_accounting, free_contract_balance, move_balance, reserved_native,
+    shares_for, stored_balance, token_info, total_shares,
+};
+
+pub struct TransferOutcome {
+    // Amount credited to the recipient
+    pub net: Uint128,
+    pub fee: Uint128,
+    pub burned: Uint128,
+    pub reflected: Uint128,
+    pub events: Vec<cosmwasm_std::Event>,
+}
+
+// Moves `amount` out of `from`, crediting the recipient with what is left after fees
+pub fn settle_transfer(
+    storage: &mut dyn cosmwasm_std::Storage,
+    env: &cosmwasm_std::Env,
+    from: &HumanAddr,
+    to: &HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<TransferOutcome> {
+    let mut events = vec![];
+    let mut fee = Uint128::zero();
+
+    if let Some(config) = fee_config(storage)? {
+        fee = config.fee_for(amount);
+        if !fee.is_zero() {
+            let mut event = cosmwasm_std::Event::new("transfer_fee").add_attribute("from", from.clone()).add_attribute("fee", fee.to_string());
+            for (recipient, share) in config.split(fee) {
+                match recipient {
+                    FeeRecipient::Address(addr) => {
+                        move_balance(storage, from, &addr, share)?;
+                        event = event.add_attribute(addr.to_string(), share.to_string());
+                    }
+                    FeeRecipient::Burn {} => {
+                        burn_balance(storage, from, share)?;
+                        event = event.add_attribute("burned", share.to_string());
+                    }
+                }
+            }
+            events.push(event);
+        }
+    }
+
+    let mut burned = Uint128::zero();
+    if let Some(tax) = burn_tax(storage)? {
+        if tax.enabled {
+            burned = amount.multiply_ratio(tax.bps, 10_000u64);
+            if !burned.is_zero() {
+                burn_balance(storage, from, burned)?;
+                events.push(cosmwasm_std::Event::new("burn_tax").add_attribute("from", from.clone()).add_attribute("amount", burned.to_string()));
+            }
+        }
+    }
+
+    let mut reflected = Uint128::zero();
+    if let Some(bps) = reflection_bps(storage)? {
+        reflected = amount.multiply_ratio(bps, 10_000u64);
+        if !reflected.is_zero() {
+            reflect_balance(storage, env, from, reflected)?;
+            events.push(cosmwasm_std::Event::new("reflection").add_attribute("from", from.clone()).add_attribute("amount", reflected.to_string()));
+        }
+    }
+
+    let net = amount.checked_sub(fee)?.checked_sub(burned)?.checked_sub(reflected)?;
+    move_balance(storage, from, to, net)?;
+
+    Ok(TransferOutcome { net, fee, burned, reflected, events })
+}
+
+pub const FEE_CONFIG_KEY: &[u8] = b"fee_config";
+pub const MAX_FEE_BPS: u64 = 10_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeConfig {
+    // Rate applied when no tier matches the transfer amount
+    pub bps: u64,
+    pub collector: HumanAddr,
+    #[serde(default)]
+    pub tiers: Vec<FeeTier>,
+    // When set, collected fees are divided by weight instead of going to `collector`
+    #[serde(default)]
+    pub splits: Vec<FeeSplit>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeRecipient {
+    Address(HumanAddr),
+    Burn {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeSplit {
+    pub recipient: FeeRecipient,
+    pub weight: u64,
+}
+
+// Applies `bps` to transfers of at least `min_amount`, the tier with the highest matching minimum wins
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeTier {
+    pub min_amount: Uint128,
+    pub bps: u64,
+}
+
+impl FeeConfig {
+    pub fn rate_for(&self, amount: Uint128) -> u64 {
+        self.tiers
+            .iter()
+            .filter(|tier| amount >= tier.min_amount)
+            .max_by_key(|tier| tier.min_amount)
+            .map(|tier| tier.bps)
+            .unwrap_or(self.bps)
+    }
+
+    pub fn fee_for(&self, amount: Uint128) -> Uint128 {
+        amount.multiply_ratio(self.rate_for(amount), 10_000u64)
+    }
+
+    // Divides `fee` among the configured splits, rounding dust goes to the collector
+    pub fn split(&self, fee: Uint128) -> Vec<(FeeRecipient, Uint128)> {
+        let total_weight: u64 = self.splits.iter().map(|s| s.weight).sum();
+        if total_weight == 0 {
+            return vec![(FeeRecipient::Address(self.collector.clone()), fee)];
+        }
+
+        let mut shares = vec![];
+        let mut distributed = Uint128::zero();
+        for split in &self.splits {
+            let share = fee.multiply_ratio(split.weight, total_weight);
+            if !share.is_zero() {
+                distributed += share;
+                shares.push((split.recipient.clone(), share));
+            }
+        }
+        let dust = Uint128::from(fee.u128() - distributed.u128());
+        if !dust.is_zero() {
+            shares.push((FeeRecipient::Address(self.collector.clone()), dust));
+        }
+        shares
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeQuoteResponse {
+    pub fee: Uint128,
+    pub burned: Uint128,
+    pub reflected: Uint128,
+    pub net: Uint128,
+}
+
+pub fn fee_config(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Option<FeeConfig>> {
+    ReadonlySingleton::new(storage, FEE_CONFIG_KEY).may_load()
+}
+
+pub fn set_fee_config(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    config: Option<FeeConfig>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<FeeConfig>::new(deps.storage, FEE_CONFIG_KEY);
+    match &config {
+        Some(config) => {
+            if config.bps > MAX_FEE_BPS || config.tiers.iter().any(|tier| tier.bps > MAX_FEE_BPS) {
+                return Err(cosmwasm_std::StdError::generic_err("Fee cannot exceed 100%"));
+            }
+            singleton.save(config)?
+        }
+        None => singleton.remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_fee_config").add_attribute("bps", config.map(|c| c.bps).unwrap_or(0).to_string()))
+}
+
+pub fn query_fee_quote(deps: cosmwasm_std::Deps, amount: Uint128) -> cosmwasm_std::StdResult<FeeQuoteResponse> {
+    let fee = match fee_config(deps.storage)? {
+        Some(config) => config.fee_for(amount),
+        None => Uint128::zero(),
+    };
+    let burned = match burn_tax(deps.storage)? {
+        Some(tax) if tax.enabled => amount.multiply_ratio(tax.bps, 10_000u64),
+        _ => Uint128::zero(),
+    };
+    let reflected = match reflection_bps(deps.storage)? {
+        Some(bps) => amount.multiply_ratio(bps, 10_000u64),
+        None => Uint128::zero(),
+    };
+    Ok(FeeQuoteResponse { fee, burned, reflected, net: amount.checked_sub(fee)?.checked_sub(burned)?.checked_sub(reflected)? })
+}
+
+pub const MAX_BURN_TAX_KEY: &[u8] = b"max_burn_tax";
+pub const BURN_TAX_KEY: &[u8] = b"burn_tax";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BurnTax {
+    pub bps: u64,
+    pub enabled: bool,
+}
+
+pub fn burn_tax(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Option<BurnTax>> {
+    ReadonlySingleton::new(storage, BURN_TAX_KEY).may_load()
+}
+
+pub fn set_burn_tax(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    bps: u64,
+    enabled: bool,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let max = ReadonlySingleton::<u64>::new(deps.storage, MAX_BURN_TAX_KEY).may_load()?.unwrap_or(0);
+    if bps > max {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Burn tax cannot exceed {} bps", max)));
+    }
+
+    Singleton::new(deps.storage, BURN_TAX_KEY).save(&BurnTax { bps, enabled })?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_burn_tax").add_attribute("bps", bps.to_string()).add_attribute("enabled", enabled.to_string()))
+}
+
+pub fn reflection_bps(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Option<u64>> {
+    ReadonlySingleton::new(storage, REFLECTION_KEY).may_load()
+}
+
+// Destroys the shares behind `amount` while leaving the supply untouched, which raises the
+// value of every remaining share and so redistributes `amount` pro-rata to all holders
+pub fn reflect_balance(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, from: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    if balance_of(storage, from)? < amount {
+        return Err(cosmwasm_std::StdError::generic_err("Insufficient balance"));
+    }
+    let shares = shares_for(storage, amount)?;
+    debit_shares(storage, from, shares)?;
+    let held = balance_of(storage, &HumanAddr::from(env.contract.address.as_str()))?;
+    adjust_total_shares(storage, shares, false)?;
+    hold_contract_balance(storage, env, held)
+}
+
+// Tokens on the contract are escrowed for other modules, which track them by amount. After
+// the share value changes the contract's shares are resized so it still holds `held`, and
+// the difference goes to the other holders.
+pub(crate) fn hold_contract_balance(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, held: Uint128) -> cosmwasm_std::StdResult<()> {
+    let total = match total_shares(storage)? {
+        Some(total) => total,
+        None => return Ok(()),
+    };
+    let contract = HumanAddr::from(env.contract.address.as_str());
+    let shares = stored_balance(storage, &contract)?;
+    let others = total.checked_sub(shares)?;
+    if others.is_zero() {
+        return Ok(());
+    }
+    let supply = token_info(storage)?.total_supply;
+    let target = if held.is_zero() {
+        Uint128::zero()
+    } else {
+        if supply <= held {
+            return Err(cosmwasm_std::StdError::generic_err(format!("A supply of {} doesn't cover the {} tokens held by this contract", supply, held)));
+        }
+        // Rounded up so the contract is never short of what it owes
+        let target = held.multiply_ratio(others, supply.checked_sub(held)?);
+        if target.multiply_ratio(supply, others.checked_add(target)?) < held {
+            target.checked_add(Uint128::from(1u128))?
+        } else {
+            target
+        }
+    };
+
+    if target > shares {
+        credit_shares(storage, &contract, target.checked_sub(shares)?)?;
+        adjust_total_shares(storage, target.checked_sub(shares)?, true)
+    } else {
+        debit_shares(storage, &contract, shares.checked_sub(target)?)?;
+        adjust_total_shares(storage, shares.checked_sub(target)?, false)
+    }
+}
+
+pub fn set_reflection(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    bps: Option<u64>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    match bps {
+        Some(bps) => {
+            if bps > MAX_FEE_BPS {
+                return Err(cosmwasm_std::StdError::generic_err("Reflection rate cannot exceed 100%"));
+            }
+            enable_share_accounting(deps.storage)?;
+            Singleton::new(deps.storage, REFLECTION_KEY).save(&bps)?;
+        }
+        None => Singleton::<u64>::new(deps.storage, REFLECTION_KEY).remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_reflection").add_attribute("bps", bps.unwrap_or(0).to_string()))
+}
+
+pub const BUYBACK_KEY: &[u8] = b"buyback";
+pub const BUYBACK_PENDING_KEY: &[u8] = b"buyback_pending";
+pub const BUYBACK_REPLY_ID: u64 = 2;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BuybackConfig {
+    pub router: HumanAddr,
+    // Native coin the contract has collected and sells for this token
+    pub offer_denom: String,
+    // Router execute message, sent with the offer coins attached. It must pay the
+    // bought tokens back to this contract.
+    pub swap_msg: cosmwasm_std::Binary,
+}
+
+pub fn buyback_pending(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<bool> {
+    Ok(ReadonlySingleton::<Uint128>::new(storage, BUYBACK_PENDING_KEY).may_load()?.is_some())
+}
+
+pub fn set_buyback(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    config: Option<BuybackConfig>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    match &config {
+        Some(config) => Singleton::new(deps.storage, BUYBACK_KEY).save(config)?,
+        None => Singleton::<BuybackConfig>::new(deps.storage, BUYBACK_KEY).remove(),
+    }
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_buyback").add_attribute("enabled", config.is_some().to_string()))
+}
+
+// Sells the collected native coins through the router. The reply burns everything
+// the contract holds outside escrow, which is the bought tokens plus any transfer
+// fees collected while the contract is its own fee collector.
+pub fn buyback(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    let config = ReadonlySingleton::<BuybackConfig>::new(deps.storage, BUYBACK_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Buyback is not configured"))?;
+    if buyback_pending(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Buyback already in progress"));
+    }
+
+    let balance = deps.querier.query_balance(env.contract.address.to_string(), config.offer_denom.clone())?.amount;
+    let offer = balance.checked_sub(reserved_native(deps.storage, &config.offer_denom)?).unwrap_or_default();
+    if offer.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Nothing to buy back with"));
+    }
+
+    let before = free_contract_balance(deps.storage, &env)?;
+    Singleton::new(deps.storage, BUYBACK_PENDING_KEY).save(&before)?;
+
+    let swap = cosmwasm_std::WasmMsg::Execute {
+        contract_addr: config.router.to_string(),
+        msg: config.swap_msg,
+        funds: vec![cosmwasm_std::Coin { denom: config.offer_denom, amount: offer }],
+    };
+    Ok(cosmwasm_std::Response::new()
+        .add_submessage(cosmwasm_std::SubMsg::reply_on_success(swap, BUYBACK_REPLY_ID))
+        .add_attribute("action", "buyback")
+        .add_attribute("router", config.router)
+        .add_attribute("offer", offer.to_string()))
+}
+
+pub(crate) fn settle_buyback(deps: cosmwasm_std::DepsMut, env: cosmwasm_std::Env) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let before = ReadonlySingleton::<Uint128>::new(deps.storage, BUYBACK_PENDING_KEY).load()?;
+    Singleton::<Uint128>::new(deps.storage, BUYBACK_PENDING_KEY).remove();
+
+    let free = free_contract_balance(deps.storage, &env)?;
+    let bought = free.checked_sub(before).unwrap_or_default();
+    if bought.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Router did not return any tokens"));
+    }
+    burn_balance(deps.storage, &HumanAddr::from(env.contract.address.as_str()), free)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "buyback_burn")
+        .add_attribute("bought", bought.to_string())
+        .add_attribute("burned", free.to_string()))
+}
@@ -0,0 +1,558 @@
+use cosmwasm_std::{HumanAddr, Uint128};
+use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BALANCES_PREFIX, Balance, BalanceResponse, DEFAULT_LIMIT, FEE_CONFIG_KEY, MAX_FEE_BPS,
+    MAX_LIMIT, MAX_TX_KEY, MAX_WALLET_KEY, MIN_TRANSFER_KEY, TOKEN_INFO_KEY, TokenInfo,
+    assert_owner, calc_range_start, fee_config, next_id, set_delegate, set_paused, stored_balance,
+    token_info, total_shares,
+};
+
+pub const BLOCK_HEIGHT_KEY: &[u8] = b"block_height";
+pub const BALANCE_CHECKPOINTS_PREFIX: &[u8] = b"balance_checkpoints";
+pub const SUPPLY_CHECKPOINTS_PREFIX: &[u8] = b"supply_checkpoints";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SupplyCheckpoint {
+    pub total_supply: Uint128,
+    pub total_shares: Option<Uint128>,
+}
+
+// The balance layer only sees storage, so the entry points leave the current height here
+pub fn record_block_height(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env) -> cosmwasm_std::StdResult<()> {
+    Singleton::new(storage, BLOCK_HEIGHT_KEY).save(&env.block.height)
+}
+
+pub(crate) fn block_height(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<u64> {
+    Ok(ReadonlySingleton::<u64>::new(storage, BLOCK_HEIGHT_KEY).may_load()?.unwrap_or(0))
+}
+
+pub(crate) fn checkpoint_balance(storage: &mut dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<()> {
+    let height = block_height(storage)?.to_be_bytes();
+    let stored = stored_balance(storage, address)?;
+    let mut checkpoints = Bucket::<Uint128>::multilevel(storage, &[BALANCE_CHECKPOINTS_PREFIX, address.as_bytes()]);
+    if checkpoints.may_load(&height)?.is_none() {
+        checkpoints.save(&height, &stored)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn checkpoint_supply(storage: &mut dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<()> {
+    let height = block_height(storage)?.to_be_bytes();
+    if ReadonlyBucket::<SupplyCheckpoint>::new(storage, SUPPLY_CHECKPOINTS_PREFIX).may_load(&height)?.is_none() {
+        // Nothing is stored yet while the token info is first saved at instantiation
+        let total_supply = ReadonlySingleton::<TokenInfo>::new(storage, TOKEN_INFO_KEY).may_load()?.map(|info| info.total_supply).unwrap_or_default();
+        let checkpoint = SupplyCheckpoint { total_supply, total_shares: total_shares(storage)? };
+        Bucket::new(storage, SUPPLY_CHECKPOINTS_PREFIX).save(&height, &checkpoint)?;
+    }
+    Ok(())
+}
+
+pub fn supply_at(storage: &dyn cosmwasm_std::Storage, height: u64) -> cosmwasm_std::StdResult<SupplyCheckpoint> {
+    let checkpoint = ReadonlyBucket::<SupplyCheckpoint>::new(storage, SUPPLY_CHECKPOINTS_PREFIX)
+        .range(Some(&height.to_be_bytes()), None, cosmwasm_std::Order::Ascending)
+        .next()
+        .transpose()?;
+    match checkpoint {
+        Some((_, checkpoint)) => Ok(checkpoint),
+        None => Ok(SupplyCheckpoint { total_supply: token_info(storage)?.total_supply, total_shares: total_shares(storage)? }),
+    }
+}
+
+// Supply that can vote at `height`. Escrow, the treasury and staked tokens sit on the
+// contract's own balance, which never votes.
+pub fn voting_supply_at(storage: &dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, height: u64) -> cosmwasm_std::StdResult<Uint128> {
+    let total = supply_at(storage, height)?.total_supply;
+    let held = balance_at(storage, &HumanAddr::from(env.contract.address.as_str()), height)?;
+    Ok(total.saturating_sub(held))
+}
+
+pub fn balance_at(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr, height: u64) -> cosmwasm_std::StdResult<Uint128> {
+    let checkpoint = ReadonlyBucket::<Uint128>::multilevel(storage, &[BALANCE_CHECKPOINTS_PREFIX, address.as_bytes()])
+        .range(Some(&height.to_be_bytes()), None, cosmwasm_std::Order::Ascending)
+        .next()
+        .transpose()?;
+    let stored = match checkpoint {
+        Some((_, stored)) => stored,
+        None => stored_balance(storage, address)?,
+    };
+    // Stored balances are shares once share accounting is on, convert with the ratio of that height
+    let supply = supply_at(storage, height)?;
+    match supply.total_shares {
+        Some(total) if !total.is_zero() => Ok(stored.multiply_ratio(supply.total_supply, total)),
+        _ => Ok(stored),
+    }
+}
+
+pub fn query_balance_at(deps: cosmwasm_std::Deps, address: HumanAddr, height: u64) -> cosmwasm_std::StdResult<BalanceResponse> {
+    Ok(BalanceResponse { amount: balance_at(deps.storage, &address, height)? })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalSupplyResponse {
+    pub total_supply: Uint128,
+}
+
+pub fn query_total_supply_at(deps: cosmwasm_std::Deps, height: u64) -> cosmwasm_std::StdResult<TotalSupplyResponse> {
+    Ok(TotalSupplyResponse { total_supply: supply_at(deps.storage, height)?.total_supply })
+}
+
+pub const DELEGATES_PREFIX: &[u8] = b"delegates";
+pub const VOTES_PREFIX: &[u8] = b"votes";
+pub const VOTE_CHECKPOINTS_PREFIX: &[u8] = b"vote_checkpoints";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegatesResponse {
+    pub delegatee: Option<HumanAddr>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotesResponse {
+    pub votes: Uint128,
+}
+
+pub fn delegate_of(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<Option<HumanAddr>> {
+    ReadonlyBucket::<HumanAddr>::new(storage, DELEGATES_PREFIX).may_load(address.as_bytes())
+}
+
+// Votes are kept in stored balance units, like balances, so rebases don't need to touch them
+pub(crate) fn stored_votes(storage: &dyn cosmwasm_std::Storage, delegatee: &HumanAddr) -> cosmwasm_std::StdResult<Uint128> {
+    Ok(ReadonlyBucket::<Uint128>::new(storage, VOTES_PREFIX).may_load(delegatee.as_bytes())?.unwrap_or_else(Uint128::zero))
+}
+
+pub(crate) fn adjust_votes(storage: &mut dyn cosmwasm_std::Storage, delegatee: &HumanAddr, units: Uint128, increase: bool) -> cosmwasm_std::StdResult<()> {
+    if units.is_zero() {
+        return Ok(());
+    }
+    let votes = stored_votes(storage, delegatee)?;
+    let height = block_height(storage)?.to_be_bytes();
+    let mut checkpoints = Bucket::<Uint128>::multilevel(storage, &[VOTE_CHECKPOINTS_PREFIX, delegatee.as_bytes()]);
+    if checkpoints.may_load(&height)?.is_none() {
+        checkpoints.save(&height, &votes)?;
+    }
+    let votes = if increase { votes.checked_add(units)? } else { votes.checked_sub(units).unwrap_or_else(|_| Uint128::zero()) };
+    Bucket::new(storage, VOTES_PREFIX).save(delegatee.as_bytes(), &votes)
+}
+
+// Called whenever a stored balance changes so the holder's delegate follows it
+pub(crate) fn move_delegated_votes(storage: &mut dyn cosmwasm_std::Storage, holder: &HumanAddr, units: Uint128, increase: bool) -> cosmwasm_std::StdResult<()> {
+    match delegate_of(storage, holder)? {
+        Some(delegatee) => adjust_votes(storage, &delegatee, units, increase),
+        None => Ok(()),
+    }
+}
+
+// Balances only carry voting weight once delegated, delegating to yourself is allowed
+pub fn delegate(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    delegatee: Option<HumanAddr>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    set_delegate(deps.storage, &HumanAddr::from(&info.sender), &delegatee)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "delegate")
+        .add_attribute("delegator", info.sender)
+        .add_attribute("delegatee", delegatee.map(|d| d.to_string()).unwrap_or_else(|| "none".to_string())))
+}
+
+pub fn votes_at(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr, height: u64) -> cosmwasm_std::StdResult<Uint128> {
+    let checkpoint = ReadonlyBucket::<Uint128>::multilevel(storage, &[VOTE_CHECKPOINTS_PREFIX, address.as_bytes()])
+        .range(Some(&height.to_be_bytes()), None, cosmwasm_std::Order::Ascending)
+        .next()
+        .transpose()?;
+    let stored = match checkpoint {
+        Some((_, votes)) => votes,
+        None => stored_votes(storage, address)?,
+    };
+    let supply = supply_at(storage, height)?;
+    match supply.total_shares {
+        Some(total) if !total.is_zero() => Ok(stored.multiply_ratio(supply.total_supply, total)),
+        _ => Ok(stored),
+    }
+}
+
+pub fn query_delegates(deps: cosmwasm_std::Deps, address: HumanAddr) -> cosmwasm_std::StdResult<DelegatesResponse> {
+    Ok(DelegatesResponse { delegatee: delegate_of(deps.storage, &address)? })
+}
+
+pub fn query_votes_at(deps: cosmwasm_std::Deps, address: HumanAddr, height: u64) -> cosmwasm_std::StdResult<VotesResponse> {
+    Ok(VotesResponse { votes: votes_at(deps.storage, &address, height)? })
+}
+
+// Responses of the cw-core voting module interface, so the token can be used as a DAO DAO
+// voting module directly. Voting power is the delegated votes at the height, the same
+// weight ballots on this contract's own proposals use.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VotingPowerAtHeightResponse {
+    pub power: Uint128,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalPowerAtHeightResponse {
+    pub power: Uint128,
+    pub height: u64,
+}
+
+pub fn query_voting_power_at_height(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, address: HumanAddr, height: Option<u64>) -> cosmwasm_std::StdResult<VotingPowerAtHeightResponse> {
+    let height = height.unwrap_or(env.block.height);
+    Ok(VotingPowerAtHeightResponse { power: votes_at(deps.storage, &address, height)?, height })
+}
+
+pub fn query_total_power_at_height(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, height: Option<u64>) -> cosmwasm_std::StdResult<TotalPowerAtHeightResponse> {
+    let height = height.unwrap_or(env.block.height);
+    Ok(TotalPowerAtHeightResponse { power: voting_supply_at(deps.storage, &env, height)?, height })
+}
+
+pub const GOVERNANCE_KEY: &[u8] = b"governance";
+pub const PROPOSALS_PREFIX: &[u8] = b"proposals";
+pub const PROPOSAL_COUNT_KEY: &[u8] = b"proposal_count";
+pub const BALLOTS_PREFIX: &[u8] = b"ballots";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GovernanceConfig {
+    // Seconds a proposal stays open for voting
+    pub voting_period: u64,
+    // Share of the snapshot supply that has to vote, in basis points
+    pub quorum_bps: u64,
+    // Share of yes among yes and no votes needed to pass, in basis points
+    pub threshold_bps: u64,
+    // Votes needed to create a proposal
+    pub proposal_threshold: Uint128,
+    #[serde(default)]
+    pub veto: Option<VetoConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VetoConfig {
+    pub vetoer: HumanAddr,
+    // Seconds a passed proposal stays queued before it can be executed
+    pub window: u64,
+}
+
+// Parameter changes a proposal can make once it passes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalAction {
+    SetFeeBps { bps: u64 },
+    SetMaxWalletBalance { max: Option<Uint128> },
+    SetMaxTransactionAmount { max: Option<Uint128> },
+    SetMinTransferAmount { min: Option<Uint128> },
+    Pause {},
+    Unpause {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteOption {
+    Yes,
+    No,
+    Abstain,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProposalStatus {
+    Open,
+    // Passed but still inside the veto window
+    Queued,
+    Passed,
+    Rejected,
+    Vetoed,
+    Executed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Proposal {
+    pub id: u64,
+    pub proposer: HumanAddr,
+    pub title: String,
+    pub description: String,
+    pub actions: Vec<ProposalAction>,
+    // Voting weight is read from delegated votes at this height
+    pub snapshot_height: u64,
+    pub voting_ends: u64,
+    pub yes: Uint128,
+    pub no: Uint128,
+    pub abstain: Uint128,
+    // Copied from the config so later config changes don't move the goalposts
+    pub quorum_bps: u64,
+    pub threshold_bps: u64,
+    #[serde(default)]
+    pub veto_window: u64,
+    pub executed: bool,
+    #[serde(default)]
+    pub vetoed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProposalResponse {
+    pub proposal: Proposal,
+    pub status: ProposalStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ProposalsResponse {
+    pub proposals: Vec<ProposalResponse>,
+}
+
+impl Proposal {
+    pub fn status(&self, storage: &dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env) -> cosmwasm_std::StdResult<ProposalStatus> {
+        if self.executed {
+            return Ok(ProposalStatus::Executed);
+        }
+        if self.vetoed {
+            return Ok(ProposalStatus::Vetoed);
+        }
+        if env.block.time.seconds() < self.voting_ends {
+            return Ok(ProposalStatus::Open);
+        }
+        let total = voting_supply_at(storage, env, self.snapshot_height)?;
+        let turnout = self.yes.checked_add(self.no)?.checked_add(self.abstain)?;
+        let quorum = turnout.u128() * MAX_FEE_BPS as u128 >= total.u128() * self.quorum_bps as u128;
+        let decisive = self.yes.checked_add(self.no)?;
+        let passed = !decisive.is_zero() && self.yes.u128() * MAX_FEE_BPS as u128 > decisive.u128() * self.threshold_bps as u128;
+        if !(quorum && passed) {
+            return Ok(ProposalStatus::Rejected);
+        }
+        if env.block.time.seconds() < self.voting_ends + self.veto_window {
+            return Ok(ProposalStatus::Queued);
+        }
+        Ok(ProposalStatus::Passed)
+    }
+}
+
+pub fn governance_config(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<GovernanceConfig> {
+    ReadonlySingleton::<GovernanceConfig>::new(storage, GOVERNANCE_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Governance is not enabled"))
+}
+
+pub fn set_governance(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    config: Option<GovernanceConfig>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    match &config {
+        Some(config) => {
+            if config.voting_period == 0 || config.quorum_bps > MAX_FEE_BPS || config.threshold_bps > MAX_FEE_BPS {
+                return Err(cosmwasm_std::StdError::generic_err("Invalid governance config"));
+            }
+            Singleton::new(deps.storage, GOVERNANCE_KEY).save(config)?;
+        }
+        None => Singleton::<GovernanceConfig>::new(deps.storage, GOVERNANCE_KEY).remove(),
+    }
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_governance").add_attribute("enabled", config.is_some().to_string()))
+}
+
+pub fn propose(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    title: String,
+    description: String,
+    actions: Vec<ProposalAction>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let config = governance_config(deps.storage)?;
+    if actions.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err("A proposal needs at least one action"));
+    }
+    // Checkpoints hold the votes from before this block, so tokens borrowed for the call don't count
+    if votes_at(deps.storage, &HumanAddr::from(&info.sender), env.block.height)? < config.proposal_threshold {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Creating a proposal needs {} votes", config.proposal_threshold)));
+    }
+
+    let id = next_id(deps.storage, PROPOSAL_COUNT_KEY)?;
+    let proposal = Proposal {
+        id,
+        proposer: HumanAddr::from(&info.sender),
+        title,
+        description,
+        actions,
+        snapshot_height: env.block.height,
+        voting_ends: env.block.time.seconds() + config.voting_period,
+        yes: Uint128::zero(),
+        no: Uint128::zero(),
+        abstain: Uint128::zero(),
+        quorum_bps: config.quorum_bps,
+        threshold_bps: config.threshold_bps,
+        veto_window: config.veto.map(|v| v.window).unwrap_or(0),
+        executed: false,
+        vetoed: false,
+    };
+    Bucket::new(deps.storage, PROPOSALS_PREFIX).save(&id.to_be_bytes(), &proposal)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "propose").add_attribute("id", id.to_string()).add_attribute("proposer", info.sender))
+}
+
+pub fn cast_vote(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+    vote: VoteOption,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut proposal = Bucket::<Proposal>::new(deps.storage, PROPOSALS_PREFIX).load(&id.to_be_bytes())?;
+    if proposal.status(deps.storage, &env)? != ProposalStatus::Open {
+        return Err(cosmwasm_std::StdError::generic_err("Voting has ended"));
+    }
+
+    let mut ballots = Bucket::<VoteOption>::multilevel(deps.storage, &[BALLOTS_PREFIX, &id.to_be_bytes()]);
+    if ballots.may_load(info.sender.as_bytes())?.is_some() {
+        return Err(cosmwasm_std::StdError::generic_err("Already voted"));
+    }
+    ballots.save(info.sender.as_bytes(), &vote)?;
+
+    // Holders who delegated vote through their delegatee, so no token counts twice
+    let weight = votes_at(deps.storage, &HumanAddr::from(&info.sender), proposal.snapshot_height)?;
+    if weight.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("No voting weight at the proposal snapshot"));
+    }
+    match vote {
+        VoteOption::Yes => proposal.yes = proposal.yes.checked_add(weight)?,
+        VoteOption::No => proposal.no = proposal.no.checked_add(weight)?,
+        VoteOption::Abstain => proposal.abstain = proposal.abstain.checked_add(weight)?,
+    }
+    Bucket::new(deps.storage, PROPOSALS_PREFIX).save(&id.to_be_bytes(), &proposal)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "vote").add_attribute("id", id.to_string()).add_attribute("voter", info.sender).add_attribute("weight", weight.to_string()))
+}
+
+pub(crate) fn apply_proposal_action(storage: &mut dyn cosmwasm_std::Storage, action: &ProposalAction) -> cosmwasm_std::StdResult<()> {
+    match action {
+        ProposalAction::SetFeeBps { bps } => {
+            if *bps > MAX_FEE_BPS {
+                return Err(cosmwasm_std::StdError::generic_err("Fee cannot exceed 100%"));
+            }
+            let mut config = fee_config(storage)?.ok_or_else(|| cosmwasm_std::StdError::generic_err("No fee is configured"))?;
+            config.bps = *bps;
+            Singleton::new(storage, FEE_CONFIG_KEY).save(&config)
+        }
+        ProposalAction::SetMaxWalletBalance { max } => save_optional(storage, MAX_WALLET_KEY, max),
+        ProposalAction::SetMaxTransactionAmount { max } => save_optional(storage, MAX_TX_KEY, max),
+        ProposalAction::SetMinTransferAmount { min } => save_optional(storage, MIN_TRANSFER_KEY, min),
+        ProposalAction::Pause {} => set_paused(storage, true),
+        ProposalAction::Unpause {} => set_paused(storage, false),
+    }
+}
+
+pub(crate) fn save_optional(storage: &mut dyn cosmwasm_std::Storage, key: &[u8], value: &Option<Uint128>) -> cosmwasm_std::StdResult<()> {
+    let mut singleton = Singleton::<Uint128>::new(storage, key);
+    match value {
+        Some(value) => singleton.save(value),
+        None => {
+            singleton.remove();
+            Ok(())
+        }
+    }
+}
+
+// Anyone can execute a proposal once it has passed
+pub fn execute_proposal(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut proposal = Bucket::<Proposal>::new(deps.storage, PROPOSALS_PREFIX).load(&id.to_be_bytes())?;
+    if proposal.status(deps.storage, &env)? != ProposalStatus::Passed {
+        return Err(cosmwasm_std::StdError::generic_err("Proposal has not passed"));
+    }
+    proposal.executed = true;
+    Bucket::new(deps.storage, PROPOSALS_PREFIX).save(&id.to_be_bytes(), &proposal)?;
+
+    for action in &proposal.actions {
+        apply_proposal_action(deps.storage, action)?;
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "execute_proposal").add_attribute("id", id.to_string()).add_attribute("executor", info.sender))
+}
+
+pub fn query_proposal(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, id: u64) -> cosmwasm_std::StdResult<ProposalResponse> {
+    let proposal = ReadonlyBucket::<Proposal>::new(deps.storage, PROPOSALS_PREFIX).load(&id.to_be_bytes())?;
+    Ok(ProposalResponse { status: proposal.status(deps.storage, &env)?, proposal })
+}
+
+pub fn query_proposals(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, start_after: Option<u64>, limit: Option<u32>) -> cosmwasm_std::StdResult<ProposalsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| (id + 1).to_be_bytes().to_vec());
+
+    let proposals = ReadonlyBucket::<Proposal>::new(deps.storage, PROPOSALS_PREFIX)
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, proposal) = item?;
+            Ok(ProposalResponse { status: proposal.status(deps.storage, &env)?, proposal })
+        })
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    Ok(ProposalsResponse { proposals })
+}
+
+pub fn veto_proposal(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let vetoer = governance_config(deps.storage)?.veto.map(|v| v.vetoer);
+    if vetoer.as_ref() != Some(&HumanAddr::from(&info.sender)) {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+
+    let mut proposal = Bucket::<Proposal>::new(deps.storage, PROPOSALS_PREFIX).load(&id.to_be_bytes())?;
+    if proposal.status(deps.storage, &env)? != ProposalStatus::Queued {
+        return Err(cosmwasm_std::StdError::generic_err("Only queued proposals can be vetoed"));
+    }
+    proposal.vetoed = true;
+    Bucket::new(deps.storage, PROPOSALS_PREFIX).save(&id.to_be_bytes(), &proposal)?;
+
+    let event = cosmwasm_std::Event::new("veto").add_attribute("id", id.to_string()).add_attribute("vetoer", info.sender);
+    Ok(cosmwasm_std::Response::new().add_event(event).add_attribute("action", "veto_proposal").add_attribute("id", id.to_string()))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HolderBalance {
+    pub address: HumanAddr,
+    pub balance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HoldersResponse {
+    pub holders: Vec<HolderBalance>,
+    // Empty balances are left out, so pages can come back short. Continue from here
+    // until it is None.
+    pub next_start_after: Option<HumanAddr>,
+}
+
+pub fn query_holders_at(deps: cosmwasm_std::Deps, height: u64, start_after: Option<HumanAddr>, limit: Option<u32>) -> cosmwasm_std::StdResult<HoldersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = calc_range_start(start_after);
+
+    // Every address that has ever held tokens keeps an entry in the balances bucket
+    let addresses = ReadonlyBucket::<Balance>::new(deps.storage, BALANCES_PREFIX)
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, _) = item?;
+            Ok(HumanAddr::from(String::from_utf8(key)?))
+        })
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    let next_start_after = if addresses.len() == limit { addresses.last().cloned() } else { None };
+    let mut holders = vec![];
+    for address in addresses {
+        let balance = balance_at(deps.storage, &address, height)?;
+        if !balance.is_zero() {
+            holders.push(HolderBalance { address, balance });
+        }
+    }
+
+    Ok(HoldersResponse { holders, next_start_after })
+}
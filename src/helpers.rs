@@ -0,0 +1,138 @@
+use cosmwasm_std::Uint128;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{proto_coin, proto_field, proto_varint_field, InitialBalance, InstantiateMsg};
+
+pub const INSTANTIATE2_TYPE_URL: &str = "/cosmwasm.wasm.v1.MsgInstantiateContract2";
+pub const MAX_SALT_LENGTH: usize = 64;
+
+// Instantiate message with every optional feature left off, for factories that only
+// decide the initial balances
+pub fn default_instantiate_msg(initial_balances: Vec<InitialBalance>) -> InstantiateMsg {
+    InstantiateMsg { initial_balances, max_burn_tax_bps: None, cap: None, treasury: None, tokenfactory_subdenom: None }
+}
+
+fn assert_salt(salt: &[u8]) -> cosmwasm_std::StdResult<()> {
+    if salt.is_empty() || salt.len() > MAX_SALT_LENGTH {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Salt must be between 1 and {} bytes", MAX_SALT_LENGTH)));
+    }
+    Ok(())
+}
+
+// MsgInstantiateContract2 sent as a Stargate message, the contract ends up at
+// `predict_instantiate2_address` of the same code checksum, creator and salt
+#[allow(clippy::too_many_arguments)]
+pub fn instantiate2_msg(
+    sender: &str,
+    admin: Option<&str>,
+    code_id: u64,
+    label: &str,
+    msg: &InstantiateMsg,
+    funds: &[cosmwasm_std::Coin],
+    salt: &[u8],
+) -> cosmwasm_std::StdResult<cosmwasm_std::CosmosMsg> {
+    assert_salt(salt)?;
+    let mut value = proto_field(1, sender.as_bytes());
+    if let Some(admin) = admin {
+        value.extend(proto_field(2, admin.as_bytes()));
+    }
+    value.extend(proto_varint_field(3, code_id));
+    value.extend(proto_field(4, label.as_bytes()));
+    value.extend(proto_field(5, &cosmwasm_std::to_vec(msg)?));
+    for coin in funds {
+        value.extend(proto_field(6, &proto_coin(&coin.denom, coin.amount)));
+    }
+    value.extend(proto_field(7, salt));
+    Ok(cosmwasm_std::CosmosMsg::Stargate { type_url: INSTANTIATE2_TYPE_URL.to_string(), value: value.into() })
+}
+
+// Canonical address wasmd derives for Instantiate2 without a fixed message, following
+// ADR-028: sha256(sha256("module") | "wasm\0" | checksum | creator | salt | msg),
+// each part prefixed by its length as a big-endian u64
+pub fn predict_instantiate2_address(checksum: &[u8], creator: &cosmwasm_std::CanonicalAddr, salt: &[u8]) -> cosmwasm_std::StdResult<cosmwasm_std::CanonicalAddr> {
+    assert_salt(salt)?;
+    if checksum.len() != 32 {
+        return Err(cosmwasm_std::StdError::generic_err("Checksum must be 32 bytes"));
+    }
+    let mut key = b"wasm\0".to_vec();
+    for part in [checksum, creator.as_slice(), salt, &[]].iter() {
+        key.extend_from_slice(&(part.len() as u64).to_be_bytes());
+        key.extend_from_slice(part);
+    }
+    let address = Sha256::new().chain(Sha256::digest(b"module")).chain(&key).finalize();
+    Ok(cosmwasm_std::CanonicalAddr::from(address.to_vec()))
+}
+
+// Salt for the n-th token a factory deploys for a project, stable across chains
+pub fn project_salt(project: &str, index: u64) -> Vec<u8> {
+    Sha256::digest(format!("{}/{}", project, index).as_bytes()).to_vec()
+}
+
+// Total minted by an instantiate message, handy for factories checking allocations
+pub fn initial_supply(msg: &InstantiateMsg) -> cosmwasm_std::StdResult<Uint128> {
+    msg.initial_balances.iter().try_fold(Uint128::zero(), |total, b| total.checked_add(b.amount).map_err(Into::into))
+}
+
+// Execute interface of cw3 multisigs, only the part needed to propose
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw3ExecuteMsg {
+    Propose { title: String, description: String, msgs: Vec<cosmwasm_std::CosmosMsg>, latest: Option<Cw3Expiration> },
+}
+
+// cw-utils expiration as cw3 expects it, times are a nanosecond timestamp
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw3Expiration {
+    AtHeight(u64),
+    AtTime(cosmwasm_std::Timestamp),
+    Never {},
+}
+
+impl From<crate::Expiration> for Cw3Expiration {
+    fn from(expiration: crate::Expiration) -> Self {
+        match expiration {
+            crate::Expiration::AtHeight(height) => Cw3Expiration::AtHeight(height),
+            crate::Expiration::AtTime(seconds) => Cw3Expiration::AtTime(cosmwasm_std::Timestamp::from_seconds(seconds)),
+        }
+    }
+}
+
+// Message executing `msg` on the token, as the multisig will send it
+pub fn token_execute_msg(token: &str, msg: &crate::ExecuteMsg) -> cosmwasm_std::StdResult<cosmwasm_std::CosmosMsg> {
+    Ok(cosmwasm_std::WasmMsg::Execute { contract_addr: token.to_string(), msg: cosmwasm_std::to_binary(msg)?, funds: vec![] }.into())
+}
+
+// cw3 Propose payload running the given admin messages on the token once passed
+pub fn cw3_proposal(
+    token: &str,
+    title: &str,
+    description: &str,
+    msgs: &[crate::ExecuteMsg],
+    latest: Option<crate::Expiration>,
+) -> cosmwasm_std::StdResult<Cw3ExecuteMsg> {
+    if msgs.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err("A proposal needs at least one message"));
+    }
+    Ok(Cw3ExecuteMsg::Propose {
+        title: title.to_string(),
+        description: description.to_string(),
+        msgs: msgs.iter().map(|msg| token_execute_msg(token, msg)).collect::<cosmwasm_std::StdResult<Vec<_>>>()?,
+        latest: latest.map(Into::into),
+    })
+}
+
+// The proposal ready to be sent to the multisig by one of its voters
+pub fn cw3_propose_msg(
+    multisig: &str,
+    token: &str,
+    title: &str,
+    description: &str,
+    msgs: &[crate::ExecuteMsg],
+    latest: Option<crate::Expiration>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::CosmosMsg> {
+    let proposal = cw3_proposal(token, title, description, msgs, latest)?;
+    Ok(cosmwasm_std::WasmMsg::Execute { contract_addr: multisig.to_string(), msg: cosmwasm_std::to_binary(&proposal)?, funds: vec![] }.into())
+}
@@ -0,0 +1,665 @@
+use cosmwasm_std::{HumanAddr, Uint128};
+use cosmwasm_storage::{Bucket, ReadonlyBucket};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Cw20ReceiveMsg, ExecuteMsg, ReceiverExecuteMsg, assert_escrow_allowed, assert_mint_allowed,
+    assert_owner, destroy_balance, escrow_in, escrow_out, is_paused, mint_balance,
+    record_block_height, spend_allowance, with_memo,
+};
+
+pub const IBC_VERSION: &str = "cw20-burn-mint-1";
+pub const IBC_CHANNELS_PREFIX: &[u8] = b"ibc_channels";
+pub const DEFAULT_IBC_TIMEOUT_SECONDS: u64 = 600;
+// Same bound ibc-go puts on ics20 memos
+pub const MAX_IBC_MEMO_LENGTH: usize = 32768;
+pub const IBC_HOOK_REPLY_ID: u64 = 5;
+pub const IBC_RECEIVE_REPLY_ID: u64 = 6;
+
+// Whitelisted by the owner before the handshake, `open` once the handshake completes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IbcChannelState {
+    pub channel_id: String,
+    pub open: bool,
+    #[serde(default)]
+    pub mode: IbcChannelMode,
+    // Tokens locked in the contract for transfers out over this channel, escrow mode only
+    #[serde(default)]
+    pub escrowed: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IbcChannelMode {
+    // Burn on send, the counterparty instance mints
+    #[default]
+    BurnMint,
+    // Lock on send, only returning tokens are released, like ics20 for the source chain
+    Escrow,
+}
+
+// Sent to the counterparty instance, which mints `amount` to `receiver`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BurnMintPacket {
+    pub sender: String,
+    pub receiver: String,
+    pub amount: Uint128,
+    // Left out when empty so packets stay readable by instances that predate it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+// JSON memos acted on when a packet arrives, in the shapes packet-forward-middleware
+// and ibc-hooks read from ics20 memos so wallets can build them the same way. Anything
+// that isn't a JSON object is passed through as a plain memo.
+#[derive(Deserialize)]
+pub(crate) struct IbcMemo {
+    #[serde(default)]
+    forward: Option<ForwardMemo>,
+    #[serde(default)]
+    wasm: Option<WasmHookMemo>,
+}
+
+// Sends the tokens on over another channel of this instance, `next` becomes the memo
+// of the forwarded packet so routes can hop further
+#[derive(Deserialize)]
+pub(crate) struct ForwardMemo {
+    receiver: String,
+    channel: String,
+    #[serde(default)]
+    next: Option<Box<serde_json::value::RawValue>>,
+}
+
+// Calls `contract` with a cw20 Receive once the tokens are minted to it, the packet
+// receiver has to be the contract itself as with ibc-hooks
+#[derive(Deserialize)]
+pub(crate) struct WasmHookMemo {
+    contract: String,
+    msg: Box<serde_json::value::RawValue>,
+}
+
+pub(crate) fn parse_ibc_memo(memo: &Option<String>) -> cosmwasm_std::StdResult<Option<IbcMemo>> {
+    match memo {
+        Some(memo) if memo.trim_start().starts_with('{') => {
+            let memo: IbcMemo = serde_json::from_str(memo).map_err(|err| cosmwasm_std::StdError::generic_err(format!("Invalid memo: {}", err)))?;
+            if memo.forward.is_some() && memo.wasm.is_some() {
+                return Err(cosmwasm_std::StdError::generic_err("Memo can't both forward and call a contract"));
+            }
+            Ok(Some(memo))
+        }
+        _ => Ok(None),
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum IbcAck {
+    Result(cosmwasm_std::Binary),
+    Error(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IbcChannelsResponse {
+    pub channels: Vec<IbcChannelState>,
+}
+
+pub fn allow_ibc_channel(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    channel_id: String,
+    mode: Option<IbcChannelMode>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    let mode = mode.unwrap_or_default();
+    let mut bucket = Bucket::<IbcChannelState>::new(deps.storage, IBC_CHANNELS_PREFIX);
+    let channel = match bucket.may_load(channel_id.as_bytes())? {
+        // The mode can't change while tokens sent in escrow mode are still out
+        Some(existing) if existing.mode != mode && !existing.escrowed.is_zero() => {
+            return Err(cosmwasm_std::StdError::generic_err("Channel still holds escrowed tokens"));
+        }
+        Some(existing) => IbcChannelState { mode, ..existing },
+        None => IbcChannelState { channel_id: channel_id.clone(), open: false, mode, escrowed: Uint128::zero() },
+    };
+    bucket.save(channel_id.as_bytes(), &channel)?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "allow_ibc_channel").add_attribute("channel_id", channel_id))
+}
+
+// Stops sending and accepting packets on the channel. Packets already in flight still
+// get refunded on ack or timeout.
+pub fn remove_ibc_channel(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    channel_id: String,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    let mut bucket = Bucket::<IbcChannelState>::new(deps.storage, IBC_CHANNELS_PREFIX);
+    if let Some(channel) = bucket.may_load(channel_id.as_bytes())? {
+        if !channel.escrowed.is_zero() {
+            return Err(cosmwasm_std::StdError::generic_err("Channel still holds escrowed tokens"));
+        }
+    }
+    bucket.remove(channel_id.as_bytes());
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "remove_ibc_channel").add_attribute("channel_id", channel_id))
+}
+
+pub(crate) fn open_ibc_channel(storage: &dyn cosmwasm_std::Storage, channel_id: &str) -> cosmwasm_std::StdResult<IbcChannelState> {
+    match ReadonlyBucket::<IbcChannelState>::new(storage, IBC_CHANNELS_PREFIX).may_load(channel_id.as_bytes())? {
+        Some(channel) if channel.open => Ok(channel),
+        _ => Err(cosmwasm_std::StdError::generic_err(format!("Channel {} is not open", channel_id))),
+    }
+}
+
+// Burns or escrows on this chain depending on the channel mode, the counterparty
+// mints once the packet is received
+#[allow(clippy::too_many_arguments)]
+pub fn ibc_transfer(
+    mut deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    channel_id: String,
+    receiver: String,
+    amount: Uint128,
+    timeout_seconds: Option<u64>,
+    memo: Option<String>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if memo.as_ref().is_some_and(|m| m.len() > MAX_IBC_MEMO_LENGTH) {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Memo is longer than {} bytes", MAX_IBC_MEMO_LENGTH)));
+    }
+    // Catch a malformed route here rather than as a refund after the round trip
+    parse_ibc_memo(&memo)?;
+    let sender = HumanAddr::from(info.sender.as_str());
+    let msg = send_ibc_packet(&mut deps, &env, &sender, &channel_id, &receiver, amount, timeout_seconds, memo.clone())?;
+
+    let response = cosmwasm_std::Response::new()
+        .add_message(msg)
+        .add_attribute("action", "ibc_transfer")
+        .add_attribute("channel_id", channel_id)
+        .add_attribute("from", info.sender)
+        .add_attribute("receiver", receiver)
+        .add_attribute("amount", amount.to_string());
+    Ok(with_memo(response, memo))
+}
+
+// Takes `amount` from `sender` the way the channel mode requires and builds the packet
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn send_ibc_packet(
+    deps: &mut cosmwasm_std::DepsMut,
+    env: &cosmwasm_std::Env,
+    sender: &HumanAddr,
+    channel_id: &str,
+    receiver: &str,
+    amount: Uint128,
+    timeout_seconds: Option<u64>,
+    memo: Option<String>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::IbcMsg> {
+    let mut channel = open_ibc_channel(deps.storage, channel_id)?;
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+    let contract = HumanAddr::from(env.contract.address.as_str());
+    assert_escrow_allowed(deps.as_ref(), env, sender, &contract, amount)?;
+    record_flow(deps.storage, env, &ibc_route(channel_id), FlowDirection::Out, amount)?;
+    match channel.mode {
+        IbcChannelMode::BurnMint => destroy_balance(deps.storage, sender, amount)?,
+        IbcChannelMode::Escrow => {
+            escrow_in(deps.storage, env, sender, amount)?;
+            channel.escrowed = channel.escrowed.checked_add(amount)?;
+            Bucket::new(deps.storage, IBC_CHANNELS_PREFIX).save(channel_id.as_bytes(), &channel)?;
+        }
+    }
+
+    let packet = BurnMintPacket { sender: sender.to_string(), receiver: receiver.to_string(), amount, memo };
+    let timeout = env.block.time.plus_seconds(timeout_seconds.unwrap_or(DEFAULT_IBC_TIMEOUT_SECONDS));
+    Ok(cosmwasm_std::IbcMsg::SendPacket {
+        channel_id: channel_id.to_string(),
+        data: cosmwasm_std::to_binary(&packet)?,
+        timeout: cosmwasm_std::IbcTimeout::with_timestamp(timeout),
+    })
+}
+
+pub fn query_ibc_channels(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<IbcChannelsResponse> {
+    let channels = ReadonlyBucket::<IbcChannelState>::new(deps.storage, IBC_CHANNELS_PREFIX)
+        .range(None, None, cosmwasm_std::Order::Ascending)
+        .map(|item| item.map(|(_, channel)| channel))
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+    Ok(IbcChannelsResponse { channels })
+}
+
+pub(crate) fn check_ibc_channel(storage: &dyn cosmwasm_std::Storage, channel: &cosmwasm_std::IbcChannel, counterparty_version: Option<&str>) -> cosmwasm_std::StdResult<()> {
+    let channel_id = &channel.endpoint.channel_id;
+    if ReadonlyBucket::<IbcChannelState>::new(storage, IBC_CHANNELS_PREFIX).may_load(channel_id.as_bytes())?.is_none() {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Channel {} is not whitelisted", channel_id)));
+    }
+    if channel.order != cosmwasm_std::IbcOrder::Unordered {
+        return Err(cosmwasm_std::StdError::generic_err("Only unordered channels are supported"));
+    }
+    if channel.version != IBC_VERSION {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Channel version must be {}", IBC_VERSION)));
+    }
+    if let Some(version) = counterparty_version {
+        if version != IBC_VERSION {
+            return Err(cosmwasm_std::StdError::generic_err(format!("Counterparty version must be {}", IBC_VERSION)));
+        }
+    }
+    Ok(())
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn ibc_channel_open(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    msg: cosmwasm_std::IbcChannelOpenMsg,
+) -> cosmwasm_std::StdResult<()> {
+    check_ibc_channel(deps.storage, msg.channel(), msg.counterparty_version())
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn ibc_channel_connect(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    msg: cosmwasm_std::IbcChannelConnectMsg,
+) -> cosmwasm_std::StdResult<cosmwasm_std::IbcBasicResponse> {
+    check_ibc_channel(deps.storage, msg.channel(), msg.counterparty_version())?;
+    let channel_id = msg.channel().endpoint.channel_id.clone();
+    let mut bucket = Bucket::<IbcChannelState>::new(deps.storage, IBC_CHANNELS_PREFIX);
+    let mut state = bucket.load(channel_id.as_bytes())?;
+    state.open = true;
+    bucket.save(channel_id.as_bytes(), &state)?;
+    Ok(cosmwasm_std::IbcBasicResponse::new().add_attribute("action", "ibc_channel_connect").add_attribute("channel_id", channel_id))
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn ibc_channel_close(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    msg: cosmwasm_std::IbcChannelCloseMsg,
+) -> cosmwasm_std::StdResult<cosmwasm_std::IbcBasicResponse> {
+    let channel_id = msg.channel().endpoint.channel_id.clone();
+    let mut bucket = Bucket::<IbcChannelState>::new(deps.storage, IBC_CHANNELS_PREFIX);
+    if let Some(mut state) = bucket.may_load(channel_id.as_bytes())? {
+        state.open = false;
+        bucket.save(channel_id.as_bytes(), &state)?;
+    }
+    Ok(cosmwasm_std::IbcBasicResponse::new().add_attribute("action", "ibc_channel_close").add_attribute("channel_id", channel_id))
+}
+
+// The packet is handled in a submessage to the contract itself, so a failure
+// anywhere along the way reverts all of its state changes. The reply then swaps
+// the acknowledgement for an error one and the sending chain refunds.
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn ibc_packet_receive(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    msg: cosmwasm_std::IbcPacketReceiveMsg,
+) -> cosmwasm_std::StdResult<cosmwasm_std::IbcReceiveResponse> {
+    record_block_height(deps.storage, &env)?;
+    let packet = msg.packet;
+    let channel_id = packet.dest.channel_id.clone();
+    let receive = cosmwasm_std::WasmMsg::Execute {
+        contract_addr: env.contract.address.to_string(),
+        msg: cosmwasm_std::to_binary(&ExecuteMsg::ReceiveIbcPacket { packet })?,
+        funds: vec![],
+    };
+    Ok(cosmwasm_std::IbcReceiveResponse::new()
+        .set_ack(cosmwasm_std::to_binary(&IbcAck::Result(cosmwasm_std::Binary::from(vec![1])))?)
+        .add_submessage(cosmwasm_std::SubMsg::reply_on_error(receive, IBC_RECEIVE_REPLY_ID))
+        .add_attribute("action", "ibc_receive")
+        .add_attribute("channel_id", channel_id))
+}
+
+pub fn receive_ibc_packet(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    packet: cosmwasm_std::IbcPacket,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if info.sender != env.contract.address {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    let (msg, msgs) = receive_burn_mint_packet(deps, &env, &packet)?;
+    Ok(cosmwasm_std::Response::new()
+        .add_submessages(msgs)
+        .add_attribute("action", "receive_ibc_packet")
+        .add_attribute("channel_id", packet.dest.channel_id)
+        .add_attribute("receiver", msg.receiver)
+        .add_attribute("amount", msg.amount.to_string()))
+}
+
+// Data set by a reply replaces the acknowledgement of the packet being received
+pub(crate) fn fail_ibc_receive(
+    result: cosmwasm_std::ContractResult<cosmwasm_std::SubMsgExecutionResponse>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let err = match result {
+        cosmwasm_std::ContractResult::Err(err) => err,
+        cosmwasm_std::ContractResult::Ok(_) => return Err(cosmwasm_std::StdError::generic_err("Only failed packets reply")),
+    };
+    Ok(cosmwasm_std::Response::new()
+        .set_data(cosmwasm_std::to_binary(&IbcAck::Error(err.clone()))?)
+        .add_attribute("action", "ibc_receive_failed")
+        .add_attribute("error", err))
+}
+
+// A memo that can't be acted on fails the packet, so the sending chain refunds
+// instead of the tokens stopping halfway along the route
+pub(crate) fn receive_burn_mint_packet(
+    mut deps: cosmwasm_std::DepsMut,
+    env: &cosmwasm_std::Env,
+    packet: &cosmwasm_std::IbcPacket,
+) -> cosmwasm_std::StdResult<(BurnMintPacket, Vec<cosmwasm_std::SubMsg>)> {
+    let channel = open_ibc_channel(deps.storage, &packet.dest.channel_id)?;
+    let msg: BurnMintPacket = cosmwasm_std::from_binary(&packet.data)?;
+    let receiver = HumanAddr::from(deps.api.addr_validate(&msg.receiver)?.as_str());
+    let memo = parse_ibc_memo(&msg.memo)?;
+    assert_mint_allowed(deps.as_ref(), &receiver, msg.amount)?;
+    record_flow(deps.storage, env, &ibc_route(&channel.channel_id), FlowDirection::In, msg.amount)?;
+    credit_ibc_channel(deps.storage, env, channel, &receiver, msg.amount)?;
+
+    let mut msgs = vec![];
+    match memo {
+        // The receiver only holds the tokens in passing. If a later hop fails the
+        // refund lands with the receiver on this chain, not the original sender.
+        Some(IbcMemo { forward: Some(forward), .. }) => {
+            let next = forward.next.map(|next| serde_json::from_str::<String>(next.get()).unwrap_or_else(|_| next.get().to_string()));
+            let send = send_ibc_packet(&mut deps, env, &receiver, &forward.channel, &forward.receiver, msg.amount, None, next)?;
+            msgs.push(cosmwasm_std::SubMsg::new(send));
+        }
+        // A failing hook leaves the tokens with the contract rather than failing the packet
+        Some(IbcMemo { wasm: Some(hook), .. }) => {
+            if hook.contract != msg.receiver {
+                return Err(cosmwasm_std::StdError::generic_err("Hook contract must be the packet receiver"));
+            }
+            let callback = ReceiverExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: HumanAddr::from(msg.sender.as_str()),
+                amount: msg.amount,
+                msg: cosmwasm_std::Binary::from(hook.msg.get().as_bytes()),
+            });
+            let callback = cosmwasm_std::WasmMsg::Execute { contract_addr: hook.contract, msg: cosmwasm_std::to_binary(&callback)?, funds: vec![] };
+            msgs.push(cosmwasm_std::SubMsg::reply_on_error(callback, IBC_HOOK_REPLY_ID));
+        }
+        _ => {}
+    }
+    Ok((msg, msgs))
+}
+
+// Mints in burn-and-mint mode, releases from the channel escrow in escrow mode
+pub(crate) fn credit_ibc_channel(
+    storage: &mut dyn cosmwasm_std::Storage,
+    env: &cosmwasm_std::Env,
+    mut channel: IbcChannelState,
+    to: &HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<()> {
+    match channel.mode {
+        IbcChannelMode::BurnMint => mint_balance(storage, to, amount),
+        IbcChannelMode::Escrow => {
+            // More can never come back than went out over the same channel
+            channel.escrowed = channel.escrowed.checked_sub(amount).map_err(|_| cosmwasm_std::StdError::generic_err("Amount exceeds the channel escrow"))?;
+            Bucket::new(storage, IBC_CHANNELS_PREFIX).save(channel.channel_id.as_bytes(), &channel)?;
+            escrow_out(storage, env, to, amount)
+        }
+    }
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn ibc_packet_ack(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    ack: cosmwasm_std::IbcPacketAckMsg,
+) -> cosmwasm_std::StdResult<cosmwasm_std::IbcBasicResponse> {
+    record_block_height(deps.storage, &env)?;
+    match cosmwasm_std::from_binary::<IbcAck>(&ack.acknowledgement.data)? {
+        IbcAck::Result(_) => Ok(cosmwasm_std::IbcBasicResponse::new().add_attribute("action", "ibc_ack").add_attribute("success", "true")),
+        IbcAck::Error(err) => refund_ibc_packet(deps, &env, &ack.original_packet, "ibc_ack").map(|res| res.add_attribute("error", err)),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn ibc_packet_timeout(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    msg: cosmwasm_std::IbcPacketTimeoutMsg,
+) -> cosmwasm_std::StdResult<cosmwasm_std::IbcBasicResponse> {
+    record_block_height(deps.storage, &env)?;
+    refund_ibc_packet(deps, &env, &msg.packet, "ibc_timeout")
+}
+
+// Gives the original sender back what was burned or escrowed. The channel may
+// have been removed since, in which case it can only have been burn-and-mint.
+pub(crate) fn refund_ibc_packet(
+    deps: cosmwasm_std::DepsMut,
+    env: &cosmwasm_std::Env,
+    packet: &cosmwasm_std::IbcPacket,
+    action: &str,
+) -> cosmwasm_std::StdResult<cosmwasm_std::IbcBasicResponse> {
+    let msg: BurnMintPacket = cosmwasm_std::from_binary(&packet.data)?;
+    let sender = HumanAddr::from(msg.sender.as_str());
+    let channel_id = &packet.src.channel_id;
+    match ReadonlyBucket::<IbcChannelState>::new(deps.storage, IBC_CHANNELS_PREFIX).may_load(channel_id.as_bytes())? {
+        Some(channel) => credit_ibc_channel(deps.storage, env, channel, &sender, msg.amount)?,
+        None => mint_balance(deps.storage, &sender, msg.amount)?,
+    }
+    Ok(cosmwasm_std::IbcBasicResponse::new()
+        .add_attribute("action", action)
+        .add_attribute("success", "false")
+        .add_attribute("refunded", sender)
+        .add_attribute("amount", msg.amount.to_string()))
+}
+
+pub const FLOW_LIMITS_PREFIX: &[u8] = b"flow_limits";
+pub const FLOW_USAGE_PREFIX: &[u8] = b"flow_usage";
+
+// Quotas for one route into or out of the contract, an IBC channel or a bridge.
+// Each quota refills linearly, it is fully available again `window_seconds` after being used up.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FlowLimits {
+    pub max_outflow: Uint128,
+    pub max_inflow: Uint128,
+    pub window_seconds: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct FlowUsage {
+    pub outflow: Uint128,
+    pub inflow: Uint128,
+    pub updated_at: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FlowDirection {
+    In,
+    Out,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FlowLimitResponse {
+    pub limits: Option<FlowLimits>,
+    pub outflow_available: Option<Uint128>,
+    pub inflow_available: Option<Uint128>,
+}
+
+pub fn ibc_route(channel_id: &str) -> String {
+    format!("ibc:{}", channel_id)
+}
+
+pub(crate) fn refill(used: Uint128, max: Uint128, elapsed: u64, window_seconds: u64) -> Uint128 {
+    if elapsed >= window_seconds {
+        return Uint128::zero();
+    }
+    used.saturating_sub(max.multiply_ratio(elapsed, window_seconds))
+}
+
+pub(crate) fn current_flow_usage(storage: &dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, route: &str, limits: &FlowLimits) -> cosmwasm_std::StdResult<FlowUsage> {
+    let now = env.block.time.seconds();
+    let usage = ReadonlyBucket::<FlowUsage>::new(storage, FLOW_USAGE_PREFIX).may_load(route.as_bytes())?.unwrap_or_default();
+    let elapsed = now.saturating_sub(usage.updated_at);
+    Ok(FlowUsage {
+        outflow: refill(usage.outflow, limits.max_outflow, elapsed, limits.window_seconds),
+        inflow: refill(usage.inflow, limits.max_inflow, elapsed, limits.window_seconds),
+        updated_at: now,
+    })
+}
+
+// Consumes quota on the route, routes without limits are unrestricted
+pub fn record_flow(
+    storage: &mut dyn cosmwasm_std::Storage,
+    env: &cosmwasm_std::Env,
+    route: &str,
+    direction: FlowDirection,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<()> {
+    let limits = match ReadonlyBucket::<FlowLimits>::new(storage, FLOW_LIMITS_PREFIX).may_load(route.as_bytes())? {
+        Some(limits) => limits,
+        None => return Ok(()),
+    };
+    let mut usage = current_flow_usage(storage, env, route, &limits)?;
+    let (used, max) = match direction {
+        FlowDirection::Out => (&mut usage.outflow, limits.max_outflow),
+        FlowDirection::In => (&mut usage.inflow, limits.max_inflow),
+    };
+    let available = max.saturating_sub(*used);
+    if amount > available {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Rate limit exceeded on {}, {} available", route, available)));
+    }
+    *used = used.checked_add(amount)?;
+    Bucket::new(storage, FLOW_USAGE_PREFIX).save(route.as_bytes(), &usage)
+}
+
+pub fn save_flow_limits(storage: &mut dyn cosmwasm_std::Storage, route: &str, limits: Option<FlowLimits>) -> cosmwasm_std::StdResult<()> {
+    match limits {
+        Some(limits) => {
+            if limits.window_seconds == 0 {
+                return Err(cosmwasm_std::StdError::generic_err("Rate limit window must be positive"));
+            }
+            Bucket::new(storage, FLOW_LIMITS_PREFIX).save(route.as_bytes(), &limits)
+        }
+        None => {
+            Bucket::<FlowLimits>::new(storage, FLOW_LIMITS_PREFIX).remove(route.as_bytes());
+            Bucket::<FlowUsage>::new(storage, FLOW_USAGE_PREFIX).remove(route.as_bytes());
+            Ok(())
+        }
+    }
+}
+
+pub fn set_ibc_rate_limit(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    channel_id: String,
+    limits: Option<FlowLimits>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    save_flow_limits(deps.storage, &ibc_route(&channel_id), limits)?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_ibc_rate_limit").add_attribute("channel_id", channel_id))
+}
+
+pub fn query_flow_limit(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, route: &str) -> cosmwasm_std::StdResult<FlowLimitResponse> {
+    let limits = ReadonlyBucket::<FlowLimits>::new(deps.storage, FLOW_LIMITS_PREFIX).may_load(route.as_bytes())?;
+    let usage = match &limits {
+        Some(limits) => Some(current_flow_usage(deps.storage, &env, route, limits)?),
+        None => None,
+    };
+    Ok(FlowLimitResponse {
+        outflow_available: limits.as_ref().zip(usage.as_ref()).map(|(l, u)| l.max_outflow.saturating_sub(u.outflow)),
+        inflow_available: limits.as_ref().zip(usage.as_ref()).map(|(l, u)| l.max_inflow.saturating_sub(u.inflow)),
+        limits,
+    })
+}
+
+pub const BRIDGES_PREFIX: &[u8] = b"bridges";
+
+// An external bridge contract allowed to mint and burn within its flow limits
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BridgeAdapter {
+    pub adapter: HumanAddr,
+    pub total_minted: Uint128,
+    pub total_burned: Uint128,
+}
+
+pub fn bridge_route(adapter: &HumanAddr) -> String {
+    format!("bridge:{}", adapter)
+}
+
+pub(crate) fn load_bridge(storage: &dyn cosmwasm_std::Storage, adapter: &HumanAddr) -> cosmwasm_std::StdResult<BridgeAdapter> {
+    ReadonlyBucket::<BridgeAdapter>::new(storage, BRIDGES_PREFIX)
+        .may_load(adapter.as_bytes())?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err(format!("{} is not a registered bridge", adapter)))
+}
+
+// Re-registering updates the limits and keeps the totals
+pub fn register_bridge(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    adapter: HumanAddr,
+    limits: FlowLimits,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    save_flow_limits(deps.storage, &bridge_route(&adapter), Some(limits))?;
+    let mut bucket = Bucket::<BridgeAdapter>::new(deps.storage, BRIDGES_PREFIX);
+    if bucket.may_load(adapter.as_bytes())?.is_none() {
+        bucket.save(adapter.as_bytes(), &BridgeAdapter { adapter: adapter.clone(), total_minted: Uint128::zero(), total_burned: Uint128::zero() })?;
+    }
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "register_bridge").add_attribute("adapter", adapter))
+}
+
+pub fn deregister_bridge(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    adapter: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    load_bridge(deps.storage, &adapter)?;
+    Bucket::<BridgeAdapter>::new(deps.storage, BRIDGES_PREFIX).remove(adapter.as_bytes());
+    save_flow_limits(deps.storage, &bridge_route(&adapter), None)?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "deregister_bridge").add_attribute("adapter", adapter))
+}
+
+// Called by the bridge once a transfer from another chain is final
+pub fn bridge_mint(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipient: HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut bridge = load_bridge(deps.storage, &HumanAddr::from(&info.sender))?;
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    record_flow(deps.storage, &env, &bridge_route(&HumanAddr::from(&info.sender)), FlowDirection::In, amount)?;
+    assert_mint_allowed(deps.as_ref(), &recipient, amount)?;
+    mint_balance(deps.storage, &recipient, amount)?;
+    bridge.total_minted = bridge.total_minted.checked_add(amount)?;
+    Bucket::new(deps.storage, BRIDGES_PREFIX).save(info.sender.as_bytes(), &bridge)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "bridge_mint").add_attribute("bridge", info.sender).add_attribute("to", recipient).add_attribute("amount", amount.to_string()))
+}
+
+// Burns tokens leaving through the bridge, from the bridge itself or from an owner
+// who approved it
+pub fn bridge_burn(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    owner: HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut bridge = load_bridge(deps.storage, &HumanAddr::from(&info.sender))?;
+    let contract = HumanAddr::from(env.contract.address.as_str());
+    assert_escrow_allowed(deps.as_ref(), &env, &owner, &contract, amount)?;
+    record_flow(deps.storage, &env, &bridge_route(&HumanAddr::from(&info.sender)), FlowDirection::Out, amount)?;
+
+    if owner.as_str() != info.sender.as_str() {
+        spend_allowance(deps.storage, &env, &owner, &HumanAddr::from(&info.sender), amount)?;
+    }
+
+    destroy_balance(deps.storage, &owner, amount)?;
+    bridge.total_burned = bridge.total_burned.checked_add(amount)?;
+    Bucket::new(deps.storage, BRIDGES_PREFIX).save(info.sender.as_bytes(), &bridge)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "bridge_burn").add_attribute("bridge", info.sender).add_attribute("from", owner).add_attribute("amount", amount.to_string()))
+}
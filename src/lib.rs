@@ -0,0 +1,4734 @@
+// HumanAddr is deprecated from cosmwasm-std 0.14 on, the contract still stores and
+// exchanges plain address strings through it
+#![allow(deprecated)]
+
+use cosmwasm_std::{Decimal, HumanAddr, Uint128};
+use cosmwasm_storage::{ReadonlySingleton, Singleton, ReadonlyBucket, Bucket};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// Shared with integrators through the cw20-msgs package
+pub use cw20_msgs::{ApprovalReceiverExecuteMsg, BalanceResponse, Cw20ApprovalMsg, Cw20ReceiveMsg, Expiration, ReceiverExecuteMsg, TokenInfoResponse};
+
+pub mod compliance;
+pub mod escrow;
+pub mod fees;
+pub mod governance;
+pub mod helpers;
+pub mod ibc;
+pub mod receiver;
+pub mod staking;
+#[cfg(test)]
+mod tests;
+
+pub use compliance::*;
+pub use escrow::*;
+pub use fees::*;
+pub use governance::*;
+pub use ibc::*;
+pub use staking::*;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenInfo {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub total_supply: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Balance {
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Allowance {
+    pub spender: HumanAddr,
+    pub owner: HumanAddr,
+    pub allowance: Uint128,
+    #[serde(default)]
+    pub expires: Option<Expiration>,
+    // Redenominations already applied to `allowance`, later ones are applied on load
+    #[serde(default)]
+    pub redenominations: u64,
+}
+
+pub const TOKEN_INFO_KEY: &[u8] = b"token_info";
+pub const BALANCES_PREFIX: &[u8] = b"balances";
+pub const ALLOWANCES_PREFIX: &[u8] = b"allowances";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub total_supply: Uint128,
+    // Add other fields as needed
+}
+
+// Allowances are rescaled lazily here, a redenomination can't rewrite all of them in one go
+pub fn load_allowance(storage: &dyn cosmwasm_std::Storage, owner: &HumanAddr, spender: &HumanAddr) -> cosmwasm_std::StdResult<Allowance> {
+    let history = ReadonlySingleton::<Vec<RedenominationRecord>>::new(storage, REDENOMINATIONS_KEY).may_load()?.unwrap_or_default();
+    let allowance = ReadonlyBucket::<Allowance>::multilevel(storage, &[ALLOWANCES_PREFIX, owner.as_bytes()]).may_load(spender.as_bytes())?;
+    let mut allowance = match allowance {
+        Some(allowance) => allowance,
+        None => return Ok(Allowance { spender: spender.clone(), owner: owner.clone(), allowance: Uint128::zero(), expires: None, redenominations: history.len() as u64 }),
+    };
+    for r in history.iter().skip(allowance.redenominations as usize) {
+        allowance.allowance = allowance.allowance.multiply_ratio(r.numerator, r.denominator);
+    }
+    allowance.redenominations = history.len() as u64;
+    Ok(allowance)
+}
+
+pub fn save_allowance(storage: &mut dyn cosmwasm_std::Storage, allowance: &Allowance) -> cosmwasm_std::StdResult<()> {
+    Bucket::multilevel(storage, &[ALLOWANCES_PREFIX, allowance.owner.as_bytes()]).save(allowance.spender.as_bytes(), allowance)
+}
+
+pub fn load_balance(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<Balance> {
+    Ok(ReadonlyBucket::<Balance>::new(storage, BALANCES_PREFIX).may_load(address.as_bytes())?.unwrap_or(Balance { amount: Uint128::zero() }))
+}
+
+pub fn save_balance(storage: &mut dyn cosmwasm_std::Storage, address: &HumanAddr, balance: &Balance) -> cosmwasm_std::StdResult<()> {
+    Bucket::new(storage, BALANCES_PREFIX).save(address.as_bytes(), balance)
+}
+
+pub const OWNER_KEY: &[u8] = b"owner";
+
+// Set to the instantiator
+pub fn contract_owner(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<HumanAddr> {
+    ReadonlySingleton::<HumanAddr>::new(storage, OWNER_KEY).load()
+}
+
+pub const PAUSED_KEY: &[u8] = b"paused";
+
+pub fn is_paused(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<bool> {
+    Ok(ReadonlySingleton::<bool>::new(storage, PAUSED_KEY).may_load()?.unwrap_or(false))
+}
+
+pub fn set_paused(storage: &mut dyn cosmwasm_std::Storage, paused: bool) -> cosmwasm_std::StdResult<()> {
+    Singleton::new(storage, PAUSED_KEY).save(&paused)
+}
+
+pub fn assert_owner(storage: &dyn cosmwasm_std::Storage, sender: &HumanAddr) -> cosmwasm_std::StdResult<()> {
+    if *sender != contract_owner(storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Unauthorized"));
+    }
+    Ok(())
+}
+
+pub fn pause(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(info.sender.as_str()))?;
+
+    set_paused(deps.storage, true)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "pause"))
+}
+
+pub fn unpause(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(info.sender.as_str()))?;
+
+    set_paused(deps.storage, false)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "unpause"))
+}
+
+// ... instantiate and query functions ...
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InstantiateMsg {
+    pub initial_balances: Vec<InitialBalance>,
+    // Hard upper bound for the burn tax, it can never be raised after instantiation
+    pub max_burn_tax_bps: Option<u64>,
+    // Immutable maximum total supply
+    pub cap: Option<Uint128>,
+    // Allocation minted to the contract and released in monthly tranches
+    pub treasury: Option<TreasuryAllocation>,
+    // Creates factory/<contract>/<subdenom> that balances can be converted into
+    #[serde(default)]
+    pub tokenfactory_subdenom: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InitialBalance {
+    pub address: HumanAddr,
+    pub amount: Uint128,
+    pub vesting: Option<VestingSchedule>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecuteMsg {
+    Transfer {
+        recipient: HumanAddr,
+        amount: Uint128,
+        #[serde(default)]
+        memo: Option<String>,
+    },
+    TransferFrom {
+        owner: HumanAddr,
+        recipient: HumanAddr,
+        amount: Uint128,
+        #[serde(default)]
+        memo: Option<String>,
+    },
+    Approve { spender: HumanAddr, amount: Uint128 },
+    DecreaseAllowance { spender: HumanAddr, amount: Uint128 },
+    Burn { amount: Uint128 },
+    Mint { recipient: HumanAddr, amount: Uint128 },
+    Pause {},
+    Unpause {},
+    SetBlacklister { blacklister: HumanAddr },
+    Blacklist { address: HumanAddr },
+    Unblacklist { address: HumanAddr },
+    SetWhitelistMode { enabled: bool },
+    AddToWhitelist { address: HumanAddr },
+    RemoveFromWhitelist { address: HumanAddr },
+    FreezeAccount { address: HumanAddr },
+    UnfreezeAccount { address: HumanAddr },
+    SetComplianceOfficer { officer: HumanAddr },
+    ForceTransfer { from: HumanAddr, to: HumanAddr, amount: Uint128, reason: String },
+    SetRestrictionContract { contract: Option<HumanAddr> },
+    SetKycRegistry { config: Option<KycConfig> },
+    UpdateSanctionsConfig { oracle: Option<HumanAddr>, enabled: Option<bool> },
+    SetMaxWalletBalance { max: Option<Uint128> },
+    SetLimitExemption { limit: LimitKind, address: HumanAddr, exempt: bool },
+    SetMaxTransactionAmount { max: Option<Uint128> },
+    SetDailyLimit { limit: Option<DailyLimit> },
+    SetCircuitBreaker { breaker: Option<CircuitBreaker> },
+    SetTransferCooldown { cooldown: Option<Duration> },
+    SetLaunchConfig { launch_height: Option<u64> },
+    EnableTrading {},
+    RecoverStuckTokens { recipient: HumanAddr, amount: Option<Uint128> },
+    SetClawbackConfig { config: ClawbackConfig },
+    Clawback { from: HumanAddr, amount: Uint128, reason: String },
+    ExecuteClawback { id: u64 },
+    VetoClawback { id: u64 },
+    SetLargeTransferConfig { config: Option<LargeTransferConfig> },
+    AnnounceTransfer { recipient: HumanAddr, amount: Uint128 },
+    ExecuteAnnouncedTransfer { id: u64 },
+    CancelAnnouncedTransfer { id: u64 },
+    LockMyAccount { until: Expiration },
+    SetBeneficiary { beneficiary: HumanAddr, inactivity_period: Duration },
+    RemoveBeneficiary {},
+    ResetInactivityTimer {},
+    ClaimInheritance { from: HumanAddr },
+    SetFeeConfig { config: Option<FeeConfig> },
+    SetBurnTax { bps: u64, enabled: bool },
+    SetReflection { bps: Option<u64> },
+    DepositDividends {},
+    ClaimDividends {},
+    SetDividendDenoms { denoms: Vec<String> },
+    Receive(Cw20ReceiveMsg),
+    SetRebaser { rebaser: Option<HumanAddr> },
+    Rebase { new_supply: Uint128 },
+    SetInterestRate { rate_bps_per_year: Option<u64> },
+    Accrue {},
+    SetEmissionSchedule { schedule: Option<EmissionScheduleMsg> },
+    Crank {},
+    SetBurnSchedule { schedule: Option<BurnScheduleMsg> },
+    TransferLocked { recipient: HumanAddr, amount: Uint128, unlock_time: u64 },
+    CreateStream { recipient: HumanAddr, amount: Uint128, start: u64, end: u64 },
+    WithdrawStream { id: u64 },
+    CancelStream { id: u64 },
+    CreateEscrow { recipient: HumanAddr, arbiter: HumanAddr, amount: Uint128, expires: Expiration },
+    ApproveEscrow { id: u64 },
+    RefundEscrow { id: u64 },
+    RegisterMerkleRoot { merkle_root: String, total_amount: Uint128, expiration: Option<Expiration> },
+    Claim { stage: u64, amount: Uint128, proof: Vec<String> },
+    ClawbackAirdrop { stage: u64 },
+    MintBatch { recipients: Vec<(HumanAddr, Uint128)> },
+    TransferBatch { transfers: Vec<(HumanAddr, Uint128)> },
+    SetLegacyMigration { config: Option<LegacyMigration> },
+    SetBondingCurve { curve: CurveType, reserve_denom: String },
+    BuyFromCurve {},
+    SellToCurve { amount: Uint128 },
+    FlashMint { amount: Uint128, msg: cosmwasm_std::Binary },
+    SetFlashMintFee { fee_bps: u64 },
+    ReleaseTreasury {},
+    SetupLockdrop { config: LockdropConfig },
+    LockdropDeposit { duration: u64 },
+    ClaimLockdropReward {},
+    WithdrawLockdrop { duration: u64 },
+    MintSplit { total: Uint128, recipients: Vec<(HumanAddr, u64)> },
+    SetBuyback { config: Option<BuybackConfig> },
+    Buyback {},
+    SetMinTransferAmount { min: Option<Uint128> },
+    SetNonCirculating { address: HumanAddr, non_circulating: bool },
+    Delegate { delegatee: Option<HumanAddr> },
+    SetStakingConfig { unbonding_period: Duration },
+    SetStakingRewardDenoms { denoms: Vec<String> },
+    Stake { amount: Uint128 },
+    Unstake { amount: Uint128 },
+    ClaimUnstaked {},
+    FundStakingRewards {},
+    ClaimRewards { denom: Option<String> },
+    SetAutoCompound { enabled: bool },
+    SetEarlyUnbondPenalty { penalty: Option<EarlyUnbondPenalty> },
+    UnbondEarly { id: u64 },
+    AddStakeHook { addr: HumanAddr },
+    RemoveStakeHook { addr: HumanAddr },
+    DelegateBySig { delegator: HumanAddr, delegatee: Option<HumanAddr>, nonce: u64, expiry: Expiration, pubkey: cosmwasm_std::Binary, signature: cosmwasm_std::Binary },
+    SetGovernance { config: Option<GovernanceConfig> },
+    Propose { title: String, description: String, actions: Vec<ProposalAction> },
+    CastVote { id: u64, vote: VoteOption },
+    ExecuteProposal { id: u64 },
+    VetoProposal { id: u64 },
+    AddTransferHook { hook: TransferHook },
+    RemoveTransferHook { contract: HumanAddr },
+    SetCollateral { token: Option<HumanAddr> },
+    Unwrap { amount: Uint128 },
+    SetNativeWrapper { denom: Option<String> },
+    Deposit {},
+    Withdraw { amount: Uint128 },
+    ConvertToBank { amount: Uint128 },
+    ConvertToCw20 {},
+    StartNativeMigration { deadline: Expiration },
+    ConvertToNative {},
+    AllowIbcChannel { channel_id: String, mode: Option<IbcChannelMode> },
+    RemoveIbcChannel { channel_id: String },
+    IbcTransfer {
+        channel_id: String,
+        receiver: String,
+        amount: Uint128,
+        timeout_seconds: Option<u64>,
+        // Forwarding or contract call on arrival, see `IbcMemo`
+        #[serde(default)]
+        memo: Option<String>,
+    },
+    SetIbcRateLimit { channel_id: String, limits: Option<FlowLimits> },
+    // Sent by the contract to itself for every received packet, see `ibc_packet_receive`
+    ReceiveIbcPacket { packet: cosmwasm_std::IbcPacket },
+    RegisterBridge { adapter: HumanAddr, limits: FlowLimits },
+    DeregisterBridge { adapter: HumanAddr },
+    BridgeMint { recipient: HumanAddr, amount: Uint128 },
+    BridgeBurn { owner: HumanAddr, amount: Uint128 },
+    IncreaseAllowanceAndCall { spender: HumanAddr, amount: Uint128, expires: Option<Expiration>, msg: cosmwasm_std::Binary },
+    Permit {
+        owner: HumanAddr,
+        spender: HumanAddr,
+        amount: Uint128,
+        nonce: u64,
+        deadline: Expiration,
+        pubkey: cosmwasm_std::Binary,
+        signature: cosmwasm_std::Binary,
+    },
+    ExecuteSigned {
+        signer: HumanAddr,
+        payload: SignedAction,
+        nonce: u64,
+        expiry: Expiration,
+        pubkey: cosmwasm_std::Binary,
+        signature: cosmwasm_std::Binary,
+    },
+    AuthorizeOperator { operator: HumanAddr },
+    RevokeOperator { operator: HumanAddr },
+    GrantAction { grantee: HumanAddr, action: GrantableAction, expires: Option<Expiration> },
+    RevokeAction { grantee: HumanAddr, action: GrantableAction },
+    ExecuteOnBehalf { granter: HumanAddr, action: DelegatedAction },
+    Send {
+        contract: HumanAddr,
+        amount: Uint128,
+        msg: cosmwasm_std::Binary,
+        #[serde(default)]
+        memo: Option<String>,
+    },
+    SendChecked {
+        contract: HumanAddr,
+        amount: Uint128,
+        msg: cosmwasm_std::Binary,
+        #[serde(default)]
+        memo: Option<String>,
+    },
+    SendFrom { from: HumanAddr, to: HumanAddr, token_id: String, value: Uint128, msg: Option<cosmwasm_std::Binary> },
+    BatchSendFrom { from: HumanAddr, to: HumanAddr, batch: Vec<(String, Uint128)>, msg: Option<cosmwasm_std::Binary> },
+    ApproveAll { operator: HumanAddr, expires: Option<Expiration> },
+    RevokeAll { operator: HumanAddr },
+    CreateInvoice { payer: HumanAddr, amount: Uint128, memo: Option<String>, expires: Expiration },
+    ApproveInvoice { id: u64 },
+    CollectInvoice { id: u64 },
+    CancelInvoice { id: u64 },
+    SetRecurringAllowance { spender: HumanAddr, allowance: Option<RecurringAllowanceMsg> },
+    LockHtlc { recipient: HumanAddr, amount: Uint128, hash: String, timeout: Expiration },
+    ClaimHtlc { id: u64, preimage: cosmwasm_std::Binary },
+    RefundHtlc { id: u64 },
+    CreateTrade { amount: Uint128, ask_token: HumanAddr, ask_amount: Uint128, expires: Expiration },
+    CancelTrade { id: u64 },
+    OpenPaymentChannel { counterparty: HumanAddr, amount: Uint128, challenge_period: u64, pubkey: cosmwasm_std::Binary },
+    UpdatePaymentChannel { id: u64, nonce: u64, paid: Uint128, signature: cosmwasm_std::Binary },
+    ClosePaymentChannel { id: u64 },
+    FinalizePaymentChannel { id: u64 },
+    RegisterPayroll { payouts: Vec<(HumanAddr, Uint128)>, interval: u64, start: Option<u64> },
+    CancelPayroll { id: u64 },
+    RunPayroll { id: u64 },
+    SetUsdLimits { limits: Option<UsdLimits> },
+    TransferPending { recipient: HumanAddr, amount: Uint128, expires: Expiration },
+    ClaimTransfer { id: u64 },
+    CancelTransfer { id: u64 },
+    ScheduleTransfer { recipient: HumanAddr, amount: Uint128, at: Expiration },
+    ExecuteScheduledTransfer { id: u64 },
+    CancelScheduledTransfer { id: u64 },
+    RecoverCw20 { token: HumanAddr, recipient: HumanAddr, amount: Uint128 },
+    SweepNative { denom: String, recipient: HumanAddr },
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn instantiate(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    msg: InstantiateMsg,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    record_block_height(deps.storage, &env)?;
+    Singleton::new(deps.storage, OWNER_KEY).save(&HumanAddr::from(info.sender.as_str()))?;
+    save_token_info(deps.storage, &TokenInfo { name: "My Token".to_string(), symbol: "MYT".to_string(), decimals: 6, total_supply: Uint128::zero() })?;
+    // Saved before anything is minted so the initial balances count against it
+    if let Some(cap) = msg.cap {
+        Singleton::new(deps.storage, CAP_KEY).save(&cap)?;
+    }
+
+    for balance in msg.initial_balances {
+        if let Some(schedule) = &balance.vesting {
+            if schedule.cliff < schedule.start || schedule.end < schedule.cliff {
+                return Err(cosmwasm_std::StdError::generic_err("Invalid vesting schedule"));
+            }
+            let vesting = Vesting { total: balance.amount, schedule: schedule.clone() };
+            Bucket::new(deps.storage, VESTING_PREFIX).save(balance.address.as_bytes(), &vesting)?;
+        }
+        mint_balance(deps.storage, &balance.address, balance.amount)?;
+    }
+
+    if let Some(max_burn_tax_bps) = msg.max_burn_tax_bps {
+        if max_burn_tax_bps > MAX_FEE_BPS {
+            return Err(cosmwasm_std::StdError::generic_err("Burn tax cannot exceed 100%"));
+        }
+        Singleton::new(deps.storage, MAX_BURN_TAX_KEY).save(&max_burn_tax_bps)?;
+    }
+
+    if let Some(allocation) = msg.treasury {
+        if allocation.amount.is_zero() || allocation.tranches == 0 {
+            return Err(cosmwasm_std::StdError::generic_err("Invalid treasury allocation"));
+        }
+        // Treasury tokens sit on the contract as escrow so they can't be recovered or swept
+        mint_balance(deps.storage, &HumanAddr::from(env.contract.address.as_str()), allocation.amount)?;
+        add_escrowed(deps.storage, allocation.amount)?;
+        let treasury = Treasury {
+            destination: allocation.destination,
+            total: allocation.amount,
+            released: Uint128::zero(),
+            start: env.block.time.seconds(),
+            tranches: allocation.tranches,
+        };
+        Singleton::new(deps.storage, TREASURY_KEY).save(&treasury)?;
+    }
+
+    let mut res = cosmwasm_std::Response::new();
+    if let Some(subdenom) = msg.tokenfactory_subdenom {
+        let contract = env.contract.address.to_string();
+        let denom = FactoryDenom { denom: format!("factory/{}/{}", contract, subdenom), bank_supply: Uint128::zero() };
+        Singleton::new(deps.storage, TOKENFACTORY_KEY).save(&denom)?;
+        res = res.add_message(tokenfactory_create_denom_msg(&contract, &subdenom));
+    }
+
+    Ok(res)
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn execute(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    msg: ExecuteMsg,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    record_block_height(deps.storage, &env)?;
+    match msg {
+        ExecuteMsg::Transfer { recipient, amount, memo } => transfer(deps, env, info, recipient, amount, memo),
+        ExecuteMsg::TransferFrom { owner, recipient, amount, memo } => transfer_from(deps, env, info, owner, recipient, amount, memo),
+        ExecuteMsg::Approve { spender, amount } => approve(deps, env, info, spender, amount),
+        ExecuteMsg::DecreaseAllowance { spender, amount } => decrease_allowance(deps, env, info, spender, amount),
+        ExecuteMsg::Burn { amount } => burn(deps, env, info, amount),
+        ExecuteMsg::Mint { recipient, amount } => mint(deps, env, info, recipient, amount),
+        ExecuteMsg::Pause {} => pause(deps, env, info),
+        ExecuteMsg::Unpause {} => unpause(deps, env, info),
+        ExecuteMsg::SetBlacklister { blacklister } => set_blacklister(deps, env, info, blacklister),
+        ExecuteMsg::Blacklist { address } => blacklist(deps, env, info, address),
+        ExecuteMsg::Unblacklist { address } => unblacklist(deps, env, info, address),
+        ExecuteMsg::SetWhitelistMode { enabled } => set_whitelist_mode(deps, env, info, enabled),
+        ExecuteMsg::AddToWhitelist { address } => add_to_whitelist(deps, env, info, address),
+        ExecuteMsg::RemoveFromWhitelist { address } => remove_from_whitelist(deps, env, info, address),
+        ExecuteMsg::FreezeAccount { address } => freeze_account(deps, env, info, address),
+        ExecuteMsg::UnfreezeAccount { address } => unfreeze_account(deps, env, info, address),
+        ExecuteMsg::SetComplianceOfficer { officer } => set_compliance_officer(deps, env, info, officer),
+        ExecuteMsg::ForceTransfer { from, to, amount, reason } => force_transfer(deps, env, info, from, to, amount, reason),
+        ExecuteMsg::SetRestrictionContract { contract } => set_restriction_contract(deps, env, info, contract),
+        ExecuteMsg::SetKycRegistry { config } => set_kyc_registry(deps, env, info, config),
+        ExecuteMsg::UpdateSanctionsConfig { oracle, enabled } => update_sanctions_config(deps, env, info, oracle, enabled),
+        ExecuteMsg::SetMaxWalletBalance { max } => set_max_wallet_balance(deps, env, info, max),
+        ExecuteMsg::SetLimitExemption { limit, address, exempt } => set_limit_exemption(deps, env, info, limit, address, exempt),
+        ExecuteMsg::SetMaxTransactionAmount { max } => set_max_transaction_amount(deps, env, info, max),
+        ExecuteMsg::SetDailyLimit { limit } => set_daily_limit(deps, env, info, limit),
+        ExecuteMsg::SetCircuitBreaker { breaker } => set_circuit_breaker(deps, env, info, breaker),
+        ExecuteMsg::SetTransferCooldown { cooldown } => set_transfer_cooldown(deps, env, info, cooldown),
+        ExecuteMsg::SetLaunchConfig { launch_height } => set_launch_config(deps, env, info, launch_height),
+        ExecuteMsg::EnableTrading {} => enable_trading(deps, env, info),
+        ExecuteMsg::RecoverStuckTokens { recipient, amount } => recover_stuck_tokens(deps, env, info, recipient, amount),
+        ExecuteMsg::SetClawbackConfig { config } => set_clawback_config(deps, env, info, config),
+        ExecuteMsg::Clawback { from, amount, reason } => propose_clawback(deps, env, info, from, amount, reason),
+        ExecuteMsg::ExecuteClawback { id } => execute_clawback(deps, env, info, id),
+        ExecuteMsg::VetoClawback { id } => veto_clawback(deps, env, info, id),
+        ExecuteMsg::SetLargeTransferConfig { config } => set_large_transfer_config(deps, env, info, config),
+        ExecuteMsg::AnnounceTransfer { recipient, amount } => announce_transfer(deps, env, info, recipient, amount),
+        ExecuteMsg::ExecuteAnnouncedTransfer { id } => execute_announced_transfer(deps, env, info, id),
+        ExecuteMsg::CancelAnnouncedTransfer { id } => cancel_announced_transfer(deps, env, info, id),
+        ExecuteMsg::LockMyAccount { until } => lock_my_account(deps, env, info, until),
+        ExecuteMsg::SetBeneficiary { beneficiary, inactivity_period } => set_beneficiary(deps, env, info, beneficiary, inactivity_period),
+        ExecuteMsg::RemoveBeneficiary {} => remove_beneficiary(deps, env, info),
+        ExecuteMsg::ResetInactivityTimer {} => reset_inactivity_timer(deps, env, info),
+        ExecuteMsg::ClaimInheritance { from } => claim_inheritance(deps, env, info, from),
+        ExecuteMsg::SetFeeConfig { config } => set_fee_config(deps, env, info, config),
+        ExecuteMsg::SetBurnTax { bps, enabled } => set_burn_tax(deps, env, info, bps, enabled),
+        ExecuteMsg::SetReflection { bps } => set_reflection(deps, env, info, bps),
+        ExecuteMsg::DepositDividends {} => deposit_dividends(deps, env, info),
+        ExecuteMsg::ClaimDividends {} => claim_dividends(deps, env, info),
+        ExecuteMsg::SetDividendDenoms { denoms } => set_dividend_denoms(deps, env, info, denoms),
+        ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
+        ExecuteMsg::SetRebaser { rebaser } => set_rebaser(deps, env, info, rebaser),
+        ExecuteMsg::Rebase { new_supply } => rebase(deps, env, info, new_supply),
+        ExecuteMsg::SetInterestRate { rate_bps_per_year } => set_interest_rate(deps, env, info, rate_bps_per_year),
+        ExecuteMsg::Accrue {} => accrue(deps, env),
+        ExecuteMsg::SetEmissionSchedule { schedule } => set_emission_schedule(deps, env, info, schedule),
+        ExecuteMsg::Crank {} => crank(deps, env),
+        ExecuteMsg::SetBurnSchedule { schedule } => set_burn_schedule(deps, env, info, schedule),
+        ExecuteMsg::TransferLocked { recipient, amount, unlock_time } => transfer_locked(deps, env, info, recipient, amount, unlock_time),
+        ExecuteMsg::CreateStream { recipient, amount, start, end } => create_stream(deps, env, info, recipient, amount, start, end),
+        ExecuteMsg::WithdrawStream { id } => withdraw_stream(deps, env, info, id),
+        ExecuteMsg::CancelStream { id } => cancel_stream(deps, env, info, id),
+        ExecuteMsg::CreateEscrow { recipient, arbiter, amount, expires } => create_escrow(deps, env, info, recipient, arbiter, amount, expires),
+        ExecuteMsg::ApproveEscrow { id } => approve_escrow(deps, env, info, id),
+        ExecuteMsg::RefundEscrow { id } => refund_escrow(deps, env, info, id),
+        ExecuteMsg::RegisterMerkleRoot { merkle_root, total_amount, expiration } => register_merkle_root(deps, env, info, merkle_root, total_amount, expiration),
+        ExecuteMsg::Claim { stage, amount, proof } => claim_airdrop(deps, env, info, stage, amount, proof),
+        ExecuteMsg::ClawbackAirdrop { stage } => clawback_airdrop(deps, env, info, stage),
+        ExecuteMsg::MintBatch { recipients } => mint_batch(deps, env, info, recipients),
+        ExecuteMsg::TransferBatch { transfers } => transfer_batch(deps, env, info, transfers),
+        ExecuteMsg::SetLegacyMigration { config } => set_legacy_migration(deps, env, info, config),
+        ExecuteMsg::SetBondingCurve { curve, reserve_denom } => set_bonding_curve(deps, env, info, curve, reserve_denom),
+        ExecuteMsg::BuyFromCurve {} => buy_from_curve(deps, env, info),
+        ExecuteMsg::SellToCurve { amount } => sell_to_curve(deps, env, info, amount),
+        ExecuteMsg::FlashMint { amount, msg } => flash_mint(deps, env, info, amount, msg),
+        ExecuteMsg::SetFlashMintFee { fee_bps } => set_flash_mint_fee(deps, env, info, fee_bps),
+        ExecuteMsg::ReleaseTreasury {} => release_treasury(deps, env, info),
+        ExecuteMsg::SetupLockdrop { config } => setup_lockdrop(deps, env, info, config),
+        ExecuteMsg::LockdropDeposit { duration } => lockdrop_native_deposit(deps, env, info, duration),
+        ExecuteMsg::ClaimLockdropReward {} => claim_lockdrop_reward(deps, env, info),
+        ExecuteMsg::WithdrawLockdrop { duration } => withdraw_lockdrop(deps, env, info, duration),
+        ExecuteMsg::MintSplit { total, recipients } => mint_split(deps, env, info, total, recipients),
+        ExecuteMsg::SetBuyback { config } => set_buyback(deps, env, info, config),
+        ExecuteMsg::Buyback {} => buyback(deps, env, info),
+        ExecuteMsg::SetMinTransferAmount { min } => set_min_transfer_amount(deps, env, info, min),
+        ExecuteMsg::SetNonCirculating { address, non_circulating } => set_non_circulating(deps, env, info, address, non_circulating),
+        ExecuteMsg::Delegate { delegatee } => delegate(deps, env, info, delegatee),
+        ExecuteMsg::SetStakingConfig { unbonding_period } => set_staking_config(deps, env, info, unbonding_period),
+        ExecuteMsg::SetStakingRewardDenoms { denoms } => set_staking_reward_denoms(deps, env, info, denoms),
+        ExecuteMsg::Stake { amount } => stake(deps, env, info, amount),
+        ExecuteMsg::Unstake { amount } => unstake(deps, env, info, amount),
+        ExecuteMsg::ClaimUnstaked {} => claim_unstaked(deps, env, info),
+        ExecuteMsg::FundStakingRewards {} => fund_staking_rewards_native(deps, env, info),
+        ExecuteMsg::ClaimRewards { denom } => claim_staking_rewards(deps, env, info, denom),
+        ExecuteMsg::SetAutoCompound { enabled } => set_auto_compound(deps, env, info, enabled),
+        ExecuteMsg::SetEarlyUnbondPenalty { penalty } => set_early_unbond_penalty(deps, env, info, penalty),
+        ExecuteMsg::UnbondEarly { id } => unbond_early(deps, env, info, id),
+        ExecuteMsg::AddStakeHook { addr } => add_stake_hook(deps, env, info, addr),
+        ExecuteMsg::RemoveStakeHook { addr } => remove_stake_hook(deps, env, info, addr),
+        ExecuteMsg::DelegateBySig { delegator, delegatee, nonce, expiry, pubkey, signature } => delegate_by_sig(deps, env, info, delegator, delegatee, nonce, expiry, pubkey, signature),
+        ExecuteMsg::SetGovernance { config } => set_governance(deps, env, info, config),
+        ExecuteMsg::Propose { title, description, actions } => propose(deps, env, info, title, description, actions),
+        ExecuteMsg::CastVote { id, vote } => cast_vote(deps, env, info, id, vote),
+        ExecuteMsg::ExecuteProposal { id } => execute_proposal(deps, env, info, id),
+        ExecuteMsg::VetoProposal { id } => veto_proposal(deps, env, info, id),
+        ExecuteMsg::AddTransferHook { hook } => add_transfer_hook(deps, env, info, hook),
+        ExecuteMsg::RemoveTransferHook { contract } => remove_transfer_hook(deps, env, info, contract),
+        ExecuteMsg::SetCollateral { token } => set_collateral(deps, env, info, token),
+        ExecuteMsg::Unwrap { amount } => unwrap_cw20(deps, env, info, amount),
+        ExecuteMsg::SetNativeWrapper { denom } => set_native_wrapper(deps, env, info, denom),
+        ExecuteMsg::Deposit {} => deposit_native(deps, env, info),
+        ExecuteMsg::Withdraw { amount } => withdraw_native(deps, env, info, amount),
+        ExecuteMsg::ConvertToBank { amount } => convert_to_bank(deps, env, info, amount),
+        ExecuteMsg::ConvertToCw20 {} => convert_to_cw20(deps, env, info),
+        ExecuteMsg::StartNativeMigration { deadline } => start_native_migration(deps, env, info, deadline),
+        ExecuteMsg::ConvertToNative {} => convert_to_native(deps, env, info),
+        ExecuteMsg::AllowIbcChannel { channel_id, mode } => allow_ibc_channel(deps, env, info, channel_id, mode),
+        ExecuteMsg::RemoveIbcChannel { channel_id } => remove_ibc_channel(deps, env, info, channel_id),
+        ExecuteMsg::IbcTransfer { channel_id, receiver, amount, timeout_seconds, memo } => ibc_transfer(deps, env, info, channel_id, receiver, amount, timeout_seconds, memo),
+        ExecuteMsg::SetIbcRateLimit { channel_id, limits } => set_ibc_rate_limit(deps, env, info, channel_id, limits),
+        ExecuteMsg::ReceiveIbcPacket { packet } => receive_ibc_packet(deps, env, info, packet),
+        ExecuteMsg::RegisterBridge { adapter, limits } => register_bridge(deps, env, info, adapter, limits),
+        ExecuteMsg::DeregisterBridge { adapter } => deregister_bridge(deps, env, info, adapter),
+        ExecuteMsg::BridgeMint { recipient, amount } => bridge_mint(deps, env, info, recipient, amount),
+        ExecuteMsg::BridgeBurn { owner, amount } => bridge_burn(deps, env, info, owner, amount),
+        ExecuteMsg::IncreaseAllowanceAndCall { spender, amount, expires, msg } => increase_allowance_and_call(deps, env, info, spender, amount, expires, msg),
+        ExecuteMsg::Permit { owner, spender, amount, nonce, deadline, pubkey, signature } => permit(deps, env, info, owner, spender, amount, nonce, deadline, pubkey, signature),
+        ExecuteMsg::ExecuteSigned { signer, payload, nonce, expiry, pubkey, signature } => execute_signed(deps, env, info, signer, payload, nonce, expiry, pubkey, signature),
+        ExecuteMsg::AuthorizeOperator { operator } => authorize_operator(deps, env, info, operator),
+        ExecuteMsg::RevokeOperator { operator } => revoke_operator(deps, env, info, operator),
+        ExecuteMsg::GrantAction { grantee, action, expires } => grant_action(deps, env, info, grantee, action, expires),
+        ExecuteMsg::RevokeAction { grantee, action } => revoke_action(deps, env, info, grantee, action),
+        ExecuteMsg::ExecuteOnBehalf { granter, action } => execute_on_behalf(deps, env, info, granter, action),
+        ExecuteMsg::Send { contract, amount, msg, memo } => send(deps, env, info, contract, amount, msg, memo),
+        ExecuteMsg::SendChecked { contract, amount, msg, memo } => send_checked(deps, env, info, contract, amount, msg, memo),
+        ExecuteMsg::SendFrom { from, to, token_id, value, msg } => cw1155_send_from(deps, env, info, from, to, vec![(token_id, value)], msg),
+        ExecuteMsg::BatchSendFrom { from, to, batch, msg } => cw1155_send_from(deps, env, info, from, to, batch, msg),
+        ExecuteMsg::ApproveAll { operator, expires } => cw1155_approve_all(deps, env, info, operator, expires),
+        ExecuteMsg::RevokeAll { operator } => revoke_operator(deps, env, info, operator),
+        ExecuteMsg::CreateInvoice { payer, amount, memo, expires } => create_invoice(deps, env, info, payer, amount, memo, expires),
+        ExecuteMsg::ApproveInvoice { id } => approve_invoice(deps, env, info, id),
+        ExecuteMsg::CollectInvoice { id } => collect_invoice(deps, env, info, id),
+        ExecuteMsg::CancelInvoice { id } => cancel_invoice(deps, env, info, id),
+        ExecuteMsg::SetRecurringAllowance { spender, allowance } => set_recurring_allowance(deps, env, info, spender, allowance),
+        ExecuteMsg::LockHtlc { recipient, amount, hash, timeout } => lock_htlc(deps, env, info, recipient, amount, hash, timeout),
+        ExecuteMsg::ClaimHtlc { id, preimage } => claim_htlc(deps, env, info, id, preimage),
+        ExecuteMsg::RefundHtlc { id } => refund_htlc(deps, env, info, id),
+        ExecuteMsg::CreateTrade { amount, ask_token, ask_amount, expires } => create_trade(deps, env, info, amount, ask_token, ask_amount, expires),
+        ExecuteMsg::CancelTrade { id } => cancel_trade(deps, env, info, id),
+        ExecuteMsg::OpenPaymentChannel { counterparty, amount, challenge_period, pubkey } => open_payment_channel(deps, env, info, counterparty, amount, challenge_period, pubkey),
+        ExecuteMsg::UpdatePaymentChannel { id, nonce, paid, signature } => update_payment_channel(deps, env, info, id, nonce, paid, signature),
+        ExecuteMsg::ClosePaymentChannel { id } => close_payment_channel(deps, env, info, id),
+        ExecuteMsg::FinalizePaymentChannel { id } => finalize_payment_channel(deps, env, info, id),
+        ExecuteMsg::RegisterPayroll { payouts, interval, start } => register_payroll(deps, env, info, payouts, interval, start),
+        ExecuteMsg::CancelPayroll { id } => cancel_payroll(deps, env, info, id),
+        ExecuteMsg::RunPayroll { id } => run_payroll(deps, env, info, id),
+        ExecuteMsg::SetUsdLimits { limits } => set_usd_limits(deps, env, info, limits),
+        ExecuteMsg::TransferPending { recipient, amount, expires } => transfer_pending(deps, env, info, recipient, amount, expires),
+        ExecuteMsg::ClaimTransfer { id } => claim_transfer(deps, env, info, id),
+        ExecuteMsg::CancelTransfer { id } => cancel_transfer(deps, env, info, id),
+        ExecuteMsg::ScheduleTransfer { recipient, amount, at } => schedule_transfer(deps, env, info, recipient, amount, at),
+        ExecuteMsg::ExecuteScheduledTransfer { id } => execute_scheduled_transfer(deps, env, info, id),
+        ExecuteMsg::CancelScheduledTransfer { id } => cancel_scheduled_transfer(deps, env, info, id),
+        ExecuteMsg::RecoverCw20 { token, recipient, amount } => recover_cw20(deps, env, info, token, recipient, amount),
+        ExecuteMsg::SweepNative { denom, recipient } => sweep_native(deps, env, info, denom, recipient),
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    // Also answers the cw1155 form `{ owner, token_id }`
+    Balance {
+        #[serde(alias = "owner")]
+        address: HumanAddr,
+        #[serde(default)]
+        token_id: Option<String>,
+    },
+    Config {},
+    IsBlacklisted { address: HumanAddr },
+    IsWhitelisted { address: HumanAddr },
+    Whitelist { start_after: Option<HumanAddr>, limit: Option<u32> },
+    IsFrozen { address: HumanAddr },
+    KycConfig {},
+    SanctionsConfig {},
+    Limits {},
+    IsLimitExempt { limit: LimitKind, address: HumanAddr },
+    RemainingQuota { address: HumanAddr },
+    CircuitBreaker {},
+    Launch {},
+    Clawback { id: u64 },
+    Clawbacks { start_after: Option<u64>, limit: Option<u32> },
+    AnnouncedTransfer { id: u64 },
+    AccountLock { address: HumanAddr },
+    Beneficiary { address: HumanAddr },
+    FeeQuote { amount: Uint128 },
+    Shares { address: HumanAddr },
+    PendingDividends { address: HumanAddr },
+    DividendDenoms {},
+    InterestIndex {},
+    TokenInfo {},
+    Minter {},
+    EmissionSchedule {},
+    NextScheduledBurn {},
+    VestingBreakdown { address: HumanAddr },
+    Locks { address: HumanAddr },
+    Stream { id: u64 },
+    Escrow { id: u64 },
+    Escrows { start_after: Option<u64>, limit: Option<u32> },
+    AirdropStage { stage: u64 },
+    IsClaimed { stage: u64, address: HumanAddr },
+    LegacyMigration {},
+    Redenominations {},
+    CurveInfo {},
+    Treasury {},
+    Lockdrop {},
+    LockdropPositions { address: HumanAddr },
+    TotalBurned {},
+    BurnedBy { address: HumanAddr },
+    TopBurners { limit: Option<u32> },
+    CirculatingSupply {},
+    StakingRewardPool {},
+    BalanceAt { address: HumanAddr, height: u64 },
+    TotalSupplyAt { height: u64 },
+    Delegates { address: HumanAddr },
+    VotesAt { address: HumanAddr, height: u64 },
+    VotingPowerAtHeight { address: HumanAddr, height: Option<u64> },
+    TotalPowerAtHeight { height: Option<u64> },
+    StakingConfig {},
+    Staked { address: HumanAddr },
+    PendingRewards { address: HumanAddr },
+    StakeHooks {},
+    DelegationNonce { address: HumanAddr },
+    Proposal { id: u64 },
+    Proposals { start_after: Option<u64>, limit: Option<u32> },
+    HoldersAt { height: u64, start_after: Option<HumanAddr>, limit: Option<u32> },
+    StakingInfo {},
+    Claims { address: HumanAddr, start_after: Option<u64>, limit: Option<u32> },
+    TransferHooks {},
+    Collateral {},
+    NativeWrapper {},
+    FactoryDenom {},
+    NativeMigration {},
+    IbcChannels {},
+    IbcChannel { channel_id: String },
+    IbcRateLimit { channel_id: String },
+    Bridge { adapter: HumanAddr },
+    BridgeRateLimit { adapter: HumanAddr },
+    PermitNonce { owner: HumanAddr },
+    SignedNonce { signer: HumanAddr },
+    IsOperator { holder: HumanAddr, operator: HumanAddr },
+    Grants { granter: HumanAddr, start_after: Option<HumanAddr>, limit: Option<u32> },
+    BatchBalance { owner: HumanAddr, token_ids: Vec<String> },
+    IsApprovedForAll { owner: HumanAddr, operator: HumanAddr },
+    Invoice { id: u64 },
+    Invoices { start_after: Option<u64>, limit: Option<u32> },
+    RecurringAllowance { owner: HumanAddr, spender: HumanAddr },
+    Htlc { id: u64 },
+    Trade { id: u64 },
+    Trades { start_after: Option<u64>, limit: Option<u32> },
+    PaymentChannel { id: u64 },
+    Payroll { id: u64 },
+    PayoutHistory { recipient: HumanAddr, start_after: Option<u64>, limit: Option<u32> },
+    UsdLimits {},
+    PendingTransfer { id: u64 },
+    ScheduledTransfer { id: u64 },
+    ScheduledTransfers { start_after: Option<u64>, limit: Option<u32> },
+    AllBalances { start_after: Option<HumanAddr>, limit: Option<u32> },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub owner: HumanAddr,
+    pub paused: bool,
+    pub fee: Option<FeeConfig>,
+    pub cap: Option<Uint128>,
+    pub min_transfer_amount: Option<Uint128>,
+    pub modules: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Duration {
+    Height(u64),
+    // Time in seconds
+    Time(u64),
+}
+
+impl Duration {
+    // Whether this much time has passed between the given block and the current one
+    pub fn has_elapsed(&self, env: &cosmwasm_std::Env, since_height: u64, since_time: u64) -> bool {
+        match self {
+            Duration::Height(blocks) => env.block.height >= since_height + blocks,
+            Duration::Time(seconds) => env.block.time.seconds() >= since_time + seconds,
+        }
+    }
+
+    // Point at which this much time will have passed from the current block
+    pub fn after(&self, env: &cosmwasm_std::Env) -> Expiration {
+        match self {
+            Duration::Height(blocks) => Expiration::AtHeight(env.block.height + blocks),
+            Duration::Time(seconds) => Expiration::AtTime(env.block.time.seconds() + seconds),
+        }
+    }
+}
+
+pub const DEFAULT_LIMIT: u32 = 10;
+pub const MAX_LIMIT: u32 = 30;
+
+// Bucket ranges are inclusive, so step past `start_after` by appending a zero byte
+pub fn calc_range_start(start_after: Option<HumanAddr>) -> Option<Vec<u8>> {
+    start_after.map(|addr| {
+        let mut v = addr.as_bytes().to_vec();
+        v.push(0);
+        v
+    })
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn query(
+    deps: cosmwasm_std::Deps,
+    env: cosmwasm_std::Env,
+    msg: QueryMsg,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Binary> {
+    match msg {
+        QueryMsg::Balance { address, token_id: None } => cosmwasm_std::to_binary(&query_balance(deps, address)?),
+        QueryMsg::Balance { address, token_id: Some(token_id) } => cosmwasm_std::to_binary(&Cw1155BalanceResponse { balance: cw1155_balance(deps.storage, &address, &token_id)? }),
+        QueryMsg::Config {} => cosmwasm_std::to_binary(&query_config(deps)?),
+        QueryMsg::IsBlacklisted { address } => cosmwasm_std::to_binary(&query_is_blacklisted(deps, address)?),
+        QueryMsg::IsWhitelisted { address } => cosmwasm_std::to_binary(&query_is_whitelisted(deps, address)?),
+        QueryMsg::Whitelist { start_after, limit } => cosmwasm_std::to_binary(&query_whitelist(deps, start_after, limit)?),
+        QueryMsg::IsFrozen { address } => cosmwasm_std::to_binary(&query_is_frozen(deps, address)?),
+        QueryMsg::KycConfig {} => cosmwasm_std::to_binary(&ReadonlySingleton::<KycConfig>::new(deps.storage, KYC_CONFIG_KEY).may_load()?),
+        QueryMsg::SanctionsConfig {} => cosmwasm_std::to_binary(&sanctions_config(deps.storage)?),
+        QueryMsg::Limits {} => cosmwasm_std::to_binary(&query_limits(deps)?),
+        QueryMsg::IsLimitExempt { limit, address } => cosmwasm_std::to_binary(&is_limit_exempt(deps.storage, &limit, &address)?),
+        QueryMsg::RemainingQuota { address } => cosmwasm_std::to_binary(&query_remaining_quota(deps, env, address)?),
+        QueryMsg::CircuitBreaker {} => cosmwasm_std::to_binary(&query_circuit_breaker(deps, env)?),
+        QueryMsg::Launch {} => cosmwasm_std::to_binary(&query_launch(deps, env)?),
+        QueryMsg::Clawback { id } => cosmwasm_std::to_binary(&ReadonlyBucket::<PendingClawback>::new(deps.storage, CLAWBACKS_PREFIX).load(&id.to_be_bytes())?),
+        QueryMsg::Clawbacks { start_after, limit } => cosmwasm_std::to_binary(&query_clawbacks(deps, start_after, limit)?),
+        QueryMsg::AnnouncedTransfer { id } => cosmwasm_std::to_binary(&ReadonlyBucket::<AnnouncedTransfer>::new(deps.storage, ANNOUNCEMENTS_PREFIX).load(&id.to_be_bytes())?),
+        QueryMsg::AccountLock { address } => cosmwasm_std::to_binary(&query_account_lock(deps, env, address)?),
+        QueryMsg::Beneficiary { address } => cosmwasm_std::to_binary(&ReadonlyBucket::<DeadManSwitch>::new(deps.storage, DEAD_MAN_SWITCH_PREFIX).may_load(address.as_bytes())?),
+        QueryMsg::FeeQuote { amount } => cosmwasm_std::to_binary(&query_fee_quote(deps, amount)?),
+        QueryMsg::Shares { address } => cosmwasm_std::to_binary(&query_shares(deps, address)?),
+        QueryMsg::PendingDividends { address } => cosmwasm_std::to_binary(&query_pending_dividends(deps, env, address)?),
+        QueryMsg::DividendDenoms {} => cosmwasm_std::to_binary(&query_dividend_denoms(deps)?),
+        QueryMsg::InterestIndex {} => cosmwasm_std::to_binary(&query_interest_index(deps)?),
+        QueryMsg::TokenInfo {} => cosmwasm_std::to_binary(&query_token_info(deps)?),
+        QueryMsg::Minter {} => cosmwasm_std::to_binary(&query_minter(deps)?),
+        QueryMsg::EmissionSchedule {} => cosmwasm_std::to_binary(&ReadonlySingleton::<EmissionSchedule>::new(deps.storage, EMISSION_KEY).may_load()?),
+        QueryMsg::NextScheduledBurn {} => cosmwasm_std::to_binary(&query_next_scheduled_burn(deps, env)?),
+        QueryMsg::VestingBreakdown { address } => cosmwasm_std::to_binary(&query_vesting_breakdown(deps, env, address)?),
+        QueryMsg::Locks { address } => cosmwasm_std::to_binary(&query_locks(deps, env, address)?),
+        QueryMsg::Stream { id } => cosmwasm_std::to_binary(&query_stream(deps, env, id)?),
+        QueryMsg::Escrow { id } => cosmwasm_std::to_binary(&ReadonlyBucket::<Escrow>::new(deps.storage, ESCROWS_PREFIX).load(&id.to_be_bytes())?),
+        QueryMsg::Escrows { start_after, limit } => cosmwasm_std::to_binary(&query_escrows(deps, start_after, limit)?),
+        QueryMsg::AirdropStage { stage } => cosmwasm_std::to_binary(&ReadonlyBucket::<AirdropStage>::new(deps.storage, AIRDROP_STAGES_PREFIX).load(&stage.to_be_bytes())?),
+        QueryMsg::IsClaimed { stage, address } => cosmwasm_std::to_binary(&query_is_claimed(deps, stage, address)?),
+        QueryMsg::LegacyMigration {} => cosmwasm_std::to_binary(&ReadonlySingleton::<LegacyMigration>::new(deps.storage, LEGACY_MIGRATION_KEY).may_load()?),
+        QueryMsg::Redenominations {} => cosmwasm_std::to_binary(&query_redenominations(deps)?),
+        QueryMsg::CurveInfo {} => cosmwasm_std::to_binary(&query_curve_info(deps)?),
+        QueryMsg::Treasury {} => cosmwasm_std::to_binary(&query_treasury(deps, env)?),
+        QueryMsg::Lockdrop {} => cosmwasm_std::to_binary(&query_lockdrop(deps)?),
+        QueryMsg::LockdropPositions { address } => cosmwasm_std::to_binary(&query_lockdrop_positions(deps, address)?),
+        QueryMsg::TotalBurned {} => cosmwasm_std::to_binary(&query_total_burned(deps)?),
+        QueryMsg::BurnedBy { address } => cosmwasm_std::to_binary(&query_burned_by(deps, address)?),
+        QueryMsg::TopBurners { limit } => cosmwasm_std::to_binary(&query_top_burners(deps, limit)?),
+        QueryMsg::CirculatingSupply {} => cosmwasm_std::to_binary(&query_circulating_supply(deps)?),
+        QueryMsg::StakingRewardPool {} => cosmwasm_std::to_binary(&staking_reward_pool(deps.storage)?),
+        QueryMsg::BalanceAt { address, height } => cosmwasm_std::to_binary(&query_balance_at(deps, address, height)?),
+        QueryMsg::TotalSupplyAt { height } => cosmwasm_std::to_binary(&query_total_supply_at(deps, height)?),
+        QueryMsg::Delegates { address } => cosmwasm_std::to_binary(&query_delegates(deps, address)?),
+        QueryMsg::VotesAt { address, height } => cosmwasm_std::to_binary(&query_votes_at(deps, address, height)?),
+        QueryMsg::VotingPowerAtHeight { address, height } => cosmwasm_std::to_binary(&query_voting_power_at_height(deps, env, address, height)?),
+        QueryMsg::TotalPowerAtHeight { height } => cosmwasm_std::to_binary(&query_total_power_at_height(deps, env, height)?),
+        QueryMsg::StakingConfig {} => cosmwasm_std::to_binary(&ReadonlySingleton::<StakingConfig>::new(deps.storage, STAKING_CONFIG_KEY).may_load()?),
+        QueryMsg::Staked { address } => cosmwasm_std::to_binary(&query_staked(deps, address)?),
+        QueryMsg::PendingRewards { address } => cosmwasm_std::to_binary(&query_pending_rewards(deps, address)?),
+        QueryMsg::StakeHooks {} => cosmwasm_std::to_binary(&HooksResponse { hooks: stake_hooks(deps.storage)? }),
+        QueryMsg::DelegationNonce { address } => cosmwasm_std::to_binary(&delegation_nonce(deps.storage, &address)?),
+        QueryMsg::Proposal { id } => cosmwasm_std::to_binary(&query_proposal(deps, env, id)?),
+        QueryMsg::Proposals { start_after, limit } => cosmwasm_std::to_binary(&query_proposals(deps, env, start_after, limit)?),
+        QueryMsg::HoldersAt { height, start_after, limit } => cosmwasm_std::to_binary(&query_holders_at(deps, height, start_after, limit)?),
+        QueryMsg::StakingInfo {} => cosmwasm_std::to_binary(&query_staking_info(deps, env)?),
+        QueryMsg::Claims { address, start_after, limit } => cosmwasm_std::to_binary(&query_claims(deps, env, address, start_after, limit)?),
+        QueryMsg::TransferHooks {} => cosmwasm_std::to_binary(&TransferHooksResponse { hooks: transfer_hooks(deps.storage)? }),
+        QueryMsg::Collateral {} => cosmwasm_std::to_binary(&ReadonlySingleton::<Collateral>::new(deps.storage, COLLATERAL_KEY).may_load()?),
+        QueryMsg::NativeWrapper {} => cosmwasm_std::to_binary(&ReadonlySingleton::<NativeWrapper>::new(deps.storage, NATIVE_WRAPPER_KEY).may_load()?),
+        QueryMsg::FactoryDenom {} => cosmwasm_std::to_binary(&ReadonlySingleton::<FactoryDenom>::new(deps.storage, TOKENFACTORY_KEY).may_load()?),
+        QueryMsg::NativeMigration {} => cosmwasm_std::to_binary(&query_native_migration(deps)?),
+        QueryMsg::IbcChannels {} => cosmwasm_std::to_binary(&query_ibc_channels(deps)?),
+        QueryMsg::IbcChannel { channel_id } => cosmwasm_std::to_binary(&ReadonlyBucket::<IbcChannelState>::new(deps.storage, IBC_CHANNELS_PREFIX).may_load(channel_id.as_bytes())?),
+        QueryMsg::IbcRateLimit { channel_id } => cosmwasm_std::to_binary(&query_flow_limit(deps, env, &ibc_route(&channel_id))?),
+        QueryMsg::Bridge { adapter } => cosmwasm_std::to_binary(&ReadonlyBucket::<BridgeAdapter>::new(deps.storage, BRIDGES_PREFIX).may_load(adapter.as_bytes())?),
+        QueryMsg::BridgeRateLimit { adapter } => cosmwasm_std::to_binary(&query_flow_limit(deps, env, &bridge_route(&adapter))?),
+        QueryMsg::PermitNonce { owner } => cosmwasm_std::to_binary(&permit_nonce(deps.storage, &owner)?),
+        QueryMsg::SignedNonce { signer } => cosmwasm_std::to_binary(&signed_nonce(deps.storage, &signer)?),
+        QueryMsg::IsOperator { holder, operator } => cosmwasm_std::to_binary(&IsOperatorResponse { is_operator: is_operator(deps.storage, &holder, &operator)? }),
+        QueryMsg::Grants { granter, start_after, limit } => cosmwasm_std::to_binary(&query_grants(deps, env, granter, start_after, limit)?),
+        QueryMsg::BatchBalance { owner, token_ids } => cosmwasm_std::to_binary(&query_cw1155_batch_balance(deps, owner, token_ids)?),
+        QueryMsg::IsApprovedForAll { owner, operator } => cosmwasm_std::to_binary(&IsApprovedForAllResponse { approved: is_operator(deps.storage, &owner, &operator)? }),
+        QueryMsg::Invoice { id } => cosmwasm_std::to_binary(&query_invoice(deps, &env, id)?),
+        QueryMsg::Invoices { start_after, limit } => cosmwasm_std::to_binary(&query_invoices(deps, env, start_after, limit)?),
+        QueryMsg::RecurringAllowance { owner, spender } => cosmwasm_std::to_binary(&query_recurring_allowance(deps, env, owner, spender)?),
+        QueryMsg::Htlc { id } => cosmwasm_std::to_binary(&ReadonlyBucket::<Htlc>::new(deps.storage, HTLCS_PREFIX).may_load(&id.to_be_bytes())?),
+        QueryMsg::Trade { id } => cosmwasm_std::to_binary(&ReadonlyBucket::<Trade>::new(deps.storage, TRADES_PREFIX).may_load(&id.to_be_bytes())?),
+        QueryMsg::Trades { start_after, limit } => cosmwasm_std::to_binary(&query_trades(deps, start_after, limit)?),
+        QueryMsg::PaymentChannel { id } => cosmwasm_std::to_binary(&ReadonlyBucket::<PaymentChannel>::new(deps.storage, PAYMENT_CHANNELS_PREFIX).may_load(&id.to_be_bytes())?),
+        QueryMsg::Payroll { id } => cosmwasm_std::to_binary(&ReadonlyBucket::<Payroll>::new(deps.storage, PAYROLLS_PREFIX).may_load(&id.to_be_bytes())?),
+        QueryMsg::PayoutHistory { recipient, start_after, limit } => cosmwasm_std::to_binary(&query_payout_history(deps, recipient, start_after, limit)?),
+        QueryMsg::UsdLimits {} => cosmwasm_std::to_binary(&query_usd_limits(deps, env)?),
+        QueryMsg::PendingTransfer { id } => cosmwasm_std::to_binary(&ReadonlyBucket::<PendingTransfer>::new(deps.storage, PENDING_TRANSFERS_PREFIX).may_load(&id.to_be_bytes())?),
+        QueryMsg::ScheduledTransfer { id } => cosmwasm_std::to_binary(&ReadonlyBucket::<ScheduledTransfer>::new(deps.storage, SCHEDULED_TRANSFERS_PREFIX).may_load(&id.to_be_bytes())?),
+        QueryMsg::ScheduledTransfers { start_after, limit } => cosmwasm_std::to_binary(&query_scheduled_transfers(deps, start_after, limit)?),
+        QueryMsg::AllBalances { start_after, limit } => cosmwasm_std::to_binary(&query_all_balances(deps, start_after, limit)?),
+    }
+}
+
+pub fn query_balance(deps: cosmwasm_std::Deps, address: HumanAddr) -> cosmwasm_std::StdResult<BalanceResponse> {
+    Ok(BalanceResponse { amount: balance_of(deps.storage, &address)? })
+}
+
+pub fn query_config(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<ConfigResponse> {
+    Ok(ConfigResponse {
+        owner: contract_owner(deps.storage)?,
+        paused: is_paused(deps.storage)?,
+        fee: fee_config(deps.storage)?,
+        cap: supply_cap(deps.storage)?,
+        min_transfer_amount: ReadonlySingleton::new(deps.storage, MIN_TRANSFER_KEY).may_load()?,
+        modules: enabled_modules(deps.storage)?,
+    })
+}
+
+// Names of the optional subsystems that are currently configured
+pub fn enabled_modules(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Vec<String>> {
+    let mut modules = vec![];
+    if ReadonlySingleton::<HumanAddr>::new(storage, BLACKLISTER_KEY).may_load()?.is_some() {
+        modules.push("blacklist".to_string());
+    }
+    if whitelist_enabled(storage)? {
+        modules.push("whitelist".to_string());
+    }
+    if ReadonlySingleton::<HumanAddr>::new(storage, COMPLIANCE_KEY).may_load()?.is_some() {
+        modules.push("compliance".to_string());
+    }
+    if ReadonlySingleton::<HumanAddr>::new(storage, RESTRICTION_CONTRACT_KEY).may_load()?.is_some() {
+        modules.push("restriction_hook".to_string());
+    }
+    if ReadonlySingleton::<KycConfig>::new(storage, KYC_CONFIG_KEY).may_load()?.is_some() {
+        modules.push("kyc".to_string());
+    }
+    if sanctions_config(storage)?.map(|c| c.enabled).unwrap_or(false) {
+        modules.push("sanctions".to_string());
+    }
+    if ReadonlySingleton::<Uint128>::new(storage, MAX_WALLET_KEY).may_load()?.is_some() {
+        modules.push("max_wallet".to_string());
+    }
+    if ReadonlySingleton::<Uint128>::new(storage, MAX_TX_KEY).may_load()?.is_some() {
+        modules.push("max_transaction".to_string());
+    }
+    if ReadonlySingleton::<DailyLimit>::new(storage, DAILY_LIMIT_KEY).may_load()?.is_some() {
+        modules.push("daily_limit".to_string());
+    }
+    if ReadonlySingleton::<CircuitBreaker>::new(storage, CIRCUIT_BREAKER_KEY).may_load()?.is_some() {
+        modules.push("circuit_breaker".to_string());
+    }
+    if ReadonlySingleton::<Duration>::new(storage, COOLDOWN_KEY).may_load()?.is_some() {
+        modules.push("cooldown".to_string());
+    }
+    if ReadonlySingleton::<LaunchConfig>::new(storage, LAUNCH_KEY).may_load()?.is_some() {
+        modules.push("launch".to_string());
+    }
+    if ReadonlySingleton::<ClawbackConfig>::new(storage, CLAWBACK_CONFIG_KEY).may_load()?.is_some() {
+        modules.push("clawback".to_string());
+    }
+    if ReadonlySingleton::<LargeTransferConfig>::new(storage, LARGE_TRANSFER_KEY).may_load()?.is_some() {
+        modules.push("large_transfer_announcements".to_string());
+    }
+    if fee_config(storage)?.is_some() {
+        modules.push("fee".to_string());
+    }
+    if burn_tax(storage)?.map(|t| t.enabled).unwrap_or(false) {
+        modules.push("burn_tax".to_string());
+    }
+    if reflection_bps(storage)?.is_some() {
+        modules.push("reflection".to_string());
+    }
+    if !dividend_denoms(storage)?.is_empty() {
+        modules.push("dividends".to_string());
+    }
+    if ReadonlySingleton::<HumanAddr>::new(storage, REBASER_KEY).may_load()?.is_some() {
+        modules.push("rebase".to_string());
+    }
+    if ReadonlySingleton::<InterestConfig>::new(storage, INTEREST_KEY).may_load()?.is_some() {
+        modules.push("interest".to_string());
+    }
+    if ReadonlySingleton::<EmissionSchedule>::new(storage, EMISSION_KEY).may_load()?.is_some() {
+        modules.push("emission".to_string());
+    }
+    if ReadonlySingleton::<BurnSchedule>::new(storage, BURN_SCHEDULE_KEY).may_load()?.is_some() {
+        modules.push("burn_schedule".to_string());
+    }
+    if ReadonlySingleton::<LegacyMigration>::new(storage, LEGACY_MIGRATION_KEY).may_load()?.is_some() {
+        modules.push("legacy_migration".to_string());
+    }
+    if ReadonlySingleton::<BondingCurve>::new(storage, BONDING_CURVE_KEY).may_load()?.is_some() {
+        modules.push("bonding_curve".to_string());
+    }
+    if ReadonlySingleton::<Treasury>::new(storage, TREASURY_KEY).may_load()?.is_some() {
+        modules.push("treasury".to_string());
+    }
+    if ReadonlySingleton::<LockdropConfig>::new(storage, LOCKDROP_KEY).may_load()?.is_some() {
+        modules.push("lockdrop".to_string());
+    }
+    if ReadonlySingleton::<BuybackConfig>::new(storage, BUYBACK_KEY).may_load()?.is_some() {
+        modules.push("buyback".to_string());
+    }
+    if ReadonlySingleton::<StakingConfig>::new(storage, STAKING_CONFIG_KEY).may_load()?.is_some() {
+        modules.push("staking".to_string());
+    }
+    if ReadonlySingleton::<GovernanceConfig>::new(storage, GOVERNANCE_KEY).may_load()?.is_some() {
+        modules.push("governance".to_string());
+    }
+    if ReadonlySingleton::<NativeWrapper>::new(storage, NATIVE_WRAPPER_KEY).may_load()?.is_some() {
+        modules.push("native_wrapper".to_string());
+    }
+    if ReadonlySingleton::<FactoryDenom>::new(storage, TOKENFACTORY_KEY).may_load()?.is_some() {
+        modules.push("tokenfactory".to_string());
+    }
+    if ReadonlySingleton::<NativeMigration>::new(storage, NATIVE_MIGRATION_KEY).may_load()?.is_some() {
+        modules.push("native_migration".to_string());
+    }
+    Ok(modules)
+}
+
+pub fn transfer(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipient: HumanAddr,
+    amount: Uint128,
+    memo: Option<String>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    validate_memo(&memo)?;
+    assert_not_large_transfer(deps.storage, amount)?;
+    assert_transfer_allowed(deps.as_ref(), &env, &HumanAddr::from(&info.sender), &recipient, amount)?;
+    let events = record_transfer(deps.storage, &env, &HumanAddr::from(&info.sender), &recipient, amount)?;
+
+    let outcome = settle_transfer(deps.storage, &env, &HumanAddr::from(&info.sender), &recipient, amount)?;
+    let (before, after) = transfer_hook_msgs(deps.storage, &HumanAddr::from(&info.sender), &recipient, outcome.net)?;
+
+    let response = cosmwasm_std::Response::new().add_submessages(before).add_submessages(after).add_events(events).add_events(outcome.events).add_attribute("action", "transfer").add_attribute("from", info.sender).add_attribute("to", recipient).add_attribute("amount", outcome.net.to_string()).add_attribute("fee", outcome.fee.to_string());
+    Ok(with_memo(response, memo))
+}
+
+pub fn approve(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    spender: HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut allowance = load_allowance(deps.storage, &HumanAddr::from(&info.sender), &spender)?;
+    allowance.allowance = allowance.allowance.checked_add(amount)?;
+    save_allowance(deps.storage, &allowance)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "approve").add_attribute("owner", info.sender).add_attribute("spender", spender).add_attribute("amount", amount.to_string()))
+}
+
+pub fn transfer_from(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    owner: HumanAddr,
+    recipient: HumanAddr,
+    amount: Uint128,
+    memo: Option<String>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    validate_memo(&memo)?;
+    assert_not_blacklisted(deps.storage, &HumanAddr::from(&info.sender))?;
+    assert_not_large_transfer(deps.storage, amount)?;
+    assert_transfer_allowed(deps.as_ref(), &env, &owner, &recipient, amount)?;
+    let events = record_transfer(deps.storage, &env, &owner, &recipient, amount)?;
+
+    // Decrease the allowance for the spender
+    spend_allowance(deps.storage, &env, &owner, &HumanAddr::from(&info.sender), amount)?;
+
+    // Move the owner's balance to the recipient, less any fees
+    let outcome = settle_transfer(deps.storage, &env, &owner, &recipient, amount)?;
+    let (before, after) = transfer_hook_msgs(deps.storage, &owner, &recipient, outcome.net)?;
+
+    let response = cosmwasm_std::Response::new().add_submessages(before).add_submessages(after).add_events(events).add_events(outcome.events).add_attribute("action", "transfer_from").add_attribute("from", owner).add_attribute("to", recipient).add_attribute("amount", outcome.net.to_string()).add_attribute("fee", outcome.fee.to_string());
+    Ok(with_memo(response, memo))
+}
+
+pub fn decrease_allowance(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    spender: HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    // Load the allowance for the spender
+    let mut allowance = load_allowance(deps.storage, &HumanAddr::from(&info.sender), &spender)?;
+    if allowance.allowance < amount {
+        return Err(cosmwasm_std::StdError::generic_err("Insufficient allowance"));
+    }
+
+    // Decrease the allowance
+    allowance.allowance = allowance.allowance.checked_sub(amount)?;
+    save_allowance(deps.storage, &allowance)?;
+
+    Ok(cosmwasm_std::Response::new())
+}
+
+pub fn burn(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    // Decrease the owner's balance and the total supply
+    burn_balance(deps.storage, &HumanAddr::from(&info.sender), amount)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "burn").add_attribute("from", info.sender).add_attribute("amount", amount.to_string()))
+}
+
+// Recipient-side checks shared by every mint path
+pub fn assert_mint_allowed(deps: cosmwasm_std::Deps, recipient: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    assert_not_blacklisted(deps.storage, recipient)?;
+    if let Some(kyc) = ReadonlySingleton::<KycConfig>::new(deps.storage, KYC_CONFIG_KEY).may_load()? {
+        assert_kyc_verified(deps, &kyc.registry, recipient)?;
+    }
+    assert_max_wallet(deps.storage, recipient, amount)
+}
+
+pub fn mint(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipient: HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+
+    assert_owner(deps.storage, &HumanAddr::from(info.sender.as_str()))?;
+
+    assert_mint_allowed(deps.as_ref(), &recipient, amount)?;
+
+    // Increase the recipient's balance and the total supply
+    mint_balance(deps.storage, &recipient, amount)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "mint").add_attribute("to", recipient).add_attribute("amount", amount.to_string()))
+}
+pub const BLACKLISTER_KEY: &[u8] = b"blacklister";
+pub const BLACKLIST_PREFIX: &[u8] = b"blacklist";
+
+// Moves `amount` between two balances without applying any transfer restriction
+pub fn move_balance(
+    storage: &mut dyn cosmwasm_std::Storage,
+    from: &HumanAddr,
+    to: &HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<()> {
+    if balance_of(storage, from)? < amount {
+        return Err(cosmwasm_std::StdError::generic_err("Insufficient balance"));
+    }
+    let mut shares = shares_for(storage, amount)?;
+    // Rounding down can lose close to a whole share, which is many tokens after a split, so
+    // take the next share up when it is worth exactly `amount` and refuse anything further off
+    let mut moved = amount_for(storage, shares)?;
+    if moved < amount && shares < stored_balance(storage, from)? && amount_for(storage, shares + Uint128::from(1u128))? == amount {
+        shares += Uint128::from(1u128);
+        moved = amount;
+    }
+    if moved + Uint128::from(1u128) < amount {
+        return Err(cosmwasm_std::StdError::generic_err(format!("{} can't be moved exactly, the nearest amount is {}", amount, moved)));
+    }
+    debit_shares(storage, from, shares)?;
+    credit_shares(storage, to, shares)
+}
+
+pub fn balance_of(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<Uint128> {
+    amount_for(storage, stored_balance(storage, address)?)
+}
+
+// Raw value in the balances bucket, which holds shares rather than amounts once share accounting is on
+pub fn stored_balance(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<Uint128> {
+    let balance = ReadonlyBucket::<Balance>::new(storage, BALANCES_PREFIX).may_load(address.as_bytes())?;
+    Ok(balance.map(|b| b.amount).unwrap_or_else(Uint128::zero))
+}
+
+fn debit_shares(storage: &mut dyn cosmwasm_std::Storage, address: &HumanAddr, shares: Uint128) -> cosmwasm_std::StdResult<()> {
+    settle_dividends(storage, address)?;
+    checkpoint_balance(storage, address)?;
+    let mut balances = Bucket::<Balance>::new(storage, BALANCES_PREFIX);
+    let mut balance = balances.may_load(address.as_bytes())?.unwrap_or(Balance { amount: Uint128::zero() });
+    // Rounding in the share conversion can ask for one unit more than is held
+    let debited = balance.amount.min(shares);
+    balance.amount = balance.amount.checked_sub(debited)?;
+    balances.save(address.as_bytes(), &balance)?;
+    move_delegated_votes(storage, address, debited, false)
+}
+
+fn credit_shares(storage: &mut dyn cosmwasm_std::Storage, address: &HumanAddr, shares: Uint128) -> cosmwasm_std::StdResult<()> {
+    settle_dividends(storage, address)?;
+    checkpoint_balance(storage, address)?;
+    let mut balances = Bucket::<Balance>::new(storage, BALANCES_PREFIX);
+    let mut balance = balances.may_load(address.as_bytes())?.unwrap_or(Balance { amount: Uint128::zero() });
+    balance.amount = balance.amount.checked_add(shares)?;
+    balances.save(address.as_bytes(), &balance)?;
+    move_delegated_votes(storage, address, shares, true)
+}
+
+pub fn token_info(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<TokenInfo> {
+    ReadonlySingleton::new(storage, TOKEN_INFO_KEY).load()
+}
+
+// Returns the next id from the counter stored under `key`
+pub fn next_id(storage: &mut dyn cosmwasm_std::Storage, key: &[u8]) -> cosmwasm_std::StdResult<u64> {
+    let mut counter = Singleton::<u64>::new(storage, key);
+    let id = counter.may_load()?.unwrap_or(0) + 1;
+    counter.save(&id)?;
+    Ok(id)
+}
+
+pub fn increase_supply(storage: &mut dyn cosmwasm_std::Storage, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    let mut info = token_info(storage)?;
+    info.total_supply = info.total_supply.checked_add(amount)?;
+    save_token_info(storage, &info)
+}
+
+pub fn decrease_supply(storage: &mut dyn cosmwasm_std::Storage, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    let mut info = token_info(storage)?;
+    info.total_supply = info.total_supply.checked_sub(amount)?;
+    save_token_info(storage, &info)
+}
+
+// Removes `amount` from an account and from the total supply
+pub fn burn_balance(storage: &mut dyn cosmwasm_std::Storage, from: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    destroy_balance(storage, from, amount)?;
+    record_burn(storage, from, amount)
+}
+
+// Same as `burn_balance` without counting towards burn statistics, for tokens that
+// leave the cw20 supply but keep existing in another form
+pub fn destroy_balance(storage: &mut dyn cosmwasm_std::Storage, from: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    if balance_of(storage, from)? < amount {
+        return Err(cosmwasm_std::StdError::generic_err("Insufficient balance"));
+    }
+    let shares = shares_for(storage, amount)?;
+    debit_shares(storage, from, shares)?;
+    adjust_total_shares(storage, shares, false)?;
+    decrease_supply(storage, amount)
+}
+
+// Applies to every way the supply grows, minting as well as rebases and interest
+pub fn assert_within_cap(storage: &dyn cosmwasm_std::Storage, supply: Uint128) -> cosmwasm_std::StdResult<()> {
+    if let Some(cap) = supply_cap(storage)? {
+        if supply > cap {
+            return Err(cosmwasm_std::StdError::generic_err(format!("CapExceeded: a supply of {} would exceed the cap of {}", supply, cap)));
+        }
+    }
+    Ok(())
+}
+
+// With the native wrapper on every token has to be backed by the reserve, otherwise
+// tokens issued some other way could be withdrawn against other holders' deposits
+pub fn assert_reserve_backs(storage: &dyn cosmwasm_std::Storage, supply: Uint128) -> cosmwasm_std::StdResult<()> {
+    if let Some(wrapper) = ReadonlySingleton::<NativeWrapper>::new(storage, NATIVE_WRAPPER_KEY).may_load()? {
+        if supply > wrapper.reserve {
+            return Err(cosmwasm_std::StdError::generic_err("Only deposits can add supply while the native wrapper is enabled"));
+        }
+    }
+    Ok(())
+}
+
+// Adds `amount` to an account and to the total supply
+pub fn mint_balance(storage: &mut dyn cosmwasm_std::Storage, to: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    let supply = token_info(storage)?.total_supply.checked_add(amount)?;
+    assert_within_cap(storage, supply)?;
+    assert_reserve_backs(storage, supply)?;
+    let shares = shares_for(storage, amount)?;
+    credit_shares(storage, to, shares)?;
+    adjust_total_shares(storage, shares, true)?;
+    increase_supply(storage, amount)
+}
+
+pub const TOTAL_SHARES_KEY: &[u8] = b"total_shares";
+pub const REFLECTION_KEY: &[u8] = b"reflection";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SharesResponse {
+    pub shares: Uint128,
+    pub total_shares: Uint128,
+    pub total_supply: Uint128,
+}
+
+// Share accounting starts at one share per token, so it can be switched on with existing balances.
+// From then on `amount = shares * total_supply / total_shares`.
+pub fn enable_share_accounting(storage: &mut dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<()> {
+    if total_shares(storage)?.is_none() {
+        let supply = token_info(storage)?.total_supply;
+        save_total_shares(storage, supply)?;
+    }
+    Ok(())
+}
+
+pub fn total_shares(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Option<Uint128>> {
+    ReadonlySingleton::new(storage, TOTAL_SHARES_KEY).may_load()
+}
+
+pub fn shares_for(storage: &dyn cosmwasm_std::Storage, amount: Uint128) -> cosmwasm_std::StdResult<Uint128> {
+    match total_shares(storage)? {
+        Some(shares) => {
+            let supply = token_info(storage)?.total_supply;
+            if supply.is_zero() || shares.is_zero() {
+                Ok(amount)
+            } else {
+                Ok(amount.multiply_ratio(shares, supply))
+            }
+        }
+        None => Ok(amount),
+    }
+}
+
+pub fn amount_for(storage: &dyn cosmwasm_std::Storage, shares: Uint128) -> cosmwasm_std::StdResult<Uint128> {
+    match total_shares(storage)? {
+        Some(total) if !total.is_zero() => Ok(shares.multiply_ratio(token_info(storage)?.total_supply, total)),
+        _ => Ok(shares),
+    }
+}
+
+fn adjust_total_shares(storage: &mut dyn cosmwasm_std::Storage, shares: Uint128, increase: bool) -> cosmwasm_std::StdResult<()> {
+    if let Some(total) = total_shares(storage)? {
+        let total = if increase { total.checked_add(shares)? } else { total.checked_sub(shares)? };
+        save_total_shares(storage, total)?;
+    }
+    Ok(())
+}
+
+pub fn query_shares(deps: cosmwasm_std::Deps, address: HumanAddr) -> cosmwasm_std::StdResult<SharesResponse> {
+    let total_supply = token_info(deps.storage)?.total_supply;
+    Ok(SharesResponse {
+        shares: stored_balance(deps.storage, &address)?,
+        total_shares: total_shares(deps.storage)?.unwrap_or(total_supply),
+        total_supply,
+    })
+}
+
+pub const DIVIDEND_DENOMS_KEY: &[u8] = b"dividend_denoms";
+pub const DIVIDEND_ALLOWLIST_KEY: &[u8] = b"dividend_allowlist";
+// Dividends in another cw20 are tracked under the denom "cw20:<token address>"
+pub const CW20_DENOM_PREFIX: &str = "cw20:";
+pub const DIVIDEND_INDEX_PREFIX: &[u8] = b"dividend_index";
+pub const DIVIDEND_HOLDERS_PREFIX: &[u8] = b"dividend_holders";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HolderDividend {
+    // Value of the denom's rewards-per-unit index when this holder was last settled
+    pub index: Decimal,
+    pub pending: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingDividendsResponse {
+    pub dividends: Vec<cosmwasm_std::Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DividendDenomsResponse {
+    // Denoms distributed so far, settled on every balance change
+    pub denoms: Vec<String>,
+    // Denoms that can be deposited besides those already in `denoms`
+    pub accepted: Vec<String>,
+}
+
+// Units that earn distributions, matching what is stored in the balances bucket. The
+// contract's own balance is escrow and collected fees, it takes no share.
+pub fn total_units(storage: &dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env) -> cosmwasm_std::StdResult<Uint128> {
+    let units = match total_shares(storage)? {
+        Some(shares) => shares,
+        None => token_info(storage)?.total_supply,
+    };
+    Ok(units.checked_sub(stored_balance(storage, &HumanAddr::from(env.contract.address.as_str()))?)?)
+}
+
+pub fn dividend_denoms(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Vec<String>> {
+    Ok(ReadonlySingleton::<Vec<String>>::new(storage, DIVIDEND_DENOMS_KEY).may_load()?.unwrap_or_default())
+}
+
+fn dividend_index(storage: &dyn cosmwasm_std::Storage, denom: &str) -> cosmwasm_std::StdResult<Decimal> {
+    Ok(ReadonlyBucket::<Decimal>::new(storage, DIVIDEND_INDEX_PREFIX).may_load(denom.as_bytes())?.unwrap_or_else(Decimal::zero))
+}
+
+fn pending_dividend(storage: &dyn cosmwasm_std::Storage, denom: &str, address: &HumanAddr) -> cosmwasm_std::StdResult<HolderDividend> {
+    let index = dividend_index(storage, denom)?;
+    let holder = ReadonlyBucket::<HolderDividend>::multilevel(storage, &[DIVIDEND_HOLDERS_PREFIX, denom.as_bytes()]).may_load(address.as_bytes())?;
+    let mut holder = holder.unwrap_or(HolderDividend { index: Decimal::zero(), pending: Uint128::zero() });
+    let units = stored_balance(storage, address)?;
+    holder.pending = holder.pending.checked_add(units * (index - holder.index))?;
+    holder.index = index;
+    Ok(holder)
+}
+
+// Credits everything accrued on the current balance, must run before the stored balance changes
+pub fn settle_dividends(storage: &mut dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<()> {
+    for denom in dividend_denoms(storage)? {
+        let holder = pending_dividend(storage, &denom, address)?;
+        Bucket::multilevel(storage, &[DIVIDEND_HOLDERS_PREFIX, denom.as_bytes()]).save(address.as_bytes(), &holder)?;
+    }
+    Ok(())
+}
+
+pub fn accepted_dividend_denoms(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Vec<String>> {
+    Ok(ReadonlySingleton::<Vec<String>>::new(storage, DIVIDEND_ALLOWLIST_KEY).may_load()?.unwrap_or_default())
+}
+
+pub fn set_dividend_denoms(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    denoms: Vec<String>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    if denoms.len() > MAX_REWARD_DENOMS {
+        return Err(cosmwasm_std::StdError::generic_err(format!("At most {} denoms can be accepted", MAX_REWARD_DENOMS)));
+    }
+    // Denoms already deposited stay tracked so holders can still claim them
+    Singleton::new(deps.storage, DIVIDEND_ALLOWLIST_KEY).save(&denoms)?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_dividend_denoms").add_attribute("denoms", denoms.join(",")))
+}
+
+pub fn query_dividend_denoms(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<DividendDenomsResponse> {
+    Ok(DividendDenomsResponse { denoms: dividend_denoms(deps.storage)?, accepted: accepted_dividend_denoms(deps.storage)? })
+}
+
+// Every transfer settles each tracked denom, so only accepted ones up to the maximum get in
+fn add_dividend(storage: &mut dyn cosmwasm_std::Storage, denom: &str, amount: Uint128, units: Uint128) -> cosmwasm_std::StdResult<()> {
+    let mut denoms = dividend_denoms(storage)?;
+    assert_reward_denom_allowed(&denoms, &accepted_dividend_denoms(storage)?, denom)?;
+    if !denoms.iter().any(|d| d == denom) {
+        denoms.push(denom.to_string());
+        Singleton::new(storage, DIVIDEND_DENOMS_KEY).save(&denoms)?;
+    }
+    let index = dividend_index(storage, denom)? + Decimal::from_ratio(amount, units);
+    Bucket::new(storage, DIVIDEND_INDEX_PREFIX).save(denom.as_bytes(), &index)
+}
+
+pub fn deposit_dividends(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if info.funds.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err("No funds sent"));
+    }
+
+    let units = total_units(deps.storage, &env)?;
+    if units.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("No holders to distribute to"));
+    }
+
+    for coin in &info.funds {
+        add_dividend(deps.storage, &coin.denom, coin.amount, units)?;
+        reserve_native(deps.storage, &coin.denom, coin.amount, true)?;
+    }
+
+    let funds = info.funds.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",");
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "deposit_dividends").add_attribute("from", info.sender).add_attribute("amount", funds))
+}
+
+pub fn claim_dividends(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    // Its balance is left out of `total_units`, whatever it seems to have accrued isn't backed
+    if info.sender.as_str() == env.contract.address.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("The token contract doesn't earn dividends"));
+    }
+    let mut coins = vec![];
+    for denom in dividend_denoms(deps.storage)? {
+        let mut holder = pending_dividend(deps.storage, &denom, &HumanAddr::from(&info.sender))?;
+        if !holder.pending.is_zero() {
+            coins.push(cosmwasm_std::Coin { denom: denom.clone(), amount: holder.pending });
+            holder.pending = Uint128::zero();
+        }
+        Bucket::multilevel(deps.storage, &[DIVIDEND_HOLDERS_PREFIX, denom.as_bytes()]).save(info.sender.as_bytes(), &holder)?;
+    }
+
+    if coins.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err("No dividends to claim"));
+    }
+
+    let claimed = coins.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",");
+    let mut msgs: Vec<cosmwasm_std::CosmosMsg> = vec![];
+    let mut native = vec![];
+    for coin in coins {
+        match coin.denom.strip_prefix(CW20_DENOM_PREFIX) {
+            Some(token) => msgs.push(cw20_transfer_msg(&HumanAddr::from(token), &HumanAddr::from(&info.sender), coin.amount)?),
+            None => native.push(coin),
+        }
+    }
+    if !native.is_empty() {
+        for coin in &native {
+            reserve_native(deps.storage, &coin.denom, coin.amount, false)?;
+        }
+        msgs.push(cosmwasm_std::BankMsg::Send { to_address: info.sender.to_string(), amount: native }.into());
+    }
+    Ok(cosmwasm_std::Response::new().add_messages(msgs).add_attribute("action", "claim_dividends").add_attribute("to", info.sender).add_attribute("amount", claimed))
+}
+
+pub fn query_pending_dividends(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, address: HumanAddr) -> cosmwasm_std::StdResult<PendingDividendsResponse> {
+    let mut dividends = vec![];
+    if address.as_str() == env.contract.address.as_str() {
+        return Ok(PendingDividendsResponse { dividends });
+    }
+    for denom in dividend_denoms(deps.storage)? {
+        let holder = pending_dividend(deps.storage, &denom, &address)?;
+        if !holder.pending.is_zero() {
+            dividends.push(cosmwasm_std::Coin { denom, amount: holder.pending });
+        }
+    }
+    Ok(PendingDividendsResponse { dividends })
+}
+
+// Payloads accepted inside `Cw20ReceiveMsg.msg`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    DistributeRewards {},
+    Migrate {},
+    LockdropDeposit { duration: u64 },
+    FundStakingRewards {},
+    // Locks the sent collateral token and mints the same amount of this token
+    Wrap {},
+    // Pays the ask of an OTC trade and takes the offered tokens
+    FillTrade { id: u64 },
+}
+
+// Subset of the cw20 execute interface used to move other tokens held by this contract
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw20ExecuteMsg {
+    Transfer { recipient: HumanAddr, amount: Uint128 },
+    Burn { amount: Uint128 },
+}
+
+pub fn cw20_transfer_msg(token: &HumanAddr, recipient: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<cosmwasm_std::CosmosMsg> {
+    Ok(cosmwasm_std::WasmMsg::Execute {
+        contract_addr: token.to_string(),
+        msg: cosmwasm_std::to_binary(&Cw20ExecuteMsg::Transfer { recipient: recipient.clone(), amount })?,
+        funds: vec![],
+    }
+    .into())
+}
+
+pub fn receive_cw20(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    // `info.sender` is the cw20 contract the tokens were sent from
+    if info.sender.as_str() == env.contract.address.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Cannot receive this token"));
+    }
+    let token = HumanAddr::from(info.sender.as_str());
+
+    if wrapper.msg.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err("Send needs a message telling this contract what to do with the tokens"));
+    }
+    match cosmwasm_std::from_binary(&wrapper.msg)? {
+        ReceiveMsg::DistributeRewards {} => distribute_cw20_rewards(deps, &env, token, wrapper.sender, wrapper.amount),
+        ReceiveMsg::Migrate {} => migrate_legacy_tokens(deps, token, wrapper.sender, wrapper.amount),
+        ReceiveMsg::FundStakingRewards {} => {
+            staking_config(deps.storage)?;
+            let denom = format!("{}{}", CW20_DENOM_PREFIX, token);
+            assert_staking_reward_allowed(deps.storage, &denom)?;
+            add_staking_reward(deps.storage, &denom, wrapper.amount)?;
+            Ok(cosmwasm_std::Response::new().add_attribute("action", "fund_staking_rewards").add_attribute("from", wrapper.sender).add_attribute("amount", format!("{}{}", wrapper.amount, denom)))
+        }
+        ReceiveMsg::Wrap {} => wrap_cw20(deps, token, wrapper.sender, wrapper.amount),
+        ReceiveMsg::FillTrade { id } => fill_trade(deps, env, token, wrapper.sender, wrapper.amount, id),
+        ReceiveMsg::LockdropDeposit { duration } => {
+            let config = lockdrop_config(deps.storage)?;
+            if config.asset != (LockdropAsset::Cw20 { address: token }) {
+                return Err(cosmwasm_std::StdError::generic_err("Token not accepted in the lockdrop"));
+            }
+            lockdrop_deposit(deps.storage, &env, config, &wrapper.sender, wrapper.amount, duration)
+        }
+    }
+}
+
+pub fn distribute_cw20_rewards(
+    deps: cosmwasm_std::DepsMut,
+    env: &cosmwasm_std::Env,
+    token: HumanAddr,
+    sender: HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let units = total_units(deps.storage, env)?;
+    if units.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("No holders to distribute to"));
+    }
+
+    add_dividend(deps.storage, &format!("{}{}", CW20_DENOM_PREFIX, token), amount, units)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "distribute_rewards").add_attribute("token", token).add_attribute("from", sender).add_attribute("amount", amount.to_string()))
+}
+
+pub const REBASER_KEY: &[u8] = b"rebaser";
+
+pub fn set_rebaser(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    rebaser: Option<HumanAddr>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<HumanAddr>::new(deps.storage, REBASER_KEY);
+    let attr = match rebaser {
+        Some(rebaser) => {
+            singleton.save(&rebaser)?;
+            rebaser.to_string()
+        }
+        None => {
+            singleton.remove();
+            "none".to_string()
+        }
+    };
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_rebaser").add_attribute("rebaser", attr))
+}
+
+// Rescales every balance at once by changing the supply while the shares stay put
+pub fn rebase(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    new_supply: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let rebaser = ReadonlySingleton::<HumanAddr>::new(deps.storage, REBASER_KEY).may_load()?;
+    if rebaser.as_ref() != Some(&HumanAddr::from(&info.sender)) {
+        assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    }
+    if new_supply.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Supply cannot be rebased to zero"));
+    }
+
+    enable_share_accounting(deps.storage)?;
+
+    let mut token = token_info(deps.storage)?;
+    let old_supply = token.total_supply;
+    assert_within_cap(deps.storage, new_supply)?;
+    assert_reserve_backs(deps.storage, new_supply)?;
+    let held = balance_of(deps.storage, &HumanAddr::from(env.contract.address.as_str()))?;
+    token.total_supply = new_supply;
+    save_token_info(deps.storage, &token)?;
+    hold_contract_balance(deps.storage, &env, held)?;
+
+    let event = cosmwasm_std::Event::new("rebase").add_attribute("old_supply", old_supply.to_string()).add_attribute("new_supply", new_supply.to_string());
+    Ok(cosmwasm_std::Response::new().add_event(event).add_attribute("action", "rebase").add_attribute("new_supply", new_supply.to_string()))
+}
+
+pub const INTEREST_KEY: &[u8] = b"interest";
+pub const SECONDS_PER_YEAR: u64 = 31_536_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InterestConfig {
+    pub rate_bps_per_year: u64,
+    pub last_accrued: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct InterestIndexResponse {
+    pub config: Option<InterestConfig>,
+    // Current balance per unit of scaled balance
+    pub index: Decimal,
+}
+
+// Grows the supply by the interest accrued since the last update, which raises every
+// balance through the share index without touching individual entries
+fn accrue_interest(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env) -> cosmwasm_std::StdResult<Uint128> {
+    let mut config = match ReadonlySingleton::<InterestConfig>::new(storage, INTEREST_KEY).may_load()? {
+        Some(config) => config,
+        None => return Ok(Uint128::zero()),
+    };
+    let now = env.block.time.seconds();
+    let elapsed = now.saturating_sub(config.last_accrued);
+
+    // Escrowed tokens on the contract don't earn interest
+    let held = balance_of(storage, &HumanAddr::from(env.contract.address.as_str()))?;
+    let mut token = token_info(storage)?;
+    let earning = token.total_supply.checked_sub(held)?;
+    let interest = earning.multiply_ratio(config.rate_bps_per_year as u128 * elapsed as u128, 10_000u128 * SECONDS_PER_YEAR as u128);
+    token.total_supply = token.total_supply.checked_add(interest)?;
+    assert_within_cap(storage, token.total_supply)?;
+    assert_reserve_backs(storage, token.total_supply)?;
+    save_token_info(storage, &token)?;
+    hold_contract_balance(storage, env, held)?;
+
+    config.last_accrued = now;
+    Singleton::new(storage, INTEREST_KEY).save(&config)?;
+    Ok(interest)
+}
+
+pub fn set_interest_rate(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    rate_bps_per_year: Option<u64>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    // Settle interest at the old rate before switching
+    accrue_interest(deps.storage, &env)?;
+
+    match rate_bps_per_year {
+        Some(rate_bps_per_year) => {
+            enable_share_accounting(deps.storage)?;
+            let config = InterestConfig { rate_bps_per_year, last_accrued: env.block.time.seconds() };
+            Singleton::new(deps.storage, INTEREST_KEY).save(&config)?;
+        }
+        None => Singleton::<InterestConfig>::new(deps.storage, INTEREST_KEY).remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_interest_rate").add_attribute("rate_bps_per_year", rate_bps_per_year.unwrap_or(0).to_string()))
+}
+
+pub fn accrue(deps: cosmwasm_std::DepsMut, env: cosmwasm_std::Env) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let interest = accrue_interest(deps.storage, &env)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "accrue").add_attribute("interest", interest.to_string()))
+}
+
+pub fn query_interest_index(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<InterestIndexResponse> {
+    let supply = token_info(deps.storage)?.total_supply;
+    let index = match total_shares(deps.storage)? {
+        Some(shares) if !shares.is_zero() => Decimal::from_ratio(supply, shares),
+        _ => Decimal::one(),
+    };
+    Ok(InterestIndexResponse { config: ReadonlySingleton::new(deps.storage, INTEREST_KEY).may_load()?, index })
+}
+
+pub const CAP_KEY: &[u8] = b"cap";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MinterResponse {
+    pub minter: HumanAddr,
+    pub cap: Option<Uint128>,
+}
+
+pub fn supply_cap(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Option<Uint128>> {
+    ReadonlySingleton::new(storage, CAP_KEY).may_load()
+}
+
+pub fn query_token_info(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<TokenInfoResponse> {
+    let info = token_info(deps.storage)?;
+    Ok(TokenInfoResponse {
+        name: info.name,
+        symbol: info.symbol,
+        decimals: info.decimals,
+        total_supply: info.total_supply,
+        cap: supply_cap(deps.storage)?,
+    })
+}
+
+pub fn query_minter(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<MinterResponse> {
+    Ok(MinterResponse { minter: contract_owner(deps.storage)?, cap: supply_cap(deps.storage)? })
+}
+
+pub const EMISSION_KEY: &[u8] = b"emission";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionScheduleMsg {
+    pub per_block: Uint128,
+    // Reduction of the per-block emission applied at every year boundary
+    pub decay_bps: u64,
+    pub blocks_per_year: u64,
+    pub target: HumanAddr,
+    // Mint into the staking reward pool instead of `target`
+    #[serde(default)]
+    pub to_staking: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionSchedule {
+    pub per_block: Uint128,
+    pub decay_bps: u64,
+    pub blocks_per_year: u64,
+    pub target: HumanAddr,
+    #[serde(default)]
+    pub to_staking: bool,
+    pub start_height: u64,
+    pub last_height: u64,
+}
+
+impl EmissionSchedule {
+    // Per-block emission at `height`
+    pub fn current_rate(&self, height: u64) -> Uint128 {
+        self.rate_in_year(height.saturating_sub(self.start_height) / self.blocks_per_year)
+    }
+
+    fn rate_in_year(&self, year: u64) -> Uint128 {
+        let mut rate = self.per_block;
+        for _ in 0..year {
+            rate = rate.multiply_ratio(10_000 - self.decay_bps, 10_000u64);
+            if rate.is_zero() {
+                break;
+            }
+        }
+        rate
+    }
+
+    // Emissions between `last_height` and `height`, walking one year segment at a time
+    pub fn accrued(&self, height: u64) -> cosmwasm_std::StdResult<Uint128> {
+        let mut total = Uint128::zero();
+        let mut from = self.last_height;
+        while from < height {
+            let year = (from - self.start_height) / self.blocks_per_year;
+            let year_end = self.start_height + (year + 1) * self.blocks_per_year;
+            let to = height.min(year_end);
+            let rate = self.rate_in_year(year);
+            if rate.is_zero() {
+                break;
+            }
+            total = total.checked_add(rate.multiply_ratio(to - from, 1u64))?;
+            from = to;
+        }
+        Ok(total)
+    }
+}
+
+pub fn set_emission_schedule(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    schedule: Option<EmissionScheduleMsg>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<EmissionSchedule>::new(deps.storage, EMISSION_KEY);
+    match schedule {
+        Some(msg) => {
+            if msg.blocks_per_year == 0 || msg.decay_bps > 10_000 {
+                return Err(cosmwasm_std::StdError::generic_err("Invalid emission schedule"));
+            }
+            singleton.save(&EmissionSchedule {
+                per_block: msg.per_block,
+                decay_bps: msg.decay_bps,
+                blocks_per_year: msg.blocks_per_year,
+                target: msg.target,
+                to_staking: msg.to_staking,
+                start_height: env.block.height,
+                last_height: env.block.height,
+            })?;
+        }
+        None => singleton.remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_emission_schedule"))
+}
+
+// Mints accrued emissions to the schedule's target, clamped to what the cap still allows
+fn run_emissions(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env) -> cosmwasm_std::StdResult<Option<cosmwasm_std::Event>> {
+    let mut schedule = match ReadonlySingleton::<EmissionSchedule>::new(storage, EMISSION_KEY).may_load()? {
+        Some(schedule) => schedule,
+        None => return Ok(None),
+    };
+
+    let mut amount = schedule.accrued(env.block.height)?;
+    if let Some(cap) = supply_cap(storage)? {
+        let remaining = cap.checked_sub(token_info(storage)?.total_supply).unwrap_or_else(|_| Uint128::zero());
+        amount = amount.min(remaining);
+    }
+    schedule.last_height = env.block.height;
+    Singleton::new(storage, EMISSION_KEY).save(&schedule)?;
+
+    if amount.is_zero() {
+        return Ok(None);
+    }
+    if schedule.to_staking {
+        fund_staking_rewards(storage, env, amount)?;
+        return Ok(Some(cosmwasm_std::Event::new("emission").add_attribute("to", "staking").add_attribute("amount", amount.to_string())));
+    }
+    mint_balance(storage, &schedule.target, amount)?;
+
+    Ok(Some(cosmwasm_std::Event::new("emission").add_attribute("to", schedule.target).add_attribute("amount", amount.to_string())))
+}
+
+pub fn crank(deps: cosmwasm_std::DepsMut, env: cosmwasm_std::Env) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut events = vec![];
+    if let Some(event) = run_emissions(deps.storage, &env)? {
+        events.push(event);
+    }
+    if let Some(event) = run_scheduled_burn(deps.storage, &env)? {
+        events.push(event);
+    }
+    let mut msgs = vec![];
+    if let Some((event, hooks)) = run_auto_compound(deps.storage, &env)? {
+        events.push(event);
+        msgs = hooks;
+    }
+
+    Ok(cosmwasm_std::Response::new().add_messages(msgs).add_events(events).add_attribute("action", "crank"))
+}
+
+pub const BURN_SCHEDULE_KEY: &[u8] = b"burn_schedule";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BurnScheduleMsg {
+    // Account whose balance is burned, the treasury destination or this contract
+    pub source: HumanAddr,
+    // Share of the source balance burned each period
+    pub bps: u64,
+    pub period_seconds: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BurnSchedule {
+    pub source: HumanAddr,
+    pub bps: u64,
+    pub period_seconds: u64,
+    pub next_burn_time: u64,
+    pub total_burned: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NextScheduledBurnResponse {
+    pub next_burn_time: Option<u64>,
+    pub estimated_amount: Uint128,
+    pub total_burned: Uint128,
+}
+
+pub fn set_burn_schedule(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    schedule: Option<BurnScheduleMsg>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    let treasury = ReadonlySingleton::<Treasury>::new(deps.storage, TREASURY_KEY).may_load()?.map(|t| t.destination);
+
+    let mut singleton = Singleton::<BurnSchedule>::new(deps.storage, BURN_SCHEDULE_KEY);
+    match schedule {
+        Some(msg) => {
+            if msg.period_seconds == 0 || msg.bps > 10_000 {
+                return Err(cosmwasm_std::StdError::generic_err("Invalid burn schedule"));
+            }
+            // Never a holder's balance, only tokens the project itself controls
+            if msg.source.as_str() != env.contract.address.as_str() && treasury != Some(msg.source.clone()) {
+                return Err(cosmwasm_std::StdError::generic_err("Burn source must be the treasury or this contract"));
+            }
+            let total_burned = singleton.may_load()?.map(|s| s.total_burned).unwrap_or_else(Uint128::zero);
+            singleton.save(&BurnSchedule {
+                source: msg.source,
+                bps: msg.bps,
+                period_seconds: msg.period_seconds,
+                next_burn_time: env.block.time.seconds() + msg.period_seconds,
+                total_burned,
+            })?;
+        }
+        None => singleton.remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_burn_schedule"))
+}
+
+// Burns one period's share of the source balance once the period has passed
+fn run_scheduled_burn(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env) -> cosmwasm_std::StdResult<Option<cosmwasm_std::Event>> {
+    let mut schedule = match ReadonlySingleton::<BurnSchedule>::new(storage, BURN_SCHEDULE_KEY).may_load()? {
+        Some(schedule) => schedule,
+        None => return Ok(None),
+    };
+    let now = env.block.time.seconds();
+    if now < schedule.next_burn_time {
+        return Ok(None);
+    }
+
+    let amount = burnable_balance(storage, env, &schedule.source)?.multiply_ratio(schedule.bps, 10_000u64);
+    if !amount.is_zero() {
+        burn_balance(storage, &schedule.source, amount)?;
+    }
+    schedule.total_burned = schedule.total_burned.checked_add(amount)?;
+    // Missed periods are not burned retroactively
+    schedule.next_burn_time = now + schedule.period_seconds;
+    Singleton::new(storage, BURN_SCHEDULE_KEY).save(&schedule)?;
+
+    Ok(Some(cosmwasm_std::Event::new("scheduled_burn").add_attribute("from", schedule.source).add_attribute("amount", amount.to_string())))
+}
+
+// Tokens escrowed on the contract belong to other modules and are left alone
+fn burnable_balance(storage: &dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, source: &HumanAddr) -> cosmwasm_std::StdResult<Uint128> {
+    let balance = balance_of(storage, source)?;
+    if source.as_str() == env.contract.address.as_str() {
+        return Ok(balance.saturating_sub(escrowed_total(storage)?));
+    }
+    Ok(balance)
+}
+
+pub fn query_next_scheduled_burn(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env) -> cosmwasm_std::StdResult<NextScheduledBurnResponse> {
+    match ReadonlySingleton::<BurnSchedule>::new(deps.storage, BURN_SCHEDULE_KEY).may_load()? {
+        Some(schedule) => Ok(NextScheduledBurnResponse {
+            next_burn_time: Some(schedule.next_burn_time),
+            estimated_amount: burnable_balance(deps.storage, &env, &schedule.source)?.multiply_ratio(schedule.bps, 10_000u64),
+            total_burned: schedule.total_burned,
+        }),
+        None => Ok(NextScheduledBurnResponse { next_burn_time: None, estimated_amount: Uint128::zero(), total_burned: Uint128::zero() }),
+    }
+}
+
+pub const VESTING_PREFIX: &[u8] = b"vesting";
+
+// Times are in seconds, nothing vests before the cliff and everything has vested at `end`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VestingSchedule {
+    pub start: u64,
+    pub cliff: u64,
+    pub end: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Vesting {
+    pub total: Uint128,
+    pub schedule: VestingSchedule,
+}
+
+impl Vesting {
+    pub fn locked(&self, now: u64) -> Uint128 {
+        let s = &self.schedule;
+        if now < s.cliff {
+            return self.total;
+        }
+        if now >= s.end || s.end == s.start {
+            return Uint128::zero();
+        }
+        let vested = self.total.multiply_ratio(now - s.start, s.end - s.start);
+        self.total.checked_sub(vested).unwrap_or_else(|_| Uint128::zero())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VestingBreakdownResponse {
+    pub vested: Uint128,
+    pub locked: Uint128,
+}
+
+// Part of an account's balance that cannot be moved yet
+pub fn locked_balance(storage: &dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, address: &HumanAddr) -> cosmwasm_std::StdResult<Uint128> {
+    let mut locked = Uint128::zero();
+    if let Some(vesting) = ReadonlyBucket::<Vesting>::new(storage, VESTING_PREFIX).may_load(address.as_bytes())? {
+        locked = locked.checked_add(vesting.locked(env.block.time.seconds()))?;
+    }
+    for lockup in active_lockups(storage, env, address)? {
+        locked = locked.checked_add(lockup.amount)?;
+    }
+    Ok(locked)
+}
+
+pub fn assert_unlocked(storage: &dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, from: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    let locked = locked_balance(storage, env, from)?;
+    if locked.is_zero() {
+        return Ok(());
+    }
+    if balance_of(storage, from)? < locked.checked_add(amount)? {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Insufficient unlocked balance, {} is still locked", locked)));
+    }
+    Ok(())
+}
+
+pub fn query_vesting_breakdown(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, address: HumanAddr) -> cosmwasm_std::StdResult<VestingBreakdownResponse> {
+    match ReadonlyBucket::<Vesting>::new(deps.storage, VESTING_PREFIX).may_load(address.as_bytes())? {
+        Some(vesting) => {
+            let locked = vesting.locked(env.block.time.seconds());
+            Ok(VestingBreakdownResponse { vested: vesting.total.checked_sub(locked)?, locked })
+        }
+        None => Ok(VestingBreakdownResponse { vested: Uint128::zero(), locked: Uint128::zero() }),
+    }
+}
+
+pub const LOCKUPS_PREFIX: &[u8] = b"lockups";
+// Every transfer out of an account walks its lockups, so anyone but the owner can only add a few and only of at least one whole token
+pub const MAX_LOCKUPS_PER_SENDER: usize = 5;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Lockup {
+    pub amount: Uint128,
+    // Time in seconds
+    pub unlock_time: u64,
+    pub from: HumanAddr,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LocksResponse {
+    pub locks: Vec<Lockup>,
+}
+
+pub fn active_lockups(storage: &dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, address: &HumanAddr) -> cosmwasm_std::StdResult<Vec<Lockup>> {
+    let lockups = ReadonlyBucket::<Vec<Lockup>>::new(storage, LOCKUPS_PREFIX).may_load(address.as_bytes())?.unwrap_or_default();
+    let now = env.block.time.seconds();
+    Ok(lockups.into_iter().filter(|l| l.unlock_time > now).collect())
+}
+
+pub fn transfer_locked(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipient: HumanAddr,
+    amount: Uint128,
+    unlock_time: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if unlock_time <= env.block.time.seconds() {
+        return Err(cosmwasm_std::StdError::generic_err("Unlock time must be in the future"));
+    }
+    let sender = HumanAddr::from(&info.sender);
+    if sender != contract_owner(deps.storage)? {
+        let minimum = Uint128::from(10u128.pow(token_info(deps.storage)?.decimals as u32));
+        if amount < minimum {
+            return Err(cosmwasm_std::StdError::generic_err(format!("Locked transfers must be at least {}", minimum)));
+        }
+        let from_sender = active_lockups(deps.storage, &env, &recipient)?.iter().filter(|l| l.from == sender).count();
+        if from_sender >= MAX_LOCKUPS_PER_SENDER {
+            return Err(cosmwasm_std::StdError::generic_err(format!("{} already holds {} active lockups from {}", recipient, MAX_LOCKUPS_PER_SENDER, sender)));
+        }
+    }
+
+    assert_not_large_transfer(deps.storage, amount)?;
+    assert_transfer_allowed(deps.as_ref(), &env, &HumanAddr::from(&info.sender), &recipient, amount)?;
+    let events = record_transfer(deps.storage, &env, &HumanAddr::from(&info.sender), &recipient, amount)?;
+    let outcome = settle_transfer(deps.storage, &env, &HumanAddr::from(&info.sender), &recipient, amount)?;
+
+    // Expired lockups are dropped whenever a new one is written
+    let mut lockups = active_lockups(deps.storage, &env, &recipient)?;
+    lockups.push(Lockup { amount: outcome.net, unlock_time, from: HumanAddr::from(&info.sender) });
+    Bucket::new(deps.storage, LOCKUPS_PREFIX).save(recipient.as_bytes(), &lockups)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_events(events)
+        .add_events(outcome.events)
+        .add_attribute("action", "transfer_locked")
+        .add_attribute("from", info.sender)
+        .add_attribute("to", recipient)
+        .add_attribute("amount", outcome.net.to_string())
+        .add_attribute("unlock_time", unlock_time.to_string()))
+}
+
+pub fn query_locks(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, address: HumanAddr) -> cosmwasm_std::StdResult<LocksResponse> {
+    Ok(LocksResponse { locks: active_lockups(deps.storage, &env, &address)? })
+}
+
+pub const AIRDROP_STAGE_COUNT_KEY: &[u8] = b"airdrop_stage_count";
+pub const AIRDROP_STAGES_PREFIX: &[u8] = b"airdrop_stages";
+pub const AIRDROP_CLAIMED_PREFIX: &[u8] = b"airdrop_claimed";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AirdropStage {
+    pub stage: u64,
+    // Hex encoded sha256 root of leaves sha256("{address}{amount}")
+    pub merkle_root: String,
+    pub total_amount: Uint128,
+    pub claimed_amount: Uint128,
+    pub expiration: Option<Expiration>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IsClaimedResponse {
+    pub is_claimed: bool,
+}
+
+pub fn verify_merkle_proof(root: &str, leaf: [u8; 32], proof: &[String]) -> cosmwasm_std::StdResult<bool> {
+    use sha2::Digest;
+
+    let mut hash = leaf;
+    for step in proof {
+        let mut sibling = [0u8; 32];
+        hex::decode_to_slice(step, &mut sibling).map_err(|_| cosmwasm_std::StdError::generic_err("Invalid proof"))?;
+        // Pairs are hashed in sorted order so proofs don't need position flags
+        let mut pair = [hash, sibling];
+        pair.sort_unstable();
+        hash = sha2::Sha256::digest(&pair.concat()).into();
+    }
+
+    let mut root_bytes = [0u8; 32];
+    hex::decode_to_slice(root, &mut root_bytes).map_err(|_| cosmwasm_std::StdError::generic_err("Invalid merkle root"))?;
+    Ok(hash == root_bytes)
+}
+
+pub fn register_merkle_root(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    merkle_root: String,
+    total_amount: Uint128,
+    expiration: Option<Expiration>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut root_bytes = [0u8; 32];
+    hex::decode_to_slice(&merkle_root, &mut root_bytes).map_err(|_| cosmwasm_std::StdError::generic_err("Invalid merkle root"))?;
+
+    // The owner funds the whole stage up front
+    escrow_in(deps.storage, &env, &HumanAddr::from(&info.sender), total_amount)?;
+
+    let stage = next_id(deps.storage, AIRDROP_STAGE_COUNT_KEY)?;
+    let airdrop = AirdropStage { stage, merkle_root: merkle_root.clone(), total_amount, claimed_amount: Uint128::zero(), expiration };
+    Bucket::new(deps.storage, AIRDROP_STAGES_PREFIX).save(&stage.to_be_bytes(), &airdrop)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "register_merkle_root").add_attribute("stage", stage.to_string()).add_attribute("merkle_root", merkle_root).add_attribute("total_amount", total_amount.to_string()))
+}
+
+pub fn claim_airdrop(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    stage: u64,
+    amount: Uint128,
+    proof: Vec<String>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    use sha2::Digest;
+
+    let mut airdrop = Bucket::<AirdropStage>::new(deps.storage, AIRDROP_STAGES_PREFIX).load(&stage.to_be_bytes())?;
+    if airdrop.expiration.map(|e| e.is_expired(&env)).unwrap_or(false) {
+        return Err(cosmwasm_std::StdError::generic_err("Airdrop stage has expired"));
+    }
+
+    let mut claimed = Bucket::<bool>::multilevel(deps.storage, &[AIRDROP_CLAIMED_PREFIX, &stage.to_be_bytes()]);
+    if claimed.may_load(info.sender.as_bytes())?.unwrap_or(false) {
+        return Err(cosmwasm_std::StdError::generic_err("Already claimed"));
+    }
+
+    let leaf: [u8; 32] = sha2::Sha256::digest(format!("{}{}", info.sender, amount).as_bytes()).into();
+    if !verify_merkle_proof(&airdrop.merkle_root, leaf, &proof)? {
+        return Err(cosmwasm_std::StdError::generic_err("Invalid proof"));
+    }
+    claimed.save(info.sender.as_bytes(), &true)?;
+
+    airdrop.claimed_amount = airdrop.claimed_amount.checked_add(amount)?;
+    if airdrop.claimed_amount > airdrop.total_amount {
+        return Err(cosmwasm_std::StdError::generic_err("Airdrop stage is exhausted"));
+    }
+    Bucket::new(deps.storage, AIRDROP_STAGES_PREFIX).save(&stage.to_be_bytes(), &airdrop)?;
+
+    assert_not_blacklisted(deps.storage, &HumanAddr::from(&info.sender))?;
+    escrow_out(deps.storage, &env, &HumanAddr::from(&info.sender), amount)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "claim").add_attribute("stage", stage.to_string()).add_attribute("address", info.sender).add_attribute("amount", amount.to_string()))
+}
+
+// Returns whatever was not claimed to the owner once the stage has expired
+pub fn clawback_airdrop(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    stage: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut airdrop = Bucket::<AirdropStage>::new(deps.storage, AIRDROP_STAGES_PREFIX).load(&stage.to_be_bytes())?;
+    match airdrop.expiration {
+        Some(expiration) if expiration.is_expired(&env) => {}
+        _ => return Err(cosmwasm_std::StdError::generic_err("Airdrop stage has not expired")),
+    }
+
+    let unclaimed = airdrop.total_amount.checked_sub(airdrop.claimed_amount)?;
+    if unclaimed.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Nothing to claw back"));
+    }
+    escrow_out(deps.storage, &env, &HumanAddr::from(&info.sender), unclaimed)?;
+
+    // Mark the remainder as claimed so it can't be clawed back twice
+    airdrop.claimed_amount = airdrop.total_amount;
+    Bucket::new(deps.storage, AIRDROP_STAGES_PREFIX).save(&stage.to_be_bytes(), &airdrop)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "clawback_airdrop").add_attribute("stage", stage.to_string()).add_attribute("amount", unclaimed.to_string()))
+}
+
+pub fn query_is_claimed(deps: cosmwasm_std::Deps, stage: u64, address: HumanAddr) -> cosmwasm_std::StdResult<IsClaimedResponse> {
+    let claimed = ReadonlyBucket::<bool>::multilevel(deps.storage, &[AIRDROP_CLAIMED_PREFIX, &stage.to_be_bytes()]).may_load(address.as_bytes())?;
+    Ok(IsClaimedResponse { is_claimed: claimed.unwrap_or(false) })
+}
+
+pub const MAX_BATCH_SIZE: usize = 500;
+
+// The whole batch is applied atomically, any failing entry reverts all of them
+pub fn mint_batch(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    recipients: Vec<(HumanAddr, Uint128)>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    if recipients.is_empty() || recipients.len() > MAX_BATCH_SIZE {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Batch must contain between 1 and {} entries", MAX_BATCH_SIZE)));
+    }
+
+    let mut total = Uint128::zero();
+    for (recipient, amount) in &recipients {
+        assert_mint_allowed(deps.as_ref(), recipient, *amount)?;
+        mint_balance(deps.storage, recipient, *amount)?;
+        total = total.checked_add(*amount)?;
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "mint_batch").add_attribute("recipients", recipients.len().to_string()).add_attribute("amount", total.to_string()))
+}
+
+pub fn transfer_batch(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    transfers: Vec<(HumanAddr, Uint128)>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if transfers.is_empty() || transfers.len() > MAX_BATCH_SIZE {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Batch must contain between 1 and {} entries", MAX_BATCH_SIZE)));
+    }
+
+    let mut events = vec![];
+    let mut total = Uint128::zero();
+    for (recipient, amount) in &transfers {
+        assert_not_large_transfer(deps.storage, *amount)?;
+        assert_transfer_allowed(deps.as_ref(), &env, &HumanAddr::from(&info.sender), recipient, *amount)?;
+        events.extend(record_transfer(deps.storage, &env, &HumanAddr::from(&info.sender), recipient, *amount)?);
+        let outcome = settle_transfer(deps.storage, &env, &HumanAddr::from(&info.sender), recipient, *amount)?;
+        events.extend(outcome.events);
+        total = total.checked_add(*amount)?;
+    }
+
+    Ok(cosmwasm_std::Response::new().add_events(events).add_attribute("action", "transfer_batch").add_attribute("from", info.sender).add_attribute("recipients", transfers.len().to_string()).add_attribute("amount", total.to_string()))
+}
+
+pub const LEGACY_MIGRATION_KEY: &[u8] = b"legacy_migration";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyMigration {
+    pub legacy_token: HumanAddr,
+    // New tokens minted per legacy token received
+    pub ratio: Decimal,
+    // Burn the legacy tokens on receipt instead of locking them in this contract
+    pub burn_legacy: bool,
+    #[serde(default)]
+    pub total_migrated: Uint128,
+}
+
+pub fn set_legacy_migration(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    config: Option<LegacyMigration>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut singleton = Singleton::<LegacyMigration>::new(deps.storage, LEGACY_MIGRATION_KEY);
+    match config {
+        Some(mut config) => {
+            if config.ratio.is_zero() {
+                return Err(cosmwasm_std::StdError::generic_err("Migration ratio must be positive"));
+            }
+            config.total_migrated = singleton.may_load()?.map(|c| c.total_migrated).unwrap_or_else(Uint128::zero);
+            singleton.save(&config)?;
+        }
+        None => singleton.remove(),
+    }
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_legacy_migration"))
+}
+
+pub fn migrate_legacy_tokens(
+    deps: cosmwasm_std::DepsMut,
+    token: HumanAddr,
+    sender: HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut config = ReadonlySingleton::<LegacyMigration>::new(deps.storage, LEGACY_MIGRATION_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Legacy migration is not enabled"))?;
+    if token != config.legacy_token {
+        return Err(cosmwasm_std::StdError::generic_err("Unsupported legacy token"));
+    }
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+
+    let minted = amount * config.ratio;
+    assert_mint_allowed(deps.as_ref(), &sender, minted)?;
+    mint_balance(deps.storage, &sender, minted)?;
+
+    config.total_migrated = config.total_migrated.checked_add(amount)?;
+    Singleton::new(deps.storage, LEGACY_MIGRATION_KEY).save(&config)?;
+
+    let mut res = cosmwasm_std::Response::new();
+    if config.burn_legacy {
+        res = res.add_message(cosmwasm_std::WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: cosmwasm_std::to_binary(&Cw20ExecuteMsg::Burn { amount })?,
+            funds: vec![],
+        });
+    }
+
+    Ok(res.add_attribute("action", "migrate_legacy").add_attribute("from", sender).add_attribute("legacy_amount", amount.to_string()).add_attribute("minted", minted.to_string()))
+}
+
+pub const REDENOMINATIONS_KEY: &[u8] = b"redenominations";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MigrateMsg {
+    pub redenomination: Option<Redenomination>,
+}
+
+// Every amount is multiplied by numerator / denominator, so 1000 / 1 is a 1000:1 split
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Redenomination {
+    pub numerator: Uint128,
+    pub denominator: Uint128,
+    pub decimals: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RedenominationRecord {
+    pub height: u64,
+    pub numerator: Uint128,
+    pub denominator: Uint128,
+    pub old_supply: Uint128,
+    pub new_supply: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RedenominationsResponse {
+    pub history: Vec<RedenominationRecord>,
+    // Current units per unit held before the first redenomination
+    pub cumulative_rate: Decimal,
+}
+
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn migrate(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    msg: MigrateMsg,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    record_block_height(deps.storage, &env)?;
+    let mut res = cosmwasm_std::Response::new().add_attribute("action", "migrate");
+    if let Some(redenomination) = msg.redenomination {
+        res = res.add_events(redenominate(deps.storage, &env, redenomination)?);
+    }
+    Ok(res)
+}
+
+fn redenominate(
+    storage: &mut dyn cosmwasm_std::Storage,
+    env: &cosmwasm_std::Env,
+    r: Redenomination,
+) -> cosmwasm_std::StdResult<Vec<cosmwasm_std::Event>> {
+    if r.numerator.is_zero() || r.denominator.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Invalid redenomination ratio"));
+    }
+    // Escrow modules keep their own amounts, those would drift from the rescaled balances
+    if !escrowed_total(storage)?.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Cannot redenominate while tokens are escrowed"));
+    }
+    let blockers = redenomination_blockers(storage)?;
+    if !blockers.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Cannot redenominate while these are active: {}", blockers.join(", "))));
+    }
+
+    let mut token = token_info(storage)?;
+    let old_supply = token.total_supply;
+    let before = cosmwasm_std::Event::new("redenomination_before").add_attribute("total_supply", old_supply.to_string()).add_attribute("decimals", token.decimals.to_string());
+
+    // Balances follow the supply through the share index without being rewritten
+    enable_share_accounting(storage)?;
+    token.total_supply = old_supply.multiply_ratio(r.numerator, r.denominator);
+    if let Some(decimals) = r.decimals {
+        token.decimals = decimals;
+    }
+    save_token_info(storage, &token)?;
+    rescale_limits(storage, &r)?;
+
+    let mut history = ReadonlySingleton::<Vec<RedenominationRecord>>::new(storage, REDENOMINATIONS_KEY).may_load()?.unwrap_or_default();
+    history.push(RedenominationRecord {
+        height: env.block.height,
+        numerator: r.numerator,
+        denominator: r.denominator,
+        old_supply,
+        new_supply: token.total_supply,
+    });
+    Singleton::new(storage, REDENOMINATIONS_KEY).save(&history)?;
+
+    let after = cosmwasm_std::Event::new("redenomination_after")
+        .add_attribute("total_supply", token.total_supply.to_string())
+        .add_attribute("decimals", token.decimals.to_string())
+        .add_attribute("ratio", format!("{}/{}", r.numerator, r.denominator));
+    Ok(vec![before, after])
+}
+
+// Modules holding amounts that can't simply be multiplied, they have to be wound down first
+fn redenomination_blockers(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Vec<&'static str>> {
+    let mut blockers = vec![];
+    if ReadonlyBucket::<Vesting>::new(storage, VESTING_PREFIX).range(None, None, cosmwasm_std::Order::Ascending).next().is_some() {
+        blockers.push("vesting");
+    }
+    if ReadonlyBucket::<Vec<Lockup>>::new(storage, LOCKUPS_PREFIX).range(None, None, cosmwasm_std::Order::Ascending).next().is_some() {
+        blockers.push("lockups");
+    }
+    if ReadonlySingleton::<BondingCurve>::new(storage, BONDING_CURVE_KEY).may_load()?.is_some() {
+        blockers.push("bonding_curve");
+    }
+    if ReadonlySingleton::<NativeWrapper>::new(storage, NATIVE_WRAPPER_KEY).may_load()?.is_some() {
+        blockers.push("native_wrapper");
+    }
+    if ReadonlySingleton::<Collateral>::new(storage, COLLATERAL_KEY).may_load()?.is_some() {
+        blockers.push("collateral");
+    }
+    if ReadonlySingleton::<FactoryDenom>::new(storage, TOKENFACTORY_KEY).may_load()?.is_some() {
+        blockers.push("tokenfactory");
+    }
+    if ReadonlySingleton::<LegacyMigration>::new(storage, LEGACY_MIGRATION_KEY).may_load()?.is_some() {
+        blockers.push("legacy_migration");
+    }
+    if ReadonlyBucket::<FlowLimits>::new(storage, FLOW_LIMITS_PREFIX).range(None, None, cosmwasm_std::Order::Ascending).next().is_some() {
+        blockers.push("flow_limits");
+    }
+    Ok(blockers)
+}
+
+// Owner configured amounts that keep their meaning when multiplied along with the balances
+fn rescale_limits(storage: &mut dyn cosmwasm_std::Storage, r: &Redenomination) -> cosmwasm_std::StdResult<()> {
+    for key in [CAP_KEY, MAX_WALLET_KEY, MAX_TX_KEY, MIN_TRANSFER_KEY] {
+        if let Some(amount) = ReadonlySingleton::<Uint128>::new(storage, key).may_load()? {
+            Singleton::new(storage, key).save(&amount.multiply_ratio(r.numerator, r.denominator))?;
+        }
+    }
+    if let Some(mut daily) = ReadonlySingleton::<DailyLimit>::new(storage, DAILY_LIMIT_KEY).may_load()? {
+        daily.limit = daily.limit.multiply_ratio(r.numerator, r.denominator);
+        Singleton::new(storage, DAILY_LIMIT_KEY).save(&daily)?;
+    }
+    if let Some(mut schedule) = ReadonlySingleton::<EmissionSchedule>::new(storage, EMISSION_KEY).may_load()? {
+        schedule.per_block = schedule.per_block.multiply_ratio(r.numerator, r.denominator);
+        Singleton::new(storage, EMISSION_KEY).save(&schedule)?;
+    }
+    if let Some(mut config) = ReadonlySingleton::<GovernanceConfig>::new(storage, GOVERNANCE_KEY).may_load()? {
+        config.proposal_threshold = config.proposal_threshold.multiply_ratio(r.numerator, r.denominator);
+        Singleton::new(storage, GOVERNANCE_KEY).save(&config)?;
+    }
+    Ok(())
+}
+
+pub fn query_redenominations(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<RedenominationsResponse> {
+    let history = ReadonlySingleton::<Vec<RedenominationRecord>>::new(deps.storage, REDENOMINATIONS_KEY).may_load()?.unwrap_or_default();
+    let (num, den) = history.iter().fold((Uint128::from(1u128), Uint128::from(1u128)), |(n, d), r| {
+        (n.multiply_ratio(r.numerator, 1u128), d.multiply_ratio(r.denominator, 1u128))
+    });
+    Ok(RedenominationsResponse { history, cumulative_rate: Decimal::from_ratio(num, den) })
+}
+
+pub const BONDING_CURVE_KEY: &[u8] = b"bonding_curve";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveType {
+    // Reserve units per token unit
+    Constant { price: Decimal },
+    // Price of a whole token rises by `slope` reserve units per whole token issued.
+    // Linear curves are evaluated on whole tokens to keep the squares inside u128.
+    Linear { slope: Decimal },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BondingCurve {
+    pub curve: CurveType,
+    pub reserve_denom: String,
+    pub reserve: Uint128,
+    // Tokens issued through the curve, other mints don't move the price
+    pub supply: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CurveInfoResponse {
+    pub reserve_denom: String,
+    pub reserve: Uint128,
+    pub supply: Uint128,
+    // Reserve units per whole token at the current supply
+    pub spot_price: Decimal,
+}
+
+// Decimal::one() in atomics, used to divide by a Decimal
+const DECIMAL_FRACTIONAL: u128 = 1_000_000_000_000_000_000;
+
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = x.div_ceil(2);
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+impl BondingCurve {
+    fn unit(decimals: u8) -> u128 {
+        10u128.pow(decimals as u32)
+    }
+
+    // Reserve required to back `supply` tokens
+    pub fn reserve_for(&self, supply: Uint128, decimals: u8) -> Uint128 {
+        match &self.curve {
+            CurveType::Constant { price } => supply * *price,
+            CurveType::Linear { slope } => {
+                let whole = supply.u128() / Self::unit(decimals);
+                Uint128::from(whole * whole / 2) * *slope
+            }
+        }
+    }
+
+    // Supply that `reserve` can back, the inverse of `reserve_for`
+    pub fn supply_for(&self, reserve: Uint128, decimals: u8) -> cosmwasm_std::StdResult<Uint128> {
+        match &self.curve {
+            CurveType::Constant { price } => {
+                let price_atomics = *price * Uint128::from(DECIMAL_FRACTIONAL);
+                if price_atomics.is_zero() {
+                    return Err(cosmwasm_std::StdError::generic_err("Price too small"));
+                }
+                Ok(reserve.multiply_ratio(DECIMAL_FRACTIONAL, price_atomics))
+            }
+            CurveType::Linear { slope } => {
+                let slope_atomics = *slope * Uint128::from(DECIMAL_FRACTIONAL);
+                if slope_atomics.is_zero() {
+                    return Err(cosmwasm_std::StdError::generic_err("Slope too small"));
+                }
+                let squared = reserve.multiply_ratio(2 * DECIMAL_FRACTIONAL, slope_atomics);
+                Ok(Uint128::from(isqrt(squared.u128()) * Self::unit(decimals)))
+            }
+        }
+    }
+
+    pub fn spot_price(&self, decimals: u8) -> Decimal {
+        match &self.curve {
+            CurveType::Constant { price } => Decimal::from_ratio(*price * Uint128::from(DECIMAL_FRACTIONAL * Self::unit(decimals)), DECIMAL_FRACTIONAL),
+            CurveType::Linear { slope } => Decimal::from_ratio(*slope * Uint128::from(DECIMAL_FRACTIONAL * (self.supply.u128() / Self::unit(decimals))), DECIMAL_FRACTIONAL),
+        }
+    }
+}
+
+pub fn set_bonding_curve(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    curve: CurveType,
+    reserve_denom: String,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    // The curve can't be changed once it holds reserves, that would reprice existing holders
+    let mut singleton = Singleton::<BondingCurve>::new(deps.storage, BONDING_CURVE_KEY);
+    if let Some(existing) = singleton.may_load()? {
+        if !existing.supply.is_zero() {
+            return Err(cosmwasm_std::StdError::generic_err("Bonding curve is already in use"));
+        }
+    }
+    singleton.save(&BondingCurve { curve, reserve_denom: reserve_denom.clone(), reserve: Uint128::zero(), supply: Uint128::zero() })?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_bonding_curve").add_attribute("reserve_denom", reserve_denom))
+}
+
+pub fn buy_from_curve(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut curve = ReadonlySingleton::<BondingCurve>::new(deps.storage, BONDING_CURVE_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Bonding curve is not enabled"))?;
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+
+    let payment = match info.funds.as_slice() {
+        [coin] if coin.denom == curve.reserve_denom && !coin.amount.is_zero() => coin.amount,
+        _ => return Err(cosmwasm_std::StdError::generic_err(format!("Send exactly one coin of {}", curve.reserve_denom))),
+    };
+
+    let decimals = token_info(deps.storage)?.decimals;
+    let total = curve.reserve.checked_add(payment)?;
+    let new_supply = curve.supply_for(total, decimals)?;
+    let minted = new_supply.checked_sub(curve.supply)?;
+    if minted.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Payment too small to mint any tokens"));
+    }
+    // Whatever the minted amount doesn't use up, linear curves only mint whole tokens
+    let refund = total.saturating_sub(curve.reserve_for(new_supply, decimals)).min(payment);
+    curve.reserve = total.checked_sub(refund)?;
+    curve.supply = new_supply;
+    Singleton::new(deps.storage, BONDING_CURVE_KEY).save(&curve)?;
+
+    assert_mint_allowed(deps.as_ref(), &HumanAddr::from(&info.sender), minted)?;
+    mint_balance(deps.storage, &HumanAddr::from(&info.sender), minted)?;
+
+    let mut res = cosmwasm_std::Response::new();
+    if !refund.is_zero() {
+        res = res.add_message(cosmwasm_std::BankMsg::Send { to_address: info.sender.to_string(), amount: vec![cosmwasm_std::Coin { denom: curve.reserve_denom, amount: refund }] });
+    }
+    Ok(res.add_attribute("refund", refund.to_string()).add_attribute("action", "buy").add_attribute("buyer", info.sender).add_attribute("payment", payment.to_string()).add_attribute("minted", minted.to_string()))
+}
+
+pub fn sell_to_curve(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut curve = ReadonlySingleton::<BondingCurve>::new(deps.storage, BONDING_CURVE_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Bonding curve is not enabled"))?;
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    assert_not_blacklisted(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let decimals = token_info(deps.storage)?.decimals;
+    // A fraction would be paid out as the whole token it rounds the supply down across
+    if matches!(curve.curve, CurveType::Linear { .. }) && !amount.u128().is_multiple_of(BondingCurve::unit(decimals)) {
+        return Err(cosmwasm_std::StdError::generic_err("Linear curves only buy back whole tokens"));
+    }
+    curve.supply = curve.supply.checked_sub(amount).map_err(|_| cosmwasm_std::StdError::generic_err("Amount exceeds curve supply"))?;
+    let new_reserve = curve.reserve_for(curve.supply, decimals);
+    let payout = curve.reserve.checked_sub(new_reserve)?;
+    curve.reserve = new_reserve;
+    Singleton::new(deps.storage, BONDING_CURVE_KEY).save(&curve)?;
+
+    burn_balance(deps.storage, &HumanAddr::from(&info.sender), amount)?;
+
+    let msg = cosmwasm_std::BankMsg::Send { to_address: info.sender.to_string(), amount: vec![cosmwasm_std::Coin { denom: curve.reserve_denom, amount: payout }] };
+    Ok(cosmwasm_std::Response::new().add_message(msg).add_attribute("action", "sell").add_attribute("seller", info.sender).add_attribute("burned", amount.to_string()).add_attribute("payout", payout.to_string()))
+}
+
+pub fn query_curve_info(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<CurveInfoResponse> {
+    let curve = ReadonlySingleton::<BondingCurve>::new(deps.storage, BONDING_CURVE_KEY).load()?;
+    let decimals = token_info(deps.storage)?.decimals;
+    Ok(CurveInfoResponse {
+        spot_price: curve.spot_price(decimals),
+        reserve_denom: curve.reserve_denom,
+        reserve: curve.reserve,
+        supply: curve.supply,
+    })
+}
+
+pub const FLASH_MINT_FEE_KEY: &[u8] = b"flash_mint_fee";
+pub const FLASH_MINT_KEY: &[u8] = b"flash_mint";
+pub const FLASH_MINT_REPLY_ID: u64 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingFlashMint {
+    pub borrower: HumanAddr,
+    pub amount: Uint128,
+    pub fee: Uint128,
+}
+
+// Sent to the borrower, which has to hold `amount` plus `fee` when it returns
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FlashMintCallbackMsg {
+    pub amount: Uint128,
+    pub fee: Uint128,
+    pub msg: cosmwasm_std::Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashMintReceiverExecuteMsg {
+    ReceiveFlashMint(FlashMintCallbackMsg),
+}
+
+pub fn set_flash_mint_fee(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    fee_bps: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    if fee_bps > MAX_FEE_BPS {
+        return Err(cosmwasm_std::StdError::generic_err("Fee cannot exceed 100%"));
+    }
+    Singleton::new(deps.storage, FLASH_MINT_FEE_KEY).save(&fee_bps)?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_flash_mint_fee").add_attribute("fee_bps", fee_bps.to_string()))
+}
+
+pub fn flash_mint(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    amount: Uint128,
+    msg: cosmwasm_std::Binary,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+    // The reply settles whatever is pending, so only one can be open at a time
+    if ReadonlySingleton::<PendingFlashMint>::new(deps.storage, FLASH_MINT_KEY).may_load()?.is_some() {
+        return Err(cosmwasm_std::StdError::generic_err("Flash mint already in progress"));
+    }
+    assert_not_blacklisted(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let fee_bps = ReadonlySingleton::<u64>::new(deps.storage, FLASH_MINT_FEE_KEY).may_load()?.unwrap_or(0);
+    let fee = amount.multiply_ratio(fee_bps, MAX_FEE_BPS);
+    let pending = PendingFlashMint {
+        borrower: HumanAddr::from(&info.sender),
+        amount,
+        fee,
+    };
+    Singleton::new(deps.storage, FLASH_MINT_KEY).save(&pending)?;
+
+    mint_balance(deps.storage, &HumanAddr::from(&info.sender), amount)?;
+
+    // Always calls back the borrower itself, so the minted tokens can't be routed
+    // through a message acting with this contract's authority
+    let callback = cosmwasm_std::WasmMsg::Execute {
+        contract_addr: info.sender.to_string(),
+        msg: cosmwasm_std::to_binary(&FlashMintReceiverExecuteMsg::ReceiveFlashMint(FlashMintCallbackMsg { amount, fee, msg }))?,
+        funds: vec![],
+    };
+
+    Ok(cosmwasm_std::Response::new()
+        .add_submessage(cosmwasm_std::SubMsg::reply_on_success(callback, FLASH_MINT_REPLY_ID))
+        .add_attribute("action", "flash_mint")
+        .add_attribute("borrower", info.sender)
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("fee", fee.to_string()))
+}
+
+// Runs after the flash mint callback, failing here reverts the whole transaction
+// including the mint itself. A failing callback never reaches this point and
+// reverts on its own.
+#[cfg_attr(not(feature = "library"), cosmwasm_std::entry_point)]
+pub fn reply(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    msg: cosmwasm_std::Reply,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    record_block_height(deps.storage, &env)?;
+    match msg.id {
+        FLASH_MINT_REPLY_ID => settle_flash_mint(deps),
+        BUYBACK_REPLY_ID => settle_buyback(deps, env),
+        // Only hooks with the ignore policy reply, and only when they fail
+        TRANSFER_HOOK_REPLY_ID => Ok(cosmwasm_std::Response::new().add_attribute("action", "transfer_hook_failed")),
+        SEND_CHECKED_REPLY_ID => settle_send_checked(deps, msg.result),
+        IBC_HOOK_REPLY_ID => Ok(cosmwasm_std::Response::new().add_attribute("action", "ibc_hook_failed")),
+        IBC_RECEIVE_REPLY_ID => fail_ibc_receive(msg.result),
+        id => Err(cosmwasm_std::StdError::generic_err(format!("Unknown reply id {}", id))),
+    }
+}
+
+fn settle_flash_mint(deps: cosmwasm_std::DepsMut) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let pending = ReadonlySingleton::<PendingFlashMint>::new(deps.storage, FLASH_MINT_KEY).load()?;
+    Singleton::<PendingFlashMint>::new(deps.storage, FLASH_MINT_KEY).remove();
+
+    // Repaid from the borrower's own balance, burning the loan and the fee together.
+    // Checking the supply alone would accept tokens burned by anyone else.
+    let due = pending.amount.checked_add(pending.fee)?;
+    if balance_of(deps.storage, &pending.borrower)? < due {
+        return Err(cosmwasm_std::StdError::generic_err("Flash mint was not repaid"));
+    }
+    destroy_balance(deps.storage, &pending.borrower, pending.amount)?;
+    if !pending.fee.is_zero() {
+        burn_balance(deps.storage, &pending.borrower, pending.fee)?;
+    }
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "flash_mint_repaid")
+        .add_attribute("borrower", pending.borrower)
+        .add_attribute("amount", pending.amount.to_string())
+        .add_attribute("fee", pending.fee.to_string()))
+}
+
+pub const TREASURY_KEY: &[u8] = b"treasury";
+pub const TREASURY_TRANCHE_SECONDS: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TreasuryAllocation {
+    pub amount: Uint128,
+    pub destination: HumanAddr,
+    // Number of monthly tranches, the first unlocks one month after instantiation
+    pub tranches: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Treasury {
+    pub destination: HumanAddr,
+    pub total: Uint128,
+    pub released: Uint128,
+    pub start: u64,
+    pub tranches: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TreasuryResponse {
+    pub destination: HumanAddr,
+    pub total: Uint128,
+    pub released: Uint128,
+    pub releasable: Uint128,
+    pub locked: Uint128,
+    pub next_release: Option<u64>,
+}
+
+impl Treasury {
+    pub fn unlocked(&self, env: &cosmwasm_std::Env) -> Uint128 {
+        let elapsed = env.block.time.seconds().saturating_sub(self.start) / TREASURY_TRANCHE_SECONDS;
+        if elapsed >= self.tranches {
+            return self.total;
+        }
+        self.total.multiply_ratio(elapsed, self.tranches)
+    }
+
+    pub fn next_release(&self, env: &cosmwasm_std::Env) -> Option<u64> {
+        let elapsed = env.block.time.seconds().saturating_sub(self.start) / TREASURY_TRANCHE_SECONDS;
+        if elapsed >= self.tranches {
+            return None;
+        }
+        Some(self.start + (elapsed + 1) * TREASURY_TRANCHE_SECONDS)
+    }
+}
+
+// Anyone can trigger a release, the tokens only ever go to the configured destination
+pub fn release_treasury(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    _info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut treasury = ReadonlySingleton::<Treasury>::new(deps.storage, TREASURY_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("No treasury allocation"))?;
+
+    let releasable = treasury.unlocked(&env).checked_sub(treasury.released)?;
+    if releasable.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Nothing to release yet"));
+    }
+    treasury.released = treasury.released.checked_add(releasable)?;
+    Singleton::new(deps.storage, TREASURY_KEY).save(&treasury)?;
+
+    escrow_out(deps.storage, &env, &treasury.destination, releasable)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "release_treasury").add_attribute("to", treasury.destination).add_attribute("amount", releasable.to_string()))
+}
+
+pub fn query_treasury(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env) -> cosmwasm_std::StdResult<TreasuryResponse> {
+    let treasury = ReadonlySingleton::<Treasury>::new(deps.storage, TREASURY_KEY).load()?;
+    let unlocked = treasury.unlocked(&env);
+    Ok(TreasuryResponse {
+        releasable: unlocked.checked_sub(treasury.released)?,
+        locked: treasury.total.checked_sub(unlocked)?,
+        next_release: treasury.next_release(&env),
+        destination: treasury.destination,
+        total: treasury.total,
+        released: treasury.released,
+    })
+}
+
+pub const LOCKDROP_KEY: &[u8] = b"lockdrop";
+pub const LOCKDROP_TOTAL_WEIGHT_KEY: &[u8] = b"lockdrop_total_weight";
+pub const LOCKDROP_POSITIONS_PREFIX: &[u8] = b"lockdrop_positions";
+pub const LOCKDROP_WEIGHT_PREFIX: &[u8] = b"lockdrop_weight";
+pub const LOCKDROP_CLAIMED_PREFIX: &[u8] = b"lockdrop_claimed";
+pub const LOCKDROP_LOCKED_KEY: &[u8] = b"lockdrop_locked";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LockdropAsset {
+    Native { denom: String },
+    Cw20 { address: HumanAddr },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockdropDuration {
+    // Seconds after the deposit window closes before the deposit can be withdrawn
+    pub seconds: u64,
+    // Multiplier applied to deposits locked for this duration
+    pub weight: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockdropConfig {
+    pub asset: LockdropAsset,
+    // Deposit window, in seconds
+    pub start: u64,
+    pub end: u64,
+    pub durations: Vec<LockdropDuration>,
+    // Tokens minted to depositors once the window has closed
+    pub reward: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockdropPosition {
+    pub duration: u64,
+    pub amount: Uint128,
+    pub withdrawn: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockdropResponse {
+    pub config: LockdropConfig,
+    pub total_weight: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LockdropPositionsResponse {
+    pub positions: Vec<LockdropPosition>,
+    pub weight: Uint128,
+    pub reward: Uint128,
+    pub claimed: bool,
+}
+
+pub fn lockdrop_config(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<LockdropConfig> {
+    ReadonlySingleton::<LockdropConfig>::new(storage, LOCKDROP_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("No lockdrop configured"))
+}
+
+fn lockdrop_total_weight(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Uint128> {
+    Ok(ReadonlySingleton::<Uint128>::new(storage, LOCKDROP_TOTAL_WEIGHT_KEY).may_load()?.unwrap_or_else(Uint128::zero))
+}
+
+// Deposits that haven't been withdrawn yet
+pub fn lockdrop_locked(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Uint128> {
+    Ok(ReadonlySingleton::<Uint128>::new(storage, LOCKDROP_LOCKED_KEY).may_load()?.unwrap_or_else(Uint128::zero))
+}
+
+fn lockdrop_weight(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<Uint128> {
+    Ok(ReadonlyBucket::<Uint128>::new(storage, LOCKDROP_WEIGHT_PREFIX).may_load(address.as_bytes())?.unwrap_or_else(Uint128::zero))
+}
+
+fn lockdrop_reward(storage: &dyn cosmwasm_std::Storage, config: &LockdropConfig, address: &HumanAddr) -> cosmwasm_std::StdResult<Uint128> {
+    let total_weight = lockdrop_total_weight(storage)?;
+    if total_weight.is_zero() {
+        return Ok(Uint128::zero());
+    }
+    Ok(config.reward.multiply_ratio(lockdrop_weight(storage, address)?, total_weight))
+}
+
+pub fn setup_lockdrop(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    config: LockdropConfig,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    if ReadonlySingleton::<LockdropConfig>::new(deps.storage, LOCKDROP_KEY).may_load()?.is_some() {
+        return Err(cosmwasm_std::StdError::generic_err("Lockdrop already configured"));
+    }
+    if config.end <= config.start || config.end <= env.block.time.seconds() {
+        return Err(cosmwasm_std::StdError::generic_err("Invalid lockdrop window"));
+    }
+    if config.durations.is_empty() || config.durations.iter().any(|d| d.weight == 0) {
+        return Err(cosmwasm_std::StdError::generic_err("Lockdrop needs at least one duration with a non-zero weight"));
+    }
+    if let LockdropAsset::Cw20 { address } = &config.asset {
+        if address.as_str() == env.contract.address.as_str() {
+            return Err(cosmwasm_std::StdError::generic_err("Cannot lock this token"));
+        }
+    }
+    Singleton::new(deps.storage, LOCKDROP_KEY).save(&config)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "setup_lockdrop").add_attribute("reward", config.reward.to_string()))
+}
+
+pub fn lockdrop_native_deposit(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    duration: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let config = lockdrop_config(deps.storage)?;
+    let denom = match &config.asset {
+        LockdropAsset::Native { denom } => denom.clone(),
+        LockdropAsset::Cw20 { .. } => return Err(cosmwasm_std::StdError::generic_err("Lockdrop only accepts cw20 deposits")),
+    };
+    let amount = match info.funds.as_slice() {
+        [coin] if coin.denom == denom => coin.amount,
+        _ => return Err(cosmwasm_std::StdError::generic_err(format!("Send exactly one coin of {}", denom))),
+    };
+    lockdrop_deposit(deps.storage, &env, config, &HumanAddr::from(&info.sender), amount, duration)
+}
+
+fn lockdrop_deposit(
+    storage: &mut dyn cosmwasm_std::Storage,
+    env: &cosmwasm_std::Env,
+    config: LockdropConfig,
+    depositor: &HumanAddr,
+    amount: Uint128,
+    duration: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let now = env.block.time.seconds();
+    if now < config.start || now >= config.end {
+        return Err(cosmwasm_std::StdError::generic_err("Lockdrop deposit window is closed"));
+    }
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+    let option = config
+        .durations
+        .iter()
+        .find(|d| d.seconds == duration)
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Unsupported lock duration"))?;
+    let weight = amount.checked_mul(Uint128::from(option.weight))?;
+
+    let mut positions = Bucket::<LockdropPosition>::multilevel(storage, &[LOCKDROP_POSITIONS_PREFIX, depositor.as_bytes()]);
+    let mut position = positions.may_load(&duration.to_be_bytes())?.unwrap_or(LockdropPosition { duration, amount: Uint128::zero(), withdrawn: false });
+    position.amount = position.amount.checked_add(amount)?;
+    positions.save(&duration.to_be_bytes(), &position)?;
+
+    let user_weight = lockdrop_weight(storage, depositor)?.checked_add(weight)?;
+    Bucket::new(storage, LOCKDROP_WEIGHT_PREFIX).save(depositor.as_bytes(), &user_weight)?;
+    let total_weight = lockdrop_total_weight(storage)?.checked_add(weight)?;
+    Singleton::new(storage, LOCKDROP_TOTAL_WEIGHT_KEY).save(&total_weight)?;
+    let locked = lockdrop_locked(storage)?.checked_add(amount)?;
+    Singleton::new(storage, LOCKDROP_LOCKED_KEY).save(&locked)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "lockdrop_deposit")
+        .add_attribute("depositor", depositor.as_str())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("duration", duration.to_string()))
+}
+
+pub fn claim_lockdrop_reward(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let config = lockdrop_config(deps.storage)?;
+    if env.block.time.seconds() < config.end {
+        return Err(cosmwasm_std::StdError::generic_err("Lockdrop is still running"));
+    }
+    let mut claimed = Bucket::<bool>::new(deps.storage, LOCKDROP_CLAIMED_PREFIX);
+    if claimed.may_load(info.sender.as_bytes())?.unwrap_or(false) {
+        return Err(cosmwasm_std::StdError::generic_err("Lockdrop reward already claimed"));
+    }
+    claimed.save(info.sender.as_bytes(), &true)?;
+
+    let reward = lockdrop_reward(deps.storage, &config, &HumanAddr::from(&info.sender))?;
+    if reward.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("No lockdrop reward"));
+    }
+    assert_mint_allowed(deps.as_ref(), &HumanAddr::from(&info.sender), reward)?;
+    mint_balance(deps.storage, &HumanAddr::from(&info.sender), reward)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "claim_lockdrop_reward").add_attribute("to", info.sender).add_attribute("amount", reward.to_string()))
+}
+
+pub fn withdraw_lockdrop(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    duration: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let config = lockdrop_config(deps.storage)?;
+    if env.block.time.seconds() < config.end + duration {
+        return Err(cosmwasm_std::StdError::generic_err("Lockdrop deposit is still locked"));
+    }
+
+    let mut positions = Bucket::<LockdropPosition>::multilevel(deps.storage, &[LOCKDROP_POSITIONS_PREFIX, info.sender.as_bytes()]);
+    let mut position = positions
+        .may_load(&duration.to_be_bytes())?
+        .filter(|p| !p.withdrawn)
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("No lockdrop deposit for this duration"))?;
+    position.withdrawn = true;
+    positions.save(&duration.to_be_bytes(), &position)?;
+    let locked = lockdrop_locked(deps.storage)?.checked_sub(position.amount)?;
+    Singleton::new(deps.storage, LOCKDROP_LOCKED_KEY).save(&locked)?;
+
+    // Weights stay in place so withdrawing doesn't change anyone's reward
+    let msg = match config.asset {
+        LockdropAsset::Native { denom } => cosmwasm_std::BankMsg::Send { to_address: info.sender.to_string(), amount: vec![cosmwasm_std::Coin { denom, amount: position.amount }] }.into(),
+        LockdropAsset::Cw20 { address } => cw20_transfer_msg(&address, &HumanAddr::from(&info.sender), position.amount)?,
+    };
+
+    Ok(cosmwasm_std::Response::new().add_message(msg).add_attribute("action", "withdraw_lockdrop").add_attribute("to", info.sender).add_attribute("amount", position.amount.to_string()))
+}
+
+pub fn query_lockdrop(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<LockdropResponse> {
+    Ok(LockdropResponse { config: lockdrop_config(deps.storage)?, total_weight: lockdrop_total_weight(deps.storage)? })
+}
+
+pub fn query_lockdrop_positions(deps: cosmwasm_std::Deps, address: HumanAddr) -> cosmwasm_std::StdResult<LockdropPositionsResponse> {
+    let config = lockdrop_config(deps.storage)?;
+    let positions = config
+        .durations
+        .iter()
+        .filter_map(|d| {
+            ReadonlyBucket::<LockdropPosition>::multilevel(deps.storage, &[LOCKDROP_POSITIONS_PREFIX, address.as_bytes()])
+                .may_load(&d.seconds.to_be_bytes())
+                .transpose()
+        })
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+    Ok(LockdropPositionsResponse {
+        positions,
+        weight: lockdrop_weight(deps.storage, &address)?,
+        reward: lockdrop_reward(deps.storage, &config, &address)?,
+        claimed: ReadonlyBucket::<bool>::new(deps.storage, LOCKDROP_CLAIMED_PREFIX).may_load(address.as_bytes())?.unwrap_or(false),
+    })
+}
+
+pub const TOTAL_BURNED_KEY: &[u8] = b"total_burned";
+pub const BURNED_PREFIX: &[u8] = b"burned";
+pub const TOP_BURNERS_KEY: &[u8] = b"top_burners";
+// Size of the on-chain leaderboard, anything beyond this has to come from BurnedBy
+pub const MAX_TOP_BURNERS: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Burner {
+    pub address: HumanAddr,
+    pub burned: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TotalBurnedResponse {
+    pub total_burned: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TopBurnersResponse {
+    pub burners: Vec<Burner>,
+}
+
+fn burned_by(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<Uint128> {
+    Ok(ReadonlyBucket::<Uint128>::new(storage, BURNED_PREFIX).may_load(address.as_bytes())?.unwrap_or_else(Uint128::zero))
+}
+
+// Keeps the global and per-address totals and the sorted leaderboard up to date
+fn record_burn(storage: &mut dyn cosmwasm_std::Storage, from: &HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+    let total = ReadonlySingleton::<Uint128>::new(storage, TOTAL_BURNED_KEY).may_load()?.unwrap_or_else(Uint128::zero);
+    Singleton::new(storage, TOTAL_BURNED_KEY).save(&total.checked_add(amount)?)?;
+
+    let burned = burned_by(storage, from)?.checked_add(amount)?;
+    Bucket::new(storage, BURNED_PREFIX).save(from.as_bytes(), &burned)?;
+
+    let mut top = ReadonlySingleton::<Vec<Burner>>::new(storage, TOP_BURNERS_KEY).may_load()?.unwrap_or_default();
+    top.retain(|b| &b.address != from);
+    let position = top.iter().position(|b| b.burned < burned).unwrap_or(top.len());
+    if position < MAX_TOP_BURNERS {
+        top.insert(position, Burner { address: from.clone(), burned });
+        top.truncate(MAX_TOP_BURNERS);
+        Singleton::new(storage, TOP_BURNERS_KEY).save(&top)?;
+    }
+    Ok(())
+}
+
+pub fn query_total_burned(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<TotalBurnedResponse> {
+    let total_burned = ReadonlySingleton::<Uint128>::new(deps.storage, TOTAL_BURNED_KEY).may_load()?.unwrap_or_else(Uint128::zero);
+    Ok(TotalBurnedResponse { total_burned })
+}
+
+pub fn query_burned_by(deps: cosmwasm_std::Deps, address: HumanAddr) -> cosmwasm_std::StdResult<Burner> {
+    Ok(Burner { burned: burned_by(deps.storage, &address)?, address })
+}
+
+pub fn query_top_burners(deps: cosmwasm_std::Deps, limit: Option<u32>) -> cosmwasm_std::StdResult<TopBurnersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let mut burners = ReadonlySingleton::<Vec<Burner>>::new(deps.storage, TOP_BURNERS_KEY).may_load()?.unwrap_or_default();
+    burners.truncate(limit);
+    Ok(TopBurnersResponse { burners })
+}
+
+// Mints `total` divided among recipients by weight in basis points, the weights
+// have to add up to exactly 100% and rounding dust goes to the last recipient
+pub fn mint_split(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    total: Uint128,
+    recipients: Vec<(HumanAddr, u64)>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    if recipients.is_empty() || recipients.len() > MAX_BATCH_SIZE {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Between 1 and {} recipients are allowed", MAX_BATCH_SIZE)));
+    }
+    if recipients.iter().any(|(_, weight)| *weight == 0) {
+        return Err(cosmwasm_std::StdError::generic_err("Weights must be greater than zero"));
+    }
+    if recipients.iter().map(|(_, weight)| weight).sum::<u64>() != MAX_FEE_BPS {
+        return Err(cosmwasm_std::StdError::generic_err("Weights must add up to 10000 basis points"));
+    }
+
+    let mut res = cosmwasm_std::Response::new().add_attribute("action", "mint_split").add_attribute("total", total.to_string());
+    let mut remaining = total;
+    let last = recipients.len() - 1;
+    for (i, (recipient, weight)) in recipients.into_iter().enumerate() {
+        let amount = if i == last { remaining } else { total.multiply_ratio(weight, MAX_FEE_BPS) };
+        remaining = remaining.checked_sub(amount)?;
+
+        assert_mint_allowed(deps.as_ref(), &recipient, amount)?;
+        mint_balance(deps.storage, &recipient, amount)?;
+        res = res.add_event(cosmwasm_std::Event::new("mint").add_attribute("to", recipient).add_attribute("amount", amount.to_string()));
+    }
+    Ok(res)
+}
+
+// Dividends and staking rewards that have been funded but not claimed yet
+pub const NATIVE_RESERVED_PREFIX: &[u8] = b"native_reserved";
+
+pub fn reserve_native(storage: &mut dyn cosmwasm_std::Storage, denom: &str, amount: Uint128, increase: bool) -> cosmwasm_std::StdResult<()> {
+    let mut reserved = Bucket::<Uint128>::new(storage, NATIVE_RESERVED_PREFIX);
+    let current = reserved.may_load(denom.as_bytes())?.unwrap_or_else(Uint128::zero);
+    // Index rounding leaves dust behind, so claims can never push this below zero
+    let updated = if increase { current.checked_add(amount)? } else { current.checked_sub(amount).unwrap_or_else(|_| Uint128::zero()) };
+    reserved.save(denom.as_bytes(), &updated)
+}
+
+// Native coins on the contract that belong to other modules and must never be spent
+pub fn reserved_native(storage: &dyn cosmwasm_std::Storage, denom: &str) -> cosmwasm_std::StdResult<Uint128> {
+    let mut reserved = ReadonlyBucket::<Uint128>::new(storage, NATIVE_RESERVED_PREFIX).may_load(denom.as_bytes())?.unwrap_or_else(Uint128::zero);
+    if let Some(curve) = ReadonlySingleton::<BondingCurve>::new(storage, BONDING_CURVE_KEY).may_load()? {
+        if curve.reserve_denom == denom {
+            reserved = reserved.checked_add(curve.reserve)?;
+        }
+    }
+    if let Some(wrapper) = ReadonlySingleton::<NativeWrapper>::new(storage, NATIVE_WRAPPER_KEY).may_load()? {
+        if wrapper.denom == denom {
+            reserved = reserved.checked_add(wrapper.reserve)?;
+        }
+    }
+    if let Some(LockdropConfig { asset: LockdropAsset::Native { denom: locked_denom }, .. }) = ReadonlySingleton::<LockdropConfig>::new(storage, LOCKDROP_KEY).may_load()? {
+        if locked_denom == denom {
+            reserved = reserved.checked_add(lockdrop_locked(storage)?)?;
+        }
+    }
+    Ok(reserved)
+}
+
+// Tokens on the contract that aren't held in escrow for someone, i.e. the fees it collected
+fn free_contract_balance(storage: &dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env) -> cosmwasm_std::StdResult<Uint128> {
+    let contract = HumanAddr::from(env.contract.address.as_str());
+    Ok(balance_of(storage, &contract)?.checked_sub(escrowed_total(storage)?).unwrap_or_default())
+}
+
+pub const NON_CIRCULATING_KEY: &[u8] = b"non_circulating";
+// Every query sums all of these balances, so the list is kept short
+pub const MAX_NON_CIRCULATING: usize = 50;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CirculatingSupplyResponse {
+    pub total_supply: Uint128,
+    pub circulating_supply: Uint128,
+    pub non_circulating: Vec<NonCirculatingBalance>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NonCirculatingBalance {
+    pub address: HumanAddr,
+    pub balance: Uint128,
+}
+
+pub fn set_non_circulating(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    address: HumanAddr,
+    non_circulating: bool,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let mut addresses = ReadonlySingleton::<Vec<HumanAddr>>::new(deps.storage, NON_CIRCULATING_KEY).may_load()?.unwrap_or_default();
+    addresses.retain(|a| a != &address);
+    if non_circulating {
+        if addresses.len() >= MAX_NON_CIRCULATING {
+            return Err(cosmwasm_std::StdError::generic_err(format!("At most {} non-circulating addresses are allowed", MAX_NON_CIRCULATING)));
+        }
+        addresses.push(address.clone());
+    }
+    Singleton::new(deps.storage, NON_CIRCULATING_KEY).save(&addresses)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_non_circulating").add_attribute("address", address).add_attribute("non_circulating", non_circulating.to_string()))
+}
+
+pub fn query_circulating_supply(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<CirculatingSupplyResponse> {
+    let total_supply = token_info(deps.storage)?.total_supply;
+    let addresses = ReadonlySingleton::<Vec<HumanAddr>>::new(deps.storage, NON_CIRCULATING_KEY).may_load()?.unwrap_or_default();
+
+    let mut locked = Uint128::zero();
+    let mut non_circulating = vec![];
+    for address in addresses {
+        let balance = balance_of(deps.storage, &address)?;
+        locked = locked.checked_add(balance)?;
+        non_circulating.push(NonCirculatingBalance { address, balance });
+    }
+
+    Ok(CirculatingSupplyResponse {
+        total_supply,
+        circulating_supply: total_supply.checked_sub(locked).unwrap_or_default(),
+        non_circulating,
+    })
+}
+
+// Checkpoints follow the cw-storage-plus snapshot convention: the entry stored at
+// height h is the value before the first change made in block h, so a lookup at
+// height h sees the state at the start of that block.
+
+pub fn save_token_info(storage: &mut dyn cosmwasm_std::Storage, token: &TokenInfo) -> cosmwasm_std::StdResult<()> {
+    checkpoint_supply(storage)?;
+    Singleton::new(storage, TOKEN_INFO_KEY).save(token)
+}
+
+fn save_total_shares(storage: &mut dyn cosmwasm_std::Storage, shares: Uint128) -> cosmwasm_std::StdResult<()> {
+    checkpoint_supply(storage)?;
+    Singleton::new(storage, TOTAL_SHARES_KEY).save(&shares)
+}
+
+fn set_delegate(storage: &mut dyn cosmwasm_std::Storage, delegator: &HumanAddr, delegatee: &Option<HumanAddr>) -> cosmwasm_std::StdResult<()> {
+    let stored = stored_balance(storage, delegator)?;
+    if let Some(previous) = delegate_of(storage, delegator)? {
+        adjust_votes(storage, &previous, stored, false)?;
+    }
+
+    let mut delegates = Bucket::<HumanAddr>::new(storage, DELEGATES_PREFIX);
+    match delegatee {
+        Some(delegatee) => delegates.save(delegator.as_bytes(), delegatee)?,
+        None => delegates.remove(delegator.as_bytes()),
+    }
+    if let Some(delegatee) = delegatee {
+        adjust_votes(storage, delegatee, stored, true)?;
+    }
+    Ok(())
+}
+
+pub const DELEGATION_NONCES_PREFIX: &[u8] = b"delegation_nonces";
+
+// What the delegator signs, as JSON hashed with sha256. Binding the chain and the
+// contract keeps a signature from being replayed anywhere else.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DelegationPayload {
+    pub chain_id: String,
+    pub contract: HumanAddr,
+    pub delegator: HumanAddr,
+    pub delegatee: Option<HumanAddr>,
+    pub nonce: u64,
+    pub expiry: Expiration,
+}
+
+pub fn delegation_nonce(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<u64> {
+    Ok(ReadonlyBucket::<u64>::new(storage, DELEGATION_NONCES_PREFIX).may_load(address.as_bytes())?.unwrap_or(0))
+}
+
+// Checks that `pubkey` belongs to `address`, whose canonical form is ripemd160(sha256(pubkey))
+pub fn assert_pubkey_matches(api: &dyn cosmwasm_std::Api, address: &HumanAddr, pubkey: &[u8]) -> cosmwasm_std::StdResult<()> {
+    use sha2::Digest;
+    let hash = ripemd160::Ripemd160::digest(&sha2::Sha256::digest(pubkey));
+    if api.addr_canonicalize(address.as_str())?.as_slice() != hash.as_slice() {
+        return Err(cosmwasm_std::StdError::generic_err("Public key does not match the address"));
+    }
+    Ok(())
+}
+
+// Verifies a secp256k1 signature by `signer` over the sha256 of the JSON encoded payload
+pub fn verify_signed<T: Serialize>(
+    api: &dyn cosmwasm_std::Api,
+    signer: &HumanAddr,
+    payload: &T,
+    pubkey: &[u8],
+    signature: &[u8],
+) -> cosmwasm_std::StdResult<()> {
+    use sha2::Digest;
+    assert_pubkey_matches(api, signer, pubkey)?;
+    let hash = sha2::Sha256::digest(&cosmwasm_std::to_vec(payload)?);
+    let valid = api.secp256k1_verify(&hash, signature, pubkey).map_err(|_| cosmwasm_std::StdError::generic_err("Invalid signature"))?;
+    if !valid {
+        return Err(cosmwasm_std::StdError::generic_err("Invalid signature"));
+    }
+    Ok(())
+}
+
+// Lets a relayer submit a delegation the delegator signed offline
+#[allow(clippy::too_many_arguments)]
+pub fn delegate_by_sig(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    delegator: HumanAddr,
+    delegatee: Option<HumanAddr>,
+    nonce: u64,
+    expiry: Expiration,
+    pubkey: cosmwasm_std::Binary,
+    signature: cosmwasm_std::Binary,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if expiry.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Signature has expired"));
+    }
+    if nonce != delegation_nonce(deps.storage, &delegator)? {
+        return Err(cosmwasm_std::StdError::generic_err("Invalid nonce"));
+    }
+    let payload = DelegationPayload {
+        chain_id: env.block.chain_id.clone(),
+        contract: HumanAddr::from(env.contract.address.as_str()),
+        delegator: delegator.clone(),
+        delegatee: delegatee.clone(),
+        nonce,
+        expiry,
+    };
+    verify_signed(deps.api, &delegator, &payload, &pubkey, &signature)?;
+
+    Bucket::new(deps.storage, DELEGATION_NONCES_PREFIX).save(delegator.as_bytes(), &(nonce + 1))?;
+    set_delegate(deps.storage, &delegator, &delegatee)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "delegate_by_sig")
+        .add_attribute("relayer", info.sender)
+        .add_attribute("delegator", delegator)
+        .add_attribute("delegatee", delegatee.map(|d| d.to_string()).unwrap_or_else(|| "none".to_string())))
+}
+
+pub const TRANSFER_HOOKS_KEY: &[u8] = b"transfer_hooks";
+pub const TRANSFER_HOOK_REPLY_ID: u64 = 3;
+pub const MAX_TRANSFER_HOOKS: usize = 5;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookPhase {
+    Before,
+    After,
+    Both,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookFailurePolicy {
+    // A failing hook reverts the transfer
+    Revert,
+    // A failing hook is rolled back on its own and the transfer goes through
+    Ignore,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransferHook {
+    pub contract: HumanAddr,
+    pub phase: HookPhase,
+    pub gas_limit: Option<u64>,
+    pub on_failure: HookFailurePolicy,
+}
+
+// Balances have already moved when any hook runs. Before hooks are dispatched ahead
+// of every other message from the transfer, so with the revert policy they act as a veto.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferHookMsg {
+    BeforeTransfer { from: HumanAddr, to: HumanAddr, amount: Uint128 },
+    AfterTransfer { from: HumanAddr, to: HumanAddr, amount: Uint128 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TransferHooksResponse {
+    pub hooks: Vec<TransferHook>,
+}
+
+pub fn transfer_hooks(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Vec<TransferHook>> {
+    Ok(ReadonlySingleton::<Vec<TransferHook>>::new(storage, TRANSFER_HOOKS_KEY).may_load()?.unwrap_or_default())
+}
+
+fn hook_submsg(hook: &TransferHook, msg: &TransferHookMsg) -> cosmwasm_std::StdResult<cosmwasm_std::SubMsg> {
+    let execute = cosmwasm_std::WasmMsg::Execute { contract_addr: hook.contract.to_string(), msg: cosmwasm_std::to_binary(msg)?, funds: vec![] };
+    Ok(cosmwasm_std::SubMsg {
+        id: TRANSFER_HOOK_REPLY_ID,
+        msg: execute.into(),
+        gas_limit: hook.gas_limit,
+        reply_on: match hook.on_failure {
+            HookFailurePolicy::Revert => cosmwasm_std::ReplyOn::Never,
+            HookFailurePolicy::Ignore => cosmwasm_std::ReplyOn::Error,
+        },
+    })
+}
+
+// Returns the before and after hook submessages for a transfer
+pub fn transfer_hook_msgs(
+    storage: &dyn cosmwasm_std::Storage,
+    from: &HumanAddr,
+    to: &HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<(Vec<cosmwasm_std::SubMsg>, Vec<cosmwasm_std::SubMsg>)> {
+    let before_msg = TransferHookMsg::BeforeTransfer { from: from.clone(), to: to.clone(), amount };
+    let after_msg = TransferHookMsg::AfterTransfer { from: from.clone(), to: to.clone(), amount };
+    let mut before = vec![];
+    let mut after = vec![];
+    for hook in transfer_hooks(storage)? {
+        if hook.phase != HookPhase::After {
+            before.push(hook_submsg(&hook, &before_msg)?);
+        }
+        if hook.phase != HookPhase::Before {
+            after.push(hook_submsg(&hook, &after_msg)?);
+        }
+    }
+    Ok((before, after))
+}
+
+pub fn add_transfer_hook(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    hook: TransferHook,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    let mut hooks = transfer_hooks(deps.storage)?;
+    if hooks.iter().any(|h| h.contract == hook.contract) {
+        return Err(cosmwasm_std::StdError::generic_err("Hook already registered"));
+    }
+    if hooks.len() >= MAX_TRANSFER_HOOKS {
+        return Err(cosmwasm_std::StdError::generic_err(format!("At most {} hooks are allowed", MAX_TRANSFER_HOOKS)));
+    }
+    let contract = hook.contract.clone();
+    hooks.push(hook);
+    Singleton::new(deps.storage, TRANSFER_HOOKS_KEY).save(&hooks)?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "add_transfer_hook").add_attribute("hook", contract))
+}
+
+pub fn remove_transfer_hook(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    contract: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    let mut hooks = transfer_hooks(deps.storage)?;
+    if !hooks.iter().any(|h| h.contract == contract) {
+        return Err(cosmwasm_std::StdError::generic_err("Hook not registered"));
+    }
+    hooks.retain(|h| h.contract != contract);
+    Singleton::new(deps.storage, TRANSFER_HOOKS_KEY).save(&hooks)?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "remove_transfer_hook").add_attribute("hook", contract))
+}
+
+pub const COLLATERAL_KEY: &[u8] = b"collateral";
+
+// A cw20 that can be wrapped 1:1 into this token
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Collateral {
+    pub token: HumanAddr,
+    pub locked: Uint128,
+}
+
+pub fn set_collateral(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    token: Option<HumanAddr>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    let mut singleton = Singleton::<Collateral>::new(deps.storage, COLLATERAL_KEY);
+    // Switching collateral while some is locked would strand the wrapped supply
+    if let Some(existing) = singleton.may_load()? {
+        if !existing.locked.is_zero() {
+            return Err(cosmwasm_std::StdError::generic_err("Collateral is still locked"));
+        }
+    }
+    match &token {
+        Some(token) => {
+            if token.as_str() == env.contract.address.as_str() {
+                return Err(cosmwasm_std::StdError::generic_err("Cannot use this token as collateral"));
+            }
+            singleton.save(&Collateral { token: token.clone(), locked: Uint128::zero() })?;
+        }
+        None => singleton.remove(),
+    }
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_collateral").add_attribute("token", token.map(|t| t.to_string()).unwrap_or_else(|| "none".to_string())))
+}
+
+fn wrap_cw20(deps: cosmwasm_std::DepsMut, token: HumanAddr, sender: HumanAddr, amount: Uint128) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut collateral = ReadonlySingleton::<Collateral>::new(deps.storage, COLLATERAL_KEY)
+        .may_load()?
+        .filter(|c| c.token == token)
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Token is not accepted as collateral"))?;
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    collateral.locked = collateral.locked.checked_add(amount)?;
+    Singleton::new(deps.storage, COLLATERAL_KEY).save(&collateral)?;
+
+    assert_mint_allowed(deps.as_ref(), &sender, amount)?;
+    mint_balance(deps.storage, &sender, amount)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "wrap").add_attribute("to", sender).add_attribute("amount", amount.to_string()))
+}
+
+pub fn unwrap_cw20(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut collateral = ReadonlySingleton::<Collateral>::new(deps.storage, COLLATERAL_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("No collateral is configured"))?;
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    assert_not_blacklisted(deps.storage, &HumanAddr::from(&info.sender))?;
+    collateral.locked = collateral.locked.checked_sub(amount).map_err(|_| cosmwasm_std::StdError::generic_err("Amount exceeds locked collateral"))?;
+    Singleton::new(deps.storage, COLLATERAL_KEY).save(&collateral)?;
+
+    burn_balance(deps.storage, &HumanAddr::from(&info.sender), amount)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_message(cw20_transfer_msg(&collateral.token, &HumanAddr::from(&info.sender), amount)?)
+        .add_attribute("action", "unwrap")
+        .add_attribute("to", info.sender)
+        .add_attribute("amount", amount.to_string()))
+}
+
+pub const NATIVE_WRAPPER_KEY: &[u8] = b"native_wrapper";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NativeWrapper {
+    pub denom: String,
+    // Native coins backing the wrapped tokens, always equal to what has been wrapped
+    pub reserve: Uint128,
+}
+
+// Everything reserved in `denom` has to be covered by the contract's bank balance
+fn assert_native_solvent(deps: cosmwasm_std::Deps, env: &cosmwasm_std::Env, denom: &str, outgoing: Uint128) -> cosmwasm_std::StdResult<()> {
+    let balance = deps.querier.query_balance(env.contract.address.to_string(), denom.to_string())?.amount;
+    let required = reserved_native(deps.storage, denom)?.checked_add(outgoing)?;
+    if balance < required {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Reserve of {} does not cover the wrapped supply", denom)));
+    }
+    Ok(())
+}
+
+pub fn set_native_wrapper(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    denom: Option<String>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    let mut singleton = Singleton::<NativeWrapper>::new(deps.storage, NATIVE_WRAPPER_KEY);
+    if let Some(existing) = singleton.may_load()? {
+        if !existing.reserve.is_zero() {
+            return Err(cosmwasm_std::StdError::generic_err("Wrapped tokens are still outstanding"));
+        }
+    }
+    match &denom {
+        Some(denom) => {
+            // The reserve starts empty, so it can only back the supply if there is none yet
+            if !token_info(deps.storage)?.total_supply.is_zero() {
+                return Err(cosmwasm_std::StdError::generic_err("The native wrapper can only be enabled before any tokens exist"));
+            }
+            Singleton::new(deps.storage, NATIVE_WRAPPER_KEY).save(&NativeWrapper { denom: denom.clone(), reserve: Uint128::zero() })?
+        }
+        None => singleton.remove(),
+    }
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_native_wrapper").add_attribute("denom", denom.unwrap_or_else(|| "none".to_string())))
+}
+
+pub fn deposit_native(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut wrapper = ReadonlySingleton::<NativeWrapper>::new(deps.storage, NATIVE_WRAPPER_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Native wrapping is not enabled"))?;
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    let amount = match info.funds.as_slice() {
+        [coin] if coin.denom == wrapper.denom && !coin.amount.is_zero() => coin.amount,
+        _ => return Err(cosmwasm_std::StdError::generic_err(format!("Send exactly one coin of {}", wrapper.denom))),
+    };
+
+    wrapper.reserve = wrapper.reserve.checked_add(amount)?;
+    Singleton::new(deps.storage, NATIVE_WRAPPER_KEY).save(&wrapper)?;
+    assert_mint_allowed(deps.as_ref(), &HumanAddr::from(&info.sender), amount)?;
+    mint_balance(deps.storage, &HumanAddr::from(&info.sender), amount)?;
+    assert_native_solvent(deps.as_ref(), &env, &wrapper.denom, Uint128::zero())?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "deposit").add_attribute("to", info.sender).add_attribute("amount", amount.to_string()))
+}
+
+pub fn withdraw_native(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut wrapper = ReadonlySingleton::<NativeWrapper>::new(deps.storage, NATIVE_WRAPPER_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Native wrapping is not enabled"))?;
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    assert_not_blacklisted(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    wrapper.reserve = wrapper.reserve.checked_sub(amount).map_err(|_| cosmwasm_std::StdError::generic_err("Amount exceeds the wrapped reserve"))?;
+    Singleton::new(deps.storage, NATIVE_WRAPPER_KEY).save(&wrapper)?;
+    burn_balance(deps.storage, &HumanAddr::from(&info.sender), amount)?;
+    assert_native_solvent(deps.as_ref(), &env, &wrapper.denom, amount)?;
+
+    let msg = cosmwasm_std::BankMsg::Send { to_address: info.sender.to_string(), amount: vec![cosmwasm_std::Coin { denom: wrapper.denom, amount }] };
+    Ok(cosmwasm_std::Response::new().add_message(msg).add_attribute("action", "withdraw").add_attribute("to", info.sender).add_attribute("amount", amount.to_string()))
+}
+
+pub const TOKENFACTORY_KEY: &[u8] = b"tokenfactory";
+
+// The same asset as a bank denom. cw20 supply plus `bank_supply` is the full amount in existence.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FactoryDenom {
+    pub denom: String,
+    pub bank_supply: Uint128,
+}
+
+// Minimal protobuf encoding for the Stargate messages we send, which only use
+// varint, string and embedded message fields
+pub(crate) fn proto_varint(mut value: u64) -> Vec<u8> {
+    let mut out = vec![];
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+    out
+}
+
+pub(crate) fn proto_field(field: u8, bytes: &[u8]) -> Vec<u8> {
+    let mut out = vec![(field << 3) | 2];
+    out.extend(proto_varint(bytes.len() as u64));
+    out.extend_from_slice(bytes);
+    out
+}
+
+pub(crate) fn proto_varint_field(field: u8, value: u64) -> Vec<u8> {
+    let mut out = vec![field << 3];
+    out.extend(proto_varint(value));
+    out
+}
+
+pub(crate) fn proto_coin(denom: &str, amount: Uint128) -> Vec<u8> {
+    [proto_field(1, denom.as_bytes()), proto_field(2, amount.to_string().as_bytes())].concat()
+}
+
+pub fn tokenfactory_create_denom_msg(sender: &str, subdenom: &str) -> cosmwasm_std::CosmosMsg {
+    let value = [proto_field(1, sender.as_bytes()), proto_field(2, subdenom.as_bytes())].concat();
+    cosmwasm_std::CosmosMsg::Stargate { type_url: "/osmosis.tokenfactory.v1beta1.MsgCreateDenom".to_string(), value: value.into() }
+}
+
+pub fn tokenfactory_mint_msg(sender: &str, denom: &str, amount: Uint128, to: &str) -> cosmwasm_std::CosmosMsg {
+    let value = [proto_field(1, sender.as_bytes()), proto_field(2, &proto_coin(denom, amount)), proto_field(3, to.as_bytes())].concat();
+    cosmwasm_std::CosmosMsg::Stargate { type_url: "/osmosis.tokenfactory.v1beta1.MsgMint".to_string(), value: value.into() }
+}
+
+pub fn tokenfactory_burn_msg(sender: &str, denom: &str, amount: Uint128) -> cosmwasm_std::CosmosMsg {
+    let value = [proto_field(1, sender.as_bytes()), proto_field(2, &proto_coin(denom, amount)), proto_field(3, sender.as_bytes())].concat();
+    cosmwasm_std::CosmosMsg::Stargate { type_url: "/osmosis.tokenfactory.v1beta1.MsgBurn".to_string(), value: value.into() }
+}
+
+pub fn factory_denom(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<FactoryDenom> {
+    ReadonlySingleton::<FactoryDenom>::new(storage, TOKENFACTORY_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("No tokenfactory denom"))
+}
+
+// Takes cw20 tokens out of the cw20 supply and returns the message minting the bank denom in their place
+fn convert_out(
+    deps: &mut cosmwasm_std::DepsMut,
+    env: &cosmwasm_std::Env,
+    factory: &mut FactoryDenom,
+    from: &HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::CosmosMsg> {
+    let contract = HumanAddr::from(env.contract.address.as_str());
+    assert_escrow_allowed(deps.as_ref(), env, from, &contract, amount)?;
+
+    destroy_balance(deps.storage, from, amount)?;
+    factory.bank_supply = factory.bank_supply.checked_add(amount)?;
+    Singleton::new(deps.storage, TOKENFACTORY_KEY).save(factory)?;
+
+    Ok(tokenfactory_mint_msg(env.contract.address.as_str(), &factory.denom, amount, from.as_str()))
+}
+
+pub fn convert_to_bank(
+    mut deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut factory = factory_denom(deps.storage)?;
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+    let msg = convert_out(&mut deps, &env, &mut factory, &HumanAddr::from(&info.sender), amount)?;
+    Ok(cosmwasm_std::Response::new().add_message(msg).add_attribute("action", "convert_to_bank").add_attribute("from", info.sender).add_attribute("amount", amount.to_string()))
+}
+
+// Burns the bank denom sent along and mints the same cw20 amount
+pub fn convert_to_cw20(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut factory = factory_denom(deps.storage)?;
+    if is_paused(deps.storage)? {
+        return Err(cosmwasm_std::StdError::generic_err("Contract is paused"));
+    }
+    if ReadonlySingleton::<NativeMigration>::new(deps.storage, NATIVE_MIGRATION_KEY).may_load()?.is_some() {
+        return Err(cosmwasm_std::StdError::generic_err("Conversion back to cw20 is closed by the native migration"));
+    }
+    let amount = match info.funds.as_slice() {
+        [coin] if coin.denom == factory.denom && !coin.amount.is_zero() => coin.amount,
+        _ => return Err(cosmwasm_std::StdError::generic_err(format!("Send exactly one coin of {}", factory.denom))),
+    };
+
+    factory.bank_supply = factory.bank_supply.checked_sub(amount)?;
+    Singleton::new(deps.storage, TOKENFACTORY_KEY).save(&factory)?;
+    assert_mint_allowed(deps.as_ref(), &HumanAddr::from(&info.sender), amount)?;
+    mint_balance(deps.storage, &HumanAddr::from(&info.sender), amount)?;
+
+    let msg = tokenfactory_burn_msg(env.contract.address.as_str(), &factory.denom, amount);
+    Ok(cosmwasm_std::Response::new().add_message(msg).add_attribute("action", "convert_to_cw20").add_attribute("to", info.sender).add_attribute("amount", amount.to_string()))
+}
+
+pub const NATIVE_MIGRATION_KEY: &[u8] = b"native_migration";
+pub const NATIVE_CONVERTED_PREFIX: &[u8] = b"native_converted";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NativeMigration {
+    // cw20 transfers stop once this expires, converting stays possible
+    pub deadline: Expiration,
+    pub converted: Uint128,
+    pub holders_converted: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NativeMigrationResponse {
+    pub denom: String,
+    pub deadline: Expiration,
+    pub converted: Uint128,
+    pub holders_converted: u64,
+    // cw20 supply still waiting to be converted
+    pub remaining: Uint128,
+}
+
+// Starting the migration is final: the deadline can be moved but the bank denom can no longer be converted back
+pub fn start_native_migration(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    deadline: Expiration,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    factory_denom(deps.storage)?;
+    if deadline.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Deadline is already expired"));
+    }
+
+    let mut singleton = Singleton::<NativeMigration>::new(deps.storage, NATIVE_MIGRATION_KEY);
+    let migration = match singleton.may_load()? {
+        Some(existing) if existing.deadline.is_expired(&env) => {
+            return Err(cosmwasm_std::StdError::generic_err("Migration deadline has passed"));
+        }
+        Some(existing) => NativeMigration { deadline, ..existing },
+        None => NativeMigration { deadline, converted: Uint128::zero(), holders_converted: 0 },
+    };
+    singleton.save(&migration)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "start_native_migration").add_attribute("deadline", format!("{:?}", deadline)))
+}
+
+// Converts the sender's unlocked cw20 balance into the tokenfactory denom. Vesting and
+// lockups stay on the cw20 side and can be converted once they release.
+pub fn convert_to_native(
+    mut deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut migration = ReadonlySingleton::<NativeMigration>::new(deps.storage, NATIVE_MIGRATION_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Native migration has not started"))?;
+    let mut factory = factory_denom(deps.storage)?;
+    let sender = HumanAddr::from(&info.sender);
+    let amount = balance_of(deps.storage, &sender)?.saturating_sub(locked_balance(deps.storage, &env, &sender)?);
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Nothing to convert"));
+    }
+
+    let msg = convert_out(&mut deps, &env, &mut factory, &sender, amount)?;
+    migration.converted = migration.converted.checked_add(amount)?;
+    // Holders with locked tokens convert more than once, count each of them once
+    let mut converted = Bucket::<bool>::new(deps.storage, NATIVE_CONVERTED_PREFIX);
+    if converted.may_load(sender.as_bytes())?.is_none() {
+        converted.save(sender.as_bytes(), &true)?;
+        migration.holders_converted += 1;
+    }
+    Singleton::new(deps.storage, NATIVE_MIGRATION_KEY).save(&migration)?;
+
+    Ok(cosmwasm_std::Response::new().add_message(msg).add_attribute("action", "convert_to_native").add_attribute("from", info.sender).add_attribute("amount", amount.to_string()))
+}
+
+pub fn query_native_migration(deps: cosmwasm_std::Deps) -> cosmwasm_std::StdResult<NativeMigrationResponse> {
+    let migration = ReadonlySingleton::<NativeMigration>::new(deps.storage, NATIVE_MIGRATION_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Native migration has not started"))?;
+    Ok(NativeMigrationResponse {
+        denom: factory_denom(deps.storage)?.denom,
+        deadline: migration.deadline,
+        converted: migration.converted,
+        holders_converted: migration.holders_converted,
+        remaining: token_info(deps.storage)?.total_supply,
+    })
+}
+
+// Expired allowances count as zero, operators of the owner need no allowance
+pub fn spend_allowance(
+    storage: &mut dyn cosmwasm_std::Storage,
+    env: &cosmwasm_std::Env,
+    owner: &HumanAddr,
+    spender: &HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<()> {
+    if is_operator(storage, owner, spender)? {
+        return Ok(());
+    }
+    // A recurring allowance replaces the plain one for the pair while it exists
+    if let Some(recurring) = ReadonlyBucket::<RecurringAllowance>::multilevel(storage, &[RECURRING_ALLOWANCES_PREFIX, owner.as_bytes()]).may_load(spender.as_bytes())? {
+        return spend_recurring_allowance(storage, env, owner, spender, recurring, amount);
+    }
+    let mut allowance = load_allowance(storage, owner, spender)?;
+    if allowance.expires.is_some_and(|e| e.is_expired(env)) {
+        return Err(cosmwasm_std::StdError::generic_err("Allowance is expired"));
+    }
+    if allowance.allowance < amount {
+        return Err(cosmwasm_std::StdError::generic_err("Insufficient allowance"));
+    }
+    allowance.allowance = allowance.allowance.checked_sub(amount)?;
+    save_allowance(storage, &allowance)
+}
+
+// Approve-then-deposit in one transaction, the spender can pull the tokens in its
+// handler of the notification
+pub fn increase_allowance_and_call(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    spender: HumanAddr,
+    amount: Uint128,
+    expires: Option<Expiration>,
+    msg: cosmwasm_std::Binary,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if expires.is_some_and(|e| e.is_expired(&env)) {
+        return Err(cosmwasm_std::StdError::generic_err("Expiration is already in the past"));
+    }
+    let mut allowance = load_allowance(deps.storage, &HumanAddr::from(&info.sender), &spender)?;
+    allowance.allowance = allowance.allowance.checked_add(amount)?;
+    allowance.expires = expires;
+    save_allowance(deps.storage, &allowance)?;
+
+    let notify = ApprovalReceiverExecuteMsg::ReceiveApproval(Cw20ApprovalMsg { owner: HumanAddr::from(&info.sender), amount, expires, msg });
+    let msg = cosmwasm_std::WasmMsg::Execute { contract_addr: spender.to_string(), msg: cosmwasm_std::to_binary(&notify)?, funds: vec![] };
+
+    Ok(cosmwasm_std::Response::new()
+        .add_message(msg)
+        .add_attribute("action", "increase_allowance_and_call")
+        .add_attribute("owner", info.sender)
+        .add_attribute("spender", spender)
+        .add_attribute("amount", amount.to_string()))
+}
+
+pub const PERMIT_NONCES_PREFIX: &[u8] = b"permit_nonces";
+
+// Separates signatures for this token from those for any other contract, chain or purpose
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignatureDomain {
+    pub name: String,
+    pub chain_id: String,
+    pub contract: HumanAddr,
+    // What the signature is for, e.g. "permit"
+    pub purpose: String,
+}
+
+impl SignatureDomain {
+    pub fn new(storage: &dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, purpose: &str) -> cosmwasm_std::StdResult<Self> {
+        Ok(SignatureDomain {
+            name: token_info(storage)?.name,
+            chain_id: env.block.chain_id.clone(),
+            contract: HumanAddr::from(env.contract.address.as_str()),
+            purpose: purpose.to_string(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PermitPayload {
+    pub domain: SignatureDomain,
+    pub owner: HumanAddr,
+    pub spender: HumanAddr,
+    pub amount: Uint128,
+    pub nonce: u64,
+    pub deadline: Expiration,
+}
+
+pub fn permit_nonce(storage: &dyn cosmwasm_std::Storage, owner: &HumanAddr) -> cosmwasm_std::StdResult<u64> {
+    Ok(ReadonlyBucket::<u64>::new(storage, PERMIT_NONCES_PREFIX).may_load(owner.as_bytes())?.unwrap_or(0))
+}
+
+// Sets the allowance of `spender` to `amount` from an approval the owner signed offline
+#[allow(clippy::too_many_arguments)]
+pub fn permit(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    owner: HumanAddr,
+    spender: HumanAddr,
+    amount: Uint128,
+    nonce: u64,
+    deadline: Expiration,
+    pubkey: cosmwasm_std::Binary,
+    signature: cosmwasm_std::Binary,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if deadline.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Permit has expired"));
+    }
+    if nonce != permit_nonce(deps.storage, &owner)? {
+        return Err(cosmwasm_std::StdError::generic_err("Invalid nonce"));
+    }
+    let payload = PermitPayload {
+        domain: SignatureDomain::new(deps.storage, &env, "permit")?,
+        owner: owner.clone(),
+        spender: spender.clone(),
+        amount,
+        nonce,
+        deadline,
+    };
+    verify_signed(deps.api, &owner, &payload, &pubkey, &signature)?;
+    Bucket::new(deps.storage, PERMIT_NONCES_PREFIX).save(owner.as_bytes(), &(nonce + 1))?;
+
+    let mut allowance = load_allowance(deps.storage, &owner, &spender)?;
+    allowance.allowance = amount;
+    save_allowance(deps.storage, &allowance)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "permit")
+        .add_attribute("relayer", info.sender)
+        .add_attribute("owner", owner)
+        .add_attribute("spender", spender)
+        .add_attribute("amount", amount.to_string()))
+}
+
+pub const SIGNED_NONCES_PREFIX: &[u8] = b"signed_nonces";
+
+// Actions a relayer can submit for a holder
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SignedAction {
+    Transfer { recipient: HumanAddr, amount: Uint128 },
+    Send { contract: HumanAddr, amount: Uint128, msg: cosmwasm_std::Binary },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SignedActionPayload {
+    pub domain: SignatureDomain,
+    pub signer: HumanAddr,
+    pub action: SignedAction,
+    pub nonce: u64,
+    pub expiry: Expiration,
+}
+
+pub fn signed_nonce(storage: &dyn cosmwasm_std::Storage, signer: &HumanAddr) -> cosmwasm_std::StdResult<u64> {
+    Ok(ReadonlyBucket::<u64>::new(storage, SIGNED_NONCES_PREFIX).may_load(signer.as_bytes())?.unwrap_or(0))
+}
+
+// Runs the signed action as if the signer had sent it, the relayer pays the fees
+#[allow(clippy::too_many_arguments)]
+pub fn execute_signed(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    signer: HumanAddr,
+    action: SignedAction,
+    nonce: u64,
+    expiry: Expiration,
+    pubkey: cosmwasm_std::Binary,
+    signature: cosmwasm_std::Binary,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if expiry.is_expired(&env) {
+        return Err(cosmwasm_std::StdError::generic_err("Signature has expired"));
+    }
+    if nonce != signed_nonce(deps.storage, &signer)? {
+        return Err(cosmwasm_std::StdError::generic_err("Invalid nonce"));
+    }
+    let payload = SignedActionPayload {
+        domain: SignatureDomain::new(deps.storage, &env, "execute_signed")?,
+        signer: signer.clone(),
+        action: action.clone(),
+        nonce,
+        expiry,
+    };
+    verify_signed(deps.api, &signer, &payload, &pubkey, &signature)?;
+    Bucket::new(deps.storage, SIGNED_NONCES_PREFIX).save(signer.as_bytes(), &(nonce + 1))?;
+
+    let signer_info = cosmwasm_std::MessageInfo { sender: cosmwasm_std::Addr::unchecked(signer.as_str()), funds: vec![] };
+    let res = match action {
+        SignedAction::Transfer { recipient, amount } => transfer(deps, env, signer_info, recipient, amount, None)?,
+        SignedAction::Send { contract, amount, msg } => send(deps, env, signer_info, contract, amount, msg, None)?,
+    };
+    Ok(res.add_attribute("relayer", info.sender).add_attribute("nonce", nonce.to_string()))
+}
+
+pub const OPERATORS_PREFIX: &[u8] = b"operators";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IsOperatorResponse {
+    pub is_operator: bool,
+}
+
+pub fn is_operator(storage: &dyn cosmwasm_std::Storage, holder: &HumanAddr, operator: &HumanAddr) -> cosmwasm_std::StdResult<bool> {
+    Ok(ReadonlyBucket::<bool>::multilevel(storage, &[OPERATORS_PREFIX, holder.as_bytes()]).may_load(operator.as_bytes())?.is_some())
+}
+
+// An operator can move any amount of the holder's balance through TransferFrom until revoked
+pub fn authorize_operator(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    operator: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if operator.as_str() == info.sender.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Cannot authorize yourself as operator"));
+    }
+    Bucket::multilevel(deps.storage, &[OPERATORS_PREFIX, info.sender.as_bytes()]).save(operator.as_bytes(), &true)?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "authorize_operator").add_attribute("holder", info.sender).add_attribute("operator", operator))
+}
+
+pub fn revoke_operator(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    operator: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if !is_operator(deps.storage, &HumanAddr::from(info.sender.as_str()), &operator)? {
+        return Err(cosmwasm_std::StdError::generic_err("Not an operator"));
+    }
+    Bucket::<bool>::multilevel(deps.storage, &[OPERATORS_PREFIX, info.sender.as_bytes()]).remove(operator.as_bytes());
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "revoke_operator").add_attribute("holder", info.sender).add_attribute("operator", operator))
+}
+
+pub const ACTION_GRANTS_PREFIX: &[u8] = b"action_grants";
+
+// Actions other than spending that a holder can let someone else perform for them
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantableAction {
+    Stake,
+    Unstake,
+    ClaimUnstaked,
+    ClaimRewards,
+    Vote,
+    Delegate,
+}
+
+// Runs with the granter as sender, so tokens and rewards always stay with the granter
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DelegatedAction {
+    Stake { amount: Uint128 },
+    Unstake { amount: Uint128 },
+    ClaimUnstaked {},
+    ClaimRewards { denom: Option<String> },
+    CastVote { id: u64, vote: VoteOption },
+    Delegate { delegatee: Option<HumanAddr> },
+}
+
+impl DelegatedAction {
+    pub fn grantable(&self) -> GrantableAction {
+        match self {
+            DelegatedAction::Stake { .. } => GrantableAction::Stake,
+            DelegatedAction::Unstake { .. } => GrantableAction::Unstake,
+            DelegatedAction::ClaimUnstaked {} => GrantableAction::ClaimUnstaked,
+            DelegatedAction::ClaimRewards { .. } => GrantableAction::ClaimRewards,
+            DelegatedAction::CastVote { .. } => GrantableAction::Vote,
+            DelegatedAction::Delegate { .. } => GrantableAction::Delegate,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ActionGrant {
+    pub action: GrantableAction,
+    pub expires: Option<Expiration>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GranteeGrants {
+    pub grantee: HumanAddr,
+    pub grants: Vec<ActionGrant>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct GrantsResponse {
+    pub grants: Vec<GranteeGrants>,
+}
+
+fn action_grants(storage: &dyn cosmwasm_std::Storage, granter: &HumanAddr, grantee: &HumanAddr) -> cosmwasm_std::StdResult<Vec<ActionGrant>> {
+    Ok(ReadonlyBucket::<Vec<ActionGrant>>::multilevel(storage, &[ACTION_GRANTS_PREFIX, granter.as_bytes()]).may_load(grantee.as_bytes())?.unwrap_or_default())
+}
+
+fn save_action_grants(storage: &mut dyn cosmwasm_std::Storage, granter: &HumanAddr, grantee: &HumanAddr, grants: &[ActionGrant]) -> cosmwasm_std::StdResult<()> {
+    let mut bucket = Bucket::<Vec<ActionGrant>>::multilevel(storage, &[ACTION_GRANTS_PREFIX, granter.as_bytes()]);
+    if grants.is_empty() {
+        bucket.remove(grantee.as_bytes());
+        Ok(())
+    } else {
+        bucket.save(grantee.as_bytes(), &grants.to_vec())
+    }
+}
+
+pub fn assert_granted(
+    storage: &dyn cosmwasm_std::Storage,
+    env: &cosmwasm_std::Env,
+    granter: &HumanAddr,
+    grantee: &HumanAddr,
+    action: GrantableAction,
+) -> cosmwasm_std::StdResult<()> {
+    let granted = action_grants(storage, granter, grantee)?
+        .iter()
+        .any(|g| g.action == action && !g.expires.is_some_and(|e| e.is_expired(env)));
+    if !granted {
+        return Err(cosmwasm_std::StdError::generic_err(format!("{} has no {:?} grant from {}", grantee, action, granter)));
+    }
+    Ok(())
+}
+
+// Granting an action again replaces its expiry
+pub fn grant_action(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    grantee: HumanAddr,
+    action: GrantableAction,
+    expires: Option<Expiration>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if grantee.as_str() == info.sender.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Cannot grant to yourself"));
+    }
+    if expires.is_some_and(|e| e.is_expired(&env)) {
+        return Err(cosmwasm_std::StdError::generic_err("Expiration is already in the past"));
+    }
+    let granter = HumanAddr::from(info.sender.as_str());
+    let mut grants = action_grants(deps.storage, &granter, &grantee)?;
+    grants.retain(|g| g.action != action);
+    grants.push(ActionGrant { action, expires });
+    save_action_grants(deps.storage, &granter, &grantee, &grants)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "grant_action")
+        .add_attribute("granter", granter)
+        .add_attribute("grantee", grantee)
+        .add_attribute("granted", format!("{:?}", action)))
+}
+
+pub fn revoke_action(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    grantee: HumanAddr,
+    action: GrantableAction,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let granter = HumanAddr::from(info.sender.as_str());
+    let mut grants = action_grants(deps.storage, &granter, &grantee)?;
+    let before = grants.len();
+    grants.retain(|g| g.action != action);
+    if grants.len() == before {
+        return Err(cosmwasm_std::StdError::generic_err("Grant not found"));
+    }
+    save_action_grants(deps.storage, &granter, &grantee, &grants)?;
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "revoke_action")
+        .add_attribute("granter", granter)
+        .add_attribute("grantee", grantee)
+        .add_attribute("revoked", format!("{:?}", action)))
+}
+
+pub fn execute_on_behalf(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    granter: HumanAddr,
+    action: DelegatedAction,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if !info.funds.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err("Actions on behalf of a granter take no funds"));
+    }
+    assert_granted(deps.storage, &env, &granter, &HumanAddr::from(info.sender.as_str()), action.grantable())?;
+
+    let granter_info = cosmwasm_std::MessageInfo { sender: cosmwasm_std::Addr::unchecked(granter.as_str()), funds: vec![] };
+    let res = match action {
+        DelegatedAction::Stake { amount } => stake(deps, env, granter_info, amount)?,
+        DelegatedAction::Unstake { amount } => unstake(deps, env, granter_info, amount)?,
+        DelegatedAction::ClaimUnstaked {} => claim_unstaked(deps, env, granter_info)?,
+        DelegatedAction::ClaimRewards { denom } => claim_staking_rewards(deps, env, granter_info, denom)?,
+        DelegatedAction::CastVote { id, vote } => cast_vote(deps, env, granter_info, id, vote)?,
+        DelegatedAction::Delegate { delegatee } => delegate(deps, env, granter_info, delegatee)?,
+    };
+    Ok(res.add_attribute("grantee", info.sender))
+}
+
+pub fn query_grants(
+    deps: cosmwasm_std::Deps,
+    env: cosmwasm_std::Env,
+    granter: HumanAddr,
+    start_after: Option<HumanAddr>,
+    limit: Option<u32>,
+) -> cosmwasm_std::StdResult<GrantsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = calc_range_start(start_after);
+
+    let grants = ReadonlyBucket::<Vec<ActionGrant>>::multilevel(deps.storage, &[ACTION_GRANTS_PREFIX, granter.as_bytes()])
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (grantee, grants) = item?;
+            let grants = grants.into_iter().filter(|g| !g.expires.is_some_and(|e| e.is_expired(&env))).collect();
+            Ok(GranteeGrants { grantee: HumanAddr::from(String::from_utf8(grantee)?), grants })
+        })
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    Ok(GrantsResponse { grants })
+}
+
+pub const SEND_CHECKED_REPLY_ID: u64 = 4;
+pub const PENDING_SENDS_KEY: &[u8] = b"pending_sends";
+
+// A stack, since the receiving contract can SendChecked again from its callback
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingSend {
+    pub from: HumanAddr,
+    pub to: HumanAddr,
+    pub amount: Uint128,
+}
+
+// Transfers to a contract and calls its Receive hook with the amount it got after fees
+pub fn send(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    contract: HumanAddr,
+    amount: Uint128,
+    msg: cosmwasm_std::Binary,
+    memo: Option<String>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    validate_memo(&memo)?;
+    let sender = HumanAddr::from(info.sender.as_str());
+    assert_not_large_transfer(deps.storage, amount)?;
+    assert_transfer_allowed(deps.as_ref(), &env, &sender, &contract, amount)?;
+    let events = record_transfer(deps.storage, &env, &sender, &contract, amount)?;
+
+    let outcome = settle_transfer(deps.storage, &env, &sender, &contract, amount)?;
+    let (before, after) = transfer_hook_msgs(deps.storage, &sender, &contract, outcome.net)?;
+
+    let callback = ReceiverExecuteMsg::Receive(Cw20ReceiveMsg { sender: sender.clone(), amount: outcome.net, msg });
+    let callback = cosmwasm_std::WasmMsg::Execute { contract_addr: contract.to_string(), msg: cosmwasm_std::to_binary(&callback)?, funds: vec![] };
+
+    let response = cosmwasm_std::Response::new()
+        .add_submessages(before)
+        .add_message(callback)
+        .add_submessages(after)
+        .add_events(events)
+        .add_events(outcome.events)
+        .add_attribute("action", "send")
+        .add_attribute("from", sender)
+        .add_attribute("to", contract)
+        .add_attribute("amount", outcome.net.to_string())
+        .add_attribute("fee", outcome.fee.to_string());
+    Ok(with_memo(response, memo))
+}
+
+// Like Send, except that a failing callback
+// moves the tokens back instead of failing the whole transaction. A transfer fee
+// that was charged is not refunded.
+pub fn send_checked(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    contract: HumanAddr,
+    amount: Uint128,
+    msg: cosmwasm_std::Binary,
+    memo: Option<String>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    validate_memo(&memo)?;
+    let sender = HumanAddr::from(info.sender.as_str());
+    assert_not_large_transfer(deps.storage, amount)?;
+    assert_transfer_allowed(deps.as_ref(), &env, &sender, &contract, amount)?;
+    let events = record_transfer(deps.storage, &env, &sender, &contract, amount)?;
+
+    let outcome = settle_transfer(deps.storage, &env, &sender, &contract, amount)?;
+    let (before, after) = transfer_hook_msgs(deps.storage, &sender, &contract, outcome.net)?;
+
+    let mut pending = ReadonlySingleton::<Vec<PendingSend>>::new(deps.storage, PENDING_SENDS_KEY).may_load()?.unwrap_or_default();
+    pending.push(PendingSend { from: sender.clone(), to: contract.clone(), amount: outcome.net });
+    Singleton::new(deps.storage, PENDING_SENDS_KEY).save(&pending)?;
+
+    let callback = ReceiverExecuteMsg::Receive(Cw20ReceiveMsg { sender: sender.clone(), amount: outcome.net, msg });
+    let callback = cosmwasm_std::WasmMsg::Execute { contract_addr: contract.to_string(), msg: cosmwasm_std::to_binary(&callback)?, funds: vec![] };
+
+    let response = cosmwasm_std::Response::new()
+        .add_submessages(before)
+        .add_submessage(cosmwasm_std::SubMsg::reply_always(callback, SEND_CHECKED_REPLY_ID))
+        .add_submessages(after)
+        .add_events(events)
+        .add_events(outcome.events)
+        .add_attribute("action", "send_checked")
+        .add_attribute("from", sender)
+        .add_attribute("to", contract)
+        .add_attribute("amount", outcome.net.to_string())
+        .add_attribute("fee", outcome.fee.to_string());
+    Ok(with_memo(response, memo))
+}
+
+fn settle_send_checked(
+    deps: cosmwasm_std::DepsMut,
+    result: cosmwasm_std::ContractResult<cosmwasm_std::SubMsgExecutionResponse>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let mut pending = ReadonlySingleton::<Vec<PendingSend>>::new(deps.storage, PENDING_SENDS_KEY).load()?;
+    let send = pending.pop().ok_or_else(|| cosmwasm_std::StdError::generic_err("No pending send"))?;
+    Singleton::new(deps.storage, PENDING_SENDS_KEY).save(&pending)?;
+
+    match result {
+        cosmwasm_std::ContractResult::Ok(_) => Ok(cosmwasm_std::Response::new().add_attribute("action", "send_checked_delivered")),
+        cosmwasm_std::ContractResult::Err(err) => {
+            // The failed callback's own changes were reverted, so the tokens are still on the receiver
+            move_balance(deps.storage, &send.to, &send.from, send.amount)?;
+            Ok(cosmwasm_std::Response::new()
+                .add_attribute("action", "send_checked_reverted")
+                .add_attribute("from", send.from)
+                .add_attribute("to", send.to)
+                .add_attribute("amount", send.amount.to_string())
+                .add_attribute("error", err))
+        }
+    }
+}
+
+// The cw1155 view of this token is a single token id, the cw20 symbol
+pub fn cw1155_token_id(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<String> {
+    Ok(token_info(storage)?.symbol)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw1155BalanceResponse {
+    pub balance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw1155BatchBalanceResponse {
+    pub balances: Vec<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct IsApprovedForAllResponse {
+    pub approved: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Cw1155ReceiveMsg {
+    pub operator: HumanAddr,
+    pub from: Option<HumanAddr>,
+    pub token_id: String,
+    pub amount: Uint128,
+    pub msg: cosmwasm_std::Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Cw1155ReceiverExecuteMsg {
+    Receive(Cw1155ReceiveMsg),
+}
+
+// Unknown token ids hold nothing
+pub fn cw1155_balance(storage: &dyn cosmwasm_std::Storage, owner: &HumanAddr, token_id: &str) -> cosmwasm_std::StdResult<Uint128> {
+    if token_id != cw1155_token_id(storage)? {
+        return Ok(Uint128::zero());
+    }
+    balance_of(storage, owner)
+}
+
+pub fn query_cw1155_batch_balance(deps: cosmwasm_std::Deps, owner: HumanAddr, token_ids: Vec<String>) -> cosmwasm_std::StdResult<Cw1155BatchBalanceResponse> {
+    let balances = token_ids.iter().map(|id| cw1155_balance(deps.storage, &owner, id)).collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+    Ok(Cw1155BatchBalanceResponse { balances })
+}
+
+// cw1155 SendFrom and BatchSendFrom. The sender moves its own tokens, or is an
+// operator of `from` or holds an allowance from it.
+pub fn cw1155_send_from(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    from: HumanAddr,
+    to: HumanAddr,
+    batch: Vec<(String, Uint128)>,
+    msg: Option<cosmwasm_std::Binary>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let token_id = cw1155_token_id(deps.storage)?;
+    if let Some((id, _)) = batch.iter().find(|(id, _)| id != &token_id) {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Unknown token id {}", id)));
+    }
+    let amount = batch.iter().try_fold(Uint128::zero(), |total, (_, value)| total.checked_add(*value))?;
+    let operator = HumanAddr::from(info.sender.as_str());
+
+    assert_not_blacklisted(deps.storage, &operator)?;
+    assert_not_large_transfer(deps.storage, amount)?;
+    assert_transfer_allowed(deps.as_ref(), &env, &from, &to, amount)?;
+    let events = record_transfer(deps.storage, &env, &from, &to, amount)?;
+    if operator != from {
+        spend_allowance(deps.storage, &env, &from, &operator, amount)?;
+    }
+
+    let outcome = settle_transfer(deps.storage, &env, &from, &to, amount)?;
+    let (before, after) = transfer_hook_msgs(deps.storage, &from, &to, outcome.net)?;
+
+    let mut res = cosmwasm_std::Response::new().add_submessages(before);
+    if let Some(msg) = msg {
+        let callback = Cw1155ReceiverExecuteMsg::Receive(Cw1155ReceiveMsg { operator: operator.clone(), from: Some(from.clone()), token_id: token_id.clone(), amount: outcome.net, msg });
+        res = res.add_message(cosmwasm_std::WasmMsg::Execute { contract_addr: to.to_string(), msg: cosmwasm_std::to_binary(&callback)?, funds: vec![] });
+    }
+
+    Ok(res
+        .add_submessages(after)
+        .add_events(events)
+        .add_events(outcome.events)
+        .add_attribute("action", "transfer")
+        .add_attribute("token_id", token_id)
+        .add_attribute("from", from)
+        .add_attribute("to", to)
+        .add_attribute("amount", outcome.net.to_string())
+        .add_attribute("fee", outcome.fee.to_string()))
+}
+
+// Operators are unbounded in time, an expiring approval is refused rather than silently kept forever
+pub fn cw1155_approve_all(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    operator: HumanAddr,
+    expires: Option<Expiration>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    if expires.is_some() {
+        return Err(cosmwasm_std::StdError::generic_err("Operator approvals cannot expire, revoke them with RevokeAll"));
+    }
+    authorize_operator(deps, env, info, operator)
+}
+
+pub fn validate_memo(memo: &Option<String>) -> cosmwasm_std::StdResult<()> {
+    if memo.as_ref().is_some_and(|m| m.len() > MAX_MEMO_LENGTH) {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Memo is longer than {} bytes", MAX_MEMO_LENGTH)));
+    }
+    Ok(())
+}
+
+// Adds the memo as an attribute so indexers can pick it up alongside the transfer
+pub fn with_memo(response: cosmwasm_std::Response, memo: Option<String>) -> cosmwasm_std::Response {
+    match memo {
+        Some(memo) => response.add_attribute("memo", memo),
+        None => response,
+    }
+}
+
+// Whether the contract holds `token` on behalf of one of its modules. Balances of these
+// aren't tracked per token, so they are never recoverable at all.
+fn holds_cw20(storage: &dyn cosmwasm_std::Storage, token: &HumanAddr) -> cosmwasm_std::StdResult<bool> {
+    if let Some(migration) = ReadonlySingleton::<LegacyMigration>::new(storage, LEGACY_MIGRATION_KEY).may_load()? {
+        if !migration.burn_legacy && &migration.legacy_token == token {
+            return Ok(true);
+        }
+    }
+    if let Some(collateral) = ReadonlySingleton::<Collateral>::new(storage, COLLATERAL_KEY).may_load()? {
+        if &collateral.token == token {
+            return Ok(true);
+        }
+    }
+    if let Some(LockdropConfig { asset: LockdropAsset::Cw20 { address }, .. }) = ReadonlySingleton::<LockdropConfig>::new(storage, LOCKDROP_KEY).may_load()? {
+        if &address == token {
+            return Ok(true);
+        }
+    }
+    // Unclaimed dividends and staking rewards paid in the token
+    let denom = format!("{}{}", CW20_DENOM_PREFIX, token);
+    Ok(dividend_denoms(storage)?.contains(&denom) || staking_reward_denoms(storage)?.contains(&denom))
+}
+
+// Forwards another project's cw20 that was sent here by mistake. OTC trade asks are
+// paid straight through to the maker, so they never sit on the contract.
+pub fn recover_cw20(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    token: HumanAddr,
+    recipient: HumanAddr,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    if token.as_str() == env.contract.address.as_str() {
+        return Err(cosmwasm_std::StdError::generic_err("Use RecoverStuckTokens for this token"));
+    }
+    if holds_cw20(deps.storage, &token)? {
+        return Err(cosmwasm_std::StdError::generic_err(format!("{} is held in escrow by the contract", token)));
+    }
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+
+    Ok(cosmwasm_std::Response::new()
+        .add_message(cw20_transfer_msg(&token, &recipient, amount)?)
+        .add_attribute("action", "recover_cw20")
+        .add_attribute("token", token)
+        .add_attribute("to", recipient)
+        .add_attribute("amount", amount.to_string()))
+}
+
+// Sends on whatever of `denom` the contract holds beyond its reserves, i.e. coins sent to
+// the contract address by mistake. Reserved coins are never touched.
+pub fn sweep_native(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    denom: String,
+    recipient: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    let balance = deps.querier.query_balance(env.contract.address.to_string(), denom.clone())?.amount;
+    let amount = balance.checked_sub(reserved_native(deps.storage, &denom)?).unwrap_or_default();
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err(format!("No unreserved {} to sweep", denom)));
+    }
+
+    Ok(cosmwasm_std::Response::new()
+        .add_message(cosmwasm_std::BankMsg::Send { to_address: recipient.to_string(), amount: vec![cosmwasm_std::Coin { denom: denom.clone(), amount }] })
+        .add_attribute("action", "sweep_native")
+        .add_attribute("to", recipient)
+        .add_attribute("amount", format!("{}{}", amount, denom)))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct AllBalancesResponse {
+    pub balances: Vec<HolderBalance>,
+    // Empty balances are left out as in `HoldersAt`, continue from here until it is None
+    pub next_start_after: Option<HumanAddr>,
+}
+
+// Current balances in address order, for snapshots without reading raw state
+pub fn query_all_balances(deps: cosmwasm_std::Deps, start_after: Option<HumanAddr>, limit: Option<u32>) -> cosmwasm_std::StdResult<AllBalancesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = calc_range_start(start_after);
+
+    let entries = ReadonlyBucket::<Balance>::new(deps.storage, BALANCES_PREFIX)
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, stored) = item?;
+            Ok((HumanAddr::from(String::from_utf8(key)?), stored.amount))
+        })
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    let next_start_after = if entries.len() == limit { entries.last().map(|(address, _)| address.clone()) } else { None };
+    let mut balances = vec![];
+    for (address, stored) in entries {
+        // Stored values are shares once share accounting is on
+        let balance = amount_for(deps.storage, stored)?;
+        if !balance.is_zero() {
+            balances.push(HolderBalance { address, balance });
+        }
+    }
+
+    Ok(AllBalancesResponse { balances, next_start_after })
+}
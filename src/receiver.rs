@@ -0,0 +1,73 @@
+use cosmwasm_std::{HumanAddr, Uint128};
+use serde::de::DeserializeOwned;
+
+use crate::Cw20ReceiveMsg;
+
+// Implemented by contracts that accept this token through Send. `receive` does the
+// checks every integration needs, implementors only decide which tokens they take
+// and what to do with a decoded message.
+pub trait Cw20Receiver {
+    type Msg: DeserializeOwned;
+
+    fn accepts_token(deps: cosmwasm_std::Deps, token: &HumanAddr) -> cosmwasm_std::StdResult<bool>;
+
+    fn on_receive(
+        deps: cosmwasm_std::DepsMut,
+        env: cosmwasm_std::Env,
+        info: cosmwasm_std::MessageInfo,
+        sender: HumanAddr,
+        amount: Uint128,
+        msg: Self::Msg,
+    ) -> cosmwasm_std::StdResult<cosmwasm_std::Response>;
+
+    fn receive(
+        deps: cosmwasm_std::DepsMut,
+        env: cosmwasm_std::Env,
+        info: cosmwasm_std::MessageInfo,
+        wrapper: Cw20ReceiveMsg,
+    ) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+        // `info.sender` is the token contract, anyone can call Receive directly
+        let token = HumanAddr::from(info.sender.as_str());
+        if !Self::accepts_token(deps.as_ref(), &token)? {
+            return Err(cosmwasm_std::StdError::generic_err(format!("Token {} is not accepted", token)));
+        }
+        if wrapper.amount.is_zero() {
+            return Err(cosmwasm_std::StdError::generic_err("Received amount must be greater than zero"));
+        }
+        let msg = cosmwasm_std::from_binary(&wrapper.msg)?;
+        Self::on_receive(deps, env, info, wrapper.sender, wrapper.amount, msg)
+    }
+}
+
+// Declares an execute enum with the `Receive(Cw20ReceiveMsg)` variant added in front:
+//
+//     cw20_receiver_execute_msg! {
+//         #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+//         #[serde(rename_all = "snake_case")]
+//         pub enum ExecuteMsg {
+//             Withdraw { amount: Uint128 },
+//         }
+//     }
+#[macro_export]
+macro_rules! cw20_receiver_execute_msg {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident { $($variants:tt)* }) => {
+        $(#[$meta])*
+        $vis enum $name {
+            Receive($crate::Cw20ReceiveMsg),
+            $($variants)*
+        }
+    };
+}
+
+// Dispatch arm for the variant generated above, forwarding to a `Cw20Receiver`:
+//
+//     match msg {
+//         ExecuteMsg::Receive(wrapper) => cw20_receive!(Vault, deps, env, info, wrapper),
+//         ...
+//     }
+#[macro_export]
+macro_rules! cw20_receive {
+    ($receiver:ty, $deps:expr, $env:expr, $info:expr, $wrapper:expr) => {
+        <$receiver as $crate::receiver::Cw20Receiver>::receive($deps, $env, $info, $wrapper)
+    };
+}
@@ -0,0 +1,685 @@
+use cosmwasm_std::{Decimal, HumanAddr, Uint128};
+use cosmwasm_storage::{Bucket, ReadonlyBucket, ReadonlySingleton, Singleton};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CW20_DENOM_PREFIX, DEFAULT_LIMIT, Duration, EMISSION_KEY, EmissionSchedule, Expiration,
+    HolderDividend, MAX_FEE_BPS, MAX_LIMIT, add_escrowed, assert_escrow_allowed,
+    assert_not_blacklisted, assert_owner, burn_balance, calc_range_start, cw20_transfer_msg,
+    escrow_in, escrow_out, mint_balance, next_id, reserve_native, sub_escrowed,
+};
+
+pub const STAKING_REWARD_POOL_KEY: &[u8] = b"staking_reward_pool";
+
+// Rewards minted for stakers that haven't been distributed yet. They sit on the
+// contract as escrow until the staking module pays them out.
+pub fn staking_reward_pool(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Uint128> {
+    Ok(ReadonlySingleton::<Uint128>::new(storage, STAKING_REWARD_POOL_KEY).may_load()?.unwrap_or_else(Uint128::zero))
+}
+
+pub fn fund_staking_rewards(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    mint_balance(storage, &HumanAddr::from(env.contract.address.as_str()), amount)?;
+    add_escrowed(storage, amount)?;
+    pool_own_rewards(storage, env, amount)
+}
+
+// Adds tokens already escrowed on the contract to the stakers' rewards
+pub(crate) fn pool_own_rewards(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    let pool = staking_reward_pool(storage)?.checked_add(amount)?;
+
+    // Held back until there are stakers to distribute to
+    if total_staked(storage)?.is_zero() {
+        return Singleton::new(storage, STAKING_REWARD_POOL_KEY).save(&pool);
+    }
+    add_staking_reward(storage, &own_reward_denom(env), pool)?;
+    Singleton::new(storage, STAKING_REWARD_POOL_KEY).save(&Uint128::zero())
+}
+
+// Rewards in this token use the same "cw20:<address>" naming as other cw20 rewards
+pub fn own_reward_denom(env: &cosmwasm_std::Env) -> String {
+    format!("{}{}", CW20_DENOM_PREFIX, env.contract.address)
+}
+
+pub const STAKING_CONFIG_KEY: &[u8] = b"staking_config";
+pub const STAKES_PREFIX: &[u8] = b"stakes";
+pub const TOTAL_STAKED_KEY: &[u8] = b"total_staked";
+pub const UNBONDING_PREFIX: &[u8] = b"unbonding";
+pub const UNBONDING_COUNT_KEY: &[u8] = b"unbonding_count";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakingConfig {
+    pub unbonding_period: Duration,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UnbondingClaim {
+    pub id: u64,
+    pub amount: Uint128,
+    pub release_at: Expiration,
+    // When unbonding started, measured in the same unit as `release_at`
+    #[serde(default)]
+    pub started_at: Option<Expiration>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakedResponse {
+    pub staked: Uint128,
+    pub unbonding: Uint128,
+    pub total_staked: Uint128,
+}
+
+pub fn staking_config(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<StakingConfig> {
+    ReadonlySingleton::<StakingConfig>::new(storage, STAKING_CONFIG_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Staking is not enabled"))
+}
+
+pub fn staked_of(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<Uint128> {
+    Ok(ReadonlyBucket::<Uint128>::new(storage, STAKES_PREFIX).may_load(address.as_bytes())?.unwrap_or_else(Uint128::zero))
+}
+
+pub fn total_staked(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Uint128> {
+    Ok(ReadonlySingleton::<Uint128>::new(storage, TOTAL_STAKED_KEY).may_load()?.unwrap_or_else(Uint128::zero))
+}
+
+// Single place that changes staked balances
+pub(crate) fn adjust_stake(storage: &mut dyn cosmwasm_std::Storage, address: &HumanAddr, amount: Uint128, increase: bool) -> cosmwasm_std::StdResult<()> {
+    settle_staking_rewards(storage, address)?;
+    let staked = staked_of(storage, address)?;
+    let staked = if increase { staked.checked_add(amount)? } else { staked.checked_sub(amount)? };
+    Bucket::new(storage, STAKES_PREFIX).save(address.as_bytes(), &staked)?;
+
+    let total = total_staked(storage)?;
+    let total = if increase { total.checked_add(amount)? } else { total.checked_sub(amount)? };
+    Singleton::new(storage, TOTAL_STAKED_KEY).save(&total)
+}
+
+pub fn unbonding_claims(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<Vec<UnbondingClaim>> {
+    ReadonlyBucket::<UnbondingClaim>::multilevel(storage, &[UNBONDING_PREFIX, address.as_bytes()])
+        .range(None, None, cosmwasm_std::Order::Ascending)
+        .map(|item| item.map(|(_, claim)| claim))
+        .collect()
+}
+
+pub fn set_staking_config(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    unbonding_period: Duration,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    // Only new unbonding entries use the new period
+    Singleton::new(deps.storage, STAKING_CONFIG_KEY).save(&StakingConfig { unbonding_period })?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_staking_config"))
+}
+
+pub fn stake(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    staking_config(deps.storage)?;
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+    let contract = HumanAddr::from(env.contract.address.as_str());
+    assert_escrow_allowed(deps.as_ref(), &env, &HumanAddr::from(&info.sender), &contract, amount)?;
+
+    escrow_in(deps.storage, &env, &HumanAddr::from(&info.sender), amount)?;
+    adjust_stake(deps.storage, &HumanAddr::from(&info.sender), amount, true)?;
+
+    let hooks = stake_hook_msgs(deps.storage, StakeChangedHookMsg::Stake { addr: HumanAddr::from(&info.sender), amount })?;
+    Ok(cosmwasm_std::Response::new().add_messages(hooks).add_attribute("action", "stake").add_attribute("from", info.sender).add_attribute("amount", amount.to_string()))
+}
+
+pub fn unstake(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    amount: Uint128,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let config = staking_config(deps.storage)?;
+    if amount.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("Amount must be greater than zero"));
+    }
+    if amount > staked_of(deps.storage, &HumanAddr::from(&info.sender))? {
+        return Err(cosmwasm_std::StdError::generic_err("Amount exceeds staked balance"));
+    }
+    adjust_stake(deps.storage, &HumanAddr::from(&info.sender), amount, false)?;
+
+    // The tokens stay escrowed until the claim matures
+    let id = next_id(deps.storage, UNBONDING_COUNT_KEY)?;
+    let release_at = config.unbonding_period.after(&env);
+    let started_at = match release_at {
+        Expiration::AtHeight(_) => Expiration::AtHeight(env.block.height),
+        Expiration::AtTime(_) => Expiration::AtTime(env.block.time.seconds()),
+    };
+    let claim = UnbondingClaim { id, amount, release_at, started_at: Some(started_at) };
+    Bucket::multilevel(deps.storage, &[UNBONDING_PREFIX, info.sender.as_bytes()]).save(&id.to_be_bytes(), &claim)?;
+
+    let hooks = stake_hook_msgs(deps.storage, StakeChangedHookMsg::Unstake { addr: HumanAddr::from(&info.sender), amount })?;
+    Ok(cosmwasm_std::Response::new()
+        .add_messages(hooks)
+        .add_attribute("action", "unstake")
+        .add_attribute("from", info.sender)
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("id", id.to_string()))
+}
+
+pub fn claim_unstaked(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let matured: Vec<UnbondingClaim> = unbonding_claims(deps.storage, &HumanAddr::from(&info.sender))?.into_iter().filter(|c| c.release_at.is_expired(&env)).collect();
+    if matured.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err("No unbonding claims have matured"));
+    }
+
+    let mut amount = Uint128::zero();
+    let mut claims = Bucket::<UnbondingClaim>::multilevel(deps.storage, &[UNBONDING_PREFIX, info.sender.as_bytes()]);
+    for claim in &matured {
+        claims.remove(&claim.id.to_be_bytes());
+        amount = amount.checked_add(claim.amount)?;
+    }
+    assert_not_blacklisted(deps.storage, &HumanAddr::from(&info.sender))?;
+    escrow_out(deps.storage, &env, &HumanAddr::from(&info.sender), amount)?;
+
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "claim_unstaked").add_attribute("to", info.sender).add_attribute("amount", amount.to_string()))
+}
+
+pub fn query_staked(deps: cosmwasm_std::Deps, address: HumanAddr) -> cosmwasm_std::StdResult<StakedResponse> {
+    let mut unbonding = Uint128::zero();
+    for claim in unbonding_claims(deps.storage, &address)? {
+        unbonding = unbonding.checked_add(claim.amount)?;
+    }
+    Ok(StakedResponse {
+        staked: staked_of(deps.storage, &address)?,
+        unbonding,
+        total_staked: total_staked(deps.storage)?,
+    })
+}
+
+pub const STAKING_REWARD_DENOMS_KEY: &[u8] = b"staking_reward_denoms";
+pub const STAKING_REWARD_ALLOWLIST_KEY: &[u8] = b"staking_reward_allowlist";
+// Every tracked denom is settled whenever a balance or stake changes, so their number stays small
+pub const MAX_REWARD_DENOMS: usize = 10;
+pub const STAKING_REWARD_INDEX_PREFIX: &[u8] = b"staking_reward_index";
+pub const STAKER_REWARDS_PREFIX: &[u8] = b"staker_rewards";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingRewardsResponse {
+    pub rewards: Vec<cosmwasm_std::Coin>,
+}
+
+pub fn staking_reward_denoms(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Vec<String>> {
+    Ok(ReadonlySingleton::<Vec<String>>::new(storage, STAKING_REWARD_DENOMS_KEY).may_load()?.unwrap_or_default())
+}
+
+// Denoms anyone may fund, rewards in this token are always accepted
+pub fn accepted_staking_reward_denoms(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Vec<String>> {
+    Ok(ReadonlySingleton::<Vec<String>>::new(storage, STAKING_REWARD_ALLOWLIST_KEY).may_load()?.unwrap_or_default())
+}
+
+// A denom that isn't tracked yet has to be accepted and fit under the maximum
+pub fn assert_reward_denom_allowed(tracked: &[String], accepted: &[String], denom: &str) -> cosmwasm_std::StdResult<()> {
+    if tracked.iter().any(|d| d == denom) {
+        return Ok(());
+    }
+    if !accepted.iter().any(|d| d == denom) {
+        return Err(cosmwasm_std::StdError::generic_err(format!("Denom {} is not accepted", denom)));
+    }
+    if tracked.len() >= MAX_REWARD_DENOMS {
+        return Err(cosmwasm_std::StdError::generic_err(format!("At most {} denoms can be distributed", MAX_REWARD_DENOMS)));
+    }
+    Ok(())
+}
+
+pub(crate) fn assert_staking_reward_allowed(storage: &dyn cosmwasm_std::Storage, denom: &str) -> cosmwasm_std::StdResult<()> {
+    assert_reward_denom_allowed(&staking_reward_denoms(storage)?, &accepted_staking_reward_denoms(storage)?, denom)
+}
+
+pub fn set_staking_reward_denoms(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    denoms: Vec<String>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    if denoms.len() > MAX_REWARD_DENOMS {
+        return Err(cosmwasm_std::StdError::generic_err(format!("At most {} denoms can be accepted", MAX_REWARD_DENOMS)));
+    }
+    // Denoms already funded stay tracked so their rewards can still be claimed
+    Singleton::new(deps.storage, STAKING_REWARD_ALLOWLIST_KEY).save(&denoms)?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_staking_reward_denoms").add_attribute("denoms", denoms.join(",")))
+}
+
+pub(crate) fn staking_reward_index(storage: &dyn cosmwasm_std::Storage, denom: &str) -> cosmwasm_std::StdResult<Decimal> {
+    Ok(ReadonlyBucket::<Decimal>::new(storage, STAKING_REWARD_INDEX_PREFIX).may_load(denom.as_bytes())?.unwrap_or_else(Decimal::zero))
+}
+
+// Rewards are tracked per staked token the same way dividends are tracked per held unit
+pub(crate) fn pending_staking_reward(storage: &dyn cosmwasm_std::Storage, denom: &str, address: &HumanAddr) -> cosmwasm_std::StdResult<HolderDividend> {
+    let index = staking_reward_index(storage, denom)?;
+    let staker = ReadonlyBucket::<HolderDividend>::multilevel(storage, &[STAKER_REWARDS_PREFIX, denom.as_bytes()]).may_load(address.as_bytes())?;
+    let mut staker = staker.unwrap_or(HolderDividend { index: Decimal::zero(), pending: Uint128::zero() });
+    staker.pending = staker.pending.checked_add(staked_of(storage, address)? * (index - staker.index))?;
+    staker.index = index;
+    Ok(staker)
+}
+
+// Must run before a staked balance changes
+pub(crate) fn settle_staking_rewards(storage: &mut dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<()> {
+    for denom in staking_reward_denoms(storage)? {
+        let staker = pending_staking_reward(storage, &denom, address)?;
+        Bucket::multilevel(storage, &[STAKER_REWARDS_PREFIX, denom.as_bytes()]).save(address.as_bytes(), &staker)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn add_staking_reward(storage: &mut dyn cosmwasm_std::Storage, denom: &str, amount: Uint128) -> cosmwasm_std::StdResult<()> {
+    let staked = total_staked(storage)?;
+    if staked.is_zero() {
+        return Err(cosmwasm_std::StdError::generic_err("No tokens are staked"));
+    }
+    let mut denoms = staking_reward_denoms(storage)?;
+    if !denoms.iter().any(|d| d == denom) {
+        denoms.push(denom.to_string());
+        Singleton::new(storage, STAKING_REWARD_DENOMS_KEY).save(&denoms)?;
+    }
+    let index = staking_reward_index(storage, denom)? + Decimal::from_ratio(amount, staked);
+    Bucket::new(storage, STAKING_REWARD_INDEX_PREFIX).save(denom.as_bytes(), &index)
+}
+
+// Anyone can add native coins to the staking rewards
+pub fn fund_staking_rewards_native(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    staking_config(deps.storage)?;
+    if info.funds.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err("No funds sent"));
+    }
+    for coin in &info.funds {
+        assert_staking_reward_allowed(deps.storage, &coin.denom)?;
+        add_staking_reward(deps.storage, &coin.denom, coin.amount)?;
+        reserve_native(deps.storage, &coin.denom, coin.amount, true)?;
+    }
+
+    let funds = info.funds.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",");
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "fund_staking_rewards").add_attribute("from", info.sender).add_attribute("amount", funds))
+}
+
+// Claims a single reward asset, or all of them when `denom` is omitted
+pub fn claim_staking_rewards(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    denom: Option<String>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let denoms = match denom {
+        Some(denom) => vec![denom],
+        None => staking_reward_denoms(deps.storage)?,
+    };
+    let mut coins = vec![];
+    for denom in denoms {
+        let mut staker = pending_staking_reward(deps.storage, &denom, &HumanAddr::from(&info.sender))?;
+        if !staker.pending.is_zero() {
+            coins.push(cosmwasm_std::Coin { denom: denom.clone(), amount: staker.pending });
+            staker.pending = Uint128::zero();
+        }
+        Bucket::multilevel(deps.storage, &[STAKER_REWARDS_PREFIX, denom.as_bytes()]).save(info.sender.as_bytes(), &staker)?;
+    }
+
+    if coins.is_empty() {
+        return Err(cosmwasm_std::StdError::generic_err("No rewards to claim"));
+    }
+
+    let claimed = coins.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(",");
+    let own_denom = own_reward_denom(&env);
+    let mut msgs: Vec<cosmwasm_std::CosmosMsg> = vec![];
+    let mut native = vec![];
+    for coin in coins {
+        if coin.denom == own_denom {
+            if auto_compounds(deps.storage, &HumanAddr::from(&info.sender))? {
+                // Stays escrowed and is simply added to the stake
+                adjust_stake(deps.storage, &HumanAddr::from(&info.sender), coin.amount, true)?;
+                msgs.extend(stake_hook_msgs(deps.storage, StakeChangedHookMsg::Stake { addr: HumanAddr::from(&info.sender), amount: coin.amount })?);
+            } else {
+                escrow_out(deps.storage, &env, &HumanAddr::from(&info.sender), coin.amount)?;
+            }
+            continue;
+        }
+        match coin.denom.strip_prefix(CW20_DENOM_PREFIX) {
+            Some(token) => msgs.push(cw20_transfer_msg(&HumanAddr::from(token), &HumanAddr::from(&info.sender), coin.amount)?),
+            None => {
+                reserve_native(deps.storage, &coin.denom, coin.amount, false)?;
+                native.push(coin);
+            }
+        }
+    }
+    if !native.is_empty() {
+        msgs.push(cosmwasm_std::BankMsg::Send { to_address: info.sender.to_string(), amount: native }.into());
+    }
+    Ok(cosmwasm_std::Response::new().add_messages(msgs).add_attribute("action", "claim_rewards").add_attribute("to", info.sender).add_attribute("amount", claimed))
+}
+
+pub fn query_pending_rewards(deps: cosmwasm_std::Deps, address: HumanAddr) -> cosmwasm_std::StdResult<PendingRewardsResponse> {
+    let mut rewards = vec![];
+    for denom in staking_reward_denoms(deps.storage)? {
+        let staker = pending_staking_reward(deps.storage, &denom, &address)?;
+        if !staker.pending.is_zero() {
+            rewards.push(cosmwasm_std::Coin { denom, amount: staker.pending });
+        }
+    }
+    Ok(PendingRewardsResponse { rewards })
+}
+
+pub const AUTO_COMPOUND_PREFIX: &[u8] = b"auto_compound";
+pub const AUTO_COMPOUND_CURSOR_KEY: &[u8] = b"auto_compound_cursor";
+
+pub fn auto_compounds(storage: &dyn cosmwasm_std::Storage, address: &HumanAddr) -> cosmwasm_std::StdResult<bool> {
+    Ok(ReadonlyBucket::<bool>::new(storage, AUTO_COMPOUND_PREFIX).may_load(address.as_bytes())?.unwrap_or(false))
+}
+
+pub fn set_auto_compound(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    enabled: bool,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    staking_config(deps.storage)?;
+    let mut bucket = Bucket::<bool>::new(deps.storage, AUTO_COMPOUND_PREFIX);
+    if enabled {
+        bucket.save(info.sender.as_bytes(), &true)?;
+    } else {
+        bucket.remove(info.sender.as_bytes());
+    }
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_auto_compound").add_attribute("address", info.sender).add_attribute("enabled", enabled.to_string()))
+}
+
+// Restakes the staker's pending rewards in this token, returns the amount restaked
+pub(crate) fn compound_rewards(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env, address: &HumanAddr) -> cosmwasm_std::StdResult<Uint128> {
+    let denom = own_reward_denom(env);
+    let mut staker = pending_staking_reward(storage, &denom, address)?;
+    let amount = staker.pending;
+    if amount.is_zero() {
+        return Ok(amount);
+    }
+    staker.pending = Uint128::zero();
+    Bucket::multilevel(storage, &[STAKER_REWARDS_PREFIX, denom.as_bytes()]).save(address.as_bytes(), &staker)?;
+    adjust_stake(storage, address, amount, true)?;
+    Ok(amount)
+}
+
+// Each crank compounds the next page of opted-in stakers, wrapping around at the end
+pub(crate) fn run_auto_compound(storage: &mut dyn cosmwasm_std::Storage, env: &cosmwasm_std::Env) -> cosmwasm_std::StdResult<Option<(cosmwasm_std::Event, Vec<cosmwasm_std::CosmosMsg>)>> {
+    if ReadonlySingleton::<StakingConfig>::new(storage, STAKING_CONFIG_KEY).may_load()?.is_none() {
+        return Ok(None);
+    }
+    let cursor = ReadonlySingleton::<HumanAddr>::new(storage, AUTO_COMPOUND_CURSOR_KEY).may_load()?;
+    let start = calc_range_start(cursor);
+    let stakers = ReadonlyBucket::<bool>::new(storage, AUTO_COMPOUND_PREFIX)
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(MAX_LIMIT as usize)
+        .map(|item| {
+            let (key, _) = item?;
+            Ok(HumanAddr::from(String::from_utf8(key)?))
+        })
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    let mut cursor = Singleton::<HumanAddr>::new(storage, AUTO_COMPOUND_CURSOR_KEY);
+    match stakers.last() {
+        Some(last) if stakers.len() == MAX_LIMIT as usize => cursor.save(last)?,
+        _ => cursor.remove(),
+    }
+
+    let mut compounded = Uint128::zero();
+    let mut hooks = vec![];
+    for staker in &stakers {
+        let amount = compound_rewards(storage, env, staker)?;
+        if !amount.is_zero() {
+            compounded = compounded.checked_add(amount)?;
+            hooks.extend(stake_hook_msgs(storage, StakeChangedHookMsg::Stake { addr: staker.clone(), amount })?);
+        }
+    }
+    if compounded.is_zero() {
+        return Ok(None);
+    }
+    let event = cosmwasm_std::Event::new("auto_compound").add_attribute("stakers", stakers.len().to_string()).add_attribute("amount", compounded.to_string());
+    Ok(Some((event, hooks)))
+}
+
+pub const EARLY_UNBOND_KEY: &[u8] = b"early_unbond";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PenaltyDestination {
+    Burn {},
+    RewardPool {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EarlyUnbondPenalty {
+    // Penalty for leaving right after unstaking, it falls linearly to zero at maturity
+    pub bps: u64,
+    pub destination: PenaltyDestination,
+}
+
+impl UnbondingClaim {
+    // Share of the unbonding window still left, as (remaining, total)
+    fn remaining(&self, env: &cosmwasm_std::Env) -> (u64, u64) {
+        match (self.started_at, self.release_at) {
+            (Some(Expiration::AtHeight(start)), Expiration::AtHeight(end)) => (end.saturating_sub(env.block.height), end.saturating_sub(start)),
+            (Some(Expiration::AtTime(start)), Expiration::AtTime(end)) => (end.saturating_sub(env.block.time.seconds()), end.saturating_sub(start)),
+            // No recorded start, charge the full penalty until maturity
+            _ if self.release_at.is_expired(env) => (0, 1),
+            _ => (1, 1),
+        }
+    }
+
+    pub fn early_penalty(&self, env: &cosmwasm_std::Env, bps: u64) -> Uint128 {
+        let (remaining, total) = self.remaining(env);
+        if remaining == 0 || total == 0 {
+            return Uint128::zero();
+        }
+        self.amount.multiply_ratio(bps as u128 * remaining as u128, MAX_FEE_BPS as u128 * total as u128)
+    }
+}
+
+pub fn set_early_unbond_penalty(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    penalty: Option<EarlyUnbondPenalty>,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    match &penalty {
+        Some(penalty) => {
+            if penalty.bps > MAX_FEE_BPS {
+                return Err(cosmwasm_std::StdError::generic_err("Penalty cannot exceed 100%"));
+            }
+            Singleton::new(deps.storage, EARLY_UNBOND_KEY).save(penalty)?;
+        }
+        None => Singleton::<EarlyUnbondPenalty>::new(deps.storage, EARLY_UNBOND_KEY).remove(),
+    }
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "set_early_unbond_penalty").add_attribute("bps", penalty.map(|p| p.bps).unwrap_or(0).to_string()))
+}
+
+// Releases an unbonding claim before maturity in exchange for the decaying penalty
+pub fn unbond_early(
+    deps: cosmwasm_std::DepsMut,
+    env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    id: u64,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    let penalty = ReadonlySingleton::<EarlyUnbondPenalty>::new(deps.storage, EARLY_UNBOND_KEY)
+        .may_load()?
+        .ok_or_else(|| cosmwasm_std::StdError::generic_err("Early unbonding is not enabled"))?;
+    let mut claims = Bucket::<UnbondingClaim>::multilevel(deps.storage, &[UNBONDING_PREFIX, info.sender.as_bytes()]);
+    let claim = claims.may_load(&id.to_be_bytes())?.ok_or_else(|| cosmwasm_std::StdError::generic_err("Unbonding claim not found"))?;
+    claims.remove(&id.to_be_bytes());
+    assert_not_blacklisted(deps.storage, &HumanAddr::from(&info.sender))?;
+
+    let charged = claim.early_penalty(&env, penalty.bps);
+    let payout = claim.amount.checked_sub(charged)?;
+    escrow_out(deps.storage, &env, &HumanAddr::from(&info.sender), payout)?;
+    if !charged.is_zero() {
+        match penalty.destination {
+            PenaltyDestination::Burn {} => {
+                sub_escrowed(deps.storage, charged)?;
+                burn_balance(deps.storage, &HumanAddr::from(env.contract.address.as_str()), charged)?;
+            }
+            PenaltyDestination::RewardPool {} => pool_own_rewards(deps.storage, &env, charged)?,
+        }
+    }
+
+    Ok(cosmwasm_std::Response::new()
+        .add_attribute("action", "unbond_early")
+        .add_attribute("to", info.sender)
+        .add_attribute("amount", payout.to_string())
+        .add_attribute("penalty", charged.to_string()))
+}
+
+pub const STAKE_HOOKS_KEY: &[u8] = b"stake_hooks";
+// Every stake change fans out to all hooks, so keep the list small
+pub const MAX_STAKE_HOOKS: usize = 10;
+
+// Same shape as the cw20-stake hooks so existing gauges can subscribe unchanged
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StakeChangedHookMsg {
+    Stake { addr: HumanAddr, amount: Uint128 },
+    Unstake { addr: HumanAddr, amount: Uint128 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StakeChangedExecuteMsg {
+    StakeChangeHook(StakeChangedHookMsg),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HooksResponse {
+    pub hooks: Vec<HumanAddr>,
+}
+
+pub fn stake_hooks(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<Vec<HumanAddr>> {
+    Ok(ReadonlySingleton::<Vec<HumanAddr>>::new(storage, STAKE_HOOKS_KEY).may_load()?.unwrap_or_default())
+}
+
+pub fn stake_hook_msgs(storage: &dyn cosmwasm_std::Storage, msg: StakeChangedHookMsg) -> cosmwasm_std::StdResult<Vec<cosmwasm_std::CosmosMsg>> {
+    let payload = cosmwasm_std::to_binary(&StakeChangedExecuteMsg::StakeChangeHook(msg))?;
+    Ok(stake_hooks(storage)?
+        .into_iter()
+        .map(|hook| cosmwasm_std::WasmMsg::Execute { contract_addr: hook.to_string(), msg: payload.clone(), funds: vec![] }.into())
+        .collect())
+}
+
+pub fn add_stake_hook(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    addr: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    let mut hooks = stake_hooks(deps.storage)?;
+    if hooks.contains(&addr) {
+        return Err(cosmwasm_std::StdError::generic_err("Hook already registered"));
+    }
+    if hooks.len() >= MAX_STAKE_HOOKS {
+        return Err(cosmwasm_std::StdError::generic_err(format!("At most {} hooks are allowed", MAX_STAKE_HOOKS)));
+    }
+    hooks.push(addr.clone());
+    Singleton::new(deps.storage, STAKE_HOOKS_KEY).save(&hooks)?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "add_stake_hook").add_attribute("hook", addr))
+}
+
+pub fn remove_stake_hook(
+    deps: cosmwasm_std::DepsMut,
+    _env: cosmwasm_std::Env,
+    info: cosmwasm_std::MessageInfo,
+    addr: HumanAddr,
+) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    assert_owner(deps.storage, &HumanAddr::from(&info.sender))?;
+    let mut hooks = stake_hooks(deps.storage)?;
+    if !hooks.contains(&addr) {
+        return Err(cosmwasm_std::StdError::generic_err("Hook not registered"));
+    }
+    hooks.retain(|h| h != &addr);
+    Singleton::new(deps.storage, STAKE_HOOKS_KEY).save(&hooks)?;
+    Ok(cosmwasm_std::Response::new().add_attribute("action", "remove_stake_hook").add_attribute("hook", addr))
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakingInfoResponse {
+    pub total_staked: Uint128,
+    // Per-block emission currently going to stakers
+    pub emission_per_block: Uint128,
+    // Emissions to stakers over the next year, including decay
+    pub annual_emission: Uint128,
+    // Token rewards waiting for stakers to be distributed to
+    pub undistributed_rewards: Uint128,
+    pub reward_denoms: Vec<String>,
+    // Denoms that can be funded besides those already in `reward_denoms`
+    pub accepted_reward_denoms: Vec<String>,
+    // annual_emission / total_staked, ignores rewards funded by anyone else
+    pub estimated_apr: Decimal,
+}
+
+pub fn query_staking_info(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env) -> cosmwasm_std::StdResult<StakingInfoResponse> {
+    let total_staked = total_staked(deps.storage)?;
+    let schedule = ReadonlySingleton::<EmissionSchedule>::new(deps.storage, EMISSION_KEY).may_load()?.filter(|s| s.to_staking);
+
+    let (emission_per_block, annual_emission) = match schedule {
+        Some(schedule) => {
+            let height = env.block.height;
+            let ahead = EmissionSchedule { last_height: height, ..schedule.clone() };
+            (schedule.current_rate(height), ahead.accrued(height + schedule.blocks_per_year)?)
+        }
+        None => (Uint128::zero(), Uint128::zero()),
+    };
+    let estimated_apr = if total_staked.is_zero() { Decimal::zero() } else { Decimal::from_ratio(annual_emission, total_staked) };
+
+    Ok(StakingInfoResponse {
+        total_staked,
+        emission_per_block,
+        annual_emission,
+        undistributed_rewards: staking_reward_pool(deps.storage)?,
+        reward_denoms: staking_reward_denoms(deps.storage)?,
+        accepted_reward_denoms: accepted_staking_reward_denoms(deps.storage)?,
+        estimated_apr,
+    })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimInfo {
+    pub id: u64,
+    pub amount: Uint128,
+    pub release_at: Expiration,
+    pub matured: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimsResponse {
+    pub claims: Vec<ClaimInfo>,
+}
+
+pub fn query_claims(deps: cosmwasm_std::Deps, env: cosmwasm_std::Env, address: HumanAddr, start_after: Option<u64>, limit: Option<u32>) -> cosmwasm_std::StdResult<ClaimsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|id| (id + 1).to_be_bytes().to_vec());
+
+    let claims = ReadonlyBucket::<UnbondingClaim>::multilevel(deps.storage, &[UNBONDING_PREFIX, address.as_bytes()])
+        .range(start.as_deref(), None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, claim) = item?;
+            Ok(ClaimInfo { id: claim.id, amount: claim.amount, matured: claim.release_at.is_expired(&env), release_at: claim.release_at })
+        })
+        .collect::<cosmwasm_std::StdResult<Vec<_>>>()?;
+
+    Ok(ClaimsResponse { claims })
+}
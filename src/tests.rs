@@ -0,0 +1,2414 @@
+use super::*;
+use cosmwasm_std::testing::{
+    mock_env, mock_ibc_channel_connect_ack, mock_ibc_channel_open_init, mock_ibc_packet_ack, mock_ibc_packet_recv, mock_ibc_packet_timeout, mock_info, MockApi, MockQuerier, MockStorage,
+};
+use cosmwasm_std::{Addr, CanonicalAddr, OwnedDeps, QuerierResult, RecoverPubkeyError, StdError, VerificationError, WasmQuery};
+use k256::ecdsa::signature::Signer;
+use sha2::Digest;
+
+type TestDeps = OwnedDeps<MockStorage, TestApi, TestQuerier>;
+
+// MockApi pads human addresses into canonical ones, signers need their canonical
+// form to be ripemd160(sha256(pubkey)), so 20 byte addresses are written as hex
+struct TestApi(MockApi);
+
+impl cosmwasm_std::Api for TestApi {
+    fn addr_validate(&self, input: &str) -> cosmwasm_std::StdResult<Addr> {
+        let normalized = self.addr_humanize(&self.addr_canonicalize(input)?)?;
+        if normalized.as_str() != input {
+            return Err(StdError::generic_err("Invalid input: address not normalized"));
+        }
+        Ok(Addr::unchecked(input))
+    }
+
+    fn addr_canonicalize(&self, input: &str) -> cosmwasm_std::StdResult<CanonicalAddr> {
+        match hex::decode(input) {
+            Ok(bytes) if bytes.len() == 20 => Ok(bytes.into()),
+            _ => self.0.addr_canonicalize(input),
+        }
+    }
+
+    fn addr_humanize(&self, canonical: &CanonicalAddr) -> cosmwasm_std::StdResult<Addr> {
+        if canonical.len() == 20 {
+            return Ok(Addr::unchecked(hex::encode(canonical.as_slice())));
+        }
+        self.0.addr_humanize(canonical)
+    }
+
+    fn secp256k1_verify(&self, message_hash: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool, VerificationError> {
+        self.0.secp256k1_verify(message_hash, signature, public_key)
+    }
+
+    fn secp256k1_recover_pubkey(&self, message_hash: &[u8], signature: &[u8], recovery_param: u8) -> Result<Vec<u8>, RecoverPubkeyError> {
+        self.0.secp256k1_recover_pubkey(message_hash, signature, recovery_param)
+    }
+
+    fn ed25519_verify(&self, message: &[u8], signature: &[u8], public_key: &[u8]) -> Result<bool, VerificationError> {
+        self.0.ed25519_verify(message, signature, public_key)
+    }
+
+    fn ed25519_batch_verify(&self, messages: &[&[u8]], signatures: &[&[u8]], public_keys: &[&[u8]]) -> Result<bool, VerificationError> {
+        self.0.ed25519_batch_verify(messages, signatures, public_keys)
+    }
+
+    fn debug(&self, message: &str) {
+        self.0.debug(message)
+    }
+}
+
+struct Key(k256::ecdsa::SigningKey);
+
+impl Key {
+    fn new(seed: u8) -> Self {
+        Key(k256::ecdsa::SigningKey::from_bytes(&[seed; 32]).unwrap())
+    }
+
+    fn pubkey(&self) -> cosmwasm_std::Binary {
+        cosmwasm_std::Binary::from(self.0.verifying_key().to_bytes().as_slice())
+    }
+
+    fn address(&self) -> HumanAddr {
+        HumanAddr::from(hex::encode(ripemd160::Ripemd160::digest(&sha2::Sha256::digest(self.pubkey().as_slice()))))
+    }
+
+    // Signs the sha256 of the JSON encoding, as the contract verifies it
+    fn sign<T: Serialize>(&self, payload: &T) -> cosmwasm_std::Binary {
+        let signature: k256::ecdsa::Signature = self.0.sign(&cosmwasm_std::to_vec(payload).unwrap());
+        cosmwasm_std::Binary::from(signature.as_ref())
+    }
+}
+
+// MockQuerier can't answer wasm queries, so smart queries go to a handler set per test
+struct TestQuerier {
+    base: MockQuerier,
+    wasm: Box<dyn Fn(&WasmQuery) -> QuerierResult>,
+}
+
+impl cosmwasm_std::Querier for TestQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        match cosmwasm_std::from_slice(bin_request) {
+            Ok(cosmwasm_std::QueryRequest::<cosmwasm_std::Empty>::Wasm(query)) => (self.wasm)(&query),
+            _ => self.base.raw_query(bin_request),
+        }
+    }
+}
+
+fn on_smart_query<T: Serialize>(deps: &mut TestDeps, handler: impl Fn(&cosmwasm_std::Binary) -> T + 'static) {
+    deps.querier.wasm = Box::new(move |query| match query {
+        WasmQuery::Smart { msg, .. } => cosmwasm_std::SystemResult::Ok(cosmwasm_std::ContractResult::Ok(cosmwasm_std::to_binary(&handler(msg)).unwrap())),
+        _ => panic!("unexpected wasm query"),
+    });
+}
+
+const OWNER: &str = "owner";
+
+fn instantiate_msg(holders: &[(&str, u128)]) -> InstantiateMsg {
+    let initial_balances = holders.iter().map(|(address, amount)| InitialBalance { address: HumanAddr::from(*address), amount: Uint128::from(*amount), vesting: None }).collect();
+    InstantiateMsg { initial_balances, max_burn_tax_bps: None, cap: None, treasury: None, tokenfactory_subdenom: None }
+}
+
+fn setup_with(msg: InstantiateMsg) -> TestDeps {
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: TestApi(MockApi::default()),
+        querier: TestQuerier { base: MockQuerier::new(&[]), wasm: Box::new(|_| panic!("unexpected wasm query")) },
+    };
+    instantiate(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), msg).unwrap();
+    deps
+}
+
+fn setup(holders: &[(&str, u128)]) -> TestDeps {
+    setup_with(instantiate_msg(holders))
+}
+
+fn assert_err<T: std::fmt::Debug>(res: cosmwasm_std::StdResult<T>, expected: &str) {
+    match res {
+        Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, expected),
+        other => panic!("expected error {:?}, got {:?}", expected, other),
+    }
+}
+
+fn exec(deps: &mut TestDeps, sender: &str, msg: ExecuteMsg) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+    execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg)
+}
+
+fn query_as<T: serde::de::DeserializeOwned>(deps: &TestDeps, msg: QueryMsg) -> T {
+    cosmwasm_std::from_binary(&query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+}
+
+fn attr(res: &cosmwasm_std::Response, key: &str) -> String {
+    res.attributes.iter().find(|a| a.key == key).map(|a| a.value.clone()).unwrap()
+}
+
+fn balance(deps: &TestDeps, address: &str) -> u128 {
+    balance_of(&deps.storage, &HumanAddr::from(address)).unwrap().u128()
+}
+
+#[test]
+fn transfer_moves_balance() {
+    let mut deps = setup(&[("alice", 100)]);
+    transfer(deps.as_mut(), mock_env(), mock_info("alice", &[]), HumanAddr::from("bob"), Uint128::from(30u128), None).unwrap();
+    assert_eq!(balance(&deps, "alice"), 70);
+    assert_eq!(balance(&deps, "bob"), 30);
+    let res = transfer(deps.as_mut(), mock_env(), mock_info("alice", &[]), HumanAddr::from("bob"), Uint128::from(71u128), None);
+    assert_err(res, "Insufficient balance");
+}
+
+#[test]
+fn transfer_from_spends_allowance() {
+    let mut deps = setup(&[("alice", 100)]);
+    approve(deps.as_mut(), mock_env(), mock_info("alice", &[]), HumanAddr::from("spender"), Uint128::from(40u128)).unwrap();
+    transfer_from(deps.as_mut(), mock_env(), mock_info("spender", &[]), HumanAddr::from("alice"), HumanAddr::from("bob"), Uint128::from(25u128), None).unwrap();
+    assert_eq!(balance(&deps, "bob"), 25);
+    let res = transfer_from(deps.as_mut(), mock_env(), mock_info("spender", &[]), HumanAddr::from("alice"), HumanAddr::from("bob"), Uint128::from(25u128), None);
+    assert_err(res, "Insufficient allowance");
+}
+
+#[test]
+fn only_owner_mints_and_pause_stops_minting() {
+    let mut deps = setup(&[]);
+    let res = mint(deps.as_mut(), mock_env(), mock_info("alice", &[]), HumanAddr::from("alice"), Uint128::from(10u128));
+    assert_err(res, "Unauthorized");
+    mint(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), HumanAddr::from("alice"), Uint128::from(10u128)).unwrap();
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::from(10u128));
+
+    pause(deps.as_mut(), mock_env(), mock_info(OWNER, &[])).unwrap();
+    let res = mint(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), HumanAddr::from("alice"), Uint128::from(10u128));
+    assert_err(res, "Contract is paused");
+}
+
+#[test]
+fn config_reports_owner_and_pause_state() {
+    let mut deps = setup(&[]);
+    let config: ConfigResponse = cosmwasm_std::from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert_eq!((config.owner, config.paused), (HumanAddr::from(OWNER), false));
+    assert!(config.modules.is_empty());
+
+    pause(deps.as_mut(), mock_env(), mock_info(OWNER, &[])).unwrap();
+    let config: ConfigResponse = cosmwasm_std::from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::Config {}).unwrap()).unwrap();
+    assert!(config.paused);
+}
+
+#[test]
+fn blacklisted_addresses_cannot_send_or_receive() {
+    let mut deps = setup(&[("alice", 100), ("bob", 100)]);
+    let msg = ExecuteMsg::Blacklist { address: HumanAddr::from("bob") };
+    assert_err(execute(deps.as_mut(), mock_env(), mock_info("mallory", &[]), msg.clone()), "Unauthorized");
+    execute(deps.as_mut(), mock_env(), mock_info(OWNER, &[]), ExecuteMsg::SetBlacklister { blacklister: HumanAddr::from("compliance") }).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("compliance", &[]), msg).unwrap();
+
+    let send = ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(1u128), memo: None };
+    assert_err(execute(deps.as_mut(), mock_env(), mock_info("alice", &[]), send), "Address bob is blacklisted");
+    let send = ExecuteMsg::Transfer { recipient: HumanAddr::from("alice"), amount: Uint128::from(1u128), memo: None };
+    assert_err(execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), send.clone()), "Address bob is blacklisted");
+
+    let res: IsBlacklistedResponse = cosmwasm_std::from_binary(&query(deps.as_ref(), mock_env(), QueryMsg::IsBlacklisted { address: HumanAddr::from("bob") }).unwrap()).unwrap();
+    assert!(res.blacklisted);
+    execute(deps.as_mut(), mock_env(), mock_info("compliance", &[]), ExecuteMsg::Unblacklist { address: HumanAddr::from("bob") }).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("bob", &[]), send).unwrap();
+}
+
+#[test]
+fn whitelist_mode_restricts_transfers_to_listed_addresses() {
+    let mut deps = setup(&[("alice", 100)]);
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::SetWhitelistMode { enabled: true }), "Unauthorized");
+    exec(&mut deps, OWNER, ExecuteMsg::SetWhitelistMode { enabled: true }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::AddToWhitelist { address: HumanAddr::from("alice") }).unwrap();
+
+    let send = ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(5u128), memo: None };
+    assert_err(exec(&mut deps, "alice", send.clone()), "Transfers are restricted to whitelisted addresses");
+    exec(&mut deps, OWNER, ExecuteMsg::AddToWhitelist { address: HumanAddr::from("bob") }).unwrap();
+    exec(&mut deps, "alice", send).unwrap();
+
+    let list: WhitelistResponse = query_as(&deps, QueryMsg::Whitelist { start_after: Some(HumanAddr::from("alice")), limit: None });
+    assert_eq!(list, WhitelistResponse { enabled: true, addresses: vec![HumanAddr::from("bob")] });
+}
+
+#[test]
+fn frozen_account_cannot_send_until_unfrozen() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::FreezeAccount { address: HumanAddr::from("alice") }).unwrap();
+    let frozen: IsFrozenResponse = query_as(&deps, QueryMsg::IsFrozen { address: HumanAddr::from("alice") });
+    assert!(frozen.frozen);
+
+    let send = ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(5u128), memo: None };
+    assert_err(exec(&mut deps, "alice", send.clone()), "Account alice is frozen");
+    exec(&mut deps, OWNER, ExecuteMsg::UnfreezeAccount { address: HumanAddr::from("alice") }).unwrap();
+    exec(&mut deps, "alice", send).unwrap();
+    assert_eq!(balance(&deps, "bob"), 5);
+}
+
+#[test]
+fn compliance_officer_force_transfers_with_reason() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetComplianceOfficer { officer: HumanAddr::from("officer") }).unwrap();
+    let force = |reason: &str| ExecuteMsg::ForceTransfer {
+        from: HumanAddr::from("alice"),
+        to: HumanAddr::from("bob"),
+        amount: Uint128::from(40u128),
+        reason: reason.to_string(),
+    };
+    assert_err(exec(&mut deps, "bob", force("court order")), "Unauthorized");
+    assert_err(exec(&mut deps, "officer", force("")), "A reason is required for forced transfers");
+    let from_contract = ExecuteMsg::ForceTransfer { from: HumanAddr::from(mock_env().contract.address.as_str()), to: HumanAddr::from("bob"), amount: Uint128::from(1u128), reason: "fees".to_string() };
+    assert_err(exec(&mut deps, "officer", from_contract), "Tokens held by the token contract can't be force transferred");
+
+    let res = exec(&mut deps, "officer", force("court order")).unwrap();
+    assert!(res.events[0].attributes.iter().any(|a| a.key == "reason" && a.value == "court order"));
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "bob")), (60, 40));
+}
+
+#[test]
+fn restriction_contract_can_reject_transfers() {
+    let mut deps = setup(&[("alice", 100)]);
+    on_smart_query(&mut deps, |msg| {
+        let RestrictionQueryMsg::DetectTransferRestriction { amount, .. } = cosmwasm_std::from_binary(msg).unwrap();
+        if amount.u128() > 10 {
+            RestrictionResponse { code: 3, message: Some("too large".to_string()) }
+        } else {
+            RestrictionResponse { code: 0, message: None }
+        }
+    });
+    exec(&mut deps, OWNER, ExecuteMsg::SetRestrictionContract { contract: Some(HumanAddr::from("rules")) }).unwrap();
+
+    let send = |amount: u128| ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount), memo: None };
+    assert_err(exec(&mut deps, "alice", send(20)), "Restriction code 3: too large");
+    exec(&mut deps, "alice", send(10)).unwrap();
+    assert_eq!(balance(&deps, "bob"), 10);
+}
+
+#[test]
+fn kyc_registry_gates_recipients_and_mints() {
+    let mut deps = setup(&[("alice", 100)]);
+    on_smart_query(&mut deps, |msg| {
+        let KycQueryMsg::IsVerified { address } = cosmwasm_std::from_binary(msg).unwrap();
+        KycVerifiedResponse { verified: address.as_str() != "mallory" }
+    });
+    let config = KycConfig { registry: HumanAddr::from("kyc"), policy: KycPolicy::RecipientOnly };
+    exec(&mut deps, OWNER, ExecuteMsg::SetKycRegistry { config: Some(config) }).unwrap();
+
+    let send = |to: &str| ExecuteMsg::Transfer { recipient: HumanAddr::from(to), amount: Uint128::from(5u128), memo: None };
+    assert_err(exec(&mut deps, "alice", send("mallory")), "Address mallory is not KYC verified");
+    exec(&mut deps, "alice", send("bob")).unwrap();
+    let mint = ExecuteMsg::Mint { recipient: HumanAddr::from("mallory"), amount: Uint128::from(5u128) };
+    assert_err(exec(&mut deps, OWNER, mint), "Address mallory is not KYC verified");
+}
+
+#[test]
+fn sanctions_oracle_blocks_listed_parties_while_enabled() {
+    let mut deps = setup(&[("alice", 100)]);
+    on_smart_query(&mut deps, |msg| {
+        let SanctionsQueryMsg::IsSanctioned { address } = cosmwasm_std::from_binary(msg).unwrap();
+        SanctionedResponse { sanctioned: address.as_str() == "mallory" }
+    });
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::UpdateSanctionsConfig { oracle: None, enabled: Some(true) }), "No sanctions oracle configured");
+    exec(&mut deps, OWNER, ExecuteMsg::UpdateSanctionsConfig { oracle: Some(HumanAddr::from("oracle")), enabled: Some(true) }).unwrap();
+
+    let send = ExecuteMsg::Transfer { recipient: HumanAddr::from("mallory"), amount: Uint128::from(5u128), memo: None };
+    assert_err(exec(&mut deps, "alice", send.clone()), "Address mallory is on the sanctions list");
+    exec(&mut deps, OWNER, ExecuteMsg::UpdateSanctionsConfig { oracle: None, enabled: Some(false) }).unwrap();
+    exec(&mut deps, "alice", send).unwrap();
+}
+
+#[test]
+fn max_wallet_balance_caps_recipients_unless_exempt() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetMaxWalletBalance { max: Some(Uint128::from(20u128)) }).unwrap();
+    let send = |amount: u128| ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount), memo: None };
+    exec(&mut deps, "alice", send(20)).unwrap();
+    assert_err(exec(&mut deps, "alice", send(1)), "Balance of bob would exceed the maximum wallet balance of 20");
+
+    exec(&mut deps, OWNER, ExecuteMsg::SetLimitExemption { limit: LimitKind::MaxWallet, address: HumanAddr::from("bob"), exempt: true }).unwrap();
+    exec(&mut deps, "alice", send(1)).unwrap();
+    assert_eq!(balance(&deps, "bob"), 21);
+}
+
+#[test]
+fn max_transaction_amount_applies_per_transfer() {
+    let mut deps = setup(&[("alice", 100), ("treasury", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetMaxTransactionAmount { max: Some(Uint128::from(10u128)) }).unwrap();
+    let send = |amount: u128| ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount), memo: None };
+    assert_err(exec(&mut deps, "alice", send(11)), "Transfer amount exceeds the maximum of 10");
+    exec(&mut deps, "alice", send(10)).unwrap();
+
+    exec(&mut deps, OWNER, ExecuteMsg::SetLimitExemption { limit: LimitKind::MaxTransaction, address: HumanAddr::from("treasury"), exempt: true }).unwrap();
+    exec(&mut deps, "treasury", send(50)).unwrap();
+    assert_eq!(balance(&deps, "bob"), 60);
+
+    let to_contract = ExecuteMsg::Send { contract: HumanAddr::from("vault"), amount: Uint128::from(11u128), msg: cosmwasm_std::Binary::default(), memo: None };
+    assert_err(exec(&mut deps, "alice", to_contract), "Transfer amount exceeds the maximum of 10");
+}
+
+#[test]
+fn daily_limit_caps_outflow_per_epoch() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetDailyLimit { limit: Some(DailyLimit { limit: Uint128::from(10u128), epoch_seconds: 86400 }) }).unwrap();
+    let send = |amount: u128| ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount), memo: None };
+    exec(&mut deps, "alice", send(6)).unwrap();
+    assert_err(exec(&mut deps, "alice", send(5)), "Daily transfer limit exceeded");
+    let quota: RemainingQuotaResponse = query_as(&deps, QueryMsg::RemainingQuota { address: HumanAddr::from("alice") });
+    assert_eq!(quota.remaining, Some(Uint128::from(4u128)));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(86400);
+    execute(deps.as_mut(), env, mock_info("alice", &[]), send(10)).unwrap();
+    assert_eq!(balance(&deps, "bob"), 16);
+}
+
+#[test]
+fn circuit_breaker_pauses_after_window_threshold() {
+    let mut deps = setup(&[("alice", 1000)]);
+    let breaker = CircuitBreaker { window_blocks: 100, max_supply_bps: 1000 };
+    exec(&mut deps, OWNER, ExecuteMsg::SetCircuitBreaker { breaker: Some(breaker) }).unwrap();
+    let send = |amount: u128| ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount), memo: None };
+    exec(&mut deps, "alice", send(100)).unwrap();
+
+    let res = exec(&mut deps, "alice", send(1)).unwrap();
+    assert!(res.events.iter().any(|e| e.ty == "circuit_breaker"));
+    assert_err(exec(&mut deps, "alice", send(1)), "Contract is paused");
+    assert_eq!(balance(&deps, "bob"), 101);
+}
+
+#[test]
+fn transfer_cooldown_spaces_out_sends() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetTransferCooldown { cooldown: Some(Duration::Height(5)) }).unwrap();
+    let send = ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(1u128), memo: None };
+    exec(&mut deps, "alice", send.clone()).unwrap();
+    assert_err(exec(&mut deps, "alice", send.clone()), "Transfer cooldown has not elapsed");
+
+    let mut env = mock_env();
+    env.block.height += 5;
+    execute(deps.as_mut(), env, mock_info("alice", &[]), send).unwrap();
+    assert_eq!(balance(&deps, "bob"), 2);
+}
+
+#[test]
+fn launch_mode_blocks_trading_until_enabled() {
+    let mut deps = setup(&[("alice", 100), ("team", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetLaunchConfig { launch_height: None }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetLimitExemption { limit: LimitKind::Launch, address: HumanAddr::from("team"), exempt: true }).unwrap();
+    let send = ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(1u128), memo: None };
+    assert_err(exec(&mut deps, "alice", send.clone()), "Trading is not enabled yet");
+    exec(&mut deps, "team", send.clone()).unwrap();
+
+    exec(&mut deps, OWNER, ExecuteMsg::EnableTrading {}).unwrap();
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::SetLaunchConfig { launch_height: Some(1) }), "Trading is already enabled");
+    exec(&mut deps, "alice", send).unwrap();
+    assert_eq!(balance(&deps, "bob"), 2);
+}
+
+#[test]
+fn transfers_to_the_contract_are_rejected_and_stuck_tokens_recoverable() {
+    let contract = mock_env().contract.address;
+    let mut deps = setup(&[("alice", 100), (contract.as_str(), 50)]);
+    let send = ExecuteMsg::Transfer { recipient: HumanAddr::from(contract.as_str()), amount: Uint128::from(1u128), memo: None };
+    assert_err(exec(&mut deps, "alice", send), "Cannot transfer to the token contract");
+
+    let recover = |amount: u128| ExecuteMsg::RecoverStuckTokens { recipient: HumanAddr::from("alice"), amount: Some(Uint128::from(amount)) };
+    assert_err(exec(&mut deps, OWNER, recover(51)), "Only 50 tokens are recoverable");
+    exec(&mut deps, OWNER, recover(50)).unwrap();
+    assert_eq!(balance(&deps, "alice"), 150);
+}
+
+#[test]
+fn clawbacks_wait_out_the_delay_and_can_be_vetoed() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetComplianceOfficer { officer: HumanAddr::from("officer") }).unwrap();
+    let config = ClawbackConfig { delay: Duration::Height(10), vetoer: HumanAddr::from("council"), recipient: HumanAddr::from("recovery") };
+    exec(&mut deps, OWNER, ExecuteMsg::SetClawbackConfig { config }).unwrap();
+    let clawback = |amount: u128| ExecuteMsg::Clawback { from: HumanAddr::from("alice"), amount: Uint128::from(amount), reason: "fraud".to_string() };
+    let from_contract = ExecuteMsg::Clawback { from: HumanAddr::from(mock_env().contract.address.as_str()), amount: Uint128::from(1u128), reason: "fees".to_string() };
+    assert_err(exec(&mut deps, "officer", from_contract), "Tokens held by the token contract can't be clawed back");
+    exec(&mut deps, "officer", clawback(30)).unwrap();
+    exec(&mut deps, "officer", clawback(20)).unwrap();
+    let listed: ClawbacksResponse = query_as(&deps, QueryMsg::Clawbacks { start_after: None, limit: None });
+    let (first, second) = (listed.clawbacks[0].id, listed.clawbacks[1].id);
+
+    assert_err(exec(&mut deps, "officer", ExecuteMsg::ExecuteClawback { id: first }), "Clawback delay has not elapsed");
+    assert_err(exec(&mut deps, "officer", ExecuteMsg::VetoClawback { id: second }), "Unauthorized");
+    exec(&mut deps, "council", ExecuteMsg::VetoClawback { id: second }).unwrap();
+
+    let mut env = mock_env();
+    env.block.height += 10;
+    execute(deps.as_mut(), env.clone(), mock_info("officer", &[]), ExecuteMsg::ExecuteClawback { id: first }).unwrap();
+    let res = execute(deps.as_mut(), env, mock_info("officer", &[]), ExecuteMsg::ExecuteClawback { id: second });
+    assert_err(res, "Clawback is not pending");
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "recovery")), (70, 30));
+}
+
+#[test]
+fn large_transfers_must_be_announced_and_wait_out_the_delay() {
+    let mut deps = setup(&[("alice", 1000)]);
+    let config = LargeTransferConfig { threshold: Uint128::from(100u128), delay: Duration::Height(10) };
+    exec(&mut deps, OWNER, ExecuteMsg::SetLargeTransferConfig { config: Some(config) }).unwrap();
+    let send = ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(500u128), memo: None };
+    assert_err(exec(&mut deps, "alice", send), "Transfers above the threshold must be announced first");
+
+    let res = exec(&mut deps, "alice", ExecuteMsg::AnnounceTransfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(500u128) }).unwrap();
+    let id: u64 = attr(&res, "id").parse().unwrap();
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::ExecuteAnnouncedTransfer { id }), "Announcement delay has not elapsed");
+    assert_err(exec(&mut deps, "bob", ExecuteMsg::CancelAnnouncedTransfer { id }), "Unauthorized");
+    // Turning the config off doesn't shorten the wait of an earlier announcement
+    exec(&mut deps, OWNER, ExecuteMsg::SetLargeTransferConfig { config: None }).unwrap();
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::ExecuteAnnouncedTransfer { id }), "Announcement delay has not elapsed");
+
+    let mut env = mock_env();
+    env.block.height += 10;
+    execute(deps.as_mut(), env, mock_info("alice", &[]), ExecuteMsg::ExecuteAnnouncedTransfer { id }).unwrap();
+    assert_eq!(balance(&deps, "bob"), 500);
+}
+
+#[test]
+fn holders_can_lock_their_own_account_until_expiration() {
+    let mut deps = setup(&[("alice", 100)]);
+    let height = mock_env().block.height;
+    exec(&mut deps, "alice", ExecuteMsg::LockMyAccount { until: Expiration::AtHeight(height + 10) }).unwrap();
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::LockMyAccount { until: Expiration::AtHeight(height + 5) }), "An active lock can only be extended");
+
+    let send = ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(1u128), memo: None };
+    assert_err(exec(&mut deps, "alice", send.clone()), "Account alice is locked by its owner");
+    let mut env = mock_env();
+    env.block.height += 10;
+    execute(deps.as_mut(), env, mock_info("alice", &[]), send).unwrap();
+}
+
+#[test]
+fn beneficiary_claims_balance_after_inactivity() {
+    let mut deps = setup(&[("alice", 100)]);
+    let designate = |to: &str| ExecuteMsg::SetBeneficiary { beneficiary: HumanAddr::from(to), inactivity_period: Duration::Height(100) };
+    assert_err(exec(&mut deps, "alice", designate("alice")), "Cannot designate yourself as beneficiary");
+    exec(&mut deps, "alice", designate("heir")).unwrap();
+
+    let claim = ExecuteMsg::ClaimInheritance { from: HumanAddr::from("alice") };
+    assert_err(exec(&mut deps, "heir", claim.clone()), "Account has not been inactive long enough");
+    let mut env = mock_env();
+    env.block.height += 100;
+    assert_err(execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), claim.clone()), "Unauthorized");
+    execute(deps.as_mut(), env, mock_info("heir", &[]), claim).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "heir")), (0, 100));
+}
+
+#[test]
+fn inheritance_leaves_locked_tokens_until_they_unlock() {
+    let mut deps = setup(&[(OWNER, 60), ("bob", 40)]);
+    let unlock_time = mock_env().block.time.seconds() + 1000;
+    exec(&mut deps, OWNER, ExecuteMsg::TransferLocked { recipient: HumanAddr::from("alice"), amount: Uint128::from(60u128), unlock_time }).unwrap();
+    exec(&mut deps, "bob", ExecuteMsg::Transfer { recipient: HumanAddr::from("alice"), amount: Uint128::from(40u128), memo: None }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::SetBeneficiary { beneficiary: HumanAddr::from("heir"), inactivity_period: Duration::Height(100) }).unwrap();
+
+    let claim = ExecuteMsg::ClaimInheritance { from: HumanAddr::from("alice") };
+    let mut env = mock_env();
+    env.block.height += 100;
+    execute(deps.as_mut(), env.clone(), mock_info("heir", &[]), claim.clone()).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "heir")), (60, 40));
+    assert_err(execute(deps.as_mut(), env.clone(), mock_info("heir", &[]), claim.clone()), "Nothing unlocked to claim");
+
+    env.block.time = env.block.time.plus_seconds(1000);
+    execute(deps.as_mut(), env, mock_info("heir", &[]), claim).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "heir")), (0, 100));
+}
+
+#[test]
+fn transfer_fee_is_credited_to_the_collector() {
+    let mut deps = setup(&[("alice", 1000)]);
+    let config = |bps: u64| ExecuteMsg::SetFeeConfig { config: Some(FeeConfig { bps, collector: HumanAddr::from("treasury"), tiers: vec![], splits: vec![] }) };
+    assert_err(exec(&mut deps, OWNER, config(10_001)), "Fee cannot exceed 100%");
+    exec(&mut deps, OWNER, config(250)).unwrap();
+
+    let quote: FeeQuoteResponse = query_as(&deps, QueryMsg::FeeQuote { amount: Uint128::from(400u128) });
+    assert_eq!((quote.fee, quote.net), (Uint128::from(10u128), Uint128::from(390u128)));
+    exec(&mut deps, "alice", ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(400u128), memo: None }).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "bob"), balance(&deps, "treasury")), (600, 390, 10));
+}
+
+#[test]
+fn burn_tax_is_bounded_by_the_instantiation_maximum() {
+    let mut deps = setup_with(InstantiateMsg { max_burn_tax_bps: Some(500), ..instantiate_msg(&[("alice", 1000)]) });
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::SetBurnTax { bps: 501, enabled: true }), "Burn tax cannot exceed 500 bps");
+    exec(&mut deps, OWNER, ExecuteMsg::SetBurnTax { bps: 100, enabled: true }).unwrap();
+
+    exec(&mut deps, "alice", ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(500u128), memo: None }).unwrap();
+    assert_eq!(balance(&deps, "bob"), 495);
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::from(995u128));
+}
+
+#[test]
+fn fee_tiers_pick_the_highest_matching_minimum() {
+    let mut deps = setup(&[("alice", 10_000)]);
+    let tiers = vec![
+        FeeTier { min_amount: Uint128::from(1000u128), bps: 50 },
+        FeeTier { min_amount: Uint128::from(5000u128), bps: 10 },
+    ];
+    exec(&mut deps, OWNER, ExecuteMsg::SetFeeConfig { config: Some(FeeConfig { bps: 100, collector: HumanAddr::from("treasury"), tiers, splits: vec![] }) }).unwrap();
+
+    let fee = |amount: u128| query_as::<FeeQuoteResponse>(&deps, QueryMsg::FeeQuote { amount: Uint128::from(amount) }).fee.u128();
+    assert_eq!((fee(500), fee(2000), fee(6000)), (5, 10, 6));
+}
+
+#[test]
+fn fee_splits_pay_weighted_recipients_and_burn() {
+    let mut deps = setup(&[("alice", 10_000)]);
+    let splits = vec![
+        FeeSplit { recipient: FeeRecipient::Address(HumanAddr::from("dev")), weight: 3 },
+        FeeSplit { recipient: FeeRecipient::Burn {}, weight: 1 },
+    ];
+    let config = FeeConfig { bps: 100, collector: HumanAddr::from("treasury"), tiers: vec![], splits };
+    exec(&mut deps, OWNER, ExecuteMsg::SetFeeConfig { config: Some(config) }).unwrap();
+
+    exec(&mut deps, "alice", ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(4000u128), memo: None }).unwrap();
+    assert_eq!((balance(&deps, "bob"), balance(&deps, "dev"), balance(&deps, "treasury")), (3960, 30, 0));
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::from(9990u128));
+}
+
+#[test]
+fn reflection_redistributes_the_fee_to_holders() {
+    let mut deps = setup(&[("alice", 900), ("carol", 100)]);
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::SetReflection { bps: Some(10_001) }), "Reflection rate cannot exceed 100%");
+    exec(&mut deps, OWNER, ExecuteMsg::SetReflection { bps: Some(1000) }).unwrap();
+
+    exec(&mut deps, "alice", ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(500u128), memo: None }).unwrap();
+    let (alice, bob, carol) = (balance(&deps, "alice"), balance(&deps, "bob"), balance(&deps, "carol"));
+    assert_eq!((alice, bob, carol), (444, 450, 105));
+    assert!(alice + bob + carol <= 1000 && alice + bob + carol >= 998);
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::from(1000u128));
+}
+
+#[test]
+fn dividends_are_shared_pro_rata_and_paid_on_claim() {
+    let mut deps = setup(&[("alice", 300), ("bob", 100)]);
+    assert_err(exec(&mut deps, "payer", ExecuteMsg::DepositDividends {}), "No funds sent");
+    let funds = cosmwasm_std::coins(100, "uatom");
+    assert_err(execute(deps.as_mut(), mock_env(), mock_info("payer", &funds), ExecuteMsg::DepositDividends {}), "Denom uatom is not accepted");
+    assert_err(exec(&mut deps, "payer", ExecuteMsg::SetDividendDenoms { denoms: vec!["uatom".to_string()] }), "Unauthorized");
+    exec(&mut deps, OWNER, ExecuteMsg::SetDividendDenoms { denoms: vec!["uatom".to_string()] }).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("payer", &funds), ExecuteMsg::DepositDividends {}).unwrap();
+
+    let pending: PendingDividendsResponse = query_as(&deps, QueryMsg::PendingDividends { address: HumanAddr::from("alice") });
+    assert_eq!(pending.dividends, cosmwasm_std::coins(75, "uatom"));
+    let res = exec(&mut deps, "alice", ExecuteMsg::ClaimDividends {}).unwrap();
+    let payout = cosmwasm_std::BankMsg::Send { to_address: "alice".to_string(), amount: cosmwasm_std::coins(75, "uatom") };
+    assert_eq!(res.messages[0].msg, cosmwasm_std::CosmosMsg::Bank(payout));
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::ClaimDividends {}), "No dividends to claim");
+}
+
+#[test]
+fn dividends_leave_out_the_contract_balance() {
+    let mut deps = setup(&[("alice", 300), ("bob", 100)]);
+    let expires = Expiration::AtHeight(mock_env().block.height + 10);
+    exec(&mut deps, "bob", ExecuteMsg::TransferPending { recipient: HumanAddr::from("carol"), amount: Uint128::from(100u128), expires }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetDividendDenoms { denoms: vec!["uatom".to_string()] }).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("payer", &cosmwasm_std::coins(90, "uatom")), ExecuteMsg::DepositDividends {}).unwrap();
+
+    let pending: PendingDividendsResponse = query_as(&deps, QueryMsg::PendingDividends { address: HumanAddr::from("alice") });
+    assert_eq!(pending.dividends, cosmwasm_std::coins(90, "uatom"));
+    let contract = mock_env().contract.address;
+    let pending: PendingDividendsResponse = query_as(&deps, QueryMsg::PendingDividends { address: HumanAddr::from(contract.as_str()) });
+    assert!(pending.dividends.is_empty());
+    assert_err(exec(&mut deps, contract.as_str(), ExecuteMsg::ClaimDividends {}), "The token contract doesn't earn dividends");
+}
+
+#[test]
+fn cw20_rewards_received_are_distributed_to_holders() {
+    let contract = mock_env().contract.address;
+    let mut deps = setup(&[("alice", 300), ("bob", 100)]);
+    let receive = |amount: u128| ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: HumanAddr::from("sponsor"),
+        amount: Uint128::from(amount),
+        msg: cosmwasm_std::to_binary(&ReceiveMsg::DistributeRewards {}).unwrap(),
+    });
+    assert_err(exec(&mut deps, contract.as_str(), receive(40)), "Cannot receive this token");
+    assert_err(exec(&mut deps, "reward_token", receive(40)), "Denom cw20:reward_token is not accepted");
+    exec(&mut deps, OWNER, ExecuteMsg::SetDividendDenoms { denoms: vec!["cw20:reward_token".to_string()] }).unwrap();
+    exec(&mut deps, "reward_token", receive(40)).unwrap();
+
+    let res = exec(&mut deps, "bob", ExecuteMsg::ClaimDividends {}).unwrap();
+    let payout = cosmwasm_std::WasmMsg::Execute {
+        contract_addr: "reward_token".to_string(),
+        msg: cosmwasm_std::to_binary(&Cw20ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(10u128) }).unwrap(),
+        funds: vec![],
+    };
+    assert_eq!(res.messages[0].msg, cosmwasm_std::CosmosMsg::Wasm(payout));
+}
+
+#[test]
+fn cw20_rewards_leave_out_the_contract_balance() {
+    let mut deps = setup(&[("alice", 300), ("bob", 100)]);
+    let expires = Expiration::AtHeight(mock_env().block.height + 10);
+    exec(&mut deps, "bob", ExecuteMsg::TransferPending { recipient: HumanAddr::from("carol"), amount: Uint128::from(100u128), expires }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetDividendDenoms { denoms: vec!["cw20:reward_token".to_string()] }).unwrap();
+    let receive = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: HumanAddr::from("sponsor"),
+        amount: Uint128::from(60u128),
+        msg: cosmwasm_std::to_binary(&ReceiveMsg::DistributeRewards {}).unwrap(),
+    });
+    exec(&mut deps, "reward_token", receive).unwrap();
+
+    let pending: PendingDividendsResponse = query_as(&deps, QueryMsg::PendingDividends { address: HumanAddr::from("alice") });
+    assert_eq!(pending.dividends, cosmwasm_std::coins(60, "cw20:reward_token"));
+}
+
+#[test]
+fn rebase_rescales_every_balance() {
+    let mut deps = setup(&[("alice", 300), ("bob", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetRebaser { rebaser: Some(HumanAddr::from("oracle")) }).unwrap();
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::Rebase { new_supply: Uint128::from(800u128) }), "Unauthorized");
+    assert_err(exec(&mut deps, "oracle", ExecuteMsg::Rebase { new_supply: Uint128::zero() }), "Supply cannot be rebased to zero");
+
+    exec(&mut deps, "oracle", ExecuteMsg::Rebase { new_supply: Uint128::from(800u128) }).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "bob")), (600, 200));
+    exec(&mut deps, "alice", ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(100u128), memo: None }).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "bob")), (500, 300));
+}
+
+#[test]
+fn accrue_grows_balances_by_the_yearly_rate() {
+    let mut deps = setup(&[("alice", 1000)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetInterestRate { rate_bps_per_year: Some(1000) }).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(SECONDS_PER_YEAR);
+    execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::Accrue {}).unwrap();
+    assert_eq!(balance(&deps, "alice"), 1100);
+    let index: InterestIndexResponse = query_as(&deps, QueryMsg::InterestIndex {});
+    assert_eq!(index.index, Decimal::percent(110));
+}
+
+#[test]
+fn supply_cap_limits_minting() {
+    let mut deps = setup_with(InstantiateMsg { cap: Some(Uint128::from(150u128)), ..instantiate_msg(&[("alice", 100)]) });
+    let mint = |amount: u128| ExecuteMsg::Mint { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount) };
+    assert_err(exec(&mut deps, OWNER, mint(51)), "CapExceeded: a supply of 151 would exceed the cap of 150");
+    exec(&mut deps, OWNER, mint(50)).unwrap();
+
+    let minter: MinterResponse = query_as(&deps, QueryMsg::Minter {});
+    assert_eq!((minter.minter, minter.cap), (HumanAddr::from(OWNER), Some(Uint128::from(150u128))));
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::Rebase { new_supply: Uint128::from(151u128) }), "CapExceeded: a supply of 151 would exceed the cap of 150");
+    exec(&mut deps, OWNER, ExecuteMsg::Rebase { new_supply: Uint128::from(140u128) }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetInterestRate { rate_bps_per_year: Some(10_000) }).unwrap();
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(SECONDS_PER_YEAR);
+    assert_err(execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::Accrue {}), "CapExceeded: a supply of 280 would exceed the cap of 150");
+
+    let mut fresh = setup(&[]);
+    let msg = InstantiateMsg { cap: Some(Uint128::from(150u128)), ..instantiate_msg(&[("alice", 151)]) };
+    assert_err(instantiate(fresh.as_mut(), mock_env(), mock_info(OWNER, &[]), msg), "CapExceeded: a supply of 151 would exceed the cap of 150");
+}
+
+#[test]
+fn crank_mints_the_decaying_emission() {
+    let mut deps = setup(&[]);
+    let schedule = EmissionScheduleMsg { per_block: Uint128::from(10u128), decay_bps: 5000, blocks_per_year: 100, target: HumanAddr::from("pool"), to_staking: false };
+    exec(&mut deps, OWNER, ExecuteMsg::SetEmissionSchedule { schedule: Some(schedule) }).unwrap();
+
+    let mut env = mock_env();
+    env.block.height += 150;
+    execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::Crank {}).unwrap();
+    assert_eq!(balance(&deps, "pool"), 1250);
+}
+
+#[test]
+fn crank_burns_a_share_of_the_treasury_each_period() {
+    let allocation = TreasuryAllocation { amount: Uint128::from(1200u128), destination: HumanAddr::from("treasury"), tranches: 12 };
+    let mut deps = setup_with(InstantiateMsg { treasury: Some(allocation), ..instantiate_msg(&[("treasury", 1000), ("alice", 1000)]) });
+    let schedule = |source: &str| ExecuteMsg::SetBurnSchedule { schedule: Some(BurnScheduleMsg { source: HumanAddr::from(source), bps: 1000, period_seconds: 3600 }) };
+    assert_err(exec(&mut deps, OWNER, schedule("alice")), "Burn source must be the treasury or this contract");
+    exec(&mut deps, OWNER, schedule(cosmwasm_std::testing::MOCK_CONTRACT_ADDR)).unwrap();
+    let next: NextScheduledBurnResponse = query_as(&deps, QueryMsg::NextScheduledBurn {});
+    assert_eq!(next.estimated_amount, Uint128::zero());
+    exec(&mut deps, OWNER, schedule("treasury")).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(3600);
+    execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::Crank {}).unwrap();
+    execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::Crank {}).unwrap();
+    assert_eq!(balance(&deps, "treasury"), 900);
+    let next: NextScheduledBurnResponse = query_as(&deps, QueryMsg::NextScheduledBurn {});
+    assert_eq!((next.estimated_amount, next.total_burned), (Uint128::from(90u128), Uint128::from(100u128)));
+}
+
+#[test]
+fn vesting_locks_initial_balances_until_they_vest() {
+    let now = mock_env().block.time.seconds();
+    let mut msg = instantiate_msg(&[("founder", 1000)]);
+    msg.initial_balances[0].vesting = Some(VestingSchedule { start: now, cliff: now + 100, end: now + 1000 });
+    let mut deps = setup_with(msg);
+
+    let send = |amount: u128| ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount), memo: None };
+    assert_err(exec(&mut deps, "founder", send(1)), "Insufficient unlocked balance, 1000 is still locked");
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(500);
+    let breakdown: VestingBreakdownResponse = cosmwasm_std::from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::VestingBreakdown { address: HumanAddr::from("founder") }).unwrap()).unwrap();
+    assert_eq!((breakdown.vested, breakdown.locked), (Uint128::from(500u128), Uint128::from(500u128)));
+    execute(deps.as_mut(), env.clone(), mock_info("founder", &[]), send(500)).unwrap();
+    assert!(execute(deps.as_mut(), env, mock_info("founder", &[]), send(1)).is_err());
+}
+
+#[test]
+fn transfer_locked_keeps_tokens_locked_for_the_recipient() {
+    let now = mock_env().block.time.seconds();
+    let mut deps = setup(&[(OWNER, 100)]);
+    let locked = |unlock_time: u64| ExecuteMsg::TransferLocked { recipient: HumanAddr::from("bob"), amount: Uint128::from(40u128), unlock_time };
+    assert_err(exec(&mut deps, OWNER, locked(now)), "Unlock time must be in the future");
+    exec(&mut deps, OWNER, locked(now + 60)).unwrap();
+
+    let locks: LocksResponse = query_as(&deps, QueryMsg::Locks { address: HumanAddr::from("bob") });
+    assert_eq!(locks.locks, vec![Lockup { amount: Uint128::from(40u128), unlock_time: now + 60, from: HumanAddr::from(OWNER) }]);
+    let send = ExecuteMsg::Transfer { recipient: HumanAddr::from("carol"), amount: Uint128::from(1u128), memo: None };
+    assert!(exec(&mut deps, "bob", send.clone()).is_err());
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(60);
+    execute(deps.as_mut(), env, mock_info("bob", &[]), send).unwrap();
+}
+
+#[test]
+fn transfer_locked_limits_lockups_from_other_senders() {
+    let unlock_time = mock_env().block.time.seconds() + 60;
+    let mut deps = setup(&[("alice", 10_000_000), (OWNER, 10)]);
+    let locked = |amount: u128| ExecuteMsg::TransferLocked { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount), unlock_time };
+    assert_err(exec(&mut deps, "alice", locked(999_999)), "Locked transfers must be at least 1000000");
+    for _ in 0..MAX_LOCKUPS_PER_SENDER {
+        exec(&mut deps, "alice", locked(1_000_000)).unwrap();
+    }
+    assert_err(exec(&mut deps, "alice", locked(1_000_000)), "bob already holds 5 active lockups from alice");
+    exec(&mut deps, OWNER, locked(10)).unwrap();
+}
+
+#[test]
+fn streams_release_linearly_and_cancel_splits_the_rest() {
+    let now = mock_env().block.time.seconds();
+    let mut deps = setup(&[("alice", 1000)]);
+    let create = ExecuteMsg::CreateStream { recipient: HumanAddr::from("bob"), amount: Uint128::from(100u128), start: now, end: now + 100 };
+    let id: u64 = attr(&exec(&mut deps, "alice", create).unwrap(), "id").parse().unwrap();
+    assert_eq!(balance(&deps, "alice"), 900);
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(30);
+    assert_err(execute(deps.as_mut(), env.clone(), mock_info("carol", &[]), ExecuteMsg::WithdrawStream { id }), "Unauthorized");
+    execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), ExecuteMsg::WithdrawStream { id }).unwrap();
+    assert_eq!(balance(&deps, "bob"), 30);
+
+    env.block.time = env.block.time.plus_seconds(20);
+    execute(deps.as_mut(), env, mock_info("alice", &[]), ExecuteMsg::CancelStream { id }).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "bob")), (950, 50));
+    assert_eq!(escrowed_total(&deps.storage).unwrap(), Uint128::zero());
+}
+
+#[test]
+fn arbiter_releases_escrow_and_anyone_refunds_after_expiry() {
+    let height = mock_env().block.height;
+    let mut deps = setup(&[("alice", 100)]);
+    let create = |amount: u128| ExecuteMsg::CreateEscrow {
+        recipient: HumanAddr::from("bob"),
+        arbiter: HumanAddr::from("judge"),
+        amount: Uint128::from(amount),
+        expires: Expiration::AtHeight(height + 10),
+    };
+    let first: u64 = attr(&exec(&mut deps, "alice", create(30)).unwrap(), "id").parse().unwrap();
+    let second: u64 = attr(&exec(&mut deps, "alice", create(20)).unwrap(), "id").parse().unwrap();
+    let listed: EscrowsResponse = query_as(&deps, QueryMsg::Escrows { start_after: Some(first), limit: None });
+    assert_eq!(listed.escrows.iter().map(|e| e.id).collect::<Vec<_>>(), vec![second]);
+
+    assert_err(exec(&mut deps, "bob", ExecuteMsg::ApproveEscrow { id: first }), "Unauthorized");
+    exec(&mut deps, "judge", ExecuteMsg::ApproveEscrow { id: first }).unwrap();
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::RefundEscrow { id: second }), "Escrow has not expired");
+    let mut env = mock_env();
+    env.block.height += 10;
+    execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::RefundEscrow { id: second }).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "bob")), (70, 30));
+}
+
+fn leaf(address: &str, amount: u128) -> [u8; 32] {
+    use sha2::Digest;
+    sha2::Sha256::digest(format!("{}{}", address, amount).as_bytes()).into()
+}
+
+fn hash_pair(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    use sha2::Digest;
+    let mut pair = [a, b];
+    pair.sort_unstable();
+    sha2::Sha256::digest(&pair.concat()).into()
+}
+
+#[test]
+fn merkle_proof_verifies_against_root() {
+    let (a, b, c) = (leaf("alice", 10), leaf("bob", 20), leaf("carol", 30));
+    let root = hex::encode(hash_pair(hash_pair(a, b), c));
+    assert!(verify_merkle_proof(&root, a, &[hex::encode(b), hex::encode(c)]).unwrap());
+    assert!(verify_merkle_proof(&root, c, &[hex::encode(hash_pair(a, b))]).unwrap());
+    assert!(!verify_merkle_proof(&root, leaf("alice", 11), &[hex::encode(b), hex::encode(c)]).unwrap());
+    assert!(!verify_merkle_proof(&root, a, &[hex::encode(c), hex::encode(b)]).unwrap());
+    assert_err(verify_merkle_proof(&root, a, &["zz".to_string()]), "Invalid proof");
+}
+
+#[test]
+fn airdrop_claims_once_with_valid_proof() {
+    let mut deps = setup(&[(OWNER, 100)]);
+    let (a, b) = (leaf("alice", 10), leaf("bob", 20));
+    let msg = ExecuteMsg::RegisterMerkleRoot { merkle_root: hex::encode(hash_pair(a, b)), total_amount: Uint128::from(30u128), expiration: None };
+    exec(&mut deps, OWNER, msg).unwrap();
+
+    let claim = |amount: u128, proof: [u8; 32]| ExecuteMsg::Claim { stage: 1, amount: Uint128::from(amount), proof: vec![hex::encode(proof)] };
+    assert_err(exec(&mut deps, "alice", claim(20, b)), "Invalid proof");
+    exec(&mut deps, "alice", claim(10, b)).unwrap();
+    assert_eq!(balance(&deps, "alice"), 10);
+    assert_err(exec(&mut deps, "alice", claim(10, b)), "Already claimed");
+}
+
+#[test]
+fn batches_move_and_mint_to_every_entry() {
+    let mut deps = setup(&[("alice", 100)]);
+    let batch = |amounts: &[(&str, u128)]| ExecuteMsg::TransferBatch { transfers: amounts.iter().map(|(to, amount)| (HumanAddr::from(*to), Uint128::from(*amount))).collect() };
+    assert_err(exec(&mut deps, "alice", batch(&[])), "Batch must contain between 1 and 500 entries");
+    exec(&mut deps, "alice", batch(&[("bob", 60), ("carol", 40)])).unwrap();
+    assert_eq!((balance(&deps, "bob"), balance(&deps, "carol")), (60, 40));
+
+    let mint = ExecuteMsg::MintBatch { recipients: vec![(HumanAddr::from("bob"), Uint128::from(5u128)), (HumanAddr::from("dave"), Uint128::from(7u128))] };
+    assert_err(exec(&mut deps, "alice", mint.clone()), "Unauthorized");
+    exec(&mut deps, OWNER, mint).unwrap();
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::from(112u128));
+}
+
+#[test]
+fn legacy_tokens_received_are_swapped_and_burned() {
+    let mut deps = setup(&[]);
+    let config = LegacyMigration { legacy_token: HumanAddr::from("old_token"), ratio: Decimal::percent(200), burn_legacy: true, total_migrated: Uint128::zero() };
+    exec(&mut deps, OWNER, ExecuteMsg::SetLegacyMigration { config: Some(config) }).unwrap();
+    let receive = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: HumanAddr::from("alice"),
+        amount: Uint128::from(25u128),
+        msg: cosmwasm_std::to_binary(&ReceiveMsg::Migrate {}).unwrap(),
+    });
+    assert_err(exec(&mut deps, "other_token", receive.clone()), "Unsupported legacy token");
+
+    let res = exec(&mut deps, "old_token", receive).unwrap();
+    assert_eq!(balance(&deps, "alice"), 50);
+    let burn = cosmwasm_std::WasmMsg::Execute {
+        contract_addr: "old_token".to_string(),
+        msg: cosmwasm_std::to_binary(&Cw20ExecuteMsg::Burn { amount: Uint128::from(25u128) }).unwrap(),
+        funds: vec![],
+    };
+    assert_eq!(res.messages[0].msg, cosmwasm_std::CosmosMsg::Wasm(burn));
+}
+
+#[test]
+fn migrate_redenominates_balances_allowances_and_supply() {
+    let mut deps = setup(&[("alice", 30), ("bob", 10)]);
+    exec(&mut deps, "alice", ExecuteMsg::Approve { spender: HumanAddr::from("bob"), amount: Uint128::from(5u128) }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetMaxWalletBalance { max: Some(Uint128::from(30u128)) }).unwrap();
+    let msg = |numerator: u128| MigrateMsg { redenomination: Some(Redenomination { numerator: Uint128::from(numerator), denominator: Uint128::from(1u128), decimals: Some(9) }) };
+    assert_err(migrate(deps.as_mut(), mock_env(), msg(0)), "Invalid redenomination ratio");
+    migrate(deps.as_mut(), mock_env(), msg(1000)).unwrap();
+
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "bob")), (30_000, 10_000));
+    assert_eq!(load_allowance(&deps.storage, &HumanAddr::from("alice"), &HumanAddr::from("bob")).unwrap().allowance, Uint128::from(5000u128));
+    let info = token_info(&deps.storage).unwrap();
+    assert_eq!((info.total_supply, info.decimals), (Uint128::from(40_000u128), 9));
+    assert_eq!(ReadonlySingleton::<Uint128>::new(&deps.storage, MAX_WALLET_KEY).load().unwrap(), Uint128::from(30_000u128));
+
+    let unlock_time = mock_env().block.time.seconds() + 60;
+    // Each pre-split unit is now a share worth 1000
+    let transfer = |amount: u128| ExecuteMsg::Transfer { recipient: HumanAddr::from(OWNER), amount: Uint128::from(amount), memo: None };
+    assert_err(exec(&mut deps, "alice", transfer(1500)), "1500 can't be moved exactly, the nearest amount is 1000");
+    exec(&mut deps, "alice", transfer(2000)).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, OWNER)), (28_000, 2000));
+    exec(&mut deps, OWNER, ExecuteMsg::TransferLocked { recipient: HumanAddr::from("bob"), amount: Uint128::from(1000u128), unlock_time }).unwrap();
+    assert_err(migrate(deps.as_mut(), mock_env(), msg(2)), "Cannot redenominate while these are active: lockups");
+}
+
+#[test]
+fn constant_curve_mints_against_the_reserve_and_pays_back_on_sell() {
+    let mut deps = setup(&[]);
+    let curve = CurveType::Constant { price: Decimal::percent(200) };
+    exec(&mut deps, OWNER, ExecuteMsg::SetBondingCurve { curve, reserve_denom: "uatom".to_string() }).unwrap();
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::BuyFromCurve {}), "Send exactly one coin of uatom");
+
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &cosmwasm_std::coins(100, "uatom")), ExecuteMsg::BuyFromCurve {}).unwrap();
+    assert_eq!(balance(&deps, "alice"), 50);
+    let res = exec(&mut deps, "alice", ExecuteMsg::SellToCurve { amount: Uint128::from(20u128) }).unwrap();
+    let payout = cosmwasm_std::BankMsg::Send { to_address: "alice".to_string(), amount: cosmwasm_std::coins(40, "uatom") };
+    assert_eq!(res.messages[0].msg, cosmwasm_std::CosmosMsg::Bank(payout));
+    assert_eq!(balance(&deps, "alice"), 30);
+}
+
+fn reply_ok(id: u64) -> cosmwasm_std::Reply {
+    cosmwasm_std::Reply { id, result: cosmwasm_std::ContractResult::Ok(cosmwasm_std::SubMsgExecutionResponse { events: vec![], data: None }) }
+}
+
+#[test]
+fn flash_mint_must_be_repaid_with_the_fee_by_the_reply() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetFlashMintFee { fee_bps: 100 }).unwrap();
+    let flash = ExecuteMsg::FlashMint { amount: Uint128::from(1000u128), msg: cosmwasm_std::Binary::default() };
+
+    let res = exec(&mut deps, "alice", flash.clone()).unwrap();
+    assert_eq!(res.messages[0].id, FLASH_MINT_REPLY_ID);
+    let callback = FlashMintReceiverExecuteMsg::ReceiveFlashMint(FlashMintCallbackMsg { amount: Uint128::from(1000u128), fee: Uint128::from(10u128), msg: cosmwasm_std::Binary::default() });
+    let expected = cosmwasm_std::WasmMsg::Execute { contract_addr: "alice".to_string(), msg: cosmwasm_std::to_binary(&callback).unwrap(), funds: vec![] };
+    assert_eq!(res.messages[0].msg, cosmwasm_std::CosmosMsg::Wasm(expected));
+    assert_err(exec(&mut deps, "alice", flash.clone()), "Flash mint already in progress");
+    reply(deps.as_mut(), mock_env(), reply_ok(FLASH_MINT_REPLY_ID)).unwrap();
+    assert_eq!(balance(&deps, "alice"), 90);
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::from(90u128));
+
+    // Tokens burned by somebody else don't repay the borrower's loan
+    exec(&mut deps, "alice", flash).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(1000u128), memo: None }).unwrap();
+    exec(&mut deps, "bob", ExecuteMsg::Burn { amount: Uint128::from(1000u128) }).unwrap();
+    assert_err(reply(deps.as_mut(), mock_env(), reply_ok(FLASH_MINT_REPLY_ID)), "Flash mint was not repaid");
+}
+
+#[test]
+fn treasury_allocation_releases_monthly_tranches() {
+    let allocation = TreasuryAllocation { amount: Uint128::from(1200u128), destination: HumanAddr::from("dao"), tranches: 12 };
+    let mut deps = setup_with(InstantiateMsg { treasury: Some(allocation), ..instantiate_msg(&[]) });
+    assert_err(exec(&mut deps, "anyone", ExecuteMsg::ReleaseTreasury {}), "Nothing to release yet");
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(3 * TREASURY_TRANCHE_SECONDS);
+    execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::ReleaseTreasury {}).unwrap();
+    assert_eq!(balance(&deps, "dao"), 300);
+    let treasury: TreasuryResponse = cosmwasm_std::from_binary(&query(deps.as_ref(), env, QueryMsg::Treasury {}).unwrap()).unwrap();
+    assert_eq!((treasury.released, treasury.locked), (Uint128::from(300u128), Uint128::from(900u128)));
+    assert_eq!(escrowed_total(&deps.storage).unwrap(), Uint128::from(900u128));
+}
+
+#[test]
+fn lockdrop_rewards_follow_duration_weights() {
+    let now = mock_env().block.time.seconds();
+    let mut deps = setup(&[]);
+    let config = LockdropConfig {
+        asset: LockdropAsset::Native { denom: "uatom".to_string() },
+        start: now,
+        end: now + 100,
+        durations: vec![LockdropDuration { seconds: 100, weight: 1 }, LockdropDuration { seconds: 200, weight: 3 }],
+        reward: Uint128::from(400u128),
+    };
+    exec(&mut deps, OWNER, ExecuteMsg::SetupLockdrop { config }).unwrap();
+    let funds = cosmwasm_std::coins(100, "uatom");
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &funds), ExecuteMsg::LockdropDeposit { duration: 100 }).unwrap();
+    execute(deps.as_mut(), mock_env(), mock_info("bob", &funds), ExecuteMsg::LockdropDeposit { duration: 200 }).unwrap();
+    assert_err(execute(deps.as_mut(), mock_env(), mock_info("bob", &funds), ExecuteMsg::LockdropDeposit { duration: 50 }), "Unsupported lock duration");
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::ClaimLockdropReward {}), "Lockdrop is still running");
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    for who in ["alice", "bob"] {
+        execute(deps.as_mut(), env.clone(), mock_info(who, &[]), ExecuteMsg::ClaimLockdropReward {}).unwrap();
+    }
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "bob")), (100, 300));
+    let withdraw = ExecuteMsg::WithdrawLockdrop { duration: 100 };
+    assert_err(execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), withdraw.clone()), "Lockdrop deposit is still locked");
+    env.block.time = env.block.time.plus_seconds(100);
+    let res = execute(deps.as_mut(), env, mock_info("alice", &[]), withdraw).unwrap();
+    let refund = cosmwasm_std::BankMsg::Send { to_address: "alice".to_string(), amount: funds };
+    assert_eq!(res.messages[0].msg, cosmwasm_std::CosmosMsg::Bank(refund));
+}
+
+#[test]
+fn burns_are_tracked_on_a_leaderboard() {
+    let mut deps = setup(&[("alice", 100), ("bob", 100)]);
+    exec(&mut deps, "alice", ExecuteMsg::Burn { amount: Uint128::from(10u128) }).unwrap();
+    exec(&mut deps, "bob", ExecuteMsg::Burn { amount: Uint128::from(25u128) }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::Burn { amount: Uint128::from(20u128) }).unwrap();
+
+    let total: TotalBurnedResponse = query_as(&deps, QueryMsg::TotalBurned {});
+    assert_eq!(total.total_burned, Uint128::from(55u128));
+    let top: TopBurnersResponse = query_as(&deps, QueryMsg::TopBurners { limit: Some(1) });
+    assert_eq!(top.burners, vec![Burner { address: HumanAddr::from("alice"), burned: Uint128::from(30u128) }]);
+}
+
+#[test]
+fn mint_split_gives_the_rounding_remainder_to_the_last_recipient() {
+    let mut deps = setup(&[]);
+    let split = |weights: &[(&str, u64)]| ExecuteMsg::MintSplit {
+        total: Uint128::from(100u128),
+        recipients: weights.iter().map(|(to, weight)| (HumanAddr::from(*to), *weight)).collect(),
+    };
+    assert_err(exec(&mut deps, OWNER, split(&[("a", 5000), ("b", 4000)])), "Weights must add up to 10000 basis points");
+    exec(&mut deps, OWNER, split(&[("a", 3333), ("b", 3333), ("c", 3334)])).unwrap();
+    assert_eq!((balance(&deps, "a"), balance(&deps, "b"), balance(&deps, "c")), (33, 33, 34));
+}
+
+#[test]
+fn buyback_swaps_collected_coins_and_burns_what_comes_back() {
+    let contract = mock_env().contract.address;
+    let mut deps = setup(&[("router", 40)]);
+    deps.querier.base.update_balance(contract.as_str(), cosmwasm_std::coins(500, "uatom"));
+    let config = BuybackConfig { router: HumanAddr::from("router"), offer_denom: "uatom".to_string(), swap_msg: cosmwasm_std::Binary::default() };
+    exec(&mut deps, OWNER, ExecuteMsg::SetBuyback { config: Some(config) }).unwrap();
+
+    let res = exec(&mut deps, OWNER, ExecuteMsg::Buyback {}).unwrap();
+    assert_eq!(res.messages[0].id, BUYBACK_REPLY_ID);
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::Buyback {}), "Buyback already in progress");
+    exec(&mut deps, "router", ExecuteMsg::Transfer { recipient: HumanAddr::from(contract.as_str()), amount: Uint128::from(40u128), memo: None }).unwrap();
+    reply(deps.as_mut(), mock_env(), reply_ok(BUYBACK_REPLY_ID)).unwrap();
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::zero());
+}
+
+#[test]
+fn minimum_transfer_amount_rejects_dust() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetMinTransferAmount { min: Some(Uint128::from(10u128)) }).unwrap();
+    let send = |amount: u128| ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount), memo: None };
+    assert_err(exec(&mut deps, "alice", send(9)), "Transfer amount is below the minimum of 10");
+    exec(&mut deps, "alice", send(10)).unwrap();
+}
+
+#[test]
+fn circulating_supply_excludes_listed_addresses() {
+    let mut deps = setup(&[("alice", 100), ("treasury", 900)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetNonCirculating { address: HumanAddr::from("treasury"), non_circulating: true }).unwrap();
+    let supply: CirculatingSupplyResponse = query_as(&deps, QueryMsg::CirculatingSupply {});
+    assert_eq!((supply.total_supply, supply.circulating_supply), (Uint128::from(1000u128), Uint128::from(100u128)));
+    assert_eq!(supply.non_circulating.len(), 1);
+}
+
+#[test]
+fn emissions_can_fund_the_staking_reward_pool() {
+    let mut deps = setup(&[]);
+    let schedule = EmissionScheduleMsg { per_block: Uint128::from(10u128), decay_bps: 0, blocks_per_year: 100, target: HumanAddr::from("pool"), to_staking: true };
+    exec(&mut deps, OWNER, ExecuteMsg::SetEmissionSchedule { schedule: Some(schedule) }).unwrap();
+
+    let mut env = mock_env();
+    env.block.height += 5;
+    execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::Crank {}).unwrap();
+    let pool: Uint128 = query_as(&deps, QueryMsg::StakingRewardPool {});
+    assert_eq!(pool, Uint128::from(50u128));
+    assert_eq!((balance(&deps, "pool"), escrowed_total(&deps.storage).unwrap()), (0, Uint128::from(50u128)));
+}
+
+#[test]
+fn balance_at_reports_balance_before_later_transfers() {
+    let mut deps = setup(&[("alice", 100)]);
+    let mut env = mock_env();
+    env.block.height += 1;
+    execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(30u128), memo: None }).unwrap();
+    let before: BalanceResponse = query_as(&deps, QueryMsg::BalanceAt { address: HumanAddr::from("alice"), height: env.block.height });
+    assert_eq!(before.amount, Uint128::from(100u128));
+    let after: BalanceResponse = query_as(&deps, QueryMsg::BalanceAt { address: HumanAddr::from("alice"), height: env.block.height + 1 });
+    assert_eq!(after.amount, Uint128::from(70u128));
+}
+
+#[test]
+fn total_supply_at_reports_supply_before_later_mints() {
+    let mut deps = setup(&[("alice", 100)]);
+    let mut env = mock_env();
+    env.block.height += 1;
+    execute(deps.as_mut(), env.clone(), mock_info(OWNER, &[]), ExecuteMsg::Mint { recipient: HumanAddr::from("bob"), amount: Uint128::from(50u128) }).unwrap();
+    let before: TotalSupplyResponse = query_as(&deps, QueryMsg::TotalSupplyAt { height: env.block.height });
+    assert_eq!(before.total_supply, Uint128::from(100u128));
+    let after: TotalSupplyResponse = query_as(&deps, QueryMsg::TotalSupplyAt { height: env.block.height + 1 });
+    assert_eq!(after.total_supply, Uint128::from(150u128));
+}
+
+#[test]
+fn delegation_moves_votes_and_follows_transfers() {
+    let mut deps = setup(&[("alice", 100)]);
+    let mut env = mock_env();
+    env.block.height += 1;
+    execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::Delegate { delegatee: Some(HumanAddr::from("dave")) }).unwrap();
+    env.block.height += 1;
+    execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(40u128), memo: None }).unwrap();
+
+    let delegates: DelegatesResponse = query_as(&deps, QueryMsg::Delegates { address: HumanAddr::from("alice") });
+    assert_eq!(delegates.delegatee, Some(HumanAddr::from("dave")));
+    let votes_at = |deps: &TestDeps, height: u64| query_as::<VotesResponse>(deps, QueryMsg::VotesAt { address: HumanAddr::from("dave"), height }).votes.u128();
+    assert_eq!(votes_at(&deps, env.block.height - 1), 0);
+    assert_eq!(votes_at(&deps, env.block.height), 100);
+    assert_eq!(votes_at(&deps, env.block.height + 1), 60);
+}
+
+#[test]
+fn voting_power_uses_delegated_votes_and_skips_the_contract_balance() {
+    let allocation = TreasuryAllocation { amount: Uint128::from(1200u128), destination: HumanAddr::from("dao"), tranches: 12 };
+    let mut deps = setup_with(InstantiateMsg { treasury: Some(allocation), ..instantiate_msg(&[("alice", 100)]) });
+    let mut env = mock_env();
+    env.block.height += 1;
+    execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::Delegate { delegatee: Some(HumanAddr::from("dave")) }).unwrap();
+
+    let height = Some(env.block.height + 1);
+    let dave: VotingPowerAtHeightResponse = query_as(&deps, QueryMsg::VotingPowerAtHeight { address: HumanAddr::from("dave"), height });
+    assert_eq!(dave.power, Uint128::from(100u128));
+    let alice: VotingPowerAtHeightResponse = query_as(&deps, QueryMsg::VotingPowerAtHeight { address: HumanAddr::from("alice"), height });
+    assert_eq!(alice.power, Uint128::zero());
+    let total: TotalPowerAtHeightResponse = query_as(&deps, QueryMsg::TotalPowerAtHeight { height });
+    assert_eq!(total.power, Uint128::from(100u128));
+}
+
+#[test]
+fn staking_unbonds_and_claims_after_the_period() {
+    let mut deps = setup(&[("alice", 100)]);
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::Stake { amount: Uint128::from(10u128) }), "Staking is not enabled");
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingConfig { unbonding_period: Duration::Height(10) }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::Stake { amount: Uint128::from(60u128) }).unwrap();
+    assert_eq!(balance(&deps, "alice"), 40);
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::Unstake { amount: Uint128::from(61u128) }), "Amount exceeds staked balance");
+    exec(&mut deps, "alice", ExecuteMsg::Unstake { amount: Uint128::from(20u128) }).unwrap();
+
+    let staked: StakedResponse = query_as(&deps, QueryMsg::Staked { address: HumanAddr::from("alice") });
+    assert_eq!((staked.staked.u128(), staked.unbonding.u128(), staked.total_staked.u128()), (40, 20, 40));
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::ClaimUnstaked {}), "No unbonding claims have matured");
+
+    let mut env = mock_env();
+    env.block.height += 10;
+    execute(deps.as_mut(), env, mock_info("alice", &[]), ExecuteMsg::ClaimUnstaked {}).unwrap();
+    assert_eq!(balance(&deps, "alice"), 60);
+}
+
+#[test]
+fn native_staking_rewards_are_shared_by_stake() {
+    let mut deps = setup(&[("alice", 300), ("bob", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingConfig { unbonding_period: Duration::Height(10) }).unwrap();
+    let fund = |deps: &mut TestDeps| execute(deps.as_mut(), mock_env(), mock_info("funder", &cosmwasm_std::coins(400, "uatom")), ExecuteMsg::FundStakingRewards {});
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingRewardDenoms { denoms: vec!["uatom".to_string()] }).unwrap();
+    assert_err(fund(&mut deps), "No tokens are staked");
+    exec(&mut deps, "alice", ExecuteMsg::Stake { amount: Uint128::from(300u128) }).unwrap();
+    exec(&mut deps, "bob", ExecuteMsg::Stake { amount: Uint128::from(100u128) }).unwrap();
+    fund(&mut deps).unwrap();
+
+    let pending: PendingRewardsResponse = query_as(&deps, QueryMsg::PendingRewards { address: HumanAddr::from("bob") });
+    assert_eq!(pending.rewards, cosmwasm_std::coins(100, "uatom"));
+    let res = exec(&mut deps, "alice", ExecuteMsg::ClaimRewards { denom: None }).unwrap();
+    assert_eq!(res.messages[0].msg, cosmwasm_std::BankMsg::Send { to_address: "alice".to_string(), amount: cosmwasm_std::coins(300, "uatom") }.into());
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::ClaimRewards { denom: None }), "No rewards to claim");
+}
+
+#[test]
+fn cw20_staking_rewards_need_an_accepted_denom() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingConfig { unbonding_period: Duration::Height(10) }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::Stake { amount: Uint128::from(100u128) }).unwrap();
+    let receive = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: HumanAddr::from("sponsor"),
+        amount: Uint128::from(50u128),
+        msg: cosmwasm_std::to_binary(&ReceiveMsg::FundStakingRewards {}).unwrap(),
+    });
+    assert_err(exec(&mut deps, "reward_token", receive.clone()), "Denom cw20:reward_token is not accepted");
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingRewardDenoms { denoms: vec!["cw20:reward_token".to_string()] }).unwrap();
+    exec(&mut deps, "reward_token", receive).unwrap();
+
+    let pending: PendingRewardsResponse = query_as(&deps, QueryMsg::PendingRewards { address: HumanAddr::from("alice") });
+    assert_eq!(pending.rewards, cosmwasm_std::coins(50, "cw20:reward_token"));
+    let res = exec(&mut deps, "alice", ExecuteMsg::ClaimRewards { denom: Some("cw20:reward_token".to_string()) }).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer_msg(&HumanAddr::from("reward_token"), &HumanAddr::from("alice"), Uint128::from(50u128)).unwrap());
+}
+
+#[test]
+fn auto_compounding_restakes_token_rewards_on_crank() {
+    let mut deps = setup(&[("alice", 100), ("bob", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingConfig { unbonding_period: Duration::Height(10) }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::Stake { amount: Uint128::from(100u128) }).unwrap();
+    exec(&mut deps, "bob", ExecuteMsg::Stake { amount: Uint128::from(100u128) }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::SetAutoCompound { enabled: true }).unwrap();
+    fund_staking_rewards(&mut deps.storage, &mock_env(), Uint128::from(50u128)).unwrap();
+
+    let res = exec(&mut deps, "anyone", ExecuteMsg::Crank {}).unwrap();
+    let event = res.events.iter().find(|e| e.ty == "auto_compound").unwrap();
+    assert_eq!(event.attributes[1].value, "25");
+    let alice: StakedResponse = query_as(&deps, QueryMsg::Staked { address: HumanAddr::from("alice") });
+    assert_eq!(alice.staked, Uint128::from(125u128));
+
+    exec(&mut deps, "bob", ExecuteMsg::ClaimRewards { denom: None }).unwrap();
+    assert_eq!(balance(&deps, "bob"), 25);
+}
+
+#[test]
+fn early_unbonding_charges_a_decaying_penalty() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingConfig { unbonding_period: Duration::Height(10) }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::Stake { amount: Uint128::from(100u128) }).unwrap();
+    let id: u64 = attr(&exec(&mut deps, "alice", ExecuteMsg::Unstake { amount: Uint128::from(100u128) }).unwrap(), "id").parse().unwrap();
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::UnbondEarly { id }), "Early unbonding is not enabled");
+    let penalty = EarlyUnbondPenalty { bps: 2000, destination: PenaltyDestination::Burn {} };
+    exec(&mut deps, OWNER, ExecuteMsg::SetEarlyUnbondPenalty { penalty: Some(penalty) }).unwrap();
+
+    // Halfway through the period half of the 20% penalty is left
+    let mut env = mock_env();
+    env.block.height += 5;
+    let res = execute(deps.as_mut(), env, mock_info("alice", &[]), ExecuteMsg::UnbondEarly { id }).unwrap();
+    assert_eq!((attr(&res, "amount"), attr(&res, "penalty")), ("90".to_string(), "10".to_string()));
+    assert_eq!(balance(&deps, "alice"), 90);
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::from(90u128));
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::UnbondEarly { id }), "Unbonding claim not found");
+}
+
+#[test]
+fn stake_changes_notify_registered_hooks() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingConfig { unbonding_period: Duration::Height(10) }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::AddStakeHook { addr: HumanAddr::from("gauge") }).unwrap();
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::AddStakeHook { addr: HumanAddr::from("gauge") }), "Hook already registered");
+
+    let res = exec(&mut deps, "alice", ExecuteMsg::Stake { amount: Uint128::from(30u128) }).unwrap();
+    let hook = StakeChangedExecuteMsg::StakeChangeHook(StakeChangedHookMsg::Stake { addr: HumanAddr::from("alice"), amount: Uint128::from(30u128) });
+    let expected: cosmwasm_std::CosmosMsg = cosmwasm_std::WasmMsg::Execute { contract_addr: "gauge".to_string(), msg: cosmwasm_std::to_binary(&hook).unwrap(), funds: vec![] }.into();
+    assert_eq!(res.messages[0].msg, expected);
+
+    exec(&mut deps, OWNER, ExecuteMsg::RemoveStakeHook { addr: HumanAddr::from("gauge") }).unwrap();
+    let res = exec(&mut deps, "alice", ExecuteMsg::Unstake { amount: Uint128::from(30u128) }).unwrap();
+    assert!(res.messages.is_empty());
+    let hooks: HooksResponse = query_as(&deps, QueryMsg::StakeHooks {});
+    assert!(hooks.hooks.is_empty());
+}
+
+fn delegate_msg(key: &Key, signer: &Key, delegatee: &str, nonce: u64) -> ExecuteMsg {
+    let env = mock_env();
+    let payload = DelegationPayload {
+        chain_id: env.block.chain_id.clone(),
+        contract: HumanAddr::from(env.contract.address.as_str()),
+        delegator: key.address(),
+        delegatee: Some(HumanAddr::from(delegatee)),
+        nonce,
+        expiry: Expiration::AtHeight(env.block.height + 10),
+    };
+    ExecuteMsg::DelegateBySig {
+        delegator: payload.delegator.clone(),
+        delegatee: payload.delegatee.clone(),
+        nonce,
+        expiry: payload.expiry,
+        pubkey: key.pubkey(),
+        signature: signer.sign(&payload),
+    }
+}
+
+#[test]
+fn delegate_by_sig_accepts_valid_signature() {
+    let alice = Key::new(1);
+    let mut deps = setup(&[(alice.address().as_str(), 100)]);
+    exec(&mut deps, "relayer", delegate_msg(&alice, &alice, "bob", 0)).unwrap();
+    assert_eq!(delegate_of(&deps.storage, &alice.address()).unwrap(), Some(HumanAddr::from("bob")));
+    assert_eq!(delegation_nonce(&deps.storage, &alice.address()).unwrap(), 1);
+}
+
+#[test]
+fn delegate_by_sig_rejects_invalid_signature() {
+    let alice = Key::new(1);
+    let mut deps = setup(&[(alice.address().as_str(), 100)]);
+    let msg = match delegate_msg(&alice, &alice, "bob", 0) {
+        ExecuteMsg::DelegateBySig { delegator, nonce, expiry, pubkey, signature, .. } => {
+            ExecuteMsg::DelegateBySig { delegator, delegatee: Some(HumanAddr::from("mallory")), nonce, expiry, pubkey, signature }
+        }
+        _ => unreachable!(),
+    };
+    assert_err(exec(&mut deps, "relayer", msg), "Invalid signature");
+    // Signed by someone else under the delegator's key
+    assert_err(exec(&mut deps, "relayer", delegate_msg(&alice, &Key::new(2), "bob", 0)), "Invalid signature");
+}
+
+#[test]
+fn delegate_by_sig_rejects_replay() {
+    let alice = Key::new(1);
+    let mut deps = setup(&[(alice.address().as_str(), 100)]);
+    let msg = delegate_msg(&alice, &alice, "bob", 0);
+    exec(&mut deps, "relayer", msg.clone()).unwrap();
+    assert_err(exec(&mut deps, "relayer", msg), "Invalid nonce");
+}
+
+#[test]
+fn delegate_by_sig_rejects_wrong_pubkey() {
+    let alice = Key::new(1);
+    let mallory = Key::new(2);
+    let mut deps = setup(&[(alice.address().as_str(), 100)]);
+    let msg = match delegate_msg(&mallory, &mallory, "bob", 0) {
+        ExecuteMsg::DelegateBySig { delegatee, nonce, expiry, pubkey, signature, .. } => {
+            ExecuteMsg::DelegateBySig { delegator: alice.address(), delegatee, nonce, expiry, pubkey, signature }
+        }
+        _ => unreachable!(),
+    };
+    assert_err(exec(&mut deps, "relayer", msg), "Public key does not match the address");
+}
+
+#[test]
+fn governance_proposal_passes_with_delegated_votes_and_executes() {
+    let mut deps = setup(&[("alice", 100), ("bob", 50)]);
+    let config = GovernanceConfig { voting_period: 100, quorum_bps: 5000, threshold_bps: 5000, proposal_threshold: Uint128::from(10u128), veto: None };
+    exec(&mut deps, OWNER, ExecuteMsg::SetGovernance { config: Some(config) }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::Delegate { delegatee: Some(HumanAddr::from("alice")) }).unwrap();
+    let propose = ExecuteMsg::Propose { title: "Pause".to_string(), description: "Halt transfers".to_string(), actions: vec![ProposalAction::Pause {}] };
+    // Votes delegated in this block only count from the next one
+    assert_err(exec(&mut deps, "alice", propose.clone()), "Creating a proposal needs 10 votes");
+
+    let mut env = mock_env();
+    env.block.height += 1;
+    let res = execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), propose).unwrap();
+    let id: u64 = attr(&res, "id").parse().unwrap();
+    let vote = |vote: VoteOption| ExecuteMsg::CastVote { id, vote };
+    assert_err(execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), vote(VoteOption::Yes)), "No voting weight at the proposal snapshot");
+    execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), vote(VoteOption::Yes)).unwrap();
+    assert_err(execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::ExecuteProposal { id }), "Proposal has not passed");
+
+    env.block.time = env.block.time.plus_seconds(100);
+    let status: ProposalResponse = cosmwasm_std::from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::Proposal { id }).unwrap()).unwrap();
+    assert_eq!(status.status, ProposalStatus::Passed);
+    execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::ExecuteProposal { id }).unwrap();
+    assert!(is_paused(&deps.storage).unwrap());
+}
+
+#[test]
+fn vetoer_can_block_a_queued_proposal() {
+    let mut deps = setup(&[("alice", 100)]);
+    let veto = VetoConfig { vetoer: HumanAddr::from("council"), window: 50 };
+    let config = GovernanceConfig { voting_period: 100, quorum_bps: 5000, threshold_bps: 5000, proposal_threshold: Uint128::zero(), veto: Some(veto) };
+    exec(&mut deps, OWNER, ExecuteMsg::SetGovernance { config: Some(config) }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::Delegate { delegatee: Some(HumanAddr::from("alice")) }).unwrap();
+
+    let mut env = mock_env();
+    env.block.height += 1;
+    let propose = ExecuteMsg::Propose { title: "Pause".to_string(), description: "Halt transfers".to_string(), actions: vec![ProposalAction::Pause {}] };
+    let id: u64 = attr(&execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), propose).unwrap(), "id").parse().unwrap();
+    execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::CastVote { id, vote: VoteOption::Yes }).unwrap();
+
+    env.block.time = env.block.time.plus_seconds(100);
+    let veto = |deps: &mut TestDeps, sender: &str| execute(deps.as_mut(), env.clone(), mock_info(sender, &[]), ExecuteMsg::VetoProposal { id });
+    assert_err(execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::ExecuteProposal { id }), "Proposal has not passed");
+    assert_err(veto(&mut deps, "alice"), "Unauthorized");
+    veto(&mut deps, "council").unwrap();
+    assert_err(veto(&mut deps, "council"), "Only queued proposals can be vetoed");
+    let status: ProposalResponse = cosmwasm_std::from_binary(&query(deps.as_ref(), env, QueryMsg::Proposal { id }).unwrap()).unwrap();
+    assert_eq!(status.status, ProposalStatus::Vetoed);
+}
+
+#[test]
+fn holders_at_pages_through_snapshot_balances() {
+    let mut deps = setup(&[("alice", 100), ("bob", 50)]);
+    let mut env = mock_env();
+    env.block.height += 1;
+    execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), ExecuteMsg::Transfer { recipient: HumanAddr::from("carol"), amount: Uint128::from(50u128), memo: None }).unwrap();
+
+    let holders_at = |start_after: Option<&str>| query_as::<HoldersResponse>(&deps, QueryMsg::HoldersAt { height: env.block.height, start_after: start_after.map(HumanAddr::from), limit: Some(2) });
+    let first = holders_at(None);
+    let balances: Vec<(String, u128)> = first.holders.iter().map(|h| (h.address.to_string(), h.balance.u128())).collect();
+    assert_eq!(balances, vec![("alice".to_string(), 100), ("bob".to_string(), 50)]);
+    assert_eq!(first.next_start_after, Some(HumanAddr::from("bob")));
+    // Carol only received her tokens in that block
+    let second = holders_at(Some("bob"));
+    assert!(second.holders.is_empty());
+    assert_eq!(second.next_start_after, None);
+}
+
+#[test]
+fn staking_info_estimates_apr_from_emissions() {
+    let mut deps = setup(&[("alice", 500)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingConfig { unbonding_period: Duration::Height(10) }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::Stake { amount: Uint128::from(500u128) }).unwrap();
+    let schedule = EmissionScheduleMsg { per_block: Uint128::from(10u128), decay_bps: 0, blocks_per_year: 100, target: HumanAddr::from("pool"), to_staking: true };
+    exec(&mut deps, OWNER, ExecuteMsg::SetEmissionSchedule { schedule: Some(schedule) }).unwrap();
+
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingRewardDenoms { denoms: vec!["uatom".to_string()] }).unwrap();
+
+    let info: StakingInfoResponse = query_as(&deps, QueryMsg::StakingInfo {});
+    assert_eq!((info.total_staked.u128(), info.emission_per_block.u128(), info.annual_emission.u128()), (500, 10, 1000));
+    assert_eq!(info.estimated_apr, Decimal::percent(200));
+    assert_eq!(info.accepted_reward_denoms, vec!["uatom".to_string()]);
+}
+
+#[test]
+fn claims_query_pages_unbonding_entries() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingConfig { unbonding_period: Duration::Height(10) }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::Stake { amount: Uint128::from(100u128) }).unwrap();
+    exec(&mut deps, "alice", ExecuteMsg::Unstake { amount: Uint128::from(10u128) }).unwrap();
+    let mut env = mock_env();
+    env.block.height += 5;
+    execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), ExecuteMsg::Unstake { amount: Uint128::from(20u128) }).unwrap();
+
+    env.block.height += 5;
+    let claims = |start_after: Option<u64>| -> ClaimsResponse {
+        let msg = QueryMsg::Claims { address: HumanAddr::from("alice"), start_after, limit: Some(1) };
+        cosmwasm_std::from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap()
+    };
+    let first = claims(None).claims;
+    assert_eq!((first.len(), first[0].amount.u128(), first[0].matured), (1, 10, true));
+    let second = claims(Some(first[0].id)).claims;
+    assert_eq!((second[0].amount.u128(), second[0].matured), (20, false));
+    assert!(claims(Some(second[0].id)).claims.is_empty());
+}
+
+#[test]
+fn transfer_hooks_wrap_transfers() {
+    let mut deps = setup(&[("alice", 100)]);
+    let hook = TransferHook { contract: HumanAddr::from("hook"), phase: HookPhase::Both, gas_limit: Some(100_000), on_failure: HookFailurePolicy::Ignore };
+    exec(&mut deps, OWNER, ExecuteMsg::AddTransferHook { hook: hook.clone() }).unwrap();
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::AddTransferHook { hook }), "Hook already registered");
+
+    let res = exec(&mut deps, "alice", ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(10u128), memo: None }).unwrap();
+    let (from, to, amount) = (HumanAddr::from("alice"), HumanAddr::from("bob"), Uint128::from(10u128));
+    let payloads: Vec<cosmwasm_std::CosmosMsg> = [TransferHookMsg::BeforeTransfer { from: from.clone(), to: to.clone(), amount }, TransferHookMsg::AfterTransfer { from, to, amount }]
+        .iter()
+        .map(|msg| cosmwasm_std::WasmMsg::Execute { contract_addr: "hook".to_string(), msg: cosmwasm_std::to_binary(msg).unwrap(), funds: vec![] }.into())
+        .collect();
+    assert_eq!(res.messages.iter().map(|m| m.msg.clone()).collect::<Vec<_>>(), payloads);
+    assert_eq!((res.messages[0].gas_limit, res.messages[0].reply_on.clone()), (Some(100_000), cosmwasm_std::ReplyOn::Error));
+
+    exec(&mut deps, OWNER, ExecuteMsg::RemoveTransferHook { contract: HumanAddr::from("hook") }).unwrap();
+    let res = exec(&mut deps, "alice", ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(10u128), memo: None }).unwrap();
+    assert!(res.messages.is_empty());
+}
+
+#[test]
+fn cw20_collateral_wraps_and_unwraps_one_to_one() {
+    let mut deps = setup(&[]);
+    let receive = |amount: u128| ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: HumanAddr::from("alice"),
+        amount: Uint128::from(amount),
+        msg: cosmwasm_std::to_binary(&ReceiveMsg::Wrap {}).unwrap(),
+    });
+    assert_err(exec(&mut deps, "collateral_token", receive(40)), "Token is not accepted as collateral");
+    exec(&mut deps, OWNER, ExecuteMsg::SetCollateral { token: Some(HumanAddr::from("collateral_token")) }).unwrap();
+    assert_err(exec(&mut deps, "other_token", receive(40)), "Token is not accepted as collateral");
+    exec(&mut deps, "collateral_token", receive(40)).unwrap();
+    assert_eq!(balance(&deps, "alice"), 40);
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::SetCollateral { token: None }), "Collateral is still locked");
+
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::Unwrap { amount: Uint128::from(41u128) }), "Amount exceeds locked collateral");
+    let res = exec(&mut deps, "alice", ExecuteMsg::Unwrap { amount: Uint128::from(15u128) }).unwrap();
+    assert_eq!(res.messages[0].msg, cw20_transfer_msg(&HumanAddr::from("collateral_token"), &HumanAddr::from("alice"), Uint128::from(15u128)).unwrap());
+    assert_eq!(balance(&deps, "alice"), 25);
+    let collateral: Option<Collateral> = query_as(&deps, QueryMsg::Collateral {});
+    assert_eq!(collateral.unwrap().locked, Uint128::from(25u128));
+}
+
+#[test]
+fn native_wrapper_keeps_the_supply_backed() {
+    let mut held = setup(&[("alice", 1)]);
+    assert_err(exec(&mut held, OWNER, ExecuteMsg::SetNativeWrapper { denom: Some("uusd".to_string()) }), "The native wrapper can only be enabled before any tokens exist");
+
+    let mut deps = setup(&[]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetNativeWrapper { denom: Some("uusd".to_string()) }).unwrap();
+    let contract = mock_env().contract.address;
+    deps.querier.base.update_balance(contract.as_str(), cosmwasm_std::coins(100, "uusd"));
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &cosmwasm_std::coins(100, "uusd")), ExecuteMsg::Deposit {}).unwrap();
+    assert_eq!(balance(&deps, "alice"), 100);
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::Mint { recipient: HumanAddr::from("bob"), amount: Uint128::from(1u128) }), "Only deposits can add supply while the native wrapper is enabled");
+
+    let res = exec(&mut deps, "alice", ExecuteMsg::Withdraw { amount: Uint128::from(30u128) }).unwrap();
+    assert_eq!(res.messages[0].msg, cosmwasm_std::BankMsg::Send { to_address: "alice".to_string(), amount: cosmwasm_std::coins(30, "uusd") }.into());
+    assert_eq!(balance(&deps, "alice"), 70);
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::SetNativeWrapper { denom: None }), "Wrapped tokens are still outstanding");
+}
+
+#[test]
+fn tokenfactory_denom_converts_both_ways() {
+    let mut deps = setup_with(InstantiateMsg { tokenfactory_subdenom: Some("mytoken".to_string()), ..instantiate_msg(&[("alice", 100)]) });
+    let contract = mock_env().contract.address.to_string();
+    let denom = format!("factory/{}/mytoken", contract);
+
+    let res = exec(&mut deps, "alice", ExecuteMsg::ConvertToBank { amount: Uint128::from(40u128) }).unwrap();
+    assert_eq!(res.messages[0].msg, tokenfactory_mint_msg(&contract, &denom, Uint128::from(40u128), "alice"));
+    assert_eq!((balance(&deps, "alice"), token_info(&deps.storage).unwrap().total_supply.u128()), (60, 60));
+
+    let convert_back = |deps: &mut TestDeps, coins: Vec<cosmwasm_std::Coin>| execute(deps.as_mut(), mock_env(), mock_info("alice", &coins), ExecuteMsg::ConvertToCw20 {});
+    assert_err(convert_back(&mut deps, cosmwasm_std::coins(40, "uatom")), &format!("Send exactly one coin of {}", denom));
+    let res = convert_back(&mut deps, cosmwasm_std::coins(40, &denom)).unwrap();
+    assert_eq!(res.messages[0].msg, tokenfactory_burn_msg(&contract, &denom, Uint128::from(40u128)));
+    assert_eq!(balance(&deps, "alice"), 100);
+    let factory: Option<FactoryDenom> = query_as(&deps, QueryMsg::FactoryDenom {});
+    assert_eq!(factory.unwrap().bank_supply, Uint128::zero());
+}
+
+#[test]
+fn native_migration_converts_unlocked_balances_and_counts_holders_once() {
+    let now = mock_env().block.time.seconds();
+    let mut msg = InstantiateMsg { tokenfactory_subdenom: Some("mytoken".to_string()), ..instantiate_msg(&[("alice", 100), ("bob", 20)]) };
+    msg.initial_balances[0].vesting = Some(VestingSchedule { start: now, cliff: now, end: now + 100 });
+    let mut deps = setup_with(msg);
+    let deadline = Expiration::AtHeight(mock_env().block.height + 1000);
+    exec(&mut deps, OWNER, ExecuteMsg::StartNativeMigration { deadline }).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(50);
+    let convert = |deps: &mut TestDeps, env: &cosmwasm_std::Env, sender: &str| execute(deps.as_mut(), env.clone(), mock_info(sender, &[]), ExecuteMsg::ConvertToNative {});
+    assert_eq!(attr(&convert(&mut deps, &env, "alice").unwrap(), "amount"), "50");
+    assert_err(convert(&mut deps, &env, "alice"), "Nothing to convert");
+    env.block.time = env.block.time.plus_seconds(50);
+    assert_eq!(attr(&convert(&mut deps, &env, "alice").unwrap(), "amount"), "50");
+    convert(&mut deps, &env, "bob").unwrap();
+
+    let migration: NativeMigrationResponse = query_as(&deps, QueryMsg::NativeMigration {});
+    assert_eq!((migration.converted.u128(), migration.holders_converted, migration.remaining.u128()), (120, 2, 0));
+    let back = execute(deps.as_mut(), env, mock_info("bob", &cosmwasm_std::coins(5, migration.denom)), ExecuteMsg::ConvertToCw20 {});
+    assert_err(back, "Conversion back to cw20 is closed by the native migration");
+}
+
+const CHANNEL: &str = "channel-7";
+
+fn setup_ibc(holders: &[(&str, u128)]) -> TestDeps {
+    let mut deps = setup(holders);
+    exec(&mut deps, OWNER, ExecuteMsg::AllowIbcChannel { channel_id: CHANNEL.to_string(), mode: None }).unwrap();
+    ibc_channel_connect(deps.as_mut(), mock_env(), mock_ibc_channel_connect_ack(CHANNEL, cosmwasm_std::IbcOrder::Unordered, IBC_VERSION)).unwrap();
+    deps
+}
+
+fn packet(sender: &str, receiver: &str, amount: u128) -> BurnMintPacket {
+    BurnMintPacket { sender: sender.to_string(), receiver: receiver.to_string(), amount: Uint128::from(amount), memo: None }
+}
+
+// Runs the self call the way the chain would and returns the final acknowledgement
+fn receive_packet(deps: &mut TestDeps, channel: &str, data: BurnMintPacket) -> IbcAck {
+    let recv = mock_ibc_packet_recv(channel, &data).unwrap();
+    let res = ibc_packet_receive(deps.as_mut(), mock_env(), recv.clone()).unwrap();
+    assert_eq!(res.messages[0].id, IBC_RECEIVE_REPLY_ID);
+    let msg = ExecuteMsg::ReceiveIbcPacket { packet: recv.packet };
+    match execute(deps.as_mut(), mock_env(), mock_info(cosmwasm_std::testing::MOCK_CONTRACT_ADDR, &[]), msg) {
+        Ok(_) => cosmwasm_std::from_binary(&res.acknowledgement).unwrap(),
+        Err(err) => {
+            let failed = cosmwasm_std::Reply { id: IBC_RECEIVE_REPLY_ID, result: cosmwasm_std::ContractResult::Err(err.to_string()) };
+            cosmwasm_std::from_binary(&reply(deps.as_mut(), mock_env(), failed).unwrap().data.unwrap()).unwrap()
+        }
+    }
+}
+
+#[test]
+fn ibc_channel_open_requires_whitelist() {
+    let mut deps = setup(&[]);
+    let open = |order| mock_ibc_channel_open_init(CHANNEL, order, IBC_VERSION);
+    assert_err(ibc_channel_open(deps.as_mut(), mock_env(), open(cosmwasm_std::IbcOrder::Unordered)), "Channel channel-7 is not whitelisted");
+    exec(&mut deps, OWNER, ExecuteMsg::AllowIbcChannel { channel_id: CHANNEL.to_string(), mode: None }).unwrap();
+    ibc_channel_open(deps.as_mut(), mock_env(), open(cosmwasm_std::IbcOrder::Unordered)).unwrap();
+    assert_err(ibc_channel_open(deps.as_mut(), mock_env(), open(cosmwasm_std::IbcOrder::Ordered)), "Only unordered channels are supported");
+}
+
+#[test]
+fn ibc_receive_mints_and_acknowledges() {
+    let mut deps = setup_ibc(&[]);
+    let ack = receive_packet(&mut deps, CHANNEL, packet("remote", "alice", 25));
+    assert_eq!(ack, IbcAck::Result(cosmwasm_std::Binary::from(vec![1])));
+    assert_eq!(balance(&deps, "alice"), 25);
+
+    let ack = receive_packet(&mut deps, "channel-unknown", packet("remote", "alice", 25));
+    assert!(matches!(ack, IbcAck::Error(_)));
+    assert_eq!(balance(&deps, "alice"), 25);
+}
+
+#[test]
+fn ibc_error_ack_and_timeout_refund_the_sender() {
+    let mut deps = setup_ibc(&[("alice", 100)]);
+    let transfer = || ExecuteMsg::IbcTransfer { channel_id: CHANNEL.to_string(), receiver: "remote".to_string(), amount: Uint128::from(40u128), timeout_seconds: None, memo: None };
+    exec(&mut deps, "alice", transfer()).unwrap();
+    assert_eq!(balance(&deps, "alice"), 60);
+
+    let success = cosmwasm_std::IbcAcknowledgement::new(cosmwasm_std::to_binary(&IbcAck::Result(cosmwasm_std::Binary::from(vec![1]))).unwrap());
+    ibc_packet_ack(deps.as_mut(), mock_env(), mock_ibc_packet_ack(CHANNEL, &packet("alice", "remote", 40), success).unwrap()).unwrap();
+    assert_eq!(balance(&deps, "alice"), 60);
+    let failure = cosmwasm_std::IbcAcknowledgement::new(cosmwasm_std::to_binary(&IbcAck::Error("failed".to_string())).unwrap());
+    ibc_packet_ack(deps.as_mut(), mock_env(), mock_ibc_packet_ack(CHANNEL, &packet("alice", "remote", 40), failure).unwrap()).unwrap();
+    assert_eq!(balance(&deps, "alice"), 100);
+
+    exec(&mut deps, "alice", transfer()).unwrap();
+    ibc_packet_timeout(deps.as_mut(), mock_env(), mock_ibc_packet_timeout(CHANNEL, &packet("alice", "remote", 40)).unwrap()).unwrap();
+    assert_eq!(balance(&deps, "alice"), 100);
+}
+
+#[test]
+fn ibc_escrow_channel_locks_and_releases() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::AllowIbcChannel { channel_id: CHANNEL.to_string(), mode: Some(IbcChannelMode::Escrow) }).unwrap();
+    ibc_channel_connect(deps.as_mut(), mock_env(), mock_ibc_channel_connect_ack(CHANNEL, cosmwasm_std::IbcOrder::Unordered, IBC_VERSION)).unwrap();
+
+    exec(&mut deps, "alice", ExecuteMsg::IbcTransfer { channel_id: CHANNEL.to_string(), receiver: "remote".to_string(), amount: Uint128::from(40u128), timeout_seconds: None, memo: None }).unwrap();
+    assert_eq!(balance(&deps, "alice"), 60);
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::from(100u128));
+    let channel: Option<IbcChannelState> = query_as(&deps, QueryMsg::IbcChannel { channel_id: CHANNEL.to_string() });
+    assert_eq!(channel.unwrap().escrowed, Uint128::from(40u128));
+
+    let ack = receive_packet(&mut deps, CHANNEL, packet("remote", "bob", 50));
+    assert!(matches!(ack, IbcAck::Error(_)));
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::RemoveIbcChannel { channel_id: CHANNEL.to_string() }), "Channel still holds escrowed tokens");
+
+    receive_packet(&mut deps, CHANNEL, packet("remote", "bob", 40));
+    assert_eq!(balance(&deps, "bob"), 40);
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::from(100u128));
+    exec(&mut deps, OWNER, ExecuteMsg::RemoveIbcChannel { channel_id: CHANNEL.to_string() }).unwrap();
+}
+
+#[test]
+fn ibc_rate_limit_refills_over_the_window() {
+    let mut deps = setup_ibc(&[("alice", 100)]);
+    let limits = FlowLimits { max_outflow: Uint128::from(30u128), max_inflow: Uint128::from(10u128), window_seconds: 100 };
+    exec(&mut deps, OWNER, ExecuteMsg::SetIbcRateLimit { channel_id: CHANNEL.to_string(), limits: Some(limits) }).unwrap();
+    let send = |amount: u128| ExecuteMsg::IbcTransfer { channel_id: CHANNEL.to_string(), receiver: "remote".to_string(), amount: Uint128::from(amount), timeout_seconds: None, memo: None };
+
+    exec(&mut deps, "alice", send(30)).unwrap();
+    assert_err(exec(&mut deps, "alice", send(1)), "Rate limit exceeded on ibc:channel-7, 0 available");
+    let ack = receive_packet(&mut deps, CHANNEL, packet("remote", "bob", 11));
+    assert!(matches!(ack, IbcAck::Error(_)));
+
+    // Half the window refills half the quota
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(50);
+    execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), send(16)).unwrap_err();
+    execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), send(15)).unwrap();
+    let res: FlowLimitResponse = cosmwasm_std::from_binary(&query(deps.as_ref(), env, QueryMsg::IbcRateLimit { channel_id: CHANNEL.to_string() }).unwrap()).unwrap();
+    assert_eq!(res.outflow_available, Some(Uint128::zero()));
+    assert_eq!(res.inflow_available, Some(Uint128::from(10u128)));
+}
+
+#[test]
+fn bridge_mints_and_burns_within_limits() {
+    let mut deps = setup(&[("alice", 100)]);
+    let limits = FlowLimits { max_outflow: Uint128::from(50u128), max_inflow: Uint128::from(20u128), window_seconds: 100 };
+    exec(&mut deps, OWNER, ExecuteMsg::RegisterBridge { adapter: HumanAddr::from("bridge"), limits }).unwrap();
+    assert_err(exec(&mut deps, "mallory", ExecuteMsg::BridgeMint { recipient: HumanAddr::from("bob"), amount: Uint128::from(5u128) }), "mallory is not a registered bridge");
+
+    exec(&mut deps, "bridge", ExecuteMsg::BridgeMint { recipient: HumanAddr::from("bob"), amount: Uint128::from(20u128) }).unwrap();
+    assert_err(exec(&mut deps, "bridge", ExecuteMsg::BridgeMint { recipient: HumanAddr::from("bob"), amount: Uint128::from(1u128) }), "Rate limit exceeded on bridge:bridge, 0 available");
+    assert_eq!(balance(&deps, "bob"), 20);
+
+    assert_err(exec(&mut deps, "bridge", ExecuteMsg::BridgeBurn { owner: HumanAddr::from("alice"), amount: Uint128::from(10u128) }), "Insufficient allowance");
+    exec(&mut deps, "alice", ExecuteMsg::Approve { spender: HumanAddr::from("bridge"), amount: Uint128::from(10u128) }).unwrap();
+    exec(&mut deps, "bridge", ExecuteMsg::BridgeBurn { owner: HumanAddr::from("alice"), amount: Uint128::from(10u128) }).unwrap();
+    assert_eq!(balance(&deps, "alice"), 90);
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::from(110u128));
+
+    let bridge: Option<BridgeAdapter> = query_as(&deps, QueryMsg::Bridge { adapter: HumanAddr::from("bridge") });
+    let bridge = bridge.unwrap();
+    assert_eq!((bridge.total_minted, bridge.total_burned), (Uint128::from(20u128), Uint128::from(10u128)));
+}
+
+#[test]
+fn increase_allowance_and_call_notifies_and_expires() {
+    let mut deps = setup(&[("alice", 100)]);
+    let expires = Some(Expiration::AtHeight(mock_env().block.height + 10));
+    let res = exec(&mut deps, "alice", ExecuteMsg::IncreaseAllowanceAndCall { spender: HumanAddr::from("vault"), amount: Uint128::from(30u128), expires, msg: cosmwasm_std::Binary::from(b"deposit".to_vec()) }).unwrap();
+    let notify = ApprovalReceiverExecuteMsg::ReceiveApproval(Cw20ApprovalMsg { owner: HumanAddr::from("alice"), amount: Uint128::from(30u128), expires, msg: cosmwasm_std::Binary::from(b"deposit".to_vec()) });
+    assert_eq!(res.messages[0].msg, cosmwasm_std::CosmosMsg::Wasm(cosmwasm_std::WasmMsg::Execute { contract_addr: "vault".to_string(), msg: cosmwasm_std::to_binary(&notify).unwrap(), funds: vec![] }));
+
+    exec(&mut deps, "vault", ExecuteMsg::TransferFrom { owner: HumanAddr::from("alice"), recipient: HumanAddr::from("vault"), amount: Uint128::from(10u128), memo: None }).unwrap();
+    assert_eq!(balance(&deps, "vault"), 10);
+
+    let mut env = mock_env();
+    env.block.height += 10;
+    let res = execute(deps.as_mut(), env, mock_info("vault", &[]), ExecuteMsg::TransferFrom { owner: HumanAddr::from("alice"), recipient: HumanAddr::from("vault"), amount: Uint128::from(10u128), memo: None });
+    assert_err(res, "Allowance is expired");
+    let mut env = mock_env();
+    env.block.height += 11;
+    assert_err(execute(deps.as_mut(), env, mock_info("alice", &[]), ExecuteMsg::IncreaseAllowanceAndCall { spender: HumanAddr::from("vault"), amount: Uint128::from(1u128), expires, msg: cosmwasm_std::Binary::default() }), "Expiration is already in the past");
+}
+
+fn permit_msg(deps: &TestDeps, key: &Key, signer: &Key, amount: u128, nonce: u64) -> ExecuteMsg {
+    let env = mock_env();
+    let payload = PermitPayload {
+        domain: SignatureDomain::new(&deps.storage, &env, "permit").unwrap(),
+        owner: key.address(),
+        spender: HumanAddr::from("spender"),
+        amount: Uint128::from(amount),
+        nonce,
+        deadline: Expiration::AtHeight(env.block.height + 10),
+    };
+    ExecuteMsg::Permit {
+        owner: payload.owner.clone(),
+        spender: payload.spender.clone(),
+        amount: payload.amount,
+        nonce,
+        deadline: payload.deadline,
+        pubkey: key.pubkey(),
+        signature: signer.sign(&payload),
+    }
+}
+
+#[test]
+fn permit_sets_allowance_from_valid_signature() {
+    let alice = Key::new(1);
+    let mut deps = setup(&[(alice.address().as_str(), 100)]);
+    let msg = permit_msg(&deps, &alice, &alice, 40, 0);
+    exec(&mut deps, "relayer", msg.clone()).unwrap();
+    let allowance = load_allowance(&deps.storage, &alice.address(), &HumanAddr::from("spender")).unwrap();
+    assert_eq!(allowance.allowance, Uint128::from(40u128));
+    assert_eq!(permit_nonce(&deps.storage, &alice.address()).unwrap(), 1);
+    assert_err(exec(&mut deps, "relayer", msg), "Invalid nonce");
+}
+
+#[test]
+fn permit_rejects_invalid_signature() {
+    let alice = Key::new(1);
+    let mut deps = setup(&[(alice.address().as_str(), 100)]);
+    let msg = match permit_msg(&deps, &alice, &alice, 40, 0) {
+        ExecuteMsg::Permit { owner, spender, nonce, deadline, pubkey, signature, .. } => {
+            ExecuteMsg::Permit { owner, spender, amount: Uint128::from(100u128), nonce, deadline, pubkey, signature }
+        }
+        _ => unreachable!(),
+    };
+    assert_err(exec(&mut deps, "relayer", msg), "Invalid signature");
+    let msg = permit_msg(&deps, &alice, &Key::new(2), 40, 0);
+    assert_err(exec(&mut deps, "relayer", msg), "Invalid signature");
+    let msg = permit_msg(&deps, &Key::new(2), &Key::new(2), 40, 0);
+    let msg = match msg {
+        ExecuteMsg::Permit { spender, amount, nonce, deadline, pubkey, signature, .. } => ExecuteMsg::Permit { owner: alice.address(), spender, amount, nonce, deadline, pubkey, signature },
+        _ => unreachable!(),
+    };
+    assert_err(exec(&mut deps, "relayer", msg), "Public key does not match the address");
+}
+
+fn signed_transfer_msg(deps: &TestDeps, key: &Key, signer: &Key, amount: u128, nonce: u64) -> ExecuteMsg {
+    let env = mock_env();
+    let payload = SignedActionPayload {
+        domain: SignatureDomain::new(&deps.storage, &env, "execute_signed").unwrap(),
+        signer: key.address(),
+        action: SignedAction::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount) },
+        nonce,
+        expiry: Expiration::AtHeight(env.block.height + 10),
+    };
+    ExecuteMsg::ExecuteSigned {
+        signer: payload.signer.clone(),
+        payload: payload.action.clone(),
+        nonce,
+        expiry: payload.expiry,
+        pubkey: key.pubkey(),
+        signature: signer.sign(&payload),
+    }
+}
+
+#[test]
+fn execute_signed_runs_action_for_signer() {
+    let alice = Key::new(1);
+    let mut deps = setup(&[(alice.address().as_str(), 100)]);
+    let msg = signed_transfer_msg(&deps, &alice, &alice, 30, 0);
+    exec(&mut deps, "relayer", msg.clone()).unwrap();
+    assert_eq!(balance(&deps, alice.address().as_str()), 70);
+    assert_eq!(balance(&deps, "bob"), 30);
+    assert_eq!(balance(&deps, "relayer"), 0);
+    assert_err(exec(&mut deps, "relayer", msg), "Invalid nonce");
+    assert_eq!(balance(&deps, "bob"), 30);
+}
+
+#[test]
+fn execute_signed_rejects_invalid_signature() {
+    let alice = Key::new(1);
+    let mut deps = setup(&[(alice.address().as_str(), 100)]);
+    let msg = match signed_transfer_msg(&deps, &alice, &alice, 30, 0) {
+        ExecuteMsg::ExecuteSigned { signer, nonce, expiry, pubkey, signature, .. } => ExecuteMsg::ExecuteSigned {
+            signer,
+            payload: SignedAction::Transfer { recipient: HumanAddr::from("mallory"), amount: Uint128::from(30u128) },
+            nonce,
+            expiry,
+            pubkey,
+            signature,
+        },
+        _ => unreachable!(),
+    };
+    assert_err(exec(&mut deps, "relayer", msg), "Invalid signature");
+    let msg = signed_transfer_msg(&deps, &alice, &Key::new(2), 30, 0);
+    assert_err(exec(&mut deps, "relayer", msg), "Invalid signature");
+    assert_eq!(balance(&deps, alice.address().as_str()), 100);
+}
+
+#[test]
+fn operator_transfers_without_allowance_until_revoked() {
+    let mut deps = setup(&[("alice", 100)]);
+    let pull = |amount: u128| ExecuteMsg::TransferFrom { owner: HumanAddr::from("alice"), recipient: HumanAddr::from("bob"), amount: Uint128::from(amount), memo: None };
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::AuthorizeOperator { operator: HumanAddr::from("alice") }), "Cannot authorize yourself as operator");
+    assert_err(exec(&mut deps, "op", pull(10)), "Insufficient allowance");
+
+    exec(&mut deps, "alice", ExecuteMsg::AuthorizeOperator { operator: HumanAddr::from("op") }).unwrap();
+    let res: IsOperatorResponse = query_as(&deps, QueryMsg::IsOperator { holder: HumanAddr::from("alice"), operator: HumanAddr::from("op") });
+    assert!(res.is_operator);
+    exec(&mut deps, "op", pull(60)).unwrap();
+    assert_eq!(balance(&deps, "bob"), 60);
+
+    exec(&mut deps, "alice", ExecuteMsg::RevokeOperator { operator: HumanAddr::from("op") }).unwrap();
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::RevokeOperator { operator: HumanAddr::from("op") }), "Not an operator");
+    assert_err(exec(&mut deps, "op", pull(10)), "Insufficient allowance");
+}
+
+#[test]
+fn grants_let_a_grantee_stake_for_the_granter() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetStakingConfig { unbonding_period: Duration::Height(10) }).unwrap();
+    let stake = ExecuteMsg::ExecuteOnBehalf { granter: HumanAddr::from("alice"), action: DelegatedAction::Stake { amount: Uint128::from(40u128) } };
+    assert_err(exec(&mut deps, "bot", stake.clone()), "bot has no Stake grant from alice");
+
+    let expires = Some(Expiration::AtHeight(mock_env().block.height + 5));
+    exec(&mut deps, "alice", ExecuteMsg::GrantAction { grantee: HumanAddr::from("bot"), action: GrantableAction::Stake, expires }).unwrap();
+    exec(&mut deps, "bot", stake.clone()).unwrap();
+    assert_eq!(staked_of(&deps.storage, &HumanAddr::from("alice")).unwrap(), Uint128::from(40u128));
+    assert_eq!(balance(&deps, "bot"), 0);
+    let unstake = ExecuteMsg::ExecuteOnBehalf { granter: HumanAddr::from("alice"), action: DelegatedAction::Unstake { amount: Uint128::from(10u128) } };
+    assert_err(exec(&mut deps, "bot", unstake), "bot has no Unstake grant from alice");
+
+    let mut env = mock_env();
+    env.block.height += 5;
+    assert_err(execute(deps.as_mut(), env, mock_info("bot", &[]), stake.clone()), "bot has no Stake grant from alice");
+    exec(&mut deps, "alice", ExecuteMsg::RevokeAction { grantee: HumanAddr::from("bot"), action: GrantableAction::Stake }).unwrap();
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::RevokeAction { grantee: HumanAddr::from("bot"), action: GrantableAction::Stake }), "Grant not found");
+}
+
+#[test]
+fn send_checked_moves_tokens_back_when_the_receiver_fails() {
+    let mut deps = setup(&[("alice", 100)]);
+    let send = ExecuteMsg::SendChecked { contract: HumanAddr::from("vault"), amount: Uint128::from(30u128), msg: cosmwasm_std::Binary::default(), memo: None };
+    let res = exec(&mut deps, "alice", send.clone()).unwrap();
+    assert_eq!(res.messages[0].id, SEND_CHECKED_REPLY_ID);
+    assert_eq!(balance(&deps, "vault"), 30);
+    let failed = cosmwasm_std::Reply { id: SEND_CHECKED_REPLY_ID, result: cosmwasm_std::ContractResult::Err("rejected".to_string()) };
+    reply(deps.as_mut(), mock_env(), failed).unwrap();
+    assert_eq!(balance(&deps, "vault"), 0);
+    assert_eq!(balance(&deps, "alice"), 100);
+
+    exec(&mut deps, "alice", send).unwrap();
+    reply(deps.as_mut(), mock_env(), reply_ok(SEND_CHECKED_REPLY_ID)).unwrap();
+    assert_eq!(balance(&deps, "vault"), 30);
+}
+
+#[test]
+fn cw1155_interface_moves_the_single_token_id() {
+    let mut deps = setup(&[("alice", 100)]);
+    let id = token_info(&deps.storage).unwrap().symbol;
+    let send = |token_id: &str| ExecuteMsg::SendFrom { from: HumanAddr::from("alice"), to: HumanAddr::from("bob"), token_id: token_id.to_string(), value: Uint128::from(10u128), msg: None };
+    assert_err(exec(&mut deps, "alice", send("other")), "Unknown token id other");
+    exec(&mut deps, "alice", send(&id)).unwrap();
+    assert_err(exec(&mut deps, "op", send(&id)), "Insufficient allowance");
+
+    exec(&mut deps, "alice", ExecuteMsg::ApproveAll { operator: HumanAddr::from("op"), expires: None }).unwrap();
+    let approved: IsApprovedForAllResponse = query_as(&deps, QueryMsg::IsApprovedForAll { owner: HumanAddr::from("alice"), operator: HumanAddr::from("op") });
+    assert!(approved.approved);
+    exec(&mut deps, "op", ExecuteMsg::BatchSendFrom { from: HumanAddr::from("alice"), to: HumanAddr::from("bob"), batch: vec![(id.clone(), Uint128::from(5u128)), (id.clone(), Uint128::from(5u128))], msg: None }).unwrap();
+
+    let res: Cw1155BalanceResponse = query_as(&deps, QueryMsg::Balance { address: HumanAddr::from("bob"), token_id: Some(id.clone()) });
+    assert_eq!(res.balance, Uint128::from(20u128));
+    let res: Cw1155BatchBalanceResponse = query_as(&deps, QueryMsg::BatchBalance { owner: HumanAddr::from("alice"), token_ids: vec![id] });
+    assert_eq!(res.balances, vec![Uint128::from(80u128)]);
+}
+
+#[test]
+fn invoices_are_collected_only_after_approval() {
+    let mut deps = setup(&[("alice", 100)]);
+    let expires = Expiration::AtHeight(mock_env().block.height + 10);
+    exec(&mut deps, "shop", ExecuteMsg::CreateInvoice { payer: HumanAddr::from("alice"), amount: Uint128::from(30u128), memo: Some("order 1".to_string()), expires }).unwrap();
+    assert_err(exec(&mut deps, "shop", ExecuteMsg::CollectInvoice { id: 1 }), "Invoice is Open");
+    assert_err(exec(&mut deps, "shop", ExecuteMsg::ApproveInvoice { id: 1 }), "Unauthorized");
+
+    exec(&mut deps, "alice", ExecuteMsg::ApproveInvoice { id: 1 }).unwrap();
+    assert_eq!(balance(&deps, "alice"), 100);
+    exec(&mut deps, "shop", ExecuteMsg::CollectInvoice { id: 1 }).unwrap();
+    assert_eq!(balance(&deps, "shop"), 30);
+    let res: InvoiceResponse = query_as(&deps, QueryMsg::Invoice { id: 1 });
+    assert_eq!(res.status, InvoiceStatus::Paid);
+    assert_err(exec(&mut deps, "shop", ExecuteMsg::CollectInvoice { id: 1 }), "Invoice is Paid");
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::CancelInvoice { id: 1 }), "Invoice is already settled");
+}
+
+#[test]
+fn recurring_allowance_refills_each_period() {
+    let mut deps = setup(&[("alice", 100)]);
+    exec(&mut deps, "alice", ExecuteMsg::SetRecurringAllowance { spender: HumanAddr::from("sub"), allowance: Some(RecurringAllowanceMsg { amount: Uint128::from(10u128), period_seconds: 100 }) }).unwrap();
+    let pull = |amount: u128| ExecuteMsg::TransferFrom { owner: HumanAddr::from("alice"), recipient: HumanAddr::from("sub"), amount: Uint128::from(amount), memo: None };
+    exec(&mut deps, "sub", pull(7)).unwrap();
+    assert_err(exec(&mut deps, "sub", pull(4)), "Recurring allowance exceeded, 3 left this period");
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    execute(deps.as_mut(), env.clone(), mock_info("sub", &[]), pull(10)).unwrap();
+    assert_eq!(balance(&deps, "sub"), 17);
+    let res: RecurringAllowanceResponse = cosmwasm_std::from_binary(&query(deps.as_ref(), env, QueryMsg::RecurringAllowance { owner: HumanAddr::from("alice"), spender: HumanAddr::from("sub") }).unwrap()).unwrap();
+    assert_eq!(res.available, Uint128::zero());
+    assert_eq!(res.resets_at, Some(mock_env().block.time.seconds() + 200));
+}
+
+#[test]
+fn htlc_charges_fees_at_lock_and_checks_the_recipient_at_claim() {
+    use sha2::Digest;
+    let mut deps = setup(&[("alice", 1000)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetFeeConfig { config: Some(FeeConfig { bps: 100, collector: HumanAddr::from("treasury"), tiers: vec![], splits: vec![] }) }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetBlacklister { blacklister: HumanAddr::from("compliance") }).unwrap();
+    let hash = hex::encode(sha2::Sha256::digest(b"secret"));
+    let timeout = Expiration::AtHeight(mock_env().block.height + 10);
+    exec(&mut deps, "alice", ExecuteMsg::LockHtlc { recipient: HumanAddr::from("bob"), amount: Uint128::from(500u128), hash, timeout }).unwrap();
+    assert_eq!(balance(&deps, "alice"), 500);
+    assert_eq!(balance(&deps, "treasury"), 5);
+
+    assert_err(exec(&mut deps, "anyone", ExecuteMsg::ClaimHtlc { id: 1, preimage: cosmwasm_std::Binary::from(b"guess".to_vec()) }), "Invalid preimage");
+    exec(&mut deps, "compliance", ExecuteMsg::Blacklist { address: HumanAddr::from("bob") }).unwrap();
+    let claim = ExecuteMsg::ClaimHtlc { id: 1, preimage: cosmwasm_std::Binary::from(b"secret".to_vec()) };
+    assert_err(exec(&mut deps, "anyone", claim.clone()), "Address bob is blacklisted");
+    exec(&mut deps, "compliance", ExecuteMsg::Unblacklist { address: HumanAddr::from("bob") }).unwrap();
+
+    // The fee was taken at lock, the claim releases the rest without charging again
+    exec(&mut deps, "anyone", claim).unwrap();
+    assert_eq!(balance(&deps, "bob"), 495);
+    assert_eq!(balance(&deps, "treasury"), 5);
+}
+
+#[test]
+fn otc_trade_settles_both_legs_on_fill() {
+    let mut deps = setup(&[("alice", 100)]);
+    let expires = Expiration::AtHeight(mock_env().block.height + 10);
+    exec(&mut deps, "alice", ExecuteMsg::CreateTrade { amount: Uint128::from(40u128), ask_token: HumanAddr::from("usdc"), ask_amount: Uint128::from(80u128), expires }).unwrap();
+    assert_eq!(balance(&deps, "alice"), 60);
+    let fill = |amount: u128| ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: HumanAddr::from("bob"),
+        amount: Uint128::from(amount),
+        msg: cosmwasm_std::to_binary(&ReceiveMsg::FillTrade { id: 1 }).unwrap(),
+    });
+    assert_err(exec(&mut deps, "usdc", fill(79)), "Trade asks exactly 80usdc");
+    assert_err(exec(&mut deps, "usdt", fill(80)), "Trade asks exactly 80usdc");
+
+    let res = exec(&mut deps, "usdc", fill(80)).unwrap();
+    let payout = cosmwasm_std::WasmMsg::Execute {
+        contract_addr: "usdc".to_string(),
+        msg: cosmwasm_std::to_binary(&Cw20ExecuteMsg::Transfer { recipient: HumanAddr::from("alice"), amount: Uint128::from(80u128) }).unwrap(),
+        funds: vec![],
+    };
+    assert_eq!(res.messages[0].msg, cosmwasm_std::CosmosMsg::Wasm(payout));
+    assert_eq!(balance(&deps, "bob"), 40);
+    assert_eq!(escrowed_total(&deps.storage).unwrap(), Uint128::zero());
+    assert!(exec(&mut deps, "alice", ExecuteMsg::CancelTrade { id: 1 }).is_err());
+}
+
+fn open_channel(deps: &mut TestDeps, funder: &Key, deposit: u128) -> u64 {
+    let msg = ExecuteMsg::OpenPaymentChannel { counterparty: HumanAddr::from("bob"), amount: Uint128::from(deposit), challenge_period: 100, pubkey: funder.pubkey() };
+    let res = exec(deps, funder.address().as_str(), msg).unwrap();
+    attr(&res, "id").parse().unwrap()
+}
+
+fn channel_update_msg(deps: &TestDeps, signer: &Key, id: u64, nonce: u64, paid: u128) -> ExecuteMsg {
+    let state = PaymentChannelState {
+        domain: SignatureDomain::new(&deps.storage, &mock_env(), "payment_channel").unwrap(),
+        channel_id: id,
+        nonce,
+        paid: Uint128::from(paid),
+    };
+    ExecuteMsg::UpdatePaymentChannel { id, nonce, paid: state.paid, signature: signer.sign(&state) }
+}
+
+#[test]
+fn payment_channel_settles_latest_signed_state() {
+    let alice = Key::new(1);
+    let mut deps = setup(&[(alice.address().as_str(), 100)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetBlacklister { blacklister: HumanAddr::from("compliance") }).unwrap();
+    let id = open_channel(&mut deps, &alice, 60);
+    assert_eq!(balance(&deps, alice.address().as_str()), 40);
+
+    let older = channel_update_msg(&deps, &alice, id, 1, 20);
+    exec(&mut deps, "relayer", older.clone()).unwrap();
+    let msg = channel_update_msg(&deps, &alice, id, 2, 25);
+    exec(&mut deps, "relayer", msg).unwrap();
+    assert_err(exec(&mut deps, alice.address().as_str(), older), "State is not newer than the one on chain");
+    let forged = match channel_update_msg(&deps, &alice, id, 3, 30) {
+        ExecuteMsg::UpdatePaymentChannel { id, nonce, signature, .. } => ExecuteMsg::UpdatePaymentChannel { id, nonce, paid: Uint128::from(60u128), signature },
+        _ => unreachable!(),
+    };
+    assert_err(exec(&mut deps, "bob", forged), "Invalid signature");
+    let msg = channel_update_msg(&deps, &Key::new(2), id, 3, 60);
+    assert_err(exec(&mut deps, "bob", msg), "Invalid signature");
+
+    // The payout only checks the counterparty again
+    exec(&mut deps, "compliance", ExecuteMsg::Blacklist { address: HumanAddr::from("bob") }).unwrap();
+    assert_err(exec(&mut deps, "bob", ExecuteMsg::ClosePaymentChannel { id }), "Address bob is blacklisted");
+    exec(&mut deps, "compliance", ExecuteMsg::Unblacklist { address: HumanAddr::from("bob") }).unwrap();
+    exec(&mut deps, "bob", ExecuteMsg::ClosePaymentChannel { id }).unwrap();
+    assert_eq!(balance(&deps, "bob"), 25);
+    assert_eq!(balance(&deps, alice.address().as_str()), 75);
+    assert!(load_payment_channel(&deps.storage, id).is_err());
+}
+
+#[test]
+fn payment_channel_funder_close_waits_for_the_challenge_period() {
+    let alice = Key::new(1);
+    let mut deps = setup(&[(alice.address().as_str(), 100)]);
+    let msg = ExecuteMsg::OpenPaymentChannel { counterparty: HumanAddr::from("bob"), amount: Uint128::from(60u128), challenge_period: 100, pubkey: Key::new(2).pubkey() };
+    assert_err(exec(&mut deps, alice.address().as_str(), msg), "Public key does not match the address");
+    let id = open_channel(&mut deps, &alice, 60);
+    exec(&mut deps, alice.address().as_str(), ExecuteMsg::ClosePaymentChannel { id }).unwrap();
+    assert_err(exec(&mut deps, "anyone", ExecuteMsg::FinalizePaymentChannel { id }), "Challenge period is not over");
+
+    // The counterparty answers within the window with the latest state
+    let msg = channel_update_msg(&deps, &alice, id, 1, 10);
+    exec(&mut deps, "bob", msg).unwrap();
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    let msg = channel_update_msg(&deps, &alice, id, 2, 20);
+    assert_err(execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), msg), "Challenge period is over");
+    execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::FinalizePaymentChannel { id }).unwrap();
+    assert_eq!(balance(&deps, "bob"), 10);
+    assert_eq!(balance(&deps, alice.address().as_str()), 90);
+}
+
+#[test]
+fn payroll_pays_one_period_per_run() {
+    let mut deps = setup(&[("employer", 100)]);
+    let payouts = vec![(HumanAddr::from("bob"), Uint128::from(10u128)), (HumanAddr::from("carol"), Uint128::from(5u128))];
+    exec(&mut deps, "employer", ExecuteMsg::RegisterPayroll { payouts, interval: 100, start: None }).unwrap();
+    exec(&mut deps, "crank", ExecuteMsg::RunPayroll { id: 1 }).unwrap();
+    let due = mock_env().block.time.seconds() + 100;
+    assert_err(exec(&mut deps, "crank", ExecuteMsg::RunPayroll { id: 1 }), &format!("Payroll is not due until {}", due));
+    assert_eq!((balance(&deps, "bob"), balance(&deps, "carol"), balance(&deps, "employer")), (10, 5, 85));
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(100);
+    execute(deps.as_mut(), env, mock_info("crank", &[]), ExecuteMsg::RunPayroll { id: 1 }).unwrap();
+    let history: PayoutHistoryResponse = query_as(&deps, QueryMsg::PayoutHistory { recipient: HumanAddr::from("bob"), start_after: None, limit: None });
+    assert_eq!(history.payouts.iter().map(|p| p.amount.u128()).collect::<Vec<_>>(), vec![10, 10]);
+
+    assert_err(exec(&mut deps, "bob", ExecuteMsg::CancelPayroll { id: 1 }), "Unauthorized");
+    exec(&mut deps, "employer", ExecuteMsg::CancelPayroll { id: 1 }).unwrap();
+    assert!(exec(&mut deps, "crank", ExecuteMsg::RunPayroll { id: 1 }).is_err());
+}
+
+#[test]
+fn instantiate2_helpers_validate_salt_and_predict_addresses() {
+    let msg = helpers::default_instantiate_msg(vec![InitialBalance { address: HumanAddr::from("alice"), amount: Uint128::from(7u128), vesting: None }, InitialBalance { address: HumanAddr::from("bob"), amount: Uint128::from(3u128), vesting: None }]);
+    assert_eq!(helpers::initial_supply(&msg).unwrap(), Uint128::from(10u128));
+    assert_err(helpers::instantiate2_msg("factory", None, 1, "token", &msg, &[], &[]), "Salt must be between 1 and 64 bytes");
+    match helpers::instantiate2_msg("factory", None, 1, "token", &msg, &[], b"salt").unwrap() {
+        cosmwasm_std::CosmosMsg::Stargate { type_url, .. } => assert_eq!(type_url, helpers::INSTANTIATE2_TYPE_URL),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let creator = CanonicalAddr::from(vec![1u8; 20]);
+    let salt = helpers::project_salt("project", 0);
+    let a = helpers::predict_instantiate2_address(&[9u8; 32], &creator, &salt).unwrap();
+    assert_eq!(a, helpers::predict_instantiate2_address(&[9u8; 32], &creator, &salt).unwrap());
+    assert_ne!(a, helpers::predict_instantiate2_address(&[9u8; 32], &creator, &helpers::project_salt("project", 1)).unwrap());
+    assert_err(helpers::predict_instantiate2_address(&[9u8; 31], &creator, &salt), "Checksum must be 32 bytes");
+}
+
+#[test]
+fn cw3_proposal_wraps_token_admin_messages() {
+    assert_err(helpers::cw3_proposal("token", "t", "d", &[], None), "A proposal needs at least one message");
+    let pause = ExecuteMsg::Pause {};
+    let proposal = helpers::cw3_proposal("token", "Pause", "Incident", std::slice::from_ref(&pause), Some(Expiration::AtTime(100))).unwrap();
+    let expected = helpers::Cw3ExecuteMsg::Propose {
+        title: "Pause".to_string(),
+        description: "Incident".to_string(),
+        msgs: vec![helpers::token_execute_msg("token", &pause).unwrap()],
+        latest: Some(helpers::Cw3Expiration::AtTime(cosmwasm_std::Timestamp::from_seconds(100))),
+    };
+    assert_eq!(proposal, expected);
+    match helpers::cw3_propose_msg("multisig", "token", "Pause", "Incident", &[pause], Some(Expiration::AtTime(100))).unwrap() {
+        cosmwasm_std::CosmosMsg::Wasm(cosmwasm_std::WasmMsg::Execute { contract_addr, msg, .. }) => {
+            assert_eq!(contract_addr, "multisig");
+            assert_eq!(cosmwasm_std::from_binary::<helpers::Cw3ExecuteMsg>(&msg).unwrap(), expected);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[test]
+fn usd_limits_price_transfers_through_the_oracle() {
+    let mut deps = setup(&[("alice", 1000)]);
+    // Two USD units per token
+    on_smart_query(&mut deps, |_| PriceResponse { price: Decimal::from_ratio(2u128, 1u128) });
+    let limits = UsdLimits { oracle: HumanAddr::from("oracle"), max_transaction_usd: Some(Uint128::from(100u128)), daily_limit_usd: Some(Uint128::from(150u128)) };
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::SetUsdLimits { limits: Some(limits.clone()) }), "A daily USD limit needs the daily limit to be configured");
+    exec(&mut deps, OWNER, ExecuteMsg::SetDailyLimit { limit: Some(DailyLimit { limit: Uint128::from(1000u128), epoch_seconds: 86400 }) }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetUsdLimits { limits: Some(limits) }).unwrap();
+
+    let send = |amount: u128| ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount), memo: None };
+    assert_err(exec(&mut deps, "alice", send(51)), "Transfer value exceeds the maximum of 100 USD");
+    exec(&mut deps, "alice", send(50)).unwrap();
+    exec(&mut deps, "alice", send(25)).unwrap();
+    assert_err(exec(&mut deps, "alice", send(1)), "Daily USD transfer limit exceeded");
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::SetDailyLimit { limit: None }), "Remove the daily USD limit before the daily limit");
+
+    let res: UsdLimitsResponse = query_as(&deps, QueryMsg::UsdLimits {});
+    assert_eq!(res.price, Some(Decimal::from_ratio(2u128, 1u128)));
+}
+
+struct TestVault;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum VaultReceiveMsg {
+    Deposit {},
+}
+
+impl receiver::Cw20Receiver for TestVault {
+    type Msg = VaultReceiveMsg;
+
+    fn accepts_token(_deps: cosmwasm_std::Deps, token: &HumanAddr) -> cosmwasm_std::StdResult<bool> {
+        Ok(token.as_str() == "token")
+    }
+
+    fn on_receive(
+        _deps: cosmwasm_std::DepsMut,
+        _env: cosmwasm_std::Env,
+        _info: cosmwasm_std::MessageInfo,
+        sender: HumanAddr,
+        amount: Uint128,
+        msg: VaultReceiveMsg,
+    ) -> cosmwasm_std::StdResult<cosmwasm_std::Response> {
+        assert_eq!(msg, VaultReceiveMsg::Deposit {});
+        Ok(cosmwasm_std::Response::new().add_attribute("depositor", sender).add_attribute("amount", amount.to_string()))
+    }
+}
+
+cw20_receiver_execute_msg! {
+    #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+    #[serde(rename_all = "snake_case")]
+    enum VaultExecuteMsg {
+        Withdraw { amount: Uint128 },
+    }
+}
+
+#[test]
+fn cw20_receiver_checks_the_token_and_decodes_the_message() {
+    let mut deps = cosmwasm_std::testing::mock_dependencies(&[]);
+    let receive = |amount: u128| VaultExecuteMsg::Receive(Cw20ReceiveMsg { sender: HumanAddr::from("alice"), amount: Uint128::from(amount), msg: cosmwasm_std::to_binary(&VaultReceiveMsg::Deposit {}).unwrap() });
+    let dispatch = |deps: cosmwasm_std::DepsMut, token: &str, msg: VaultExecuteMsg| match msg {
+        VaultExecuteMsg::Receive(wrapper) => cw20_receive!(TestVault, deps, mock_env(), mock_info(token, &[]), wrapper),
+        VaultExecuteMsg::Withdraw { .. } => unreachable!(),
+    };
+    assert_err(dispatch(deps.as_mut(), "other", receive(5)), "Token other is not accepted");
+    assert_err(dispatch(deps.as_mut(), "token", receive(0)), "Received amount must be greater than zero");
+    let res = dispatch(deps.as_mut(), "token", receive(5)).unwrap();
+    assert_eq!(attr(&res, "depositor"), "alice");
+}
+
+#[test]
+fn send_calls_the_receiver_and_records_the_memo() {
+    let mut deps = setup(&[("alice", 100)]);
+    let send = ExecuteMsg::Send { contract: HumanAddr::from("vault"), amount: Uint128::from(30u128), msg: cosmwasm_std::Binary::default(), memo: Some("invoice 7".to_string()) };
+    let res = exec(&mut deps, "alice", send).unwrap();
+    assert_eq!(attr(&res, "action"), "send");
+    assert_eq!(attr(&res, "memo"), "invoice 7");
+    assert_eq!(balance(&deps, "vault"), 30);
+    let callback = ReceiverExecuteMsg::Receive(Cw20ReceiveMsg { sender: HumanAddr::from("alice"), amount: Uint128::from(30u128), msg: cosmwasm_std::Binary::default() });
+    let expected = cosmwasm_std::WasmMsg::Execute { contract_addr: "vault".to_string(), msg: cosmwasm_std::to_binary(&callback).unwrap(), funds: vec![] };
+    assert_eq!(res.messages[0].msg, cosmwasm_std::CosmosMsg::Wasm(expected));
+
+    let long = ExecuteMsg::Send { contract: HumanAddr::from("vault"), amount: Uint128::from(1u128), msg: cosmwasm_std::Binary::default(), memo: Some("x".repeat(257)) };
+    assert_err(exec(&mut deps, "alice", long), "Memo is longer than 256 bytes");
+    let to_self = ExecuteMsg::Send { contract: HumanAddr::from(mock_env().contract.address.as_str()), amount: Uint128::from(1u128), msg: cosmwasm_std::Binary::default(), memo: None };
+    assert_err(exec(&mut deps, "alice", to_self), "Cannot transfer to the token contract");
+}
+
+#[test]
+fn ibc_hook_memo_calls_the_receiving_contract() {
+    let mut deps = setup_ibc(&[("alice", 100)]);
+    let mut data = packet("remote", "vault", 25);
+    data.memo = Some(r#"{"wasm":{"contract":"vault","msg":{"deposit":{}}}}"#.to_string());
+    let recv = mock_ibc_packet_recv(CHANNEL, &data).unwrap();
+    let msg = ExecuteMsg::ReceiveIbcPacket { packet: recv.packet };
+    let res = execute(deps.as_mut(), mock_env(), mock_info(cosmwasm_std::testing::MOCK_CONTRACT_ADDR, &[]), msg).unwrap();
+    assert_eq!(balance(&deps, "vault"), 25);
+    assert_eq!(res.messages[0].id, IBC_HOOK_REPLY_ID);
+    let callback = ReceiverExecuteMsg::Receive(Cw20ReceiveMsg { sender: HumanAddr::from("remote"), amount: Uint128::from(25u128), msg: cosmwasm_std::Binary::from(br#"{"deposit":{}}"#.to_vec()) });
+    let expected = cosmwasm_std::WasmMsg::Execute { contract_addr: "vault".to_string(), msg: cosmwasm_std::to_binary(&callback).unwrap(), funds: vec![] };
+    assert_eq!(res.messages[0].msg, cosmwasm_std::CosmosMsg::Wasm(expected));
+
+    data.receiver = "bob".to_string();
+    assert!(matches!(receive_packet(&mut deps, CHANNEL, data), IbcAck::Error(_)));
+    let send = ExecuteMsg::IbcTransfer { channel_id: CHANNEL.to_string(), receiver: "remote".to_string(), amount: Uint128::from(10u128), timeout_seconds: None, memo: Some("{not json".to_string()) };
+    assert!(exec(&mut deps, "alice", send).is_err());
+}
+
+#[test]
+fn pending_transfer_charges_fees_once_and_checks_the_recipient_at_claim() {
+    let mut deps = setup(&[("alice", 1000)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetFeeConfig { config: Some(FeeConfig { bps: 100, collector: HumanAddr::from("treasury"), tiers: vec![], splits: vec![] }) }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetBlacklister { blacklister: HumanAddr::from("compliance") }).unwrap();
+    let expires = Expiration::AtHeight(mock_env().block.height + 10);
+    let pending = |amount: u128| ExecuteMsg::TransferPending { recipient: HumanAddr::from("bob"), amount: Uint128::from(amount), expires };
+    exec(&mut deps, "alice", pending(500)).unwrap();
+    exec(&mut deps, "alice", pending(200)).unwrap();
+    assert_eq!(balance(&deps, "alice"), 300);
+    assert_eq!(balance(&deps, "treasury"), 7);
+
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::ClaimTransfer { id: 1 }), "Unauthorized");
+    exec(&mut deps, "compliance", ExecuteMsg::Blacklist { address: HumanAddr::from("bob") }).unwrap();
+    assert_err(exec(&mut deps, "bob", ExecuteMsg::ClaimTransfer { id: 1 }), "Address bob is blacklisted");
+    assert_err(exec(&mut deps, "alice", pending(100)), "Address bob is blacklisted");
+    exec(&mut deps, "compliance", ExecuteMsg::Unblacklist { address: HumanAddr::from("bob") }).unwrap();
+
+    exec(&mut deps, "bob", ExecuteMsg::ClaimTransfer { id: 1 }).unwrap();
+    assert_eq!(balance(&deps, "bob"), 495);
+    // Cancelling hands back what was escrowed, the fee stays with the collector
+    exec(&mut deps, "alice", ExecuteMsg::CancelTransfer { id: 2 }).unwrap();
+    assert_eq!(balance(&deps, "alice"), 498);
+    assert_eq!(balance(&deps, "treasury"), 7);
+}
+
+#[test]
+fn scheduled_transfer_charges_fees_once_and_checks_the_recipient_when_due() {
+    let mut deps = setup(&[("alice", 1000)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetFeeConfig { config: Some(FeeConfig { bps: 100, collector: HumanAddr::from("treasury"), tiers: vec![], splits: vec![] }) }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetBlacklister { blacklister: HumanAddr::from("compliance") }).unwrap();
+    let at = Expiration::AtHeight(mock_env().block.height + 10);
+    exec(&mut deps, "alice", ExecuteMsg::ScheduleTransfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(500u128), at }).unwrap();
+    assert_eq!(balance(&deps, "alice"), 500);
+    assert_eq!(balance(&deps, "treasury"), 5);
+    assert_err(exec(&mut deps, "anyone", ExecuteMsg::ExecuteScheduledTransfer { id: 1 }), "Scheduled transfer is not due yet");
+
+    let mut env = mock_env();
+    env.block.height += 10;
+    exec(&mut deps, "compliance", ExecuteMsg::Blacklist { address: HumanAddr::from("bob") }).unwrap();
+    assert_err(execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::ExecuteScheduledTransfer { id: 1 }), "Address bob is blacklisted");
+    exec(&mut deps, "compliance", ExecuteMsg::Unblacklist { address: HumanAddr::from("bob") }).unwrap();
+    execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::ExecuteScheduledTransfer { id: 1 }).unwrap();
+    assert_eq!(balance(&deps, "bob"), 495);
+    assert_eq!(balance(&deps, "treasury"), 5);
+}
+
+#[test]
+fn owner_recovers_foreign_cw20_tokens() {
+    let mut deps = setup(&[]);
+    let recover = |token: &str| ExecuteMsg::RecoverCw20 { token: HumanAddr::from(token), recipient: HumanAddr::from("alice"), amount: Uint128::from(40u128) };
+    assert_err(exec(&mut deps, "alice", recover("other_token")), "Unauthorized");
+    assert_err(exec(&mut deps, OWNER, recover(mock_env().contract.address.as_str())), "Use RecoverStuckTokens for this token");
+
+    let res = exec(&mut deps, OWNER, recover("other_token")).unwrap();
+    let expected = cw20_transfer_msg(&HumanAddr::from("other_token"), &HumanAddr::from("alice"), Uint128::from(40u128)).unwrap();
+    assert_eq!(res.messages[0].msg, expected);
+}
+
+#[test]
+fn sweep_native_leaves_reserved_coins() {
+    let mut deps = setup(&[]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetNativeWrapper { denom: Some("uusd".to_string()) }).unwrap();
+    let contract = mock_env().contract.address;
+    deps.querier.base.update_balance(contract.as_str(), cosmwasm_std::coins(100, "uusd"));
+    execute(deps.as_mut(), mock_env(), mock_info("alice", &cosmwasm_std::coins(100, "uusd")), ExecuteMsg::Deposit {}).unwrap();
+    let sweep = ExecuteMsg::SweepNative { denom: "uusd".to_string(), recipient: HumanAddr::from("treasury") };
+    assert_err(exec(&mut deps, OWNER, sweep.clone()), "No unreserved uusd to sweep");
+    assert_err(exec(&mut deps, "alice", sweep.clone()), "Unauthorized");
+
+    deps.querier.base.update_balance(contract.as_str(), cosmwasm_std::coins(125, "uusd"));
+    let res = exec(&mut deps, OWNER, sweep).unwrap();
+    assert_eq!(res.messages[0].msg, cosmwasm_std::BankMsg::Send { to_address: "treasury".to_string(), amount: cosmwasm_std::coins(25, "uusd") }.into());
+}
+
+#[test]
+fn cw20_msgs_types_match_the_contract_messages() {
+    let msgs = vec![
+        cw20_msgs::ExecuteMsg::Transfer { recipient: HumanAddr::from("bob"), amount: Uint128::from(5u128), memo: None },
+        cw20_msgs::ExecuteMsg::Send { contract: HumanAddr::from("vault"), amount: Uint128::from(5u128), msg: cosmwasm_std::Binary::default(), memo: Some("m".to_string()) },
+    ];
+    let mut deps = setup(&[("alice", 100)]);
+    for msg in msgs {
+        let msg: ExecuteMsg = cosmwasm_std::from_slice(&cosmwasm_std::to_vec(&msg).unwrap()).unwrap();
+        exec(&mut deps, "alice", msg).unwrap();
+    }
+    assert_eq!(balance(&deps, "bob"), 5);
+    let query = cosmwasm_std::to_vec(&cw20_msgs::QueryMsg::Balance { address: HumanAddr::from("vault") }).unwrap();
+    let res: BalanceResponse = query_as(&deps, cosmwasm_std::from_slice(&query).unwrap());
+    assert_eq!(res.amount, Uint128::from(5u128));
+}
+
+#[test]
+fn all_balances_pages_through_holders() {
+    let mut deps = setup(&[("alice", 10), ("bob", 20), ("carol", 30)]);
+    exec(&mut deps, "bob", ExecuteMsg::Transfer { recipient: HumanAddr::from("carol"), amount: Uint128::from(20u128), memo: None }).unwrap();
+    let page: AllBalancesResponse = query_as(&deps, QueryMsg::AllBalances { start_after: None, limit: Some(2) });
+    assert_eq!(page.balances, vec![HolderBalance { address: HumanAddr::from("alice"), balance: Uint128::from(10u128) }]);
+    assert_eq!(page.next_start_after, Some(HumanAddr::from("bob")));
+    let page: AllBalancesResponse = query_as(&deps, QueryMsg::AllBalances { start_after: page.next_start_after, limit: Some(2) });
+    assert_eq!(page.balances, vec![HolderBalance { address: HumanAddr::from("carol"), balance: Uint128::from(50u128) }]);
+    assert_eq!(page.next_start_after, None);
+}
+
+#[test]
+fn crank_clamps_emissions_to_the_cap() {
+    let mut deps = setup_with(InstantiateMsg { cap: Some(Uint128::from(1100u128)), ..instantiate_msg(&[("alice", 1000)]) });
+    let schedule = EmissionScheduleMsg { per_block: Uint128::from(10u128), decay_bps: 5000, blocks_per_year: 100, target: HumanAddr::from("pool"), to_staking: false };
+    exec(&mut deps, OWNER, ExecuteMsg::SetEmissionSchedule { schedule: Some(schedule) }).unwrap();
+
+    let mut env = mock_env();
+    env.block.height += 150;
+    execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), ExecuteMsg::Crank {}).unwrap();
+    assert_eq!(balance(&deps, "pool"), 100);
+
+    env.block.height += 10;
+    execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::Crank {}).unwrap();
+    assert_eq!(balance(&deps, "pool"), 100);
+}
+
+#[test]
+fn stream_charges_fees_once_and_checks_the_recipient_on_withdraw() {
+    let now = mock_env().block.time.seconds();
+    let mut deps = setup(&[("alice", 1000)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetFeeConfig { config: Some(FeeConfig { bps: 100, collector: HumanAddr::from("treasury"), tiers: vec![], splits: vec![] }) }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetBlacklister { blacklister: HumanAddr::from("compliance") }).unwrap();
+    let create = ExecuteMsg::CreateStream { recipient: HumanAddr::from("bob"), amount: Uint128::from(100u128), start: now, end: now + 99 };
+    let res = exec(&mut deps, "alice", create).unwrap();
+    assert_eq!((attr(&res, "amount"), attr(&res, "fee")), ("99".to_string(), "1".to_string()));
+    assert_eq!(balance(&deps, "treasury"), 1);
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(99);
+    exec(&mut deps, "compliance", ExecuteMsg::Blacklist { address: HumanAddr::from("bob") }).unwrap();
+    assert_err(execute(deps.as_mut(), env.clone(), mock_info("bob", &[]), ExecuteMsg::WithdrawStream { id: 1 }), "Address bob is blacklisted");
+    exec(&mut deps, "compliance", ExecuteMsg::Unblacklist { address: HumanAddr::from("bob") }).unwrap();
+    execute(deps.as_mut(), env, mock_info("bob", &[]), ExecuteMsg::WithdrawStream { id: 1 }).unwrap();
+    assert_eq!((balance(&deps, "bob"), balance(&deps, "treasury")), (99, 1));
+}
+
+#[test]
+fn escrow_charges_fees_once_and_checks_the_recipient_on_approval() {
+    let height = mock_env().block.height;
+    let mut deps = setup(&[("alice", 1000)]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetFeeConfig { config: Some(FeeConfig { bps: 100, collector: HumanAddr::from("treasury"), tiers: vec![], splits: vec![] }) }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetBlacklister { blacklister: HumanAddr::from("compliance") }).unwrap();
+    let create = ExecuteMsg::CreateEscrow { recipient: HumanAddr::from("bob"), arbiter: HumanAddr::from("judge"), amount: Uint128::from(500u128), expires: Expiration::AtHeight(height + 10) };
+    let res = exec(&mut deps, "alice", create).unwrap();
+    assert_eq!((attr(&res, "amount"), attr(&res, "fee")), ("495".to_string(), "5".to_string()));
+
+    exec(&mut deps, "compliance", ExecuteMsg::Blacklist { address: HumanAddr::from("bob") }).unwrap();
+    assert_err(exec(&mut deps, "judge", ExecuteMsg::ApproveEscrow { id: 1 }), "Address bob is blacklisted");
+    exec(&mut deps, "compliance", ExecuteMsg::Unblacklist { address: HumanAddr::from("bob") }).unwrap();
+    exec(&mut deps, "judge", ExecuteMsg::ApproveEscrow { id: 1 }).unwrap();
+    assert_eq!((balance(&deps, "bob"), balance(&deps, "treasury")), (495, 5));
+}
+
+#[test]
+fn linear_curve_refunds_the_remainder_and_buys_back_whole_tokens() {
+    let mut deps = setup(&[]);
+    exec(&mut deps, OWNER, ExecuteMsg::SetBondingCurve { curve: CurveType::Linear { slope: Decimal::one() }, reserve_denom: "uatom".to_string() }).unwrap();
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("alice", &cosmwasm_std::coins(9, "uatom")), ExecuteMsg::BuyFromCurve {}).unwrap();
+    assert_eq!((attr(&res, "minted"), attr(&res, "refund")), ("4000000".to_string(), "1".to_string()));
+    let refund = cosmwasm_std::BankMsg::Send { to_address: "alice".to_string(), amount: cosmwasm_std::coins(1, "uatom") };
+    assert_eq!(res.messages[0].msg, cosmwasm_std::CosmosMsg::Bank(refund));
+
+    assert_err(exec(&mut deps, "alice", ExecuteMsg::SellToCurve { amount: Uint128::from(1_500_000u128) }), "Linear curves only buy back whole tokens");
+    let res = exec(&mut deps, "alice", ExecuteMsg::SellToCurve { amount: Uint128::from(2_000_000u128) }).unwrap();
+    assert_eq!(attr(&res, "payout"), "6");
+    assert_eq!(balance(&deps, "alice"), 2_000_000);
+}
+
+#[test]
+fn reflection_leaves_the_escrowed_contract_balance_alone() {
+    let mut deps = setup(&[("alice", 900), ("carol", 100)]);
+    let expires = Expiration::AtHeight(mock_env().block.height + 10);
+    exec(&mut deps, "alice", ExecuteMsg::TransferPending { recipient: HumanAddr::from("bob"), amount: Uint128::from(400u128), expires }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetReflection { bps: Some(1000) }).unwrap();
+
+    exec(&mut deps, "alice", ExecuteMsg::Transfer { recipient: HumanAddr::from("dave"), amount: Uint128::from(100u128), memo: None }).unwrap();
+    assert_eq!(balance(&deps, cosmwasm_std::testing::MOCK_CONTRACT_ADDR), 400);
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "carol"), balance(&deps, "dave")), (407, 101, 90));
+    exec(&mut deps, "bob", ExecuteMsg::ClaimTransfer { id: 1 }).unwrap();
+    assert_eq!(balance(&deps, "bob"), 400);
+}
+
+#[test]
+fn rebase_leaves_the_escrowed_contract_balance_alone() {
+    let mut deps = setup(&[("alice", 600), ("carol", 400)]);
+    let expires = Expiration::AtHeight(mock_env().block.height + 10);
+    exec(&mut deps, "alice", ExecuteMsg::TransferPending { recipient: HumanAddr::from("bob"), amount: Uint128::from(500u128), expires }).unwrap();
+
+    exec(&mut deps, OWNER, ExecuteMsg::Rebase { new_supply: Uint128::from(1400u128) }).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "carol"), balance(&deps, cosmwasm_std::testing::MOCK_CONTRACT_ADDR)), (179, 719, 500));
+    exec(&mut deps, OWNER, ExecuteMsg::Rebase { new_supply: Uint128::from(550u128) }).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, "carol")), (10, 40));
+    exec(&mut deps, "bob", ExecuteMsg::ClaimTransfer { id: 1 }).unwrap();
+    assert_eq!(balance(&deps, "bob"), 500);
+
+    exec(&mut deps, "carol", ExecuteMsg::TransferPending { recipient: HumanAddr::from("bob"), amount: Uint128::from(40u128), expires }).unwrap();
+    assert_err(exec(&mut deps, OWNER, ExecuteMsg::Rebase { new_supply: Uint128::from(40u128) }), "A supply of 40 doesn't cover the 40 tokens held by this contract");
+}
+
+#[test]
+fn accrue_leaves_the_escrowed_contract_balance_alone() {
+    let mut deps = setup(&[("alice", 1000)]);
+    let expires = Expiration::AtHeight(mock_env().block.height + 10);
+    exec(&mut deps, "alice", ExecuteMsg::TransferPending { recipient: HumanAddr::from("bob"), amount: Uint128::from(500u128), expires }).unwrap();
+    exec(&mut deps, OWNER, ExecuteMsg::SetInterestRate { rate_bps_per_year: Some(1000) }).unwrap();
+
+    let mut env = mock_env();
+    env.block.time = env.block.time.plus_seconds(SECONDS_PER_YEAR);
+    execute(deps.as_mut(), env, mock_info("anyone", &[]), ExecuteMsg::Accrue {}).unwrap();
+    assert_eq!((balance(&deps, "alice"), balance(&deps, cosmwasm_std::testing::MOCK_CONTRACT_ADDR)), (549, 500));
+    assert_eq!(token_info(&deps.storage).unwrap().total_supply, Uint128::from(1050u128));
+    exec(&mut deps, "bob", ExecuteMsg::ClaimTransfer { id: 1 }).unwrap();
+    assert_eq!(balance(&deps, "bob"), 500);
+}